@@ -1,45 +1,487 @@
 use gl;
 use glfw::{Action, Context};
 
+pub struct RunConfig<'t> {
+    pub title: &'t str,
+    pub width: u32,
+    pub height: u32,
+    // overrides the monitor's content scale
+    pub scale_factor: Option<f64>,
+    pub msaa_samples: Option<u32>,
+    // clears to transparent black and asks for a framebuffer the compositor blends
+    pub transparent: bool,
+    // starts fullscreen, F11 toggles either way
+    pub fullscreen: bool,
+    pub on_frame: Option<Box<dyn FnMut(&FrameStats) + 't>>,
+    pub frame_stats: Option<Rc<RefCell<FrameStats>>>,
+    pub redraw: RedrawHandle,
+    // kept in sync with the window's clipboard around shortcuts
+    pub clipboard: MemoryClipboard,
+}
+
+impl<'t> RunConfig<'t> {
+    pub fn msaa(mut self, samples: u32) -> Self {
+        self.msaa_samples = Some(samples);
+        self
+    }
+
+    pub fn transparent(mut self, transparent: bool) -> Self {
+        self.transparent = transparent;
+        self
+    }
+
+    pub fn fullscreen(mut self, fullscreen: bool) -> Self {
+        self.fullscreen = fullscreen;
+        self
+    }
+
+    fn clear_color(&self) -> Color {
+        if self.transparent {
+            color::TRANSPARENT
+        } else {
+            color::WHITE
+        }
+    }
+
+    // called after every frame, e.g. to show the stats in a debug overlay
+    pub fn on_frame<F: FnMut(&FrameStats) + 't>(mut self, on_frame: F) -> Self {
+        self.on_frame = Some(Box::new(on_frame));
+        self
+    }
+
+    pub fn frame_stats(mut self, state: &mut State<FrameStats>) -> Self {
+        let stats = Rc::new(RefCell::new(FrameStats::new()));
+        state.bind(stats.clone());
+        self.frame_stats = Some(stats);
+        self
+    }
+
+    // can be sent to other threads to make the loop lay out and draw again
+    pub fn redraw_handle(&self) -> RedrawHandle {
+        self.redraw.clone()
+    }
+
+    // for the text inputs in the tree, so copy and paste reach the system clipboard
+    pub fn clipboard(&self) -> MemoryClipboard {
+        self.clipboard.clone()
+    }
+
+    fn window_hints(&self) -> Vec<glfw::WindowHint> {
+        let mut hints = Vec::new();
+        if let Some(samples) = self.msaa_samples {
+            hints.push(glfw::WindowHint::Samples(Some(samples)));
+        }
+        if self.transparent {
+            hints.push(glfw::WindowHint::TransparentFramebuffer(true));
+        }
+        hints
+    }
+}
+
+impl<'t> Default for RunConfig<'t> {
+    fn default() -> Self {
+        RunConfig {
+            title: "winkel",
+            width: 800,
+            height: 600,
+            scale_factor: None,
+            msaa_samples: None,
+            transparent: false,
+            fullscreen: false,
+            on_frame: None,
+            frame_stats: None,
+            redraw: RedrawHandle::new(),
+            clipboard: MemoryClipboard::new(),
+        }
+    }
+}
+
+// maps window and cursor coordinates to the logical pixels widgets are laid out in
+fn logical_scale(window: &glfw::Window, scale_factor: f64) -> f64 {
+    let (window_width, _) = window.get_size();
+    let (framebuffer_width, _) = window.get_framebuffer_size();
+    if window_width == 0 {
+        return 1.0 / scale_factor;
+    }
+    framebuffer_width as f64 / window_width as f64 / scale_factor
+}
+
+fn winkel_key(key: glfw::Key) -> Option<winkel::Key> {
+    use glfw::Key as G;
+    use winkel::Key;
+    let code = key as i32;
+    Some(match key {
+        G::Enter | G::KpEnter => Key::Enter,
+        G::Escape => Key::Escape,
+        G::Tab => Key::Tab,
+        G::Backspace => Key::Backspace,
+        G::Delete => Key::Delete,
+        G::Space => Key::Space,
+        G::Left => Key::Left,
+        G::Right => Key::Right,
+        G::Up => Key::Up,
+        G::Down => Key::Down,
+        G::Home => Key::Home,
+        G::End => Key::End,
+        // glfw codes for letters and digits are their ascii values
+        _ if (G::A as i32..=G::Z as i32).contains(&code) => {
+            Key::Char((code as u8 as char).to_ascii_lowercase())
+        }
+        _ if (G::Num0 as i32..=G::Num9 as i32).contains(&code) => Key::Char(code as u8 as char),
+        _ if (G::F1 as i32..=G::F25 as i32).contains(&code) => {
+            Key::F((code - G::F1 as i32 + 1) as u8)
+        }
+        _ => return None,
+    })
+}
+
+fn winkel_modifiers(modifiers: glfw::Modifiers) -> winkel::Modifiers {
+    winkel::Modifiers {
+        ctrl: modifiers.contains(glfw::Modifiers::Control),
+        shift: modifiers.contains(glfw::Modifiers::Shift),
+        alt: modifiers.contains(glfw::Modifiers::Alt),
+        logo: modifiers.contains(glfw::Modifiers::Super),
+    }
+}
+
+// position and size of a window in screen coordinates
+#[derive(Debug, Clone, Copy, PartialEq)]
+struct WindowGeometry {
+    x: i32,
+    y: i32,
+    width: i32,
+    height: i32,
+}
+
+impl WindowGeometry {
+    fn contains(&self, (x, y): (i32, i32)) -> bool {
+        x >= self.x && y >= self.y && x < self.x + self.width && y < self.y + self.height
+    }
+
+    fn center(&self) -> (i32, i32) {
+        (self.x + self.width / 2, self.y + self.height / 2)
+    }
+}
+
+// the monitor showing the middle of the window, or the first one if the window is off screen
+fn monitor_for(window: WindowGeometry, monitors: &[WindowGeometry]) -> Option<usize> {
+    monitors
+        .iter()
+        .position(|monitor| monitor.contains(window.center()))
+        .or((!monitors.is_empty()).then_some(0))
+}
+
+// what a toggle switches to, the saved windowed geometry or a monitor by index
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum FullscreenChange {
+    Windowed(WindowGeometry),
+    Monitor(usize),
+}
+
+// leaving fullscreen hands back the saved geometry, entering it saves the current one.
+// nothing changes if there is no monitor to go fullscreen on
+fn fullscreen_change(
+    windowed: &mut Option<WindowGeometry>,
+    current: WindowGeometry,
+    monitors: &[WindowGeometry],
+) -> Option<FullscreenChange> {
+    if let Some(geometry) = windowed.take() {
+        return Some(FullscreenChange::Windowed(geometry));
+    }
+    let index = monitor_for(current, monitors)?;
+    *windowed = Some(current);
+    Some(FullscreenChange::Monitor(index))
+}
+
+// goes fullscreen on the window's monitor in its current video mode, remembering where the
+// window was. going back restores that geometry
+fn toggle_fullscreen(
+    glfw: &mut glfw::Glfw,
+    window: &mut glfw::Window,
+    windowed: &mut Option<WindowGeometry>,
+) {
+    let (x, y) = window.get_pos();
+    let (width, height) = window.get_size();
+    let current = WindowGeometry {
+        x,
+        y,
+        width,
+        height,
+    };
+    glfw.with_connected_monitors_mut(|_, monitors| {
+        let modes: Vec<(&glfw::Monitor, glfw::VidMode)> = monitors
+            .iter()
+            .filter_map(|monitor| Some((monitor, monitor.get_video_mode()?)))
+            .collect();
+        let areas: Vec<WindowGeometry> = modes
+            .iter()
+            .map(|(monitor, mode)| {
+                let (x, y) = monitor.get_pos();
+                WindowGeometry {
+                    x,
+                    y,
+                    width: mode.width as i32,
+                    height: mode.height as i32,
+                }
+            })
+            .collect();
+        match fullscreen_change(windowed, current, &areas) {
+            Some(FullscreenChange::Windowed(geometry)) => window.set_monitor(
+                glfw::WindowMode::Windowed,
+                geometry.x,
+                geometry.y,
+                geometry.width as u32,
+                geometry.height as u32,
+                None,
+            ),
+            Some(FullscreenChange::Monitor(index)) => {
+                let (monitor, mode) = &modes[index];
+                window.set_monitor(
+                    glfw::WindowMode::FullScreen(monitor),
+                    0,
+                    0,
+                    mode.width,
+                    mode.height,
+                    Some(mode.refresh_rate),
+                );
+            }
+            None => {}
+        }
+    });
+}
+
+// other apps may have changed the system clipboard since the last shortcut, so its text is
+// taken over before the shortcut runs. the text to write back is only returned if the
+// shortcut copied something, a clipboard holding an image or files stays untouched otherwise
+fn sync_clipboard<F: FnOnce()>(
+    clipboard: &MemoryClipboard,
+    system: Option<String>,
+    shortcut: F,
+) -> Option<String> {
+    if let Some(text) = &system {
+        clipboard.set(text);
+    }
+    clipboard.take_changed();
+    shortcut();
+    if clipboard.take_changed() {
+        clipboard.get()
+    } else {
+        None
+    }
+}
+
+// how long an idle loop sleeps before ticking again, about one frame
+const IDLE_TIMEOUT: f64 = 1.0 / 60.0;
+
+// logical pixels one wheel notch scrolls
+const SCROLL_LINE: f64 = 40.0;
+
+// draws and presents a frame unless it would look like the one on screen.
+// returns whether it drew
+fn draw<'a>(
+    window: &mut glfw::Window,
+    renderer: &mut dyn Renderer,
+    config: &RunConfig,
+    computed: &HashMap<usize, ComputedWidget<'a>>,
+    drawn: &mut Option<HashMap<usize, ComputedWidget<'a>>>,
+    win_width: f64,
+    win_height: f64,
+) -> bool {
+    if let Some(drawn) = drawn {
+        if winkel::dirty_regions(drawn, computed).is_empty() {
+            return false;
+        }
+    }
+    unsafe {
+        let [r, g, b, a] = config.clear_color();
+        gl::ClearColor(r, g, b, a);
+        gl::Clear(gl::COLOR_BUFFER_BIT | gl::DEPTH_BUFFER_BIT);
+    }
+    renderer.render(computed, win_width, win_height);
+    window.swap_buffers();
+    *drawn = Some(computed.clone());
+    true
+}
+
 pub fn run<'a, F: FnOnce() -> Box<dyn Renderer>>(
     tree: Rc<RefCell<dyn Widget<'a> + 'a>>,
     get_renderer: F,
-    start_width: u32,
-    start_height: u32,
-    title: &str,
+    config: RunConfig,
+) {
+    run_loop(tree, get_renderer, config, &mut || None);
+}
+
+// like `run`, but the tree comes from a spec file and is rebuilt whenever the file changes
+#[cfg(feature = "hot-reload")]
+pub fn run_spec<'a, F: FnOnce() -> Box<dyn Renderer>>(
+    mut reloader: winkel::spec::SpecReloader<'a>,
+    get_renderer: F,
+    config: RunConfig,
+) {
+    let tree = reloader.load().expect("Failed to load the spec file.");
+    run_loop(tree, get_renderer, config, &mut || reloader.poll());
+}
+
+fn run_loop<'a, F: FnOnce() -> Box<dyn Renderer>>(
+    mut tree: Rc<RefCell<dyn Widget<'a> + 'a>>,
+    get_renderer: F,
+    mut config: RunConfig,
+    reload: &mut dyn FnMut() -> Option<Rc<RefCell<dyn Widget<'a> + 'a>>>,
 ) {
     let mut glfw = glfw::init(glfw::FAIL_ON_ERRORS).unwrap();
+    for hint in config.window_hints() {
+        glfw.window_hint(hint);
+    }
 
     let (mut window, events) = glfw
-        .create_window(start_width, start_height, title, glfw::WindowMode::Windowed)
+        .create_window(
+            config.width,
+            config.height,
+            config.title,
+            glfw::WindowMode::Windowed,
+        )
         .expect("Failed to create GLFW window.");
 
     window.make_current();
     gl::load_with(|s| window.get_proc_address(s));
 
+    if let Some(requested) = config.msaa_samples {
+        let mut samples = 0;
+        unsafe {
+            gl::GetIntegerv(gl::SAMPLES, &mut samples);
+        }
+        if (samples as u32) < requested {
+            eprintln!(
+                "requested {} MSAA samples, but the context provides {}",
+                requested, samples
+            );
+        }
+    }
+
+    // where the window goes back to when leaving fullscreen, None while windowed
+    let mut windowed = None;
+    if config.fullscreen {
+        toggle_fullscreen(&mut glfw, &mut window, &mut windowed);
+    }
+
     let mut renderer: Box<dyn Renderer> = get_renderer();
 
-    let mut mouse_x: f64 = 0.0;
-    let mut mouse_y: f64 = 0.0;
-    let mut win_width: f64 = start_width as f64;
-    let mut win_height: f64 = start_height as f64;
+    let mut scale_factor = config
+        .scale_factor
+        .unwrap_or_else(|| window.get_content_scale().0 as f64);
+    renderer.set_scale_factor(scale_factor);
 
-    let mut computed = compute(&tree, start_width as f64, start_height as f64);
+    let (framebuffer_width, framebuffer_height) = window.get_framebuffer_size();
+    unsafe {
+        gl::Viewport(0, 0, framebuffer_width, framebuffer_height);
+    }
+    let mut win_width: f64 = framebuffer_width as f64 / scale_factor;
+    let mut win_height: f64 = framebuffer_height as f64 / scale_factor;
+
+    let mut computed = compute(&tree, win_width, win_height);
+
+    // start from the real cursor position so the first move doesn't come from (0, 0)
+    let (cursor_x, cursor_y) = window.get_cursor_pos();
+    let mut mouse_x = cursor_x * logical_scale(&window, scale_factor);
+    let mut mouse_y = cursor_y * logical_scale(&window, scale_factor);
+    if mouse_x < 0.0 || mouse_y < 0.0 || mouse_x >= win_width || mouse_y >= win_height {
+        mouse_x = -1.0;
+        mouse_y = -1.0;
+    } else if tree
+        .borrow()
+        .dispatch(
+            WinkelEvent::MouseMove {
+                prev_x: -1.0,
+                prev_y: -1.0,
+                x: mouse_x,
+                y: mouse_y,
+            },
+            false,
+            &computed,
+        )
+        .1
+    {
+        computed = compute(&tree, win_width, win_height);
+    }
 
     window.set_mouse_button_polling(true);
     window.set_cursor_pos_polling(true);
-    window.set_size_polling(true);
+    window.set_framebuffer_size_polling(true);
+    window.set_content_scale_polling(true);
     window.set_cursor_enter_polling(true);
+    window.set_key_polling(true);
+    window.set_char_polling(true);
+    window.set_scroll_polling(true);
 
+    // posting an empty event is the one glfw call that is safe from any thread
+    config.redraw.set_waker(Some(Box::new(|| unsafe {
+        glfw::ffi::glfwPostEmptyEvent()
+    })));
+    // the layout on screen, None when the next frame has to be drawn regardless
+    let mut drawn: Option<HashMap<usize, ComputedWidget>> = None;
+    let mut stats = FrameStats::new();
+    let mut frame_start = glfw.get_time();
     while !window.should_close() {
-        unsafe {
-            gl::ClearColor(1.0, 1.0, 1.0, 1.0);
-            gl::Clear(gl::COLOR_BUFFER_BIT);
+        // a minimized window has a zero sized framebuffer, so only wait for it to come back
+        let minimized = win_width <= 0.0 || win_height <= 0.0;
+        if !minimized {
+            if draw(
+                &mut window,
+                &mut *renderer,
+                &config,
+                &computed,
+                &mut drawn,
+                win_width,
+                win_height,
+            ) {
+                glfw.poll_events();
+            } else {
+                // nothing to draw, so there is no swap to wait on either
+                glfw.wait_events_timeout(IDLE_TIMEOUT);
+            }
+        } else {
+            glfw.wait_events();
+        }
+        if tree
+            .borrow()
+            .dispatch(
+                WinkelEvent::Tick {
+                    time: glfw.get_time(),
+                },
+                false,
+                &computed,
+            )
+            .1
+        {
+            computed = compute(&tree, win_width, win_height);
+        }
+        if config.redraw.take_request() {
+            computed = compute(&tree, win_width, win_height);
+            // state outside the tree may have changed what widgets draw, e.g. a reloaded image
+            drawn = None;
+        }
+        if let Some(reloaded) = reload() {
+            tree = reloaded;
+            computed = compute(&tree, win_width, win_height);
+            // the new widgets haven't seen the cursor yet, so hover state is restored
+            if mouse_x >= 0.0
+                && tree
+                    .borrow()
+                    .dispatch(
+                        WinkelEvent::MouseMove {
+                            prev_x: -1.0,
+                            prev_y: -1.0,
+                            x: mouse_x,
+                            y: mouse_y,
+                        },
+                        false,
+                        &computed,
+                    )
+                    .1
+            {
+                computed = compute(&tree, win_width, win_height);
+            }
         }
-        renderer.render(&computed, win_width, win_height);
-        window.swap_buffers();
-
-        glfw.poll_events();
         for (_, event) in glfw::flush_messages(&events) {
             match event {
                 glfw::WindowEvent::CursorEnter(entered) => {
@@ -65,6 +507,8 @@ pub fn run<'a, F: FnOnce() -> Box<dyn Renderer>>(
                     }
                 }
                 glfw::WindowEvent::CursorPos(x, y) => {
+                    let x = x * logical_scale(&window, scale_factor);
+                    let y = y * logical_scale(&window, scale_factor);
                     if tree
                         .borrow()
                         .dispatch(
@@ -118,54 +562,225 @@ pub fn run<'a, F: FnOnce() -> Box<dyn Renderer>>(
                         computed = compute(&tree, win_width, win_height);
                     }
                 }
-                glfw::WindowEvent::Size(width, height) => {
-                    win_width = width as f64;
-                    win_height = height as f64;
-                    unsafe {
-                        gl::Viewport(0, 0, width, height);
-                    };
-                    computed = compute(&tree, width as f64, height as f64);
+                glfw::WindowEvent::Key(glfw::Key::F11, _, Action::Press, _) => {
+                    toggle_fullscreen(&mut glfw, &mut window, &mut windowed);
+                }
+                glfw::WindowEvent::Key(glfw::Key::F12, _, Action::Press, _) => {
+                    let enabled = renderer.debug_bounds();
+                    renderer.set_debug_bounds(!enabled);
+                    drawn = None;
+                }
+                glfw::WindowEvent::Key(key, _, Action::Press | Action::Repeat, modifiers) => {
+                    if let Some(key) = winkel_key(key) {
+                        let modifiers = winkel_modifiers(modifiers);
+                        let system = if modifiers.ctrl {
+                            window.get_clipboard_string()
+                        } else {
+                            None
+                        };
+                        let copied = sync_clipboard(&config.clipboard, system, || {
+                            if tree
+                                .borrow()
+                                .dispatch(WinkelEvent::KeyDown { key, modifiers }, false, &computed)
+                                .1
+                            {
+                                computed = compute(&tree, win_width, win_height);
+                            }
+                        });
+                        if let Some(copied) = copied {
+                            window.set_clipboard_string(&copied);
+                        }
+                    }
+                }
+                // glfw reports wheel notches, positive when the content should come back
+                glfw::WindowEvent::Scroll(dx, dy) => {
+                    if tree
+                        .borrow()
+                        .dispatch(
+                            WinkelEvent::Scroll {
+                                x: mouse_x,
+                                y: mouse_y,
+                                dx: -dx * SCROLL_LINE,
+                                dy: -dy * SCROLL_LINE,
+                            },
+                            false,
+                            &computed,
+                        )
+                        .1
+                    {
+                        computed = compute(&tree, win_width, win_height);
+                    }
+                }
+                glfw::WindowEvent::Char(codepoint) => {
+                    if tree
+                        .borrow()
+                        .dispatch(WinkelEvent::Char { codepoint }, false, &computed)
+                        .1
+                    {
+                        computed = compute(&tree, win_width, win_height);
+                    }
+                }
+                glfw::WindowEvent::FramebufferSize(width, height) => {
+                    win_width = width.max(0) as f64 / scale_factor;
+                    win_height = height.max(0) as f64 / scale_factor;
+                    if width > 0 && height > 0 {
+                        unsafe {
+                            gl::Viewport(0, 0, width, height);
+                        };
+                        computed = compute(&tree, win_width, win_height);
+                        drawn = None;
+                    }
+                }
+                glfw::WindowEvent::ContentScale(x_scale, _) if config.scale_factor.is_none() => {
+                    scale_factor = x_scale as f64;
+                    renderer.set_scale_factor(scale_factor);
+                    drawn = None;
+                    let (width, height) = window.get_framebuffer_size();
+                    win_width = width as f64 / scale_factor;
+                    win_height = height as f64 / scale_factor;
+                    computed = compute(&tree, win_width, win_height);
                 }
                 _ => {}
             }
         }
-        unsafe {
-            gl::ClearColor(1.0, 1.0, 1.0, 1.0);
-            gl::Clear(gl::COLOR_BUFFER_BIT);
+        if win_width > 0.0 && win_height > 0.0 {
+            draw(
+                &mut window,
+                &mut *renderer,
+                &config,
+                &computed,
+                &mut drawn,
+                win_width,
+                win_height,
+            );
+        }
+        let now = glfw.get_time();
+        stats.record_frame(now - frame_start);
+        frame_start = now;
+        stats.widgets = computed.len();
+        stats.draw_calls = renderer.take_draw_calls();
+        if let Some(on_frame) = &mut config.on_frame {
+            on_frame(&stats);
+        }
+        if let Some(shared) = &config.frame_stats {
+            shared.borrow_mut().clone_from(&stats);
         }
-        renderer.render(&computed, win_width, win_height);
-        window.swap_buffers();
     }
+    config.redraw.set_waker(None);
 }
 
 use std::cell::RefCell;
+use std::collections::HashMap;
 use std::rc::Rc;
-use winkel::color;
+use winkel::color::{self, Color};
 use winkel::compute;
 use winkel::widgets::*;
+use winkel::ComputedWidget;
 use winkel::Event as WinkelEvent;
+use winkel::FrameStats;
 use winkel::GlRenderer;
+use winkel::RedrawHandle;
 use winkel::Renderer;
 use winkel::State;
+use winkel::{Clipboard, MemoryClipboard};
+
+// renders a solid rectangle offscreen and reads it back, skipped without a GL context
+fn check_render_to_texture() {
+    let mut glfw = match glfw::init(glfw::LOG_ERRORS) {
+        Ok(glfw) => glfw,
+        Err(_) => return eprintln!("no display, skipping the render to texture check"),
+    };
+    glfw.window_hint(glfw::WindowHint::Visible(false));
+    let (mut window, _events) =
+        match glfw.create_window(4, 4, "offscreen", glfw::WindowMode::Windowed) {
+            Some(window) => window,
+            None => return eprintln!("no GL context, skipping the render to texture check"),
+        };
+    window.make_current();
+    gl::load_with(|s| window.get_proc_address(s));
+    let tree: Rc<RefCell<dyn Widget>> = Rectangle::new(color::RED).build();
+    let computed = compute(&tree, 4.0, 4.0);
+    let texture = GlRenderer::new().render_to_texture(&computed, 4.0, 4.0);
+    assert_eq!((texture.width(), texture.height()), (4, 4));
+    for pixel in texture.read_rgba().chunks(4) {
+        assert_eq!(pixel, [255, 0, 0, 255]);
+    }
+}
+
+fn check_msaa_hint() {
+    let hints = RunConfig::default().msaa(4).window_hints();
+    assert!(matches!(hints[..], [glfw::WindowHint::Samples(Some(4))]));
+    assert!(RunConfig::default().window_hints().is_empty());
+}
+
+fn check_transparent_config() {
+    let config = RunConfig::default().transparent(true);
+    assert!(matches!(
+        config.window_hints()[..],
+        [glfw::WindowHint::TransparentFramebuffer(true)]
+    ));
+    assert_eq!(config.clear_color()[3], 0.0);
+    assert_eq!(RunConfig::default().clear_color(), color::WHITE);
+}
+
+fn check_clipboard_sync() {
+    let clipboard = MemoryClipboard::new();
+    clipboard.set("copied earlier");
+    // the system clipboard holds something that isn't text, e.g. an image
+    let written = sync_clipboard(&clipboard, None, || {});
+    assert_eq!(written, None);
+    assert_eq!(
+        sync_clipboard(&clipboard, Some(String::from("elsewhere")), || {}),
+        None
+    );
+    assert_eq!(clipboard.get().as_deref(), Some("elsewhere"));
+    // copying the same text again still goes back to the system clipboard
+    let written = sync_clipboard(&clipboard, None, || clipboard.set("elsewhere"));
+    assert_eq!(written.as_deref(), Some("elsewhere"));
+}
+
+fn check_fullscreen_geometry() {
+    let geometry = |x, y, width, height| WindowGeometry {
+        x,
+        y,
+        width,
+        height,
+    };
+    let monitors = [geometry(0, 0, 1920, 1080), geometry(1920, 0, 2560, 1440)];
+    let window = geometry(2200, 100, 800, 600);
+    let mut windowed = None;
+    assert_eq!(
+        fullscreen_change(&mut windowed, window, &monitors),
+        Some(FullscreenChange::Monitor(1))
+    );
+    assert_eq!(windowed, Some(window));
+    // the fullscreen size doesn't replace the saved one on the way back
+    let fullscreen = monitors[1];
+    assert_eq!(
+        fullscreen_change(&mut windowed, fullscreen, &monitors),
+        Some(FullscreenChange::Windowed(window))
+    );
+    assert_eq!(windowed, None);
+    assert_eq!(fullscreen_change(&mut windowed, window, &[]), None);
+    assert_eq!(windowed, None);
+}
 
 fn main() {
+    check_msaa_hint();
+    check_transparent_config();
+    check_fullscreen_geometry();
+    check_clipboard_sync();
+    check_render_to_texture();
     let mut button1: State<Rectangle> = State::new();
     let tree: Rc<RefCell<dyn Widget>> = Padding::new(
         Button::new(color::RED)
             .border(20.0)
             .child(
                 Column::new()
+                    .add(Text::new("Hello World", 20, "Raleway-Regular.ttf").color(color::BLUE))
                     .add(
-                        Text::new("Hello World", 20, "Raleway-Regular.ttf")
-                            .color(color::BLUE)
-                            .build(),
-                    )
-                    .add(
-                        Text::new("Hello World 2", 54, "Raleway-Regular.ttf")
-                            .color(color::MAGENTA)
-                            .build(),
-                    )
-                    .build(),
+                        Text::new("Hello World 2", 54, "Raleway-Regular.ttf").color(color::MAGENTA),
+                    ),
             )
             .on_pressed(|_| {
                 println!("Clicked!");
@@ -176,5 +791,15 @@ fn main() {
     )
     .all(30.0)
     .build();
-    run(tree, || Box::new(GlRenderer::new()), 1024, 768, "Test");
+    run(
+        tree,
+        || Box::new(GlRenderer::new()),
+        RunConfig {
+            title: "Test",
+            width: 1024,
+            height: 768,
+            ..Default::default()
+        }
+        .msaa(4),
+    );
 }
@@ -8,3 +8,304 @@ pub const BLUE: [f32; 4] = [0.0, 0.0, 1.0, 1.0];
 pub const MAGENTA: [f32; 4] = [1.0, 0.0, 1.0, 1.0];
 pub const YELLOW: [f32; 4] = [1.0, 1.0, 0.0, 1.0];
 pub const CYAN: [f32; 4] = [0.0, 1.0, 1.0, 1.0];
+pub const TRANSPARENT: [f32; 4] = [0.0; 4];
+
+pub fn with_alpha(color: Color, alpha: f32) -> Color {
+    [color[0], color[1], color[2], alpha]
+}
+
+pub fn premultiply(color: Color) -> Color {
+    [
+        color[0] * color[3],
+        color[1] * color[3],
+        color[2] * color[3],
+        color[3],
+    ]
+}
+
+// wcag relative luminance of the srgb channels, alpha is ignored
+pub fn luminance(c: Color) -> f32 {
+    let linear = |channel: f32| {
+        if channel <= 0.03928 {
+            channel / 12.92
+        } else {
+            ((channel + 0.055) / 1.055).powf(2.4)
+        }
+    };
+    0.2126 * linear(c[0]) + 0.7152 * linear(c[1]) + 0.0722 * linear(c[2])
+}
+
+// wcag contrast ratio, from 1 for equal luminance up to 21 for black on white
+pub fn contrast_ratio(a: Color, b: Color) -> f32 {
+    let (a, b) = (luminance(a), luminance(b));
+    (a.max(b) + 0.05) / (a.min(b) + 0.05)
+}
+
+// black or white, whichever contrasts more with the background
+pub fn readable_on(bg: Color) -> Color {
+    if contrast_ratio(bg, BLACK) >= contrast_ratio(bg, WHITE) {
+        BLACK
+    } else {
+        WHITE
+    }
+}
+
+// componentwise blend, t = 0 gives `from` and t = 1 gives `to`
+pub fn lerp(from: Color, to: Color, t: f32) -> Color {
+    [
+        from[0] + (to[0] - from[0]) * t,
+        from[1] + (to[1] - from[1]) * t,
+        from[2] + (to[2] - from[2]) * t,
+        from[3] + (to[3] - from[3]) * t,
+    ]
+}
+
+// colors at offsets from 0 to 1, stops are kept sorted by offset
+#[derive(Debug, Clone, PartialEq, Default)]
+pub struct Gradient {
+    pub stops: Vec<(f32, Color)>,
+}
+
+impl Gradient {
+    pub fn new() -> Self {
+        Gradient::default()
+    }
+
+    pub fn stop(mut self, offset: f32, color: Color) -> Self {
+        let index = self.stops.partition_point(|(o, _)| *o <= offset);
+        self.stops.insert(index, (offset, color));
+        self
+    }
+
+    // outside the first and last stop the nearest stop's color is used
+    pub fn sample(&self, t: f32) -> Color {
+        let (first, last) = match (self.stops.first(), self.stops.last()) {
+            (Some(first), Some(last)) => (first, last),
+            _ => return [0.0; 4],
+        };
+        if t <= first.0 {
+            return first.1;
+        }
+        if t >= last.0 {
+            return last.1;
+        }
+        let index = self.stops.partition_point(|(o, _)| *o <= t);
+        let (start, from) = self.stops[index - 1];
+        let (end, to) = self.stops[index];
+        lerp(from, to, (t - start) / (end - start))
+    }
+}
+
+// css color names, sorted for binary search
+static NAMED_COLORS: [(&str, u32); 148] = [
+    ("aliceblue", 0xf0f8ff),
+    ("antiquewhite", 0xfaebd7),
+    ("aqua", 0x00ffff),
+    ("aquamarine", 0x7fffd4),
+    ("azure", 0xf0ffff),
+    ("beige", 0xf5f5dc),
+    ("bisque", 0xffe4c4),
+    ("black", 0x000000),
+    ("blanchedalmond", 0xffebcd),
+    ("blue", 0x0000ff),
+    ("blueviolet", 0x8a2be2),
+    ("brown", 0xa52a2a),
+    ("burlywood", 0xdeb887),
+    ("cadetblue", 0x5f9ea0),
+    ("chartreuse", 0x7fff00),
+    ("chocolate", 0xd2691e),
+    ("coral", 0xff7f50),
+    ("cornflowerblue", 0x6495ed),
+    ("cornsilk", 0xfff8dc),
+    ("crimson", 0xdc143c),
+    ("cyan", 0x00ffff),
+    ("darkblue", 0x00008b),
+    ("darkcyan", 0x008b8b),
+    ("darkgoldenrod", 0xb8860b),
+    ("darkgray", 0xa9a9a9),
+    ("darkgreen", 0x006400),
+    ("darkgrey", 0xa9a9a9),
+    ("darkkhaki", 0xbdb76b),
+    ("darkmagenta", 0x8b008b),
+    ("darkolivegreen", 0x556b2f),
+    ("darkorange", 0xff8c00),
+    ("darkorchid", 0x9932cc),
+    ("darkred", 0x8b0000),
+    ("darksalmon", 0xe9967a),
+    ("darkseagreen", 0x8fbc8f),
+    ("darkslateblue", 0x483d8b),
+    ("darkslategray", 0x2f4f4f),
+    ("darkslategrey", 0x2f4f4f),
+    ("darkturquoise", 0x00ced1),
+    ("darkviolet", 0x9400d3),
+    ("deeppink", 0xff1493),
+    ("deepskyblue", 0x00bfff),
+    ("dimgray", 0x696969),
+    ("dimgrey", 0x696969),
+    ("dodgerblue", 0x1e90ff),
+    ("firebrick", 0xb22222),
+    ("floralwhite", 0xfffaf0),
+    ("forestgreen", 0x228b22),
+    ("fuchsia", 0xff00ff),
+    ("gainsboro", 0xdcdcdc),
+    ("ghostwhite", 0xf8f8ff),
+    ("gold", 0xffd700),
+    ("goldenrod", 0xdaa520),
+    ("gray", 0x808080),
+    ("green", 0x008000),
+    ("greenyellow", 0xadff2f),
+    ("grey", 0x808080),
+    ("honeydew", 0xf0fff0),
+    ("hotpink", 0xff69b4),
+    ("indianred", 0xcd5c5c),
+    ("indigo", 0x4b0082),
+    ("ivory", 0xfffff0),
+    ("khaki", 0xf0e68c),
+    ("lavender", 0xe6e6fa),
+    ("lavenderblush", 0xfff0f5),
+    ("lawngreen", 0x7cfc00),
+    ("lemonchiffon", 0xfffacd),
+    ("lightblue", 0xadd8e6),
+    ("lightcoral", 0xf08080),
+    ("lightcyan", 0xe0ffff),
+    ("lightgoldenrodyellow", 0xfafad2),
+    ("lightgray", 0xd3d3d3),
+    ("lightgreen", 0x90ee90),
+    ("lightgrey", 0xd3d3d3),
+    ("lightpink", 0xffb6c1),
+    ("lightsalmon", 0xffa07a),
+    ("lightseagreen", 0x20b2aa),
+    ("lightskyblue", 0x87cefa),
+    ("lightslategray", 0x778899),
+    ("lightslategrey", 0x778899),
+    ("lightsteelblue", 0xb0c4de),
+    ("lightyellow", 0xffffe0),
+    ("lime", 0x00ff00),
+    ("limegreen", 0x32cd32),
+    ("linen", 0xfaf0e6),
+    ("magenta", 0xff00ff),
+    ("maroon", 0x800000),
+    ("mediumaquamarine", 0x66cdaa),
+    ("mediumblue", 0x0000cd),
+    ("mediumorchid", 0xba55d3),
+    ("mediumpurple", 0x9370db),
+    ("mediumseagreen", 0x3cb371),
+    ("mediumslateblue", 0x7b68ee),
+    ("mediumspringgreen", 0x00fa9a),
+    ("mediumturquoise", 0x48d1cc),
+    ("mediumvioletred", 0xc71585),
+    ("midnightblue", 0x191970),
+    ("mintcream", 0xf5fffa),
+    ("mistyrose", 0xffe4e1),
+    ("moccasin", 0xffe4b5),
+    ("navajowhite", 0xffdead),
+    ("navy", 0x000080),
+    ("oldlace", 0xfdf5e6),
+    ("olive", 0x808000),
+    ("olivedrab", 0x6b8e23),
+    ("orange", 0xffa500),
+    ("orangered", 0xff4500),
+    ("orchid", 0xda70d6),
+    ("palegoldenrod", 0xeee8aa),
+    ("palegreen", 0x98fb98),
+    ("paleturquoise", 0xafeeee),
+    ("palevioletred", 0xdb7093),
+    ("papayawhip", 0xffefd5),
+    ("peachpuff", 0xffdab9),
+    ("peru", 0xcd853f),
+    ("pink", 0xffc0cb),
+    ("plum", 0xdda0dd),
+    ("powderblue", 0xb0e0e6),
+    ("purple", 0x800080),
+    ("rebeccapurple", 0x663399),
+    ("red", 0xff0000),
+    ("rosybrown", 0xbc8f8f),
+    ("royalblue", 0x4169e1),
+    ("saddlebrown", 0x8b4513),
+    ("salmon", 0xfa8072),
+    ("sandybrown", 0xf4a460),
+    ("seagreen", 0x2e8b57),
+    ("seashell", 0xfff5ee),
+    ("sienna", 0xa0522d),
+    ("silver", 0xc0c0c0),
+    ("skyblue", 0x87ceeb),
+    ("slateblue", 0x6a5acd),
+    ("slategray", 0x708090),
+    ("slategrey", 0x708090),
+    ("snow", 0xfffafa),
+    ("springgreen", 0x00ff7f),
+    ("steelblue", 0x4682b4),
+    ("tan", 0xd2b48c),
+    ("teal", 0x008080),
+    ("thistle", 0xd8bfd8),
+    ("tomato", 0xff6347),
+    ("turquoise", 0x40e0d0),
+    ("violet", 0xee82ee),
+    ("wheat", 0xf5deb3),
+    ("white", 0xffffff),
+    ("whitesmoke", 0xf5f5f5),
+    ("yellow", 0xffff00),
+    ("yellowgreen", 0x9acd32),
+];
+
+// css color names like "cornflowerblue", case insensitive. "transparent" has zero alpha
+pub fn by_name(name: &str) -> Option<Color> {
+    let name = name.to_ascii_lowercase();
+    if name == "transparent" {
+        return Some(TRANSPARENT);
+    }
+    let index = NAMED_COLORS
+        .binary_search_by(|(n, _)| (*n).cmp(name.as_str()))
+        .ok()?;
+    let rgb = NAMED_COLORS[index].1;
+    let channel = |shift: u32| ((rgb >> shift) & 0xff) as f32 / 255.0;
+    Some([channel(16), channel(8), channel(0), 1.0])
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn gradients_blend_between_neighbouring_stops() {
+        let gradient = Gradient::new()
+            .stop(1.0, BLUE)
+            .stop(0.0, RED)
+            .stop(0.5, GREEN);
+        assert_eq!(gradient.sample(0.0), RED);
+        assert_eq!(gradient.sample(0.5), GREEN);
+        assert_eq!(gradient.sample(1.0), BLUE);
+        assert_eq!(gradient.sample(0.25), [0.5, 0.5, 0.0, 1.0]);
+        assert_eq!(gradient.sample(0.75), [0.0, 0.5, 0.5, 1.0]);
+        // outside the stops the ends are held
+        assert_eq!(gradient.sample(-1.0), RED);
+        assert_eq!(gradient.sample(2.0), BLUE);
+        assert_eq!(Gradient::new().sample(0.5), TRANSPARENT);
+    }
+
+    #[test]
+    fn names_resolve_to_colors() {
+        assert_eq!(by_name("red"), Some(RED));
+        assert_eq!(by_name("Red"), Some(RED));
+        assert_eq!(
+            by_name("cornflowerblue"),
+            Some([100.0 / 255.0, 149.0 / 255.0, 237.0 / 255.0, 1.0])
+        );
+        assert_eq!(by_name("transparent"), Some(TRANSPARENT));
+        assert_eq!(by_name("notacolor"), None);
+        // the lookup is a binary search
+        assert!(NAMED_COLORS.windows(2).all(|pair| pair[0].0 < pair[1].0));
+    }
+
+    #[test]
+    fn readable_foregrounds_contrast_most() {
+        assert_eq!(readable_on(WHITE), BLACK);
+        assert_eq!(readable_on(BLACK), WHITE);
+        assert_eq!(readable_on(YELLOW), BLACK);
+        assert_eq!(readable_on(BLUE), WHITE);
+        assert!((contrast_ratio(BLACK, WHITE) - 21.0).abs() < 1e-4);
+        assert_eq!(contrast_ratio(WHITE, BLACK), contrast_ratio(BLACK, WHITE));
+        assert_eq!(contrast_ratio(RED, RED), 1.0);
+        assert_eq!((luminance(BLACK), luminance(WHITE)), (0.0, 1.0));
+    }
+}
@@ -1,11 +1,218 @@
+use super::super::{Hinting, TextDecoration};
 use super::utils::Texture;
+use freetype::bitmap::PixelMode;
+use freetype::face::LoadFlag;
+use freetype::FtResult;
+use std::cell::Cell;
 use std::collections::HashMap;
+use std::rc::Rc;
+
+thread_local! {
+    // every renderer and measurer on a thread loads its fonts through the same library
+    static LIBRARY: Rc<freetype::Library> = {
+        LIBRARY_INITS.with(|inits| inits.set(inits.get() + 1));
+        Rc::new(freetype::Library::init().unwrap())
+    };
+    static LIBRARY_INITS: Cell<usize> = const { Cell::new(0) };
+}
+
+pub fn shared_library() -> Rc<freetype::Library> {
+    LIBRARY.with(Rc::clone)
+}
+
+// how often this thread set up a freetype library, at most once
+#[cfg(test)]
+fn library_inits() -> usize {
+    LIBRARY_INITS.with(Cell::get)
+}
+
+const DEFAULT_FONT: &[u8] = include_bytes!("../../Raleway-Regular.ttf");
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct FontConfig {
+    pub hinting: Hinting,
+    pub snap_to_pixel: bool,
+}
+
+impl FontConfig {
+    fn load_flags(&self) -> LoadFlag {
+        match self.hinting {
+            Hinting::None => LoadFlag::RENDER | LoadFlag::NO_HINTING,
+            Hinting::Light => LoadFlag::RENDER | LoadFlag::TARGET_LIGHT,
+            Hinting::Normal => LoadFlag::RENDER | LoadFlag::TARGET_NORMAL,
+        }
+    }
+
+    pub fn snap(&self, position: f64) -> f64 {
+        if self.snap_to_pixel {
+            position.round()
+        } else {
+            position
+        }
+    }
+}
+
+impl From<Hinting> for FontConfig {
+    fn from(hinting: Hinting) -> Self {
+        FontConfig {
+            hinting,
+            snap_to_pixel: hinting != Hinting::None,
+        }
+    }
+}
+
+#[derive(Clone)]
+enum FontSource {
+    File(String),
+    Bytes(Rc<Vec<u8>>),
+}
+
+#[derive(Default, Clone)]
+pub struct FontRegistry {
+    sources: HashMap<String, FontSource>,
+    fallbacks: Vec<String>,
+}
+
+impl FontRegistry {
+    pub fn new() -> Self {
+        FontRegistry {
+            sources: HashMap::new(),
+            fallbacks: Vec::new(),
+        }
+    }
+
+    pub fn add_file(&mut self, name: &str, path: &str) {
+        self.sources
+            .insert(String::from(name), FontSource::File(String::from(path)));
+    }
+
+    pub fn add_bytes(&mut self, name: &str, bytes: &[u8]) {
+        self.sources.insert(
+            String::from(name),
+            FontSource::Bytes(Rc::new(bytes.to_vec())),
+        );
+    }
+
+    pub fn add_fallback(&mut self, name: &str) {
+        self.fallbacks.push(String::from(name));
+    }
+
+    pub fn chain<'a>(&'a self, name: &'a str) -> impl Iterator<Item = &'a str> {
+        std::iter::once(name).chain(
+            self.fallbacks
+                .iter()
+                .map(|fallback| fallback.as_str())
+                .filter(move |fallback| *fallback != name),
+        )
+    }
+
+    // a font that fails to load is replaced by the bundled default instead of taking the app down
+    pub fn load(&self, lib: &freetype::Library, name: &str, size: u32, config: FontConfig) -> Font {
+        let font = match self.sources.get(name) {
+            Some(FontSource::File(path)) => Font::new(lib, path, size, config),
+            Some(FontSource::Bytes(bytes)) => Font::from_bytes(lib, bytes.clone(), size, config),
+            None => Font::new(lib, name, size, config),
+        };
+        font.unwrap_or_else(|err| {
+            eprintln!(
+                "winkel: failed to load font '{}' ({}), using the default font",
+                name, err
+            );
+            Font::default_font(lib, size, config)
+        })
+    }
+}
+
+// bitmap color fonts only come in fixed strikes, those get picked and scaled instead
+fn set_size(face: &mut freetype::Face, size: u32) -> FtResult<f64> {
+    if face.set_pixel_sizes(0, size).is_ok() {
+        return Ok(1.0);
+    }
+    let raw = face.raw_mut();
+    let strikes = if raw.available_sizes.is_null() {
+        &[][..]
+    } else {
+        unsafe { std::slice::from_raw_parts(raw.available_sizes, raw.num_fixed_sizes as usize) }
+    };
+    let target = size as f64;
+    let strike = strikes.iter().enumerate().min_by(|(_, a), (_, b)| {
+        let a = (a.y_ppem as f64 / 64.0 - target).abs();
+        let b = (b.y_ppem as f64 / 64.0 - target).abs();
+        a.partial_cmp(&b).unwrap()
+    });
+    match strike {
+        Some((index, strike)) => {
+            let ppem = strike.y_ppem as f64 / 64.0;
+            unsafe { freetype::ffi::FT_Select_Size(raw, index as i32) };
+            Ok(if ppem > 0.0 { target / ppem } else { 1.0 })
+        }
+        None => Err(freetype::Error::InvalidPixelSize),
+    }
+}
+
+// freetype hands out premultiplied BGRA, textures hold straight RGBA
+fn bgra_to_rgba(data: &[u8]) -> Vec<u8> {
+    data.chunks_exact(4)
+        .flat_map(|px| {
+            let (b, g, r, a) = (px[0], px[1], px[2], px[3]);
+            let unpremultiply = |c: u8| {
+                if a == 0 {
+                    0
+                } else {
+                    (c as u32 * 255 / a as u32).min(255) as u8
+                }
+            };
+            [unpremultiply(r), unpremultiply(g), unpremultiply(b), a]
+        })
+        .collect()
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum GlyphKey {
+    Char(char),
+    Index(u32),
+}
+
+#[cfg(feature = "shaping")]
+pub struct ShapedGlyph {
+    pub index: u32,
+    pub x_offset: f64,
+    pub y_offset: f64,
+    pub advance: f64,
+}
+
+// an unhinted grayscale glyph bitmap, rows are tightly packed
+#[cfg(any(feature = "sdf", feature = "wgpu"))]
+pub struct GlyphCoverage {
+    pub left: i32,
+    pub top: i32,
+    pub width: usize,
+    pub height: usize,
+    pub data: Vec<u8>,
+}
 
 #[derive(Debug)]
 pub struct Font {
     face: freetype::Face,
     size: u32,
-    characters: HashMap<char, Character>,
+    config: FontConfig,
+    characters: HashMap<GlyphKey, Character>,
+    advances: HashMap<GlyphKey, f64>,
+    glyph_scale: f64,
+    #[cfg(feature = "shaping")]
+    data: Rc<Vec<u8>>,
+}
+
+// a glyph as freetype rendered it, before scaling. color glyphs hold RGBA pixels,
+// the others one coverage byte per pixel
+struct GlyphBitmap {
+    left: i32,
+    top: i32,
+    width: i32,
+    height: i32,
+    advance: i32,
+    color: bool,
+    pixels: Vec<u8>,
 }
 
 #[derive(Debug)]
@@ -15,45 +222,233 @@ pub struct Character {
     width: i32,
     height: i32,
     advance: i32,
+    color: bool,
     texture: Texture,
 }
 
 impl Font {
-    pub fn new(file: &str, size: u32) -> Self {
-        let lib = freetype::Library::init().unwrap();
-        let face = lib.new_face(file, 0).unwrap();
-        face.set_pixel_sizes(0, size).unwrap();
+    #[cfg(not(feature = "shaping"))]
+    pub fn new(
+        lib: &freetype::Library,
+        file: &str,
+        size: u32,
+        config: FontConfig,
+    ) -> FtResult<Self> {
+        let mut face = lib.new_face(file, 0)?;
+        let glyph_scale = set_size(&mut face, size)?;
 
-        Font {
+        Ok(Font {
             face,
             size,
+            config,
             characters: HashMap::new(),
-        }
+            advances: HashMap::new(),
+            glyph_scale,
+        })
+    }
+
+    #[cfg(feature = "shaping")]
+    pub fn new(
+        lib: &freetype::Library,
+        file: &str,
+        size: u32,
+        config: FontConfig,
+    ) -> FtResult<Self> {
+        let bytes = std::fs::read(file).map_err(|_| freetype::Error::CannotOpenResource)?;
+        Font::from_bytes(lib, Rc::new(bytes), size, config)
+    }
+
+    pub fn from_bytes(
+        lib: &freetype::Library,
+        bytes: Rc<Vec<u8>>,
+        size: u32,
+        config: FontConfig,
+    ) -> FtResult<Self> {
+        let mut face = lib.new_memory_face(bytes.clone(), 0)?;
+        let glyph_scale = set_size(&mut face, size)?;
+
+        Ok(Font {
+            face,
+            size,
+            config,
+            characters: HashMap::new(),
+            advances: HashMap::new(),
+            glyph_scale,
+            #[cfg(feature = "shaping")]
+            data: bytes,
+        })
+    }
+
+    pub fn default_font(lib: &freetype::Library, size: u32, config: FontConfig) -> Self {
+        Font::from_bytes(lib, Rc::new(DEFAULT_FONT.to_vec()), size, config)
+            .expect("bundled default font failed to load")
+    }
+
+    pub fn has_char(&self, ch: char) -> bool {
+        self.face.get_char_index(ch as usize) != 0
     }
 
     pub fn get_char(&mut self, ch: char) -> &Character {
-        if !self.characters.contains_key(&ch) {
-            self.face
-                .load_char(ch as usize, freetype::face::LoadFlag::RENDER)
-                .unwrap();
-            let glyph = self.face.glyph();
-            let bmp = glyph.bitmap();
+        self.get_glyph(GlyphKey::Char(ch))
+    }
+
+    pub fn get_glyph(&mut self, key: GlyphKey) -> &Character {
+        if !self.characters.contains_key(&key) {
+            let bitmap = self.rasterize(key);
+            let texture = if bitmap.color {
+                Texture::from_rgba(bitmap.width, bitmap.height, &bitmap.pixels)
+            } else {
+                Texture::new(bitmap.width, bitmap.height, &bitmap.pixels)
+            };
+            let scale = |value: i32| (value as f64 * self.glyph_scale).round() as i32;
             let renderchar = Character {
-                left: glyph.bitmap_left(),
-                top: glyph.bitmap_top(),
-                width: bmp.width(),
-                height: bmp.rows(),
-                advance: glyph.advance().x as i32,
-                texture: Texture::new(bmp.width(), bmp.rows(), bmp.buffer()),
+                left: scale(bitmap.left),
+                top: scale(bitmap.top),
+                width: scale(bitmap.width),
+                height: scale(bitmap.height),
+                advance: scale(bitmap.advance),
+                color: bitmap.color,
+                texture,
             };
-            self.characters.insert(ch, renderchar);
+            self.characters.insert(key, renderchar);
+        }
+        self.characters.get(&key).unwrap()
+    }
+
+    fn rasterize(&mut self, key: GlyphKey) -> GlyphBitmap {
+        let mut flags = self.config.load_flags();
+        if self.face.has_color() {
+            flags |= LoadFlag::COLOR;
+        }
+        match key {
+            GlyphKey::Char(ch) => self.face.load_char(ch as usize, flags),
+            GlyphKey::Index(index) => self.face.load_glyph(index, flags),
+        }
+        .unwrap();
+        let glyph = self.face.glyph();
+        let bmp = glyph.bitmap();
+        let color = bmp.pixel_mode().ok() == Some(PixelMode::Bgra);
+        GlyphBitmap {
+            left: glyph.bitmap_left(),
+            top: glyph.bitmap_top(),
+            width: bmp.width(),
+            height: bmp.rows(),
+            advance: glyph.advance().x as i32,
+            color,
+            pixels: if color {
+                bgra_to_rgba(bmp.buffer())
+            } else {
+                bmp.buffer().to_vec()
+            },
+        }
+    }
+
+    // rasterizes a glyph without caching a texture, color glyphs have no coverage to offer
+    #[cfg(any(feature = "sdf", feature = "wgpu"))]
+    pub fn coverage(&mut self, key: GlyphKey) -> Option<GlyphCoverage> {
+        let flags = LoadFlag::RENDER | LoadFlag::NO_HINTING;
+        match key {
+            GlyphKey::Char(ch) => self.face.load_char(ch as usize, flags),
+            GlyphKey::Index(index) => self.face.load_glyph(index, flags),
+        }
+        .ok()?;
+        let glyph = self.face.glyph();
+        let bmp = glyph.bitmap();
+        if bmp.pixel_mode().ok() != Some(PixelMode::Gray) {
+            return None;
         }
-        self.characters.get(&ch).unwrap()
+        let (width, height) = (bmp.width().max(0) as usize, bmp.rows().max(0) as usize);
+        let pitch = bmp.pitch().unsigned_abs() as usize;
+        let data = (0..height)
+            .flat_map(|row| &bmp.buffer()[row * pitch..row * pitch + width])
+            .copied()
+            .collect();
+        Some(GlyphCoverage {
+            left: glyph.bitmap_left(),
+            top: glyph.bitmap_top(),
+            width,
+            height,
+            data,
+        })
+    }
+
+    // advance in pixels without rasterizing, so text can be measured without a GL context
+    pub fn glyph_advance(&mut self, key: GlyphKey) -> f64 {
+        if let Some(character) = self.characters.get(&key) {
+            return character.advance as f64 / 64.0;
+        }
+        if let Some(advance) = self.advances.get(&key) {
+            return *advance;
+        }
+        let flags = self.config.load_flags() - LoadFlag::RENDER;
+        let loaded = match key {
+            GlyphKey::Char(ch) => self.face.load_char(ch as usize, flags),
+            GlyphKey::Index(index) => self.face.load_glyph(index, flags),
+        };
+        let advance = match loaded {
+            Ok(()) => self.face.glyph().advance().x as f64 / 64.0 * self.glyph_scale,
+            Err(_) => 0.0,
+        };
+        self.advances.insert(key, advance);
+        advance
+    }
+
+    // positions are in pixels, glyphs of right-to-left text come out in visual order
+    #[cfg(feature = "shaping")]
+    pub fn shape(&self, text: &str, rtl: bool) -> Vec<ShapedGlyph> {
+        let face = match rustybuzz::Face::from_slice(&self.data, 0) {
+            Some(face) => face,
+            None => return Vec::new(),
+        };
+        let scale = self.size as f64 / face.units_per_em() as f64;
+        let mut buffer = rustybuzz::UnicodeBuffer::new();
+        buffer.push_str(text);
+        if rtl {
+            buffer.set_direction(rustybuzz::Direction::RightToLeft);
+        }
+        let glyphs = rustybuzz::shape(&face, &[], buffer);
+        glyphs
+            .glyph_infos()
+            .iter()
+            .zip(glyphs.glyph_positions())
+            .map(|(info, position)| ShapedGlyph {
+                index: info.glyph_id,
+                x_offset: position.x_offset as f64 * scale,
+                y_offset: position.y_offset as f64 * scale,
+                advance: position.x_advance as f64 * scale,
+            })
+            .collect()
     }
 
     pub fn size(&self) -> u32 {
         self.size
     }
+
+    pub fn line_height(&self) -> f64 {
+        self.face
+            .size_metrics()
+            .map(|metrics| metrics.height as f64 / 64.0)
+            .unwrap_or(self.size as f64)
+    }
+
+    // offset of the line's center below the baseline and its thickness, in pixels
+    pub fn decoration_line(&self, decoration: TextDecoration) -> (f64, f64) {
+        let size = self.size as f64;
+        let raw = self.face.raw();
+        let (underline, thickness) = if raw.units_per_EM == 0 {
+            (size * 0.1, (size / 14.0).max(1.0))
+        } else {
+            let units = size / raw.units_per_EM as f64;
+            (
+                -raw.underline_position as f64 * units,
+                (raw.underline_thickness as f64 * units).max(1.0),
+            )
+        };
+        match decoration {
+            TextDecoration::None | TextDecoration::Underline => (underline, thickness),
+            TextDecoration::Strikethrough => (-size * 0.3, thickness),
+        }
+    }
 }
 
 impl Character {
@@ -76,6 +471,11 @@ impl Character {
         self.advance
     }
 
+    // color glyphs carry their own colors and aren't tinted with the text color
+    pub fn is_color(&self) -> bool {
+        self.color
+    }
+
     pub fn top(&self) -> i32 {
         self.top
     }
@@ -83,3 +483,87 @@ impl Character {
         self.left
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn snapped_pen_positions_are_whole() {
+        let snapped = FontConfig::from(Hinting::Normal);
+        let unsnapped = FontConfig::from(Hinting::None);
+        for i in 0..20 {
+            let pen = 10.0 + i as f64 * 0.37;
+            assert_eq!(snapped.snap(pen).fract(), 0.0);
+            assert!((snapped.snap(pen) - pen).abs() <= 0.5);
+            assert_eq!(unsnapped.snap(pen), pen);
+        }
+    }
+
+    #[test]
+    fn fonts_share_one_library() {
+        let registry = FontRegistry::new();
+        let config = FontConfig::from(Hinting::Normal);
+        let first = shared_library();
+        let small = registry.load(&first, "Raleway-Regular.ttf", 16, config);
+        let second = shared_library();
+        let large = registry.load(&second, "Raleway-Regular.ttf", 32, config);
+        assert!(small.line_height() < large.line_height());
+        assert!(Rc::ptr_eq(&first, &second));
+        assert_eq!(library_inits(), 1);
+    }
+
+    #[cfg(feature = "shaping")]
+    #[test]
+    fn kerning_tightens_known_pairs() {
+        let config = FontConfig::from(Hinting::None);
+        let font = FontRegistry::new().load(&shared_library(), "Raleway-Regular.ttf", 32, config);
+        let width = |text| {
+            font.shape(text, false)
+                .iter()
+                .map(|g| g.advance)
+                .sum::<f64>()
+        };
+        // each letter shaped on its own, the same as the sum of their advances
+        let unkerned = width("A") + width("V");
+        assert!(width("AV") < unkerned - 0.5);
+    }
+
+    #[test]
+    fn color_glyphs_rasterize_to_rgba() {
+        let config = FontConfig::from(Hinting::Normal);
+        let registry = FontRegistry::new();
+        let mut font = registry.load(&shared_library(), "Raleway-Regular.ttf", 16, config);
+        let plain = font.rasterize(GlyphKey::Char('a'));
+        assert!(!plain.color);
+        assert_eq!(plain.pixels.len(), (plain.width * plain.height) as usize);
+        // freetype's premultiplied BGRA comes out as straight RGBA
+        assert_eq!(bgra_to_rgba(&[0, 0, 64, 128]), [127, 0, 0, 128]);
+        let path = "/usr/share/fonts/truetype/noto/NotoColorEmoji.ttf";
+        if !std::path::Path::new(path).exists() {
+            return eprintln!("{} not found, skipping the color glyph", path);
+        }
+        let mut emoji = registry.load(&shared_library(), path, 16, config);
+        let color = emoji.rasterize(GlyphKey::Char('\u{1f600}'));
+        assert!(color.color);
+        assert_eq!(
+            color.pixels.len(),
+            (color.width * color.height * 4) as usize
+        );
+    }
+
+    #[test]
+    fn missing_fonts_fall_back_to_the_default() {
+        let config = FontConfig::from(Hinting::Normal);
+        let lib = shared_library();
+        assert!(Font::new(&lib, "does-not-exist.ttf", 16, config).is_err());
+        let mut font = FontRegistry::new().load(&lib, "does-not-exist.ttf", 16, config);
+        let mut default = Font::default_font(&lib, 16, config);
+        assert!(font.has_char('a'));
+        assert_eq!(font.line_height(), default.line_height());
+        assert_eq!(
+            font.glyph_advance(GlyphKey::Char('a')),
+            default.glyph_advance(GlyphKey::Char('a'))
+        );
+    }
+}
@@ -0,0 +1,56 @@
+use super::utils::Texture;
+use std::collections::HashMap;
+use std::fs::File;
+use std::rc::Rc;
+
+// decodes a png into rgba rows, top row first
+fn decode_png(path: &str) -> Result<(u32, u32, Vec<u8>), String> {
+    let file = File::open(path).map_err(|err| err.to_string())?;
+    let mut decoder = png::Decoder::new(file);
+    decoder.set_transformations(png::Transformations::normalize_to_color8());
+    let mut reader = decoder.read_info().map_err(|err| err.to_string())?;
+    let mut buffer = vec![0; reader.output_buffer_size()];
+    let info = reader
+        .next_frame(&mut buffer)
+        .map_err(|err| err.to_string())?;
+    let pixels = &buffer[..info.buffer_size()];
+    let rgba = match info.color_type {
+        png::ColorType::Rgba => pixels.to_vec(),
+        png::ColorType::Rgb => pixels
+            .chunks_exact(3)
+            .flat_map(|px| [px[0], px[1], px[2], 255])
+            .collect(),
+        png::ColorType::GrayscaleAlpha => pixels
+            .chunks_exact(2)
+            .flat_map(|px| [px[0], px[0], px[0], px[1]])
+            .collect(),
+        png::ColorType::Grayscale => pixels.iter().flat_map(|v| [*v, *v, *v, 255]).collect(),
+        png::ColorType::Indexed => return Err(String::from("unexpanded palette")),
+    };
+    Ok((info.width, info.height, rgba))
+}
+
+// textures are loaded on first use, failures are remembered so they are reported once
+#[derive(Default)]
+pub struct ImageCache {
+    textures: HashMap<Rc<str>, Option<Rc<Texture>>>,
+}
+
+impl ImageCache {
+    pub fn get(&mut self, path: &Rc<str>) -> Option<Rc<Texture>> {
+        self.textures
+            .entry(path.clone())
+            .or_insert_with(|| match decode_png(path) {
+                Ok((width, height, rgba)) => Some(Rc::new(Texture::from_rgba(
+                    width as i32,
+                    height as i32,
+                    &rgba,
+                ))),
+                Err(err) => {
+                    eprintln!("winkel: failed to load image '{}' ({})", path, err);
+                    None
+                }
+            })
+            .clone()
+    }
+}
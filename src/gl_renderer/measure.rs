@@ -0,0 +1,232 @@
+use super::font::{shared_library, Font, FontConfig, FontRegistry, GlyphKey};
+use super::{next_tab_stop, FontDescription};
+use crate::{TabStop, TextStyle};
+use std::cell::RefCell;
+use std::collections::HashMap;
+use std::rc::Rc;
+use unicode_segmentation::UnicodeSegmentation;
+
+thread_local! {
+    static MEASURER: RefCell<Option<TextMeasurer>> = const { RefCell::new(None) };
+}
+
+// measures text in logical pixels with the same fonts the renderer uses, but without textures
+struct TextMeasurer {
+    library: Rc<freetype::Library>,
+    registry: FontRegistry,
+    fonts: HashMap<FontDescription, Font>,
+}
+
+impl TextMeasurer {
+    fn new() -> Self {
+        TextMeasurer {
+            library: shared_library(),
+            registry: FontRegistry::new(),
+            fonts: HashMap::new(),
+        }
+    }
+
+    fn load_font(&mut self, name: &str, size: u32, config: FontConfig) -> FontDescription {
+        let description = FontDescription {
+            name: String::from(name),
+            size,
+            config,
+        };
+        if !self.fonts.contains_key(&description) {
+            let font = self.registry.load(&self.library, name, size, config);
+            self.fonts.insert(description.clone(), font);
+        }
+        description
+    }
+
+    fn tab_width(&mut self, style: &TextStyle, description: &FontDescription) -> f64 {
+        match style.tab_stop {
+            TabStop::Pixels(width) => width,
+            TabStop::Spaces(count) => {
+                let font = self.fonts.get_mut(description).unwrap();
+                count as f64 * font.glyph_advance(GlyphKey::Char(' '))
+            }
+        }
+    }
+
+    fn load_chain(&mut self, style: &TextStyle) -> Vec<FontDescription> {
+        let config = FontConfig::from(style.hinting);
+        let names: Vec<String> = self.registry.chain(style.font).map(String::from).collect();
+        names
+            .iter()
+            .map(|name| self.load_font(name, style.size, config))
+            .collect()
+    }
+
+    fn measure(&mut self, text: &str, style: &TextStyle) -> (f64, f64) {
+        let descriptions = self.load_chain(style);
+        let tab_width = self.tab_width(style, &descriptions[0]);
+        let line_height = self.fonts[&descriptions[0]].line_height();
+        let mut width: f64 = 0.0;
+        let mut lines = 0;
+        for line in text.split('\n') {
+            let line_width = self.line_width(line.trim_end_matches('\r'), tab_width, &descriptions);
+            width = width.max(line_width);
+            lines += 1;
+        }
+        (width, lines as f64 * line_height)
+    }
+
+    // byte index and x offset of every grapheme boundary in a line, including both ends
+    fn grapheme_offsets(&mut self, line: &str, style: &TextStyle) -> Vec<(usize, f64)> {
+        let descriptions = self.load_chain(style);
+        let tab_width = self.tab_width(style, &descriptions[0]);
+        let mut offset: f64 = 0.0;
+        let mut offsets = vec![(0, 0.0)];
+        for (index, cluster) in line.grapheme_indices(true) {
+            let ch = cluster.chars().next().unwrap();
+            offset = if ch == '\t' {
+                next_tab_stop(offset, tab_width, false)
+            } else {
+                offset + self.advance(ch, &descriptions)
+            };
+            offsets.push((index + cluster.len(), offset));
+        }
+        offsets
+    }
+
+    fn advance(&mut self, ch: char, descriptions: &[FontDescription]) -> f64 {
+        let description = descriptions
+            .iter()
+            .find(|description| self.fonts[*description].has_char(ch))
+            .unwrap_or(&descriptions[0]);
+        self.fonts
+            .get_mut(description)
+            .unwrap()
+            .glyph_advance(GlyphKey::Char(ch))
+    }
+
+    // mirrors the unshaped layout: one advance per grapheme, taken from the first font having it
+    #[cfg(not(feature = "shaping"))]
+    fn line_width(&mut self, line: &str, tab_width: f64, descriptions: &[FontDescription]) -> f64 {
+        let mut offset: f64 = 0.0;
+        for cluster in line.graphemes(true) {
+            let ch = cluster.chars().next().unwrap();
+            if ch == '\t' {
+                offset = next_tab_stop(offset, tab_width, false);
+                continue;
+            }
+            offset += self.advance(ch, descriptions);
+        }
+        offset
+    }
+
+    #[cfg(feature = "shaping")]
+    fn line_width(&mut self, line: &str, tab_width: f64, descriptions: &[FontDescription]) -> f64 {
+        let font = &self.fonts[&descriptions[0]];
+        let mut offset: f64 = 0.0;
+        for (i, segment) in line.split('\t').enumerate() {
+            if i > 0 {
+                offset = next_tab_stop(offset, tab_width, false);
+            }
+            offset += font
+                .shape(segment, false)
+                .iter()
+                .map(|glyph| glyph.advance)
+                .sum::<f64>();
+        }
+        offset
+    }
+}
+
+// width and height the text takes up when rendered, in logical pixels
+pub fn measure_text(text: &str, style: &TextStyle) -> (f64, f64) {
+    MEASURER.with(|measurer| {
+        measurer
+            .borrow_mut()
+            .get_or_insert_with(TextMeasurer::new)
+            .measure(text, style)
+    })
+}
+
+// caret positions for a single line of unshaped text, in logical pixels
+pub fn grapheme_offsets(line: &str, style: &TextStyle) -> Vec<(usize, f64)> {
+    MEASURER.with(|measurer| {
+        measurer
+            .borrow_mut()
+            .get_or_insert_with(TextMeasurer::new)
+            .grapheme_offsets(line, style)
+    })
+}
+
+pub fn line_height(style: &TextStyle) -> f64 {
+    MEASURER.with(|measurer| {
+        let mut measurer = measurer.borrow_mut();
+        let measurer = measurer.get_or_insert_with(TextMeasurer::new);
+        let descriptions = measurer.load_chain(style);
+        measurer.fonts[&descriptions[0]].line_height()
+    })
+}
+
+// the renderer keeps the measurer's fonts in sync with its own
+pub(super) fn set_registry(registry: &FontRegistry) {
+    MEASURER.with(|measurer| {
+        let mut measurer = measurer.borrow_mut();
+        let measurer = measurer.get_or_insert_with(TextMeasurer::new);
+        measurer.registry = registry.clone();
+        measurer.fonts.clear();
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{Hinting, TextDecoration, TextDirection};
+    use std::rc::Rc;
+
+    fn style(font: &str) -> TextStyle<'_> {
+        TextStyle {
+            font,
+            color: [0.0, 0.0, 0.0, 1.0],
+            size: 16,
+            hinting: Hinting::Normal,
+            tab_stop: TabStop::Spaces(4),
+            direction: TextDirection::Ltr,
+            decoration: TextDecoration::None,
+            decoration_color: None,
+        }
+    }
+
+    #[test]
+    fn measures_fonts_loaded_from_bytes() {
+        let bytes: &[u8] = include_bytes!("../../Raleway-Regular.ttf");
+        let config = FontConfig::from(Hinting::Normal);
+        assert!(Font::from_bytes(&shared_library(), Rc::new(bytes.to_vec()), 16, config).is_ok());
+        let mut registry = FontRegistry::new();
+        registry.add_bytes("embedded", bytes);
+        set_registry(&registry);
+        let (width, height) = measure_text("hello", &style("embedded"));
+        assert!(width > 0.0);
+        assert!(height > 0.0);
+        assert!(measure_text("hello hello", &style("embedded")).0 > width);
+    }
+
+    #[test]
+    fn newlines_add_a_line_of_height() {
+        let style = style("Raleway-Regular.ttf");
+        let (_, one_line) = measure_text("a", &style);
+        let (width, two_lines) = measure_text("a\nb", &style);
+        assert_eq!(one_line, line_height(&style));
+        assert_eq!(two_lines, 2.0 * one_line);
+        // the width is the widest line, not both lines side by side
+        assert!(width < measure_text("ab", &style).0);
+    }
+
+    #[test]
+    fn combining_marks_do_not_advance() {
+        let style = style("Raleway-Regular.ttf");
+        let text = "cafe\u{301}";
+        let offsets = grapheme_offsets(text, &style);
+        // one caret step per grapheme, the accent shares the e's
+        assert_eq!(offsets.len() - 1, 4);
+        assert!(offsets.len() - 1 < text.chars().count());
+        let (end, width) = *offsets.last().unwrap();
+        assert_eq!(end, text.len());
+        assert_eq!(width, grapheme_offsets("cafe", &style).last().unwrap().1);
+    }
+}
@@ -1,36 +1,49 @@
+use super::color::{self, Color};
 use super::Renderer;
-use super::{Style, TextStyle};
+use super::{
+    nine_patch_regions, BorderRadius, ComputedWidget, LineStroke, NinePatchQuad, Quad, Style,
+    TabStop, TextDecoration, TextDirection, TextStyle,
+};
+use std::cell::Cell;
 use std::collections::HashMap;
+use std::rc::Rc;
+#[cfg(not(feature = "shaping"))]
+use unicode_segmentation::UnicodeSegmentation;
 
 mod font;
+mod image;
+mod measure;
+#[cfg(feature = "sdf")]
+mod sdf;
 mod utils;
+#[cfg(feature = "wgpu")]
+mod wgpu_renderer;
 
-use font::Font;
+pub use font::FontRegistry;
+use font::{shared_library, Font, FontConfig, GlyphKey};
+use image::ImageCache;
+pub(crate) use measure::{grapheme_offsets, line_height, measure_text};
+#[cfg(feature = "sdf")]
+pub use sdf::SdfStyle;
+#[cfg(feature = "sdf")]
+use sdf::{SdfAtlas, SDF_SIZE, SDF_SPREAD};
+pub use utils::Texture;
 use utils::{
     shader::{Program, Shader},
-    VertexArray,
+    Framebuffer, InstanceBuffer, VertexArray,
 };
+#[cfg(feature = "wgpu")]
+pub use wgpu_renderer::WgpuRenderer;
 
-#[derive(Eq, PartialEq, Hash)]
-struct FontDescription {
-    size: u32,
-    name: String,
-}
+const DEPTH_LAYERS: usize = 1 << 16;
 
-pub struct GlRenderer<'a> {
-    quad: VertexArray,
-    fonts: HashMap<FontDescription, Font>,
-    rect_shader: Program<'a>,
-    text_shader: Program<'a>,
+// maps z layers to normalized depth, higher layers are closer to the viewer
+fn z_to_depth(z: usize) -> f64 {
+    let z = z.min(DEPTH_LAYERS - 1);
+    1.0 - 2.0 * (z + 1) as f64 / DEPTH_LAYERS as f64
 }
 
-impl<'a, 'fonts> GlRenderer<'a> {
-    pub fn new() -> Self {
-        let vertex_data: [f32; 18] = [
-            -1.0, 1.0, 0.0, -1.0, -1.0, 0.0, 1.0, -1.0, 0.0, 1.0, -1.0, 0.0, 1.0, 1.0, 0.0, -1.0,
-            1.0, 0.0,
-        ];
-        let vert_shader_src = "#version 330 core
+const VERT_SHADER_SRC: &str = "#version 330 core
         in vec3 position;
         uniform mat4 transform;
         out vec2 pass_pos;
@@ -40,67 +53,523 @@ impl<'a, 'fonts> GlRenderer<'a> {
             pass_pos = position.xy;
             gl_Position = transform * vec4(position, 1.0);
         }";
-        let rect_frag_shader_src = "#version 330 core
+
+const TEXT_FRAG_SHADER_SRC: &str = "#version 330 core
+        out vec4 out_color;
+        uniform vec4 color;
+        uniform sampler2D tex;
         in vec2 pass_pos;
+        
+        void main()
+        {
+            out_color = mix(vec4(0.0, 0.0, 0.0, 0.0), color, texture2D(tex, pass_pos * 0.5 * vec2(1, -1) + 0.5).x);
+        }";
+
+const TEXT_PREMULTIPLIED_FRAG_SHADER_SRC: &str = "#version 330 core
         out vec4 out_color;
         uniform vec4 color;
+        uniform sampler2D tex;
+        in vec2 pass_pos;
+        
+        void main()
+        {
+            out_color = vec4(color.rgb * color.a, color.a) * texture2D(tex, pass_pos * 0.5 * vec2(1, -1) + 0.5).x;
+        }";
 
-        uniform float border_radius;
-        uniform float x;
-        uniform float y;
-        uniform float width;
-        uniform float height;
-        uniform float win_height;
+// color glyphs keep their own colors, only the text's alpha fades them
+const COLOR_TEXT_FRAG_SHADER_SRC: &str = "#version 330 core
+        out vec4 out_color;
+        uniform vec4 color;
+        uniform float premultiplied;
+        uniform sampler2D tex;
+        in vec2 pass_pos;
         
         void main()
         {
-            vec2 corner0 = vec2(x, win_height - y - height);
-            vec2 corner1 = vec2(x, win_height - y);
-            vec2 corner2 = vec2(x + width, win_height - y - height);
-            vec2 corner3 = vec2(x + width, win_height - y);
-            out_color = color;
-            float border = min(min(width, height) / 2, border_radius);
-            
-            if (distance(gl_FragCoord.xy, corner0 + vec2(border)) < border) {
-                return;
+            vec4 glyph = texture2D(tex, pass_pos * 0.5 * vec2(1, -1) + 0.5);
+            float alpha = glyph.a * color.a;
+            if (premultiplied > 0.5) {
+                out_color = vec4(glyph.rgb * alpha, alpha);
+            } else {
+                out_color = vec4(glyph.rgb, alpha);
             }
-            if (distance(gl_FragCoord.xy, corner1 + vec2(border, -border)) < border) {
-                return;
+        }";
+
+// the texture holds distances to the outline, 0.5 being the outline itself. widths are in
+// the same units, so outlines and glows cost no more than plain glyphs
+#[cfg(feature = "sdf")]
+const SDF_TEXT_FRAG_SHADER_SRC: &str = "#version 330 core
+        out vec4 out_color;
+        uniform vec4 color;
+        uniform vec4 outline_color;
+        uniform float outline_width;
+        uniform vec4 glow_color;
+        uniform float glow_width;
+        uniform float premultiplied;
+        uniform sampler2D tex;
+        in vec2 pass_pos;
+        
+        void main()
+        {
+            float dist = texture2D(tex, pass_pos * 0.5 * vec2(1, -1) + 0.5).x;
+            float smoothing = max(fwidth(dist) * 0.5, 0.001);
+            float fill = smoothstep(0.5 - smoothing, 0.5 + smoothing, dist);
+            vec4 result = vec4(0.0);
+            if (glow_width > 0.0) {
+                float glow = smoothstep(0.5 - glow_width, 0.5, dist);
+                result = vec4(glow_color.rgb, 1.0) * glow_color.a * glow;
             }
-            if (distance(gl_FragCoord.xy, corner2 + vec2(-border, border)) < border) {
-                return;
+            if (outline_width > 0.0) {
+                float edge = 0.5 - outline_width;
+                float outline = smoothstep(edge - smoothing, edge + smoothing, dist);
+                result = mix(result, vec4(outline_color.rgb, 1.0) * outline_color.a, outline);
             }
-            if (distance(gl_FragCoord.xy, corner3 - vec2(border)) < border) {
-                return;
+            result = mix(result, vec4(color.rgb, 1.0) * color.a, fill);
+            if (premultiplied > 0.5) {
+                out_color = result;
+            } else if (result.a > 0.0) {
+                out_color = vec4(result.rgb / result.a, result.a);
+            } else {
+                discard;
             }
-            if (gl_FragCoord.x < width - border + x && gl_FragCoord.x > x + border && gl_FragCoord.y > win_height - y - height && gl_FragCoord.y < win_height - y) {
-                return;
+        }";
+
+// per instance: bounds in top left origin framebuffer pixels, color, corner radii, depth
+const QUAD_VERT_SHADER_SRC: &str = "#version 330 core
+        layout(location = 0) in vec3 position;
+        layout(location = 1) in vec4 rect;
+        layout(location = 2) in vec4 color;
+        layout(location = 3) in vec4 radii;
+        layout(location = 4) in float depth;
+        uniform float win_width;
+        uniform float win_height;
+        flat out vec4 pass_rect;
+        flat out vec4 pass_color;
+        flat out vec4 pass_radii;
+        
+        void main()
+        {
+            pass_rect = rect;
+            pass_color = color;
+            pass_radii = radii;
+            vec2 pixel = rect.xy + (position.xy * vec2(0.5, -0.5) + 0.5) * rect.zw;
+            gl_Position = vec4(
+                pixel.x / win_width * 2.0 - 1.0,
+                1.0 - pixel.y / win_height * 2.0,
+                depth,
+                1.0
+            );
+        }";
+
+// the rect shader's rounding without its texture support
+const QUAD_FRAG_SHADER_SRC: &str = "#version 330 core
+        flat in vec4 pass_rect;
+        flat in vec4 pass_color;
+        flat in vec4 pass_radii;
+        out vec4 out_color;
+        uniform float win_height;
+        uniform float premultiplied;
+        
+        void main()
+        {
+            vec2 pos = vec2(gl_FragCoord.x, win_height - gl_FragCoord.y);
+            vec2 half_size = pass_rect.zw / 2.0;
+            vec2 p = pos - pass_rect.xy - half_size;
+            float corner = p.x < 0.0
+                ? (p.y < 0.0 ? pass_radii.x : pass_radii.z)
+                : (p.y < 0.0 ? pass_radii.y : pass_radii.w);
+            float radius = min(min(pass_rect.z, pass_rect.w) / 2.0, corner);
+            vec2 q = abs(p) - half_size + vec2(radius);
+            float dist = length(max(q, 0.0)) + min(max(q.x, q.y), 0.0) - radius;
+            float coverage = 1.0 - smoothstep(-0.5, 0.5, dist);
+            if (coverage <= 0.0) {
+                discard;
             }
-            if (gl_FragCoord.x > x && gl_FragCoord.x < x + width && gl_FragCoord.y < win_height - y - border && gl_FragCoord.y > win_height - y - height + border) {
-                return;
+            if (premultiplied > 0.5) {
+                out_color = pass_color * coverage;
+            } else {
+                out_color = vec4(pass_color.rgb, pass_color.a * coverage);
             }
-            discard;
         }";
-        let text_frag_shader_src = "#version 330 core
+
+const LINE_FRAG_SHADER_SRC: &str = "#version 330 core
         out vec4 out_color;
         uniform vec4 color;
-        uniform sampler2D tex;
+        
+        void main()
+        {
+            out_color = color;
+        }";
+
+const ELLIPSE_FRAG_SHADER_SRC: &str = "#version 330 core
         in vec2 pass_pos;
+        out vec4 out_color;
+        uniform vec4 color;
+        uniform float premultiplied;
         
         void main()
         {
-            out_color = mix(vec4(0.0, 0.0, 0.0, 0.0), color, texture2D(tex, pass_pos * 0.5 * vec2(1, -1) + 0.5).x);
+            float dist = length(pass_pos);
+            float edge = fwidth(dist);
+            float coverage = 1.0 - smoothstep(1.0 - edge, 1.0, dist);
+            if (coverage <= 0.0) {
+                discard;
+            }
+            if (premultiplied > 0.5) {
+                out_color = color * coverage;
+            } else {
+                out_color = vec4(color.rgb, color.a * coverage);
+            }
         }";
+
+// sharper joins than this are clamped instead of producing long spikes
+const MITER_LIMIT: f64 = 4.0;
+
+// triangle list (x, y, 0) covering the polyline, with mitered joins
+fn polyline_triangles(points: &[(f64, f64)], width: f64) -> Vec<f32> {
+    let normal = |a: (f64, f64), b: (f64, f64)| {
+        let (dx, dy) = (b.0 - a.0, b.1 - a.1);
+        let length = (dx * dx + dy * dy).sqrt();
+        if length == 0.0 {
+            (0.0, 0.0)
+        } else {
+            (-dy / length, dx / length)
+        }
+    };
+    let half = width / 2.0;
+    let offsets: Vec<(f64, f64)> = (0..points.len())
+        .map(|i| {
+            let before = if i > 0 {
+                normal(points[i - 1], points[i])
+            } else {
+                (0.0, 0.0)
+            };
+            let after = if i + 1 < points.len() {
+                normal(points[i], points[i + 1])
+            } else {
+                (0.0, 0.0)
+            };
+            let (mx, my) = (before.0 + after.0, before.1 + after.1);
+            let length = (mx * mx + my * my).sqrt();
+            if length == 0.0 {
+                return (before.0 * half, before.1 * half);
+            }
+            let (mx, my) = (mx / length, my / length);
+            let reference = if before == (0.0, 0.0) { after } else { before };
+            let scale = (half / (mx * reference.0 + my * reference.1)).min(half * MITER_LIMIT);
+            (mx * scale, my * scale)
+        })
+        .collect();
+    let mut vertices = Vec::with_capacity(points.len().saturating_sub(1) * 18);
+    for i in 0..points.len().saturating_sub(1) {
+        let (a, b) = (points[i], points[i + 1]);
+        let (oa, ob) = (offsets[i], offsets[i + 1]);
+        let corners = [
+            (a.0 + oa.0, a.1 + oa.1),
+            (a.0 - oa.0, a.1 - oa.1),
+            (b.0 + ob.0, b.1 + ob.1),
+            (a.0 - oa.0, a.1 - oa.1),
+            (b.0 - ob.0, b.1 - ob.1),
+            (b.0 + ob.0, b.1 + ob.1),
+        ];
+        for (x, y) in corners {
+            vertices.extend_from_slice(&[x as f32, y as f32, 0.0]);
+        }
+    }
+    vertices
+}
+
+// floats per quad instance: rect, color, radii, depth
+const QUAD_ATTRIBUTES: [usize; 4] = [4, 4, 4, 1];
+
+// fonts are rasterized at the framebuffer resolution
+fn scaled_font_size(size: u32, scale_factor: f64) -> u32 {
+    (size as f64 * scale_factor).round().max(1.0) as u32
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum BlendMode {
+    Straight,
+    Premultiplied,
+}
+
+impl BlendMode {
+    fn text_fragment_source(self) -> &'static str {
+        match self {
+            BlendMode::Straight => TEXT_FRAG_SHADER_SRC,
+            BlendMode::Premultiplied => TEXT_PREMULTIPLIED_FRAG_SHADER_SRC,
+        }
+    }
+
+    fn apply(self) {
         unsafe {
-            gl::Enable(gl::BLEND);
-            gl::Enable(gl::MULTISAMPLE);
-            gl::BlendFunc(gl::SRC_ALPHA, gl::ONE_MINUS_SRC_ALPHA);
+            match self {
+                // alpha accumulates like premultiplied alpha, so drawing over a transparent
+                // clear leaves coverage the compositor can blend
+                BlendMode::Straight => gl::BlendFuncSeparate(
+                    gl::SRC_ALPHA,
+                    gl::ONE_MINUS_SRC_ALPHA,
+                    gl::ONE,
+                    gl::ONE_MINUS_SRC_ALPHA,
+                ),
+                BlendMode::Premultiplied => gl::BlendFunc(gl::ONE, gl::ONE_MINUS_SRC_ALPHA),
+            }
+        }
+    }
+}
+
+fn apply_gl_state(blend_mode: BlendMode) {
+    unsafe {
+        gl::Enable(gl::BLEND);
+        gl::Enable(gl::MULTISAMPLE);
+        gl::Enable(gl::DEPTH_TEST);
+        gl::DepthFunc(gl::LEQUAL);
+    }
+    blend_mode.apply();
+}
+
+// right-to-left text grows towards negative offsets
+fn next_tab_stop(offset: f64, tab_width: f64, rtl: bool) -> f64 {
+    if tab_width <= 0.0 {
+        return offset;
+    }
+    let distance = ((offset.abs() / tab_width).floor() + 1.0) * tab_width;
+    if rtl {
+        -distance
+    } else {
+        distance
+    }
+}
+
+#[cfg(not(feature = "shaping"))]
+const ZERO_WIDTH_JOINER: char = '\u{200d}';
+
+// selectors pick a presentation of the previous char and have no glyph of their own
+#[cfg(not(feature = "shaping"))]
+fn is_variation_selector(ch: char) -> bool {
+    matches!(ch, '\u{fe00}'..='\u{fe0f}' | '\u{e0100}'..='\u{e01ef}')
+}
+
+struct PlacedGlyph {
+    font: usize,
+    key: GlyphKey,
+    x: f64,
+    y: f64,
+}
+
+#[derive(Clone, Eq, PartialEq, Hash)]
+struct FontDescription {
+    size: u32,
+    name: String,
+    config: FontConfig,
+}
+
+// places glyphs in pixels relative to the text's origin, only the fonts' metrics are needed
+#[cfg(not(feature = "shaping"))]
+fn layout_glyphs(
+    fonts: &mut HashMap<FontDescription, Font>,
+    text: &str,
+    style: &TextStyle,
+    descriptions: &[FontDescription],
+    tab_width: f64,
+) -> Vec<PlacedGlyph> {
+    let line_height = fonts[&descriptions[0]].line_height();
+    let rtl = style.direction == TextDirection::Rtl;
+    let mut glyphs = Vec::new();
+    let mut offset: f64 = 0.0;
+    let mut line_offset: f64 = 0.0;
+    for cluster in text.graphemes(true) {
+        let mut chars = cluster.chars();
+        let ch = chars.next().unwrap();
+        if ch == '\n' || cluster == "\r\n" {
+            offset = 0.0;
+            line_offset += line_height;
+            continue;
+        }
+        if ch == '\t' {
+            offset = next_tab_stop(offset, tab_width, rtl);
+            continue;
+        }
+        let (font, advance) = fallback_char(fonts, ch, descriptions);
+        if rtl {
+            offset -= advance;
+        }
+        let base_x = offset;
+        glyphs.push(PlacedGlyph {
+            font,
+            key: GlyphKey::Char(ch),
+            x: base_x,
+            y: line_offset,
+        });
+        if !rtl {
+            offset += advance;
+        }
+        // without shaping a joined sequence can't become one glyph, so only its first part is drawn
+        if cluster.contains(ZERO_WIDTH_JOINER) {
+            continue;
         }
+        // combining marks sit on the base without advancing the pen
+        for mark in chars.filter(|c| !is_variation_selector(*c)) {
+            let (font, _) = fallback_char(fonts, mark, descriptions);
+            glyphs.push(PlacedGlyph {
+                font,
+                key: GlyphKey::Char(mark),
+                x: base_x + advance,
+                y: line_offset,
+            });
+        }
+    }
+    glyphs
+}
+
+#[cfg(not(feature = "shaping"))]
+fn fallback_char(
+    fonts: &mut HashMap<FontDescription, Font>,
+    ch: char,
+    descriptions: &[FontDescription],
+) -> (usize, f64) {
+    let font = descriptions
+        .iter()
+        .position(|description| fonts[description].has_char(ch))
+        .unwrap_or(0);
+    let advance = fonts
+        .get_mut(&descriptions[font])
+        .unwrap()
+        .glyph_advance(GlyphKey::Char(ch));
+    (font, advance)
+}
+
+// shaped text is laid out with the primary font only
+#[cfg(feature = "shaping")]
+fn layout_glyphs(
+    fonts: &mut HashMap<FontDescription, Font>,
+    text: &str,
+    style: &TextStyle,
+    descriptions: &[FontDescription],
+    tab_width: f64,
+) -> Vec<PlacedGlyph> {
+    let font = &fonts[&descriptions[0]];
+    let line_height = font.line_height();
+    let rtl = style.direction == TextDirection::Rtl;
+    let mut glyphs = Vec::new();
+    let mut line_offset: f64 = 0.0;
+    for line in text.split('\n') {
+        let mut offset: f64 = 0.0;
+        for (i, segment) in line.split('\t').enumerate() {
+            if i > 0 {
+                offset = next_tab_stop(offset, tab_width, rtl);
+            }
+            let shaped = font.shape(segment, rtl);
+            let segment_width = shaped.iter().map(|glyph| glyph.advance).sum::<f64>();
+            let mut pen = if rtl { offset - segment_width } else { offset };
+            for glyph in shaped {
+                glyphs.push(PlacedGlyph {
+                    font: 0,
+                    key: GlyphKey::Index(glyph.index),
+                    x: pen + glyph.x_offset,
+                    y: line_offset - glyph.y_offset,
+                });
+                pen += glyph.advance;
+            }
+            offset += if rtl { -segment_width } else { segment_width };
+        }
+        line_offset += line_height;
+    }
+    glyphs
+}
+
+pub struct GlRenderer<'a> {
+    quad: VertexArray,
+    library: Rc<freetype::Library>,
+    registry: FontRegistry,
+    fonts: HashMap<FontDescription, Font>,
+    images: ImageCache,
+    rect_shader: Program<'a>,
+    quad_shader: Program<'a>,
+    quad_instances: InstanceBuffer,
+    text_shader: Program<'a>,
+    color_text_shader: Program<'a>,
+    line_shader: Program<'a>,
+    line_buffer: VertexArray,
+    ellipse_shader: Program<'a>,
+    #[cfg(feature = "sdf")]
+    sdf_shader: Program<'a>,
+    #[cfg(feature = "sdf")]
+    sdf_atlas: SdfAtlas,
+    #[cfg(feature = "sdf")]
+    sdf_style: Option<SdfStyle>,
+    blend_mode: BlendMode,
+    debug_bounds: bool,
+    scale_factor: f64,
+    draw_calls: Cell<usize>,
+}
+
+impl<'a, 'fonts> GlRenderer<'a> {
+    pub fn new() -> Self {
+        let vertex_data: [f32; 18] = [
+            -1.0, 1.0, 0.0, -1.0, -1.0, 0.0, 1.0, -1.0, 0.0, 1.0, -1.0, 0.0, 1.0, 1.0, 0.0, -1.0,
+            1.0, 0.0,
+        ];
+        let rect_frag_shader_src = "#version 330 core
+        in vec2 pass_pos;
+        out vec4 out_color;
+        uniform vec4 color;
+
+        // top left, top right, bottom left, bottom right
+        uniform vec4 border_radius;
+        uniform float x;
+        uniform float y;
+        uniform float width;
+        uniform float height;
+        uniform float win_height;
+        uniform float premultiplied;
+        uniform float has_texture;
+        uniform vec4 uv_rect;
+        uniform sampler2D tex;
+        
+        void main()
+        {
+            // signed distance to the rounded rect, in top-left origin pixels
+            vec2 pos = vec2(gl_FragCoord.x, win_height - gl_FragCoord.y);
+            vec2 half_size = vec2(width, height) / 2.0;
+            vec2 p = pos - vec2(x, y) - half_size;
+            // each quadrant is rounded by its own corner
+            float corner = p.x < 0.0
+                ? (p.y < 0.0 ? border_radius.x : border_radius.z)
+                : (p.y < 0.0 ? border_radius.y : border_radius.w);
+            float radius = min(min(width, height) / 2.0, corner);
+            vec2 q = abs(p) - half_size + vec2(radius);
+            float dist = length(max(q, 0.0)) + min(max(q.x, q.y), 0.0) - radius;
+            float coverage = 1.0 - smoothstep(-0.5, 0.5, dist);
+            if (coverage <= 0.0) {
+                discard;
+            }
+            // blend in premultiplied space, the image goes over the color
+            vec4 fill = premultiplied > 0.5 ? color : vec4(color.rgb * color.a, color.a);
+            if (has_texture > 0.5) {
+                vec2 uv = (pos - vec2(x, y)) / vec2(width, height) * uv_rect.zw + uv_rect.xy;
+                if (all(greaterThanEqual(uv, vec2(0.0))) && all(lessThanEqual(uv, vec2(1.0)))) {
+                    vec4 texel = texture(tex, uv);
+                    fill = vec4(texel.rgb * texel.a, texel.a) + fill * (1.0 - texel.a);
+                }
+            }
+            if (premultiplied > 0.5) {
+                out_color = fill * coverage;
+            } else if (fill.a > 0.0) {
+                out_color = vec4(fill.rgb / fill.a, fill.a * coverage);
+            } else {
+                discard;
+            }
+        }";
+        apply_gl_state(BlendMode::Straight);
         GlRenderer {
             quad: VertexArray::new(&vertex_data),
+            library: shared_library(),
+            registry: FontRegistry::new(),
             fonts: HashMap::new(),
+            images: ImageCache::default(),
             rect_shader: Program::new(
-                Shader::new_vertex(vert_shader_src),
+                Shader::new_vertex(VERT_SHADER_SRC),
                 Shader::new_fragment(rect_frag_shader_src),
                 vec![
                     "transform",
@@ -111,16 +580,316 @@ impl<'a, 'fonts> GlRenderer<'a> {
                     "height",
                     "win_height",
                     "border_radius",
+                    "premultiplied",
+                    "has_texture",
+                    "uv_rect",
                 ],
             ),
-            text_shader: Program::new(
-                Shader::new_vertex(vert_shader_src),
-                Shader::new_fragment(text_frag_shader_src),
+            quad_shader: Program::new(
+                Shader::new_vertex(QUAD_VERT_SHADER_SRC),
+                Shader::new_fragment(QUAD_FRAG_SHADER_SRC),
+                vec!["win_width", "win_height", "premultiplied"],
+            ),
+            quad_instances: InstanceBuffer::new(&vertex_data, &QUAD_ATTRIBUTES),
+            text_shader: GlRenderer::text_program(BlendMode::Straight),
+            color_text_shader: Program::new(
+                Shader::new_vertex(VERT_SHADER_SRC),
+                Shader::new_fragment(COLOR_TEXT_FRAG_SHADER_SRC),
+                vec!["transform", "color", "premultiplied"],
+            ),
+            line_shader: Program::new(
+                Shader::new_vertex(VERT_SHADER_SRC),
+                Shader::new_fragment(LINE_FRAG_SHADER_SRC),
                 vec!["transform", "color"],
             ),
+            line_buffer: VertexArray::new(&[]),
+            ellipse_shader: Program::new(
+                Shader::new_vertex(VERT_SHADER_SRC),
+                Shader::new_fragment(ELLIPSE_FRAG_SHADER_SRC),
+                vec!["transform", "color", "premultiplied"],
+            ),
+            #[cfg(feature = "sdf")]
+            sdf_shader: Program::new(
+                Shader::new_vertex(VERT_SHADER_SRC),
+                Shader::new_fragment(SDF_TEXT_FRAG_SHADER_SRC),
+                vec![
+                    "transform",
+                    "color",
+                    "outline_color",
+                    "outline_width",
+                    "glow_color",
+                    "glow_width",
+                    "premultiplied",
+                ],
+            ),
+            #[cfg(feature = "sdf")]
+            sdf_atlas: SdfAtlas::new(),
+            #[cfg(feature = "sdf")]
+            sdf_style: None,
+            blend_mode: BlendMode::Straight,
+            debug_bounds: false,
+            scale_factor: 1.0,
+            draw_calls: Cell::new(0),
         }
     }
 
+    fn text_program(blend_mode: BlendMode) -> Program<'a> {
+        Program::new(
+            Shader::new_vertex(VERT_SHADER_SRC),
+            Shader::new_fragment(blend_mode.text_fragment_source()),
+            vec!["transform", "color"],
+        )
+    }
+
+    pub fn set_blend_mode(&mut self, blend_mode: BlendMode) {
+        if blend_mode != self.blend_mode {
+            self.text_shader = GlRenderer::text_program(blend_mode);
+            self.blend_mode = blend_mode;
+        }
+        blend_mode.apply();
+    }
+
+    pub fn blend_mode(&self) -> BlendMode {
+        self.blend_mode
+    }
+
+    // for embedding in an application that owns the window and context. the host makes its
+    // context current and loads the function pointers with gl::load_with before calling this
+    pub fn new_in_current_context() -> Self {
+        assert!(
+            gl::Enable::is_loaded() && gl::CreateProgram::is_loaded(),
+            "winkel: no OpenGL functions are loaded, call gl::load_with with the host's context first"
+        );
+        GlRenderer::new()
+    }
+
+    // sets the blending, depth and multisampling state the renderer draws with. a host that
+    // changes these between frames calls this before rendering
+    pub fn restore_gl_state(&self) {
+        apply_gl_state(self.blend_mode);
+    }
+
+    pub fn scale_factor(&self) -> f64 {
+        self.scale_factor
+    }
+
+    // width and height are logical pixels, the texture is scaled by the scale factor
+    pub fn render_to_texture(
+        &mut self,
+        computed: &HashMap<usize, ComputedWidget>,
+        width: f64,
+        height: f64,
+    ) -> Texture {
+        let texture = Texture::new_rgba(
+            (width * self.scale_factor).ceil() as i32,
+            (height * self.scale_factor).ceil() as i32,
+        );
+        let framebuffer = Framebuffer::new(&texture);
+        let mut viewport = [0i32; 4];
+        let mut previous_framebuffer = 0i32;
+        unsafe {
+            gl::GetIntegerv(gl::VIEWPORT, viewport.as_mut_ptr());
+            gl::GetIntegerv(gl::FRAMEBUFFER_BINDING, &mut previous_framebuffer);
+            framebuffer.bind();
+            gl::Viewport(0, 0, texture.width(), texture.height());
+            gl::ClearColor(0.0, 0.0, 0.0, 0.0);
+            gl::Clear(gl::COLOR_BUFFER_BIT | gl::DEPTH_BUFFER_BIT);
+        }
+        self.render(computed, width, height);
+        unsafe {
+            gl::BindFramebuffer(gl::FRAMEBUFFER, previous_framebuffer as u32);
+            gl::Viewport(viewport[0], viewport[1], viewport[2], viewport[3]);
+        }
+        texture
+    }
+
+    // draws text from distance fields instead of per size bitmaps, None switches back
+    #[cfg(feature = "sdf")]
+    pub fn set_sdf_text(&mut self, style: Option<SdfStyle>) {
+        self.sdf_style = style;
+    }
+
+    // distance fields are shared by every size a glyph is drawn at
+    #[cfg(feature = "sdf")]
+    pub fn sdf_glyph_count(&self) -> usize {
+        self.sdf_atlas.glyph_count()
+    }
+
+    pub fn add_font_bytes(&mut self, name: &str, bytes: &[u8]) {
+        self.registry.add_bytes(name, bytes);
+        self.fonts.retain(|desc, _| desc.name != name);
+        #[cfg(feature = "sdf")]
+        self.sdf_atlas.remove_font(name);
+        measure::set_registry(&self.registry);
+    }
+
+    pub fn set_font_registry(&mut self, registry: FontRegistry) {
+        self.registry = registry;
+        self.fonts.clear();
+        #[cfg(feature = "sdf")]
+        self.sdf_atlas.clear();
+        measure::set_registry(&self.registry);
+    }
+
+    fn load_font(&mut self, name: &str, size: u32, config: FontConfig) -> FontDescription {
+        let description = FontDescription {
+            name: String::from(name),
+            size,
+            config,
+        };
+        if !self.fonts.contains_key(&description) {
+            let font = self.registry.load(&self.library, name, size, config);
+            self.fonts.insert(description.clone(), font);
+        }
+        description
+    }
+
+    fn layout_text(
+        &mut self,
+        text: &str,
+        style: &TextStyle,
+        descriptions: &[FontDescription],
+    ) -> Vec<PlacedGlyph> {
+        let tab_width = self.tab_width(style, &descriptions[0]);
+        layout_glyphs(&mut self.fonts, text, style, descriptions, tab_width)
+    }
+
+    fn tab_width(&mut self, style: &TextStyle, description: &FontDescription) -> f64 {
+        match style.tab_stop {
+            TabStop::Pixels(width) => width * self.scale_factor,
+            TabStop::Spaces(count) => {
+                let space = self.fonts.get_mut(description).unwrap().get_char(' ');
+                count as f64 * space.advance() as f64 / 64.0
+            }
+        }
+    }
+
+    // bounds are x, y, z, width, height; the texture is drawn over the color
+    fn draw_rect(
+        &self,
+        bounds: (f64, f64, usize, f64, f64),
+        color: Color,
+        border_radius: BorderRadius,
+        texture: Option<(&Texture, [f32; 4])>,
+        window_width: f64,
+        window_height: f64,
+    ) {
+        let (x, y, z, width, height) = bounds;
+        let mat = GlRenderer::get_tranform_matrix(
+            width / window_width,
+            height / window_height,
+            (x + width / 2.0 - window_width / 2.0) / window_width * 2.0,
+            -(y + height / 2.0 - window_height / 2.0) / window_height * 2.0,
+            z_to_depth(z),
+        );
+        let color = match self.blend_mode {
+            BlendMode::Straight => color,
+            BlendMode::Premultiplied => color::premultiply(color),
+        };
+        self.rect_shader.start();
+        self.rect_shader.load("transform", mat);
+        self.rect_shader.load("color", color);
+        // the shader compares against gl_FragCoord, which is in framebuffer pixels
+        let scale = self.scale_factor;
+        self.rect_shader.load("x", (x * scale) as f32);
+        self.rect_shader.load("y", (y * scale) as f32);
+        self.rect_shader.load("width", (width * scale) as f32);
+        self.rect_shader.load("height", (height * scale) as f32);
+        self.rect_shader
+            .load("win_height", (window_height * scale) as f32);
+        self.rect_shader.load(
+            "border_radius",
+            [
+                (border_radius.top_left * scale) as f32,
+                (border_radius.top_right * scale) as f32,
+                (border_radius.bottom_left * scale) as f32,
+                (border_radius.bottom_right * scale) as f32,
+            ],
+        );
+        self.rect_shader.load(
+            "premultiplied",
+            (self.blend_mode == BlendMode::Premultiplied) as u8 as f32,
+        );
+        self.rect_shader
+            .load("has_texture", texture.is_some() as u8 as f32);
+        if let Some((texture, uv_rect)) = texture {
+            self.rect_shader.load("uv_rect", uv_rect);
+            texture.bind();
+        }
+        self.quad.draw();
+        self.count_draw_call();
+        if let Some((texture, _)) = texture {
+            texture.unbind();
+        }
+        self.rect_shader.stop();
+    }
+
+    // pen position and window size are in framebuffer pixels. false if the glyph has
+    // no distance field and has to be drawn as a bitmap
+    #[cfg(feature = "sdf")]
+    fn draw_sdf_glyph(
+        &mut self,
+        font: &str,
+        key: GlyphKey,
+        pen: (f64, f64, f64),
+        fontsize: f64,
+        color: Color,
+        sdf_style: &SdfStyle,
+        window: (f64, f64),
+    ) -> bool {
+        let (pen_x, baseline, depth) = pen;
+        let (window_width, window_height) = window;
+        let glyph = match self
+            .sdf_atlas
+            .glyph(&self.library, &self.registry, font, key)
+        {
+            Some(glyph) => glyph,
+            None => return false,
+        };
+        let scale = fontsize / SDF_SIZE as f64;
+        let width = glyph.width as f64 * scale;
+        let height = glyph.height as f64 * scale;
+        let x = pen_x + glyph.left * scale;
+        let y = baseline - glyph.top * scale;
+        let mat = GlRenderer::get_tranform_matrix(
+            width / window_width,
+            height / window_height,
+            (x + width / 2.0 - window_width / 2.0) / window_width * 2.0,
+            -(y + height / 2.0 - window_height / 2.0) / window_height * 2.0,
+            depth,
+        );
+        // logical pixels at the drawn size to distance field units
+        let to_field = self.scale_factor / scale / (2.0 * SDF_SPREAD as f64);
+        let (outline_width, outline_color) = sdf_style.outline.unwrap_or((0.0, [0.0; 4]));
+        let (glow_width, glow_color) = sdf_style.glow.unwrap_or((0.0, [0.0; 4]));
+        let texture = glyph.texture();
+        self.sdf_shader.start();
+        texture.bind();
+        self.sdf_shader.load("transform", mat);
+        self.sdf_shader.load("color", color);
+        self.sdf_shader.load("outline_color", outline_color);
+        self.sdf_shader.load(
+            "outline_width",
+            ((outline_width * to_field).min(0.5)) as f32,
+        );
+        self.sdf_shader.load("glow_color", glow_color);
+        self.sdf_shader
+            .load("glow_width", ((glow_width * to_field).min(0.5)) as f32);
+        self.sdf_shader.load(
+            "premultiplied",
+            (self.blend_mode == BlendMode::Premultiplied) as u8 as f32,
+        );
+        self.quad.draw();
+        texture.unbind();
+        self.sdf_shader.stop();
+        self.count_draw_call();
+        true
+    }
+
+    fn count_draw_call(&self) {
+        self.draw_calls.set(self.draw_calls.get() + 1);
+    }
+
     fn get_tranform_matrix(
         x_scale: f64,
         y_scale: f64,
@@ -149,12 +918,129 @@ impl<'a, 'fonts> GlRenderer<'a> {
     }
 }
 
+// the bar of one decorated line, centered offset below the baseline the glyphs sit on
+fn decoration_rect(
+    baseline: f64,
+    start: f64,
+    end: f64,
+    (offset, thickness): (f64, f64),
+) -> (f64, f64, f64, f64) {
+    let center = baseline + offset;
+    (start, center - thickness / 2.0, end - start, thickness)
+}
+
 impl<'a> Renderer for GlRenderer<'a> {
     fn render_quad(
         &mut self,
         x: f64,
         y: f64,
-        _z: usize,
+        z: usize,
+        width: f64,
+        height: f64,
+        style: &Style,
+        window_width: f64,
+        window_height: f64,
+    ) {
+        if style.color.is_none() && style.image.is_none() {
+            return;
+        }
+        let texture = style.image.as_ref().and_then(|image| {
+            let texture = self.images.get(&image.path)?;
+            let uv_rect = image.uv_rect(
+                width,
+                height,
+                texture.width() as f64,
+                texture.height() as f64,
+            );
+            Some((texture, uv_rect))
+        });
+        if style.color.is_none() && texture.is_none() {
+            return;
+        }
+        self.draw_rect(
+            (x, y, z, width, height),
+            style.color.unwrap_or([0.0; 4]),
+            style.border_radius,
+            texture
+                .as_ref()
+                .map(|(texture, uv_rect)| (&**texture, *uv_rect)),
+            window_width,
+            window_height,
+        );
+    }
+
+    fn render_quads(&mut self, quads: &[Quad], window_width: f64, window_height: f64) {
+        let scale = self.scale_factor;
+        let stride = QUAD_ATTRIBUTES.iter().sum::<usize>();
+        let mut data = Vec::with_capacity(quads.len() * stride);
+        for quad in quads {
+            let color = match self.blend_mode {
+                BlendMode::Straight => quad.color,
+                BlendMode::Premultiplied => color::premultiply(quad.color),
+            };
+            let radii = quad.border_radius;
+            data.extend_from_slice(&[
+                (quad.x * scale) as f32,
+                (quad.y * scale) as f32,
+                (quad.width * scale) as f32,
+                (quad.height * scale) as f32,
+            ]);
+            data.extend_from_slice(&color);
+            data.extend_from_slice(&[
+                (radii.top_left * scale) as f32,
+                (radii.top_right * scale) as f32,
+                (radii.bottom_left * scale) as f32,
+                (radii.bottom_right * scale) as f32,
+                z_to_depth(quad.z) as f32,
+            ]);
+        }
+        self.quad_shader.start();
+        self.quad_shader
+            .load("win_width", (window_width * scale) as f32);
+        self.quad_shader
+            .load("win_height", (window_height * scale) as f32);
+        self.quad_shader.load(
+            "premultiplied",
+            (self.blend_mode == BlendMode::Premultiplied) as u8 as f32,
+        );
+        self.quad_instances.draw(&data);
+        self.count_draw_call();
+        self.quad_shader.stop();
+    }
+
+    fn render_nine_patch(&mut self, patch: &NinePatchQuad, window_width: f64, window_height: f64) {
+        let texture = match self.images.get(&Rc::from(patch.path)) {
+            Some(texture) => texture,
+            None => return,
+        };
+        let regions = nine_patch_regions(
+            patch.width,
+            patch.height,
+            texture.width() as f64,
+            texture.height() as f64,
+            patch.insets,
+        );
+        let (x, y, z) = (patch.x, patch.y, patch.z);
+        for region in regions {
+            if region.width <= 0.0 || region.height <= 0.0 {
+                continue;
+            }
+            self.draw_rect(
+                (x + region.x, y + region.y, z, region.width, region.height),
+                [0.0; 4],
+                BorderRadius::default(),
+                Some((&texture, region.uv_rect)),
+                window_width,
+                window_height,
+            );
+        }
+    }
+
+    fn render_ellipse(
+        &mut self,
+        x: f64,
+        y: f64,
+        z: usize,
         width: f64,
         height: f64,
         style: &Style,
@@ -166,21 +1052,23 @@ impl<'a> Renderer for GlRenderer<'a> {
             height / window_height,
             (x + width / 2.0 - window_width / 2.0) / window_width * 2.0,
             -(y + height / 2.0 - window_height / 2.0) / window_height * 2.0,
-            0.0,
+            z_to_depth(z),
         );
         if let Some(color) = style.color {
-            self.rect_shader.start();
-            self.rect_shader.load("transform", mat);
-            self.rect_shader.load("color", color);
-            self.rect_shader.load("x", x as f32);
-            self.rect_shader.load("y", y as f32);
-            self.rect_shader.load("width", width as f32);
-            self.rect_shader.load("height", height as f32);
-            self.rect_shader.load("win_height", window_height as f32);
-            self.rect_shader
-                .load("border_radius", style.border_radius as f32);
+            let color = match self.blend_mode {
+                BlendMode::Straight => color,
+                BlendMode::Premultiplied => color::premultiply(color),
+            };
+            self.ellipse_shader.start();
+            self.ellipse_shader.load("transform", mat);
+            self.ellipse_shader.load("color", color);
+            self.ellipse_shader.load(
+                "premultiplied",
+                (self.blend_mode == BlendMode::Premultiplied) as u8 as f32,
+            );
             self.quad.draw();
-            self.rect_shader.stop();
+            self.count_draw_call();
+            self.ellipse_shader.stop();
         }
     }
 
@@ -188,44 +1076,435 @@ impl<'a> Renderer for GlRenderer<'a> {
         &mut self,
         x: f64,
         y: f64,
-        _z: usize,
-        _width: f64,
+        z: usize,
+        width: f64,
         _height: f64,
         text: &'b str,
         style: &TextStyle<'b>,
         window_width: f64,
         window_height: f64,
     ) {
-        let font = self
-            .fonts
-            .entry(FontDescription {
-                name: String::from(style.font),
-                size: style.size,
-            })
-            .or_insert_with(|| Font::new(style.font, style.size));
-        let fontsize = font.size() as f64;
-        let mut offset: f64 = 0.0;
-        for ch in text.chars() {
-            let renderchar = font.get_char(ch);
+        let config = FontConfig::from(style.hinting);
+        let size = scaled_font_size(style.size, self.scale_factor);
+        let names: Vec<String> = self.registry.chain(style.font).map(String::from).collect();
+        let descriptions: Vec<FontDescription> = names
+            .iter()
+            .map(|name| self.load_font(name, size, config))
+            .collect();
+        // glyphs are placed in framebuffer pixels
+        let scale = self.scale_factor;
+        let (logical_width, logical_height) = (window_width, window_height);
+        let (x, y, width) = (x * scale, y * scale, width * scale);
+        let (window_width, window_height) = (window_width * scale, window_height * scale);
+        let fontsize = size as f64;
+        let origin = match style.direction {
+            TextDirection::Ltr => x,
+            TextDirection::Rtl => x + width,
+        };
+        let depth = z_to_depth(z);
+        // horizontal extent of each line, keyed by its y offset
+        let mut lines: Vec<(f64, f64, f64)> = Vec::new();
+        for glyph in self.layout_text(text, style, &descriptions) {
+            if style.decoration != TextDecoration::None {
+                let start = origin + glyph.x;
+                let end = start
+                    + self
+                        .fonts
+                        .get_mut(&descriptions[glyph.font])
+                        .unwrap()
+                        .glyph_advance(glyph.key);
+                match lines.iter_mut().find(|line| line.0 == glyph.y) {
+                    Some(line) => {
+                        line.1 = line.1.min(start);
+                        line.2 = line.2.max(end);
+                    }
+                    None => lines.push((glyph.y, start, end)),
+                }
+            }
+            #[cfg(feature = "sdf")]
+            if let Some(sdf_style) = self.sdf_style {
+                let name = &descriptions[glyph.font].name;
+                let glyph_x = origin + glyph.x;
+                let baseline = y + glyph.y + fontsize;
+                if self.draw_sdf_glyph(
+                    name,
+                    glyph.key,
+                    (glyph_x, baseline, depth),
+                    fontsize,
+                    style.color,
+                    &sdf_style,
+                    (window_width, window_height),
+                ) {
+                    continue;
+                }
+            }
+            let font = self.fonts.get_mut(&descriptions[glyph.font]).unwrap();
+            let renderchar = font.get_glyph(glyph.key);
             let width = renderchar.width() as f64;
             let height = renderchar.height() as f64;
-            let x = x + offset + renderchar.left() as f64;
-            let y = y - renderchar.top() as f64 + fontsize;
-            offset += renderchar.advance() as f64 / 64.0;
+            let x = config.snap(origin + glyph.x) + renderchar.left() as f64;
+            let y = config.snap(y + glyph.y + fontsize) - renderchar.top() as f64;
             let mat = GlRenderer::get_tranform_matrix(
                 width / window_width,
                 height / window_height,
                 (x + width / 2.0 - window_width / 2.0) / window_width * 2.0,
                 -(y + height / 2.0 - window_height / 2.0) / window_height * 2.0,
-                0.0,
+                depth,
             );
-            self.text_shader.start();
+            let shader = if renderchar.is_color() {
+                &self.color_text_shader
+            } else {
+                &self.text_shader
+            };
+            shader.start();
             renderchar.bind();
-            self.text_shader.load("transform", mat);
-            self.text_shader.load("color", style.color);
+            shader.load("transform", mat);
+            shader.load("color", style.color);
+            if renderchar.is_color() {
+                shader.load(
+                    "premultiplied",
+                    (self.blend_mode == BlendMode::Premultiplied) as u8 as f32,
+                );
+            }
             self.quad.draw();
             renderchar.unbind();
-            self.text_shader.stop();
+            shader.stop();
+            self.count_draw_call();
+        }
+        if lines.is_empty() {
+            return;
+        }
+        let (offset, thickness) = self.fonts[&descriptions[0]].decoration_line(style.decoration);
+        let color = style.decoration_color.unwrap_or(style.color);
+        for (line_y, start, end) in lines {
+            let (x, y, width, height) =
+                decoration_rect(y + line_y + fontsize, start, end, (offset, thickness));
+            self.draw_rect(
+                (x / scale, y / scale, z, width / scale, height / scale),
+                color,
+                BorderRadius::default(),
+                None,
+                logical_width,
+                logical_height,
+            );
+        }
+    }
+
+    fn set_clip(
+        &mut self,
+        clip: Option<(f64, f64, f64, f64)>,
+        _window_width: f64,
+        window_height: f64,
+    ) {
+        let scale = self.scale_factor;
+        unsafe {
+            match clip {
+                Some((x, y, width, height)) => {
+                    let (x, y, width, height) =
+                        (x * scale, y * scale, width * scale, height * scale);
+                    let window_height = window_height * scale;
+                    gl::Enable(gl::SCISSOR_TEST);
+                    gl::Scissor(
+                        x.floor() as i32,
+                        (window_height - y - height).floor() as i32,
+                        width.ceil() as i32,
+                        height.ceil() as i32,
+                    );
+                }
+                None => gl::Disable(gl::SCISSOR_TEST),
+            }
+        }
+    }
+
+    fn render_line(
+        &mut self,
+        x: f64,
+        y: f64,
+        z: usize,
+        stroke: &LineStroke,
+        window_width: f64,
+        window_height: f64,
+    ) {
+        let points: Vec<(f64, f64)> = stroke
+            .points
+            .iter()
+            .map(|(px, py)| (x + px, y + py))
+            .collect();
+        self.line_buffer
+            .set_data(&polyline_triangles(&points, stroke.width));
+        // maps window pixels straight to clip space
+        let mat = GlRenderer::get_tranform_matrix(
+            2.0 / window_width,
+            -2.0 / window_height,
+            -1.0,
+            1.0,
+            z_to_depth(z),
+        );
+        let color = match self.blend_mode {
+            BlendMode::Straight => stroke.color,
+            BlendMode::Premultiplied => color::premultiply(stroke.color),
+        };
+        self.line_shader.start();
+        self.line_shader.load("transform", mat);
+        self.line_shader.load("color", color);
+        self.line_buffer.draw();
+        self.count_draw_call();
+        self.line_shader.stop();
+    }
+
+    fn set_scale_factor(&mut self, scale_factor: f64) {
+        self.scale_factor = scale_factor;
+    }
+
+    fn debug_bounds(&self) -> bool {
+        self.debug_bounds
+    }
+
+    fn set_debug_bounds(&mut self, enabled: bool) {
+        self.debug_bounds = enabled;
+    }
+
+    fn take_draw_calls(&mut self) -> usize {
+        self.draw_calls.replace(0)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::Hinting;
+
+    const FONT: &str = "Raleway-Regular.ttf";
+
+    fn style(font: &str) -> TextStyle<'_> {
+        TextStyle {
+            font,
+            color: [0.0, 0.0, 0.0, 1.0],
+            size: 16,
+            hinting: Hinting::Normal,
+            tab_stop: TabStop::Spaces(4),
+            direction: TextDirection::Ltr,
+            decoration: TextDecoration::None,
+            decoration_color: None,
+        }
+    }
+
+    // lays text out the way render_text does, minus the textures
+    fn layout(
+        registry: &FontRegistry,
+        text: &str,
+        style: &TextStyle,
+    ) -> (Vec<PlacedGlyph>, HashMap<FontDescription, Font>) {
+        let config = FontConfig::from(style.hinting);
+        let mut fonts = HashMap::new();
+        let descriptions: Vec<FontDescription> = registry
+            .chain(style.font)
+            .map(|name| {
+                let description = FontDescription {
+                    name: String::from(name),
+                    size: style.size,
+                    config,
+                };
+                let font = registry.load(&shared_library(), name, style.size, config);
+                fonts.insert(description.clone(), font);
+                description
+            })
+            .collect();
+        let tab_width = match style.tab_stop {
+            TabStop::Pixels(width) => width,
+            TabStop::Spaces(count) => {
+                let font = fonts.get_mut(&descriptions[0]).unwrap();
+                count as f64 * font.glyph_advance(GlyphKey::Char(' '))
+            }
+        };
+        let glyphs = layout_glyphs(&mut fonts, text, style, &descriptions, tab_width);
+        (glyphs, fonts)
+    }
+
+    #[test]
+    fn higher_layers_map_closer() {
+        assert!(z_to_depth(0) < 1.0);
+        assert!(z_to_depth(1) < z_to_depth(0));
+        assert!(z_to_depth(crate::widgets::OVERLAY_Z) < z_to_depth(1));
+        // layers past the depth range all share the closest depth, still inside clip space
+        assert_eq!(z_to_depth(DEPTH_LAYERS - 1), -1.0);
+        assert_eq!(z_to_depth(DEPTH_LAYERS + 5), -1.0);
+        let matrix = GlRenderer::get_tranform_matrix(1.0, 1.0, 0.0, 0.0, z_to_depth(3));
+        assert_eq!(matrix[14], z_to_depth(3) as f32);
+    }
+
+    #[test]
+    fn blend_mode_picks_text_shader() {
+        assert_eq!(
+            BlendMode::Straight.text_fragment_source(),
+            TEXT_FRAG_SHADER_SRC
+        );
+        assert_eq!(
+            BlendMode::Premultiplied.text_fragment_source(),
+            TEXT_PREMULTIPLIED_FRAG_SHADER_SRC
+        );
+        // the premultiplied shader scales the color by its own alpha
+        assert!(TEXT_PREMULTIPLIED_FRAG_SHADER_SRC.contains("color.rgb * color.a"));
+        assert!(!TEXT_FRAG_SHADER_SRC.contains("color.rgb * color.a"));
+    }
+
+    // the bundled font has no hebrew letters, a common system font does
+    #[cfg(not(feature = "shaping"))]
+    #[test]
+    fn missing_glyphs_come_from_the_fallback() {
+        let fallback = "/usr/share/fonts/truetype/dejavu/DejaVuSans.ttf";
+        if !std::path::Path::new(fallback).exists() {
+            eprintln!("skipped, {} is not installed", fallback);
+            return;
         }
+        let mut registry = FontRegistry::new();
+        registry.add_file("fallback", fallback);
+        registry.add_fallback("fallback");
+        let (glyphs, fonts) = layout(&registry, "a\u{5d0}", &style(FONT));
+        let primary = fonts.keys().find(|description| description.name == FONT);
+        assert!(!fonts[primary.unwrap()].has_char('\u{5d0}'));
+        assert_eq!(glyphs.len(), 2);
+        assert_eq!(glyphs[0].font, 0);
+        assert_eq!(glyphs[1].font, 1);
+        assert_eq!(glyphs[1].key, GlyphKey::Char('\u{5d0}'));
+    }
+
+    #[cfg(feature = "shaping")]
+    #[test]
+    fn shaped_text_stays_in_the_primary_font() {
+        let mut registry = FontRegistry::new();
+        registry.add_bytes("fallback", include_bytes!("../../Raleway-Regular.ttf"));
+        registry.add_fallback("fallback");
+        let (glyphs, _) = layout(&registry, "a\u{5d0}", &style(FONT));
+        assert!(!glyphs.is_empty());
+        assert!(glyphs.iter().all(|glyph| glyph.font == 0));
+    }
+
+    #[test]
+    fn newlines_start_a_new_line() {
+        let (glyphs, fonts) = layout(&FontRegistry::new(), "a\nb", &style(FONT));
+        let line_height = fonts.values().next().unwrap().line_height();
+        assert_eq!(glyphs.len(), 2);
+        assert_eq!((glyphs[0].x, glyphs[0].y), (0.0, 0.0));
+        assert_eq!((glyphs[1].x, glyphs[1].y), (0.0, line_height));
+    }
+
+    #[test]
+    fn leading_tab_moves_to_the_tab_stop() {
+        let registry = FontRegistry::new();
+        let (glyphs, mut fonts) = layout(&registry, "\ta", &style(FONT));
+        let space = fonts
+            .values_mut()
+            .next()
+            .unwrap()
+            .glyph_advance(GlyphKey::Char(' '));
+        assert_eq!(glyphs.len(), 1);
+        assert_eq!(glyphs[0].x, 4.0 * space);
+        let pixels = TextStyle {
+            tab_stop: TabStop::Pixels(50.0),
+            ..style(FONT)
+        };
+        let (glyphs, _) = layout(&registry, "\ta", &pixels);
+        assert_eq!(glyphs[0].x, 50.0);
+    }
+
+    #[test]
+    fn rtl_puts_the_first_character_rightmost() {
+        let rtl = TextStyle {
+            direction: TextDirection::Rtl,
+            ..style(FONT)
+        };
+        let (glyphs, mut fonts) = layout(&FontRegistry::new(), "mi", &rtl);
+        let font = fonts.values_mut().next().unwrap();
+        let (m, i) = (
+            font.glyph_advance(GlyphKey::Char('m')),
+            font.glyph_advance(GlyphKey::Char('i')),
+        );
+        // rtl text grows leftwards from the origin, so the wide m has to end right at it
+        let mut xs: Vec<f64> = glyphs.iter().map(|glyph| glyph.x).collect();
+        xs.sort_by(|a, b| a.partial_cmp(b).unwrap());
+        assert_eq!(xs.len(), 2);
+        assert!((xs[1] + m).abs() < 1.0);
+        assert!((xs[0] + m + i).abs() < 1.0);
+    }
+
+    #[test]
+    fn fonts_load_at_the_scaled_size() {
+        assert_eq!(scaled_font_size(20, 2.0), 40);
+        assert_eq!(scaled_font_size(15, 1.5), 23);
+        // never loads an empty face
+        assert_eq!(scaled_font_size(1, 0.25), 1);
+    }
+
+    // the quad shader's rounding, ported line by line
+    fn rounded_coverage(p: (f64, f64), half_size: (f64, f64), radius: f64) -> f64 {
+        let smoothstep = |edge0: f64, edge1: f64, x: f64| {
+            let t = ((x - edge0) / (edge1 - edge0)).clamp(0.0, 1.0);
+            t * t * (3.0 - 2.0 * t)
+        };
+        let q = (
+            p.0.abs() - half_size.0 + radius,
+            p.1.abs() - half_size.1 + radius,
+        );
+        let dist = q.0.max(0.0).hypot(q.1.max(0.0)) + q.0.max(q.1).min(0.0) - radius;
+        1.0 - smoothstep(-0.5, 0.5, dist)
+    }
+
+    #[test]
+    fn coverage_is_half_on_the_rounded_edge() {
+        let (half_size, radius) = ((50.0, 50.0), 10.0);
+        // on the corner arc, 45 degrees from the corner center at (40, 40)
+        let d = radius / 2f64.sqrt();
+        let on_arc = rounded_coverage((40.0 + d, 40.0 + d), half_size, radius);
+        assert!((on_arc - 0.5).abs() < 1e-9);
+        assert_eq!(rounded_coverage((0.0, 0.0), half_size, radius), 1.0);
+        // straight edges stay opaque until their last half pixel
+        assert_eq!(rounded_coverage((49.0, 0.0), half_size, radius), 1.0);
+        assert_eq!(rounded_coverage((49.0, 49.0), half_size, radius), 0.0);
+    }
+
+    #[test]
+    fn two_points_make_one_segment() {
+        let vertices = polyline_triangles(&[(0.0, 0.0), (10.0, 0.0)], 2.0);
+        // two triangles of three (x, y, 0) vertices
+        assert_eq!(vertices.len(), 18);
+        let ys: Vec<f32> = vertices.chunks(3).map(|v| v[1]).collect();
+        assert_eq!(ys, [1.0, -1.0, 1.0, -1.0, -1.0, 1.0]);
+    }
+
+    #[test]
+    fn underlines_sit_below_the_baseline() {
+        let registry = FontRegistry::new();
+        let font = registry.load(
+            &shared_library(),
+            "Raleway-Regular.ttf",
+            20,
+            FontConfig::from(Hinting::Normal),
+        );
+        let underline = font.decoration_line(TextDecoration::Underline);
+        let (x, y, width, height) = decoration_rect(100.0, 10.0, 60.0, underline);
+        assert_eq!((x, width, height), (10.0, 50.0, underline.1));
+        // centered on baseline + offset, inside the descent below it
+        assert_eq!(y + height / 2.0, 100.0 + underline.0);
+        assert!(underline.0 > 0.0 && underline.0 < 10.0);
+        let strike = font.decoration_line(TextDecoration::Strikethrough);
+        let (_, y, _, height) = decoration_rect(100.0, 10.0, 60.0, strike);
+        assert!(y + height < 100.0);
+    }
+
+    #[test]
+    fn embedding_checks_for_a_context_before_any_gl_call() {
+        // nothing here creates a window or loads GL, like a host that forgot to
+        assert!(!gl::CreateProgram::is_loaded());
+        let message = std::panic::catch_unwind(GlRenderer::new_in_current_context)
+            .err()
+            .and_then(|panic| panic.downcast_ref::<&str>().map(|m| m.to_string()))
+            .unwrap();
+        assert!(message.contains("call gl::load_with"));
+        // the shaders it would compile only depend on the blend mode
+        assert_ne!(
+            BlendMode::Straight.text_fragment_source(),
+            BlendMode::Premultiplied.text_fragment_source()
+        );
     }
 }
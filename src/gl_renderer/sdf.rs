@@ -0,0 +1,149 @@
+use super::font::{FontConfig, FontRegistry, GlyphKey};
+use super::{Font, Texture};
+use crate::color::Color;
+use crate::Hinting;
+use std::collections::HashMap;
+
+// glyphs are rasterized once at this size and scaled to whatever size they are drawn at
+pub const SDF_SIZE: u32 = 48;
+// how far from the outline distances are stored, in pixels at SDF_SIZE
+pub const SDF_SPREAD: usize = 6;
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct SdfStyle {
+    // width in logical pixels and color of a line around each glyph
+    pub outline: Option<(f64, Color)>,
+    // how far a soft halo reaches out from each glyph, in logical pixels
+    pub glow: Option<(f64, Color)>,
+}
+
+// a glyph's distance field, padded by the spread on every side. values are 128 on the
+// outline and grow towards 255 inside the glyph
+pub struct SdfGlyph {
+    pub left: f64,
+    pub top: f64,
+    pub width: usize,
+    pub height: usize,
+    pub data: Vec<u8>,
+    texture: Option<Texture>,
+}
+
+impl SdfGlyph {
+    pub fn texture(&mut self) -> &Texture {
+        let (width, height, data) = (self.width as i32, self.height as i32, &self.data);
+        self.texture
+            .get_or_insert_with(|| Texture::new(width, height, data))
+    }
+}
+
+// brute force signed distance transform, fine for the small bitmaps of single glyphs
+pub fn distance_field(coverage: &[u8], width: usize, height: usize, spread: usize) -> Vec<u8> {
+    let inside = |x: isize, y: isize| {
+        x >= 0
+            && y >= 0
+            && (x as usize) < width
+            && (y as usize) < height
+            && coverage[y as usize * width + x as usize] >= 128
+    };
+    let (out_width, out_height) = (width + 2 * spread, height + 2 * spread);
+    let reach = spread as isize;
+    let mut field = Vec::with_capacity(out_width * out_height);
+    for oy in 0..out_height {
+        for ox in 0..out_width {
+            let (x, y) = (ox as isize - reach, oy as isize - reach);
+            let here = inside(x, y);
+            let mut nearest = (spread * spread) as isize;
+            for dy in -reach..=reach {
+                for dx in -reach..=reach {
+                    let distance = dx * dx + dy * dy;
+                    if distance < nearest && inside(x + dx, y + dy) != here {
+                        nearest = distance;
+                    }
+                }
+            }
+            // the outline runs between pixel centers
+            let distance = ((nearest as f64).sqrt() - 0.5).min(spread as f64);
+            let signed = if here { distance } else { -distance };
+            let value = 0.5 + 0.5 * signed / spread as f64;
+            field.push((value * 255.0).round().clamp(0.0, 255.0) as u8);
+        }
+    }
+    field
+}
+
+// one distance field per font and glyph, however many sizes the glyph is drawn at
+#[derive(Default)]
+pub struct SdfAtlas {
+    fonts: HashMap<String, Font>,
+    glyphs: HashMap<(String, GlyphKey), Option<SdfGlyph>>,
+}
+
+impl SdfAtlas {
+    pub fn new() -> Self {
+        SdfAtlas::default()
+    }
+
+    // None for glyphs without coverage, like color glyphs, which are drawn as bitmaps
+    pub fn glyph(
+        &mut self,
+        library: &freetype::Library,
+        registry: &FontRegistry,
+        font: &str,
+        key: GlyphKey,
+    ) -> Option<&mut SdfGlyph> {
+        let entry = (String::from(font), key);
+        if !self.glyphs.contains_key(&entry) {
+            let face = self.fonts.entry(String::from(font)).or_insert_with(|| {
+                registry.load(library, font, SDF_SIZE, FontConfig::from(Hinting::None))
+            });
+            let glyph = face.coverage(key).map(|coverage| SdfGlyph {
+                left: (coverage.left - SDF_SPREAD as i32) as f64,
+                top: (coverage.top + SDF_SPREAD as i32) as f64,
+                width: coverage.width + 2 * SDF_SPREAD,
+                height: coverage.height + 2 * SDF_SPREAD,
+                data: distance_field(&coverage.data, coverage.width, coverage.height, SDF_SPREAD),
+                texture: None,
+            });
+            self.glyphs.insert(entry.clone(), glyph);
+        }
+        self.glyphs.get_mut(&entry).unwrap().as_mut()
+    }
+
+    pub fn glyph_count(&self) -> usize {
+        self.glyphs.len()
+    }
+
+    pub fn remove_font(&mut self, font: &str) {
+        self.fonts.remove(font);
+        self.glyphs.retain(|(name, _), _| name != font);
+    }
+
+    pub fn clear(&mut self) {
+        self.fonts.clear();
+        self.glyphs.clear();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::super::font::shared_library;
+    use super::*;
+
+    #[test]
+    fn one_entry_per_glyph_at_any_size() {
+        let library = shared_library();
+        let registry = FontRegistry::new();
+        let mut atlas = SdfAtlas::new();
+        let font = "Raleway-Regular.ttf";
+        // drawing at another size only changes the scale, not the lookup
+        for _fontsize in &[12.0, 24.0, 96.0] {
+            for &ch in &['a', 'b', 'a'] {
+                let glyph = atlas.glyph(&library, &registry, font, GlyphKey::Char(ch));
+                assert!(glyph.unwrap().width > 2 * SDF_SPREAD);
+            }
+        }
+        assert_eq!(atlas.glyph_count(), 2);
+        atlas.remove_font(font);
+        assert_eq!(atlas.glyph_count(), 0);
+    }
+}
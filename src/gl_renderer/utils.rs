@@ -144,6 +144,20 @@ impl VertexArray {
         }
     }
 
+    pub fn set_data(&mut self, vertex_data: &[f32]) {
+        unsafe {
+            gl::BindBuffer(gl::ARRAY_BUFFER, self.vertex_buffer);
+            gl::BufferData(
+                gl::ARRAY_BUFFER,
+                std::mem::size_of_val(vertex_data) as isize,
+                vertex_data.as_ptr() as *const std::ffi::c_void,
+                gl::DYNAMIC_DRAW,
+            );
+            gl::BindBuffer(gl::ARRAY_BUFFER, 0);
+        }
+        self.count = vertex_data.len() / 3;
+    }
+
     pub fn draw(&self) {
         unsafe {
             gl::BindVertexArray(self.id);
@@ -164,14 +178,114 @@ impl std::ops::Drop for VertexArray {
     }
 }
 
+// a shared mesh drawn once per instance, each instance reading its own attributes.
+// the mesh is attribute 0, the instance attributes follow with the given float sizes
+pub struct InstanceBuffer {
+    id: u32,
+    mesh_buffer: u32,
+    instance_buffer: u32,
+    mesh_count: usize,
+    stride: usize,
+}
+
+impl InstanceBuffer {
+    pub fn new(mesh: &[f32], attributes: &[usize]) -> Self {
+        let stride = attributes.iter().sum::<usize>();
+        unsafe {
+            let mut vaoid: u32 = 0;
+            gl::GenVertexArrays(1, &mut vaoid);
+            gl::BindVertexArray(vaoid);
+            let mut buffers = [0u32; 2];
+            gl::GenBuffers(2, buffers.as_mut_ptr());
+            gl::BindBuffer(gl::ARRAY_BUFFER, buffers[0]);
+            gl::BufferData(
+                gl::ARRAY_BUFFER,
+                std::mem::size_of_val(mesh) as isize,
+                mesh.as_ptr() as *const std::ffi::c_void,
+                gl::STATIC_DRAW,
+            );
+            gl::VertexAttribPointer(0, 3, gl::FLOAT, gl::FALSE, 0, std::ptr::null());
+            gl::EnableVertexAttribArray(0);
+            gl::BindBuffer(gl::ARRAY_BUFFER, buffers[1]);
+            let float = std::mem::size_of::<f32>();
+            let mut offset = 0;
+            for (i, size) in attributes.iter().enumerate() {
+                let location = i as u32 + 1;
+                gl::VertexAttribPointer(
+                    location,
+                    *size as i32,
+                    gl::FLOAT,
+                    gl::FALSE,
+                    (stride * float) as i32,
+                    (offset * float) as *const std::ffi::c_void,
+                );
+                gl::VertexAttribDivisor(location, 1);
+                gl::EnableVertexAttribArray(location);
+                offset += size;
+            }
+            gl::BindBuffer(gl::ARRAY_BUFFER, 0);
+            gl::BindVertexArray(0);
+            InstanceBuffer {
+                id: vaoid,
+                mesh_buffer: buffers[0],
+                instance_buffer: buffers[1],
+                mesh_count: mesh.len() / 3,
+                stride,
+            }
+        }
+    }
+
+    // one draw call for every instance in the data
+    pub fn draw(&self, instance_data: &[f32]) {
+        let instances = instance_data.len() / self.stride;
+        if instances == 0 {
+            return;
+        }
+        unsafe {
+            gl::BindBuffer(gl::ARRAY_BUFFER, self.instance_buffer);
+            gl::BufferData(
+                gl::ARRAY_BUFFER,
+                std::mem::size_of_val(instance_data) as isize,
+                instance_data.as_ptr() as *const std::ffi::c_void,
+                gl::STREAM_DRAW,
+            );
+            gl::BindBuffer(gl::ARRAY_BUFFER, 0);
+            gl::BindVertexArray(self.id);
+            gl::DrawArraysInstanced(gl::TRIANGLES, 0, self.mesh_count as i32, instances as i32);
+            gl::BindVertexArray(0);
+        }
+    }
+}
+
+impl std::ops::Drop for InstanceBuffer {
+    fn drop(&mut self) {
+        unsafe {
+            gl::DeleteBuffers(1, &self.instance_buffer);
+            gl::DeleteBuffers(1, &self.mesh_buffer);
+            gl::DeleteVertexArrays(1, &self.id);
+        }
+    }
+}
+
 #[derive(Debug)]
 pub struct Texture {
     id: u32,
+    width: i32,
+    height: i32,
 }
 
 impl Texture {
     pub fn new(width: i32, height: i32, data: &[u8]) -> Self {
         assert_eq!(data.len() as i32, width * height);
+        Texture::with_format(width, height, gl::RED, data)
+    }
+
+    pub fn from_rgba(width: i32, height: i32, data: &[u8]) -> Self {
+        assert_eq!(data.len() as i32, width * height * 4);
+        Texture::with_format(width, height, gl::RGBA, data)
+    }
+
+    fn with_format(width: i32, height: i32, format: gl::types::GLenum, data: &[u8]) -> Self {
         unsafe {
             let mut texture: u32 = 0;
             gl::GenTextures(1, &mut texture);
@@ -192,18 +306,75 @@ impl Texture {
             gl::TexImage2D(
                 gl::TEXTURE_2D,
                 0,
-                gl::RED as i32,
+                format as i32,
                 width,
                 height,
                 0,
-                gl::RED,
+                format,
                 gl::UNSIGNED_BYTE,
                 data.as_ptr() as *const std::ffi::c_void,
             );
-            Texture { id: texture }
+            Texture {
+                id: texture,
+                width,
+                height,
+            }
         }
     }
 
+    pub fn new_rgba(width: i32, height: i32) -> Self {
+        unsafe {
+            let mut texture: u32 = 0;
+            gl::GenTextures(1, &mut texture);
+            gl::BindTexture(gl::TEXTURE_2D, texture);
+            gl::TexParameteri(gl::TEXTURE_2D, gl::TEXTURE_MAG_FILTER, gl::LINEAR as i32);
+            gl::TexParameteri(gl::TEXTURE_2D, gl::TEXTURE_MIN_FILTER, gl::LINEAR as i32);
+            gl::TexImage2D(
+                gl::TEXTURE_2D,
+                0,
+                gl::RGBA8 as i32,
+                width,
+                height,
+                0,
+                gl::RGBA,
+                gl::UNSIGNED_BYTE,
+                std::ptr::null(),
+            );
+            gl::BindTexture(gl::TEXTURE_2D, 0);
+            Texture {
+                id: texture,
+                width,
+                height,
+            }
+        }
+    }
+
+    pub fn width(&self) -> i32 {
+        self.width
+    }
+
+    pub fn height(&self) -> i32 {
+        self.height
+    }
+
+    // rows are bottom to top, as stored by GL
+    pub fn read_rgba(&self) -> Vec<u8> {
+        let mut data = vec![0u8; (self.width * self.height * 4) as usize];
+        unsafe {
+            gl::BindTexture(gl::TEXTURE_2D, self.id);
+            gl::PixelStorei(gl::PACK_ALIGNMENT, 1);
+            gl::GetTexImage(
+                gl::TEXTURE_2D,
+                0,
+                gl::RGBA,
+                gl::UNSIGNED_BYTE,
+                data.as_mut_ptr() as *mut std::ffi::c_void,
+            );
+            gl::BindTexture(gl::TEXTURE_2D, 0);
+        }
+        data
+    }
+
     pub fn bind(&self) {
         unsafe {
             gl::BindTexture(gl::TEXTURE_2D, self.id);
@@ -223,3 +394,61 @@ impl std::ops::Drop for Texture {
         }
     }
 }
+
+pub struct Framebuffer {
+    id: u32,
+    depth_buffer: u32,
+}
+
+impl Framebuffer {
+    pub fn new(target: &Texture) -> Self {
+        unsafe {
+            let mut fboid: u32 = 0;
+            gl::GenFramebuffers(1, &mut fboid);
+            gl::BindFramebuffer(gl::FRAMEBUFFER, fboid);
+            gl::FramebufferTexture2D(
+                gl::FRAMEBUFFER,
+                gl::COLOR_ATTACHMENT0,
+                gl::TEXTURE_2D,
+                target.id,
+                0,
+            );
+            let mut rboid: u32 = 0;
+            gl::GenRenderbuffers(1, &mut rboid);
+            gl::BindRenderbuffer(gl::RENDERBUFFER, rboid);
+            gl::RenderbufferStorage(
+                gl::RENDERBUFFER,
+                gl::DEPTH_COMPONENT24,
+                target.width,
+                target.height,
+            );
+            gl::FramebufferRenderbuffer(
+                gl::FRAMEBUFFER,
+                gl::DEPTH_ATTACHMENT,
+                gl::RENDERBUFFER,
+                rboid,
+            );
+            gl::BindRenderbuffer(gl::RENDERBUFFER, 0);
+            gl::BindFramebuffer(gl::FRAMEBUFFER, 0);
+            Framebuffer {
+                id: fboid,
+                depth_buffer: rboid,
+            }
+        }
+    }
+
+    pub fn bind(&self) {
+        unsafe {
+            gl::BindFramebuffer(gl::FRAMEBUFFER, self.id);
+        }
+    }
+}
+
+impl std::ops::Drop for Framebuffer {
+    fn drop(&mut self) {
+        unsafe {
+            gl::DeleteRenderbuffers(1, &self.depth_buffer);
+            gl::DeleteFramebuffers(1, &self.id);
+        }
+    }
+}
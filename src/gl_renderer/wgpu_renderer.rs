@@ -0,0 +1,893 @@
+use super::font::{shared_library, Font, FontConfig, FontRegistry, GlyphKey};
+use super::{decoration_rect, layout_glyphs, measure, scaled_font_size, FontDescription};
+use crate::color::Color;
+use crate::{
+    BorderRadius, Quad, Renderer, Style, TabStop, TextDecoration, TextDirection, TextStyle,
+};
+use std::collections::HashMap;
+use std::rc::Rc;
+use wgpu::util::DeviceExt;
+
+// glyph bitmaps share one single channel texture of this size
+const ATLAS_SIZE: u32 = 1024;
+
+// both kinds of instances are a rect and two more vec4s
+const INSTANCE_FLOATS: usize = 12;
+const INSTANCE_ATTRIBUTES: [wgpu::VertexAttribute; 3] =
+    wgpu::vertex_attr_array![0 => Float32x4, 1 => Float32x4, 2 => Float32x4];
+
+const SHADER_SRC: &str = "
+struct Globals {
+    viewport: vec2<f32>,
+    padding: vec2<f32>,
+}
+
+@group(0) @binding(0) var<uniform> globals: Globals;
+@group(1) @binding(0) var atlas: texture_2d<f32>;
+@group(1) @binding(1) var atlas_sampler: sampler;
+
+fn corner(index: u32) -> vec2<f32> {
+    var corners = array<vec2<f32>, 6>(
+        vec2<f32>(0.0, 0.0), vec2<f32>(1.0, 0.0), vec2<f32>(0.0, 1.0),
+        vec2<f32>(0.0, 1.0), vec2<f32>(1.0, 0.0), vec2<f32>(1.0, 1.0),
+    );
+    return corners[index];
+}
+
+// framebuffer pixels with a top left origin to clip space
+fn to_clip(pixel: vec2<f32>) -> vec4<f32> {
+    let ndc = pixel / globals.viewport * 2.0 - 1.0;
+    return vec4<f32>(ndc.x, -ndc.y, 0.0, 1.0);
+}
+
+struct QuadOut {
+    @builtin(position) position: vec4<f32>,
+    @location(0) local: vec2<f32>,
+    @location(1) size: vec2<f32>,
+    @location(2) color: vec4<f32>,
+    @location(3) radii: vec4<f32>,
+}
+
+@vertex
+fn quad_vertex(
+    @builtin(vertex_index) index: u32,
+    @location(0) rect: vec4<f32>,
+    @location(1) color: vec4<f32>,
+    @location(2) radii: vec4<f32>,
+) -> QuadOut {
+    let local = corner(index) * rect.zw;
+    var out: QuadOut;
+    out.position = to_clip(rect.xy + local);
+    out.local = local;
+    out.size = rect.zw;
+    out.color = color;
+    out.radii = radii;
+    return out;
+}
+
+@fragment
+fn quad_fragment(in: QuadOut) -> @location(0) vec4<f32> {
+    // top left, top right, bottom left, bottom right, each quadrant is rounded by its own corner
+    let half_size = in.size / 2.0;
+    let p = in.local - half_size;
+    let corner = select(
+        select(in.radii.w, in.radii.y, p.y < 0.0),
+        select(in.radii.z, in.radii.x, p.y < 0.0),
+        p.x < 0.0,
+    );
+    let radius = min(min(in.size.x, in.size.y) / 2.0, corner);
+    let q = abs(p) - half_size + vec2<f32>(radius);
+    let dist = length(max(q, vec2<f32>(0.0))) + min(max(q.x, q.y), 0.0) - radius;
+    let coverage = 1.0 - smoothstep(-0.5, 0.5, dist);
+    return vec4<f32>(in.color.rgb, in.color.a * coverage);
+}
+
+struct GlyphOut {
+    @builtin(position) position: vec4<f32>,
+    @location(0) uv: vec2<f32>,
+    @location(1) color: vec4<f32>,
+}
+
+@vertex
+fn glyph_vertex(
+    @builtin(vertex_index) index: u32,
+    @location(0) rect: vec4<f32>,
+    @location(1) uv_rect: vec4<f32>,
+    @location(2) color: vec4<f32>,
+) -> GlyphOut {
+    let c = corner(index);
+    var out: GlyphOut;
+    out.position = to_clip(rect.xy + c * rect.zw);
+    out.uv = uv_rect.xy + c * uv_rect.zw;
+    out.color = color;
+    return out;
+}
+
+@fragment
+fn glyph_fragment(in: GlyphOut) -> @location(0) vec4<f32> {
+    let coverage = textureSample(atlas, atlas_sampler, in.uv).r;
+    return vec4<f32>(in.color.rgb, in.color.a * coverage);
+}
+";
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum BatchKind {
+    Quads,
+    Glyphs,
+}
+
+// instances drawn with one pipeline and scissor, in the order they were recorded
+#[derive(Debug, Clone, Copy, PartialEq)]
+struct Batch {
+    kind: BatchKind,
+    clip: Option<(f64, f64, f64, f64)>,
+    start: u32,
+    count: u32,
+}
+
+// where a glyph's bitmap sits in the atlas, offsets are from the pen position in pixels
+#[derive(Debug, Clone, Copy)]
+struct AtlasGlyph {
+    left: f64,
+    top: f64,
+    width: f64,
+    height: f64,
+    uv_rect: [f32; 4],
+}
+
+// packs bitmaps left to right into rows as tall as their tallest bitmap
+#[derive(Debug)]
+struct Shelves {
+    size: u32,
+    x: u32,
+    y: u32,
+    row_height: u32,
+}
+
+impl Shelves {
+    fn new(size: u32) -> Self {
+        Shelves {
+            size,
+            x: 0,
+            y: 0,
+            row_height: 0,
+        }
+    }
+
+    // a pixel of space is left between bitmaps so sampling never bleeds into a neighbour
+    fn place(&mut self, width: u32, height: u32) -> Option<(u32, u32)> {
+        if width > self.size || height > self.size {
+            return None;
+        }
+        if self.x + width > self.size {
+            self.x = 0;
+            self.y += self.row_height;
+            self.row_height = 0;
+        }
+        if self.y + height > self.size {
+            return None;
+        }
+        let position = (self.x, self.y);
+        self.x += width + 1;
+        self.row_height = self.row_height.max(height + 1);
+        Some(position)
+    }
+}
+
+fn f32_bytes(values: &[f32]) -> Vec<u8> {
+    values
+        .iter()
+        .flat_map(|value| value.to_le_bytes())
+        .collect()
+}
+
+// a clip in framebuffer pixels as a scissor rect inside the viewport, None if nothing is left
+fn scissor_rect(clip: Option<(f64, f64, f64, f64)>, viewport: (f64, f64)) -> Option<[u32; 4]> {
+    let (width, height) = (viewport.0.floor(), viewport.1.floor());
+    let (x, y, clip_width, clip_height) = clip.unwrap_or((0.0, 0.0, width, height));
+    let left = x.floor().clamp(0.0, width);
+    let top = y.floor().clamp(0.0, height);
+    let right = (x + clip_width).ceil().clamp(left, width);
+    let bottom = (y + clip_height).ceil().clamp(top, height);
+    if right <= left || bottom <= top {
+        return None;
+    }
+    Some([
+        left as u32,
+        top as u32,
+        (right - left) as u32,
+        (bottom - top) as u32,
+    ])
+}
+
+// draws quads and text with wgpu for hosts that can't or don't want to use OpenGL. the
+// Renderer calls record instances and draw_frame encodes them into one render pass.
+// images, nine-patches, lines and color glyphs are left out
+pub struct WgpuRenderer {
+    device: wgpu::Device,
+    queue: wgpu::Queue,
+    quad_pipeline: wgpu::RenderPipeline,
+    glyph_pipeline: wgpu::RenderPipeline,
+    globals: wgpu::Buffer,
+    globals_group: wgpu::BindGroup,
+    atlas: wgpu::Texture,
+    atlas_group: wgpu::BindGroup,
+    shelves: Shelves,
+    atlas_full: bool,
+    glyphs: HashMap<(FontDescription, GlyphKey), Option<AtlasGlyph>>,
+    library: Rc<freetype::Library>,
+    registry: FontRegistry,
+    fonts: HashMap<FontDescription, Font>,
+    instances: Vec<f32>,
+    batches: Vec<Batch>,
+    clip: Option<(f64, f64, f64, f64)>,
+    viewport: (f64, f64),
+    scale_factor: f64,
+    debug_bounds: bool,
+    draw_calls: usize,
+}
+
+impl WgpuRenderer {
+    // format is the format of the views draw_frame renders into
+    pub fn new(device: wgpu::Device, queue: wgpu::Queue, format: wgpu::TextureFormat) -> Self {
+        let shader = device.create_shader_module(wgpu::ShaderModuleDescriptor {
+            label: Some("winkel shader"),
+            source: wgpu::ShaderSource::Wgsl(SHADER_SRC.into()),
+        });
+        let globals_layout = device.create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor {
+            label: Some("winkel globals"),
+            entries: &[wgpu::BindGroupLayoutEntry {
+                binding: 0,
+                visibility: wgpu::ShaderStages::VERTEX,
+                ty: wgpu::BindingType::Buffer {
+                    ty: wgpu::BufferBindingType::Uniform,
+                    has_dynamic_offset: false,
+                    min_binding_size: None,
+                },
+                count: None,
+            }],
+        });
+        let atlas_layout = device.create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor {
+            label: Some("winkel atlas"),
+            entries: &[
+                wgpu::BindGroupLayoutEntry {
+                    binding: 0,
+                    visibility: wgpu::ShaderStages::FRAGMENT,
+                    ty: wgpu::BindingType::Texture {
+                        sample_type: wgpu::TextureSampleType::Float { filterable: true },
+                        view_dimension: wgpu::TextureViewDimension::D2,
+                        multisampled: false,
+                    },
+                    count: None,
+                },
+                wgpu::BindGroupLayoutEntry {
+                    binding: 1,
+                    visibility: wgpu::ShaderStages::FRAGMENT,
+                    ty: wgpu::BindingType::Sampler(wgpu::SamplerBindingType::Filtering),
+                    count: None,
+                },
+            ],
+        });
+        let globals = device.create_buffer(&wgpu::BufferDescriptor {
+            label: Some("winkel globals"),
+            size: 16,
+            usage: wgpu::BufferUsages::UNIFORM | wgpu::BufferUsages::COPY_DST,
+            mapped_at_creation: false,
+        });
+        let globals_group = device.create_bind_group(&wgpu::BindGroupDescriptor {
+            label: Some("winkel globals"),
+            layout: &globals_layout,
+            entries: &[wgpu::BindGroupEntry {
+                binding: 0,
+                resource: globals.as_entire_binding(),
+            }],
+        });
+        let atlas = device.create_texture(&wgpu::TextureDescriptor {
+            label: Some("winkel glyph atlas"),
+            size: wgpu::Extent3d {
+                width: ATLAS_SIZE,
+                height: ATLAS_SIZE,
+                depth_or_array_layers: 1,
+            },
+            mip_level_count: 1,
+            sample_count: 1,
+            dimension: wgpu::TextureDimension::D2,
+            format: wgpu::TextureFormat::R8Unorm,
+            usage: wgpu::TextureUsages::TEXTURE_BINDING | wgpu::TextureUsages::COPY_DST,
+            view_formats: &[],
+        });
+        // glyphs are drawn at the size they were rasterized at, so nearest sampling is exact
+        let sampler = device.create_sampler(&wgpu::SamplerDescriptor {
+            label: Some("winkel glyph atlas"),
+            ..Default::default()
+        });
+        let atlas_group = device.create_bind_group(&wgpu::BindGroupDescriptor {
+            label: Some("winkel atlas"),
+            layout: &atlas_layout,
+            entries: &[
+                wgpu::BindGroupEntry {
+                    binding: 0,
+                    resource: wgpu::BindingResource::TextureView(
+                        &atlas.create_view(&wgpu::TextureViewDescriptor::default()),
+                    ),
+                },
+                wgpu::BindGroupEntry {
+                    binding: 1,
+                    resource: wgpu::BindingResource::Sampler(&sampler),
+                },
+            ],
+        });
+        let quad_pipeline = WgpuRenderer::pipeline(
+            &device,
+            &[Some(&globals_layout)],
+            &shader,
+            ("quad_vertex", "quad_fragment"),
+            format,
+        );
+        let glyph_pipeline = WgpuRenderer::pipeline(
+            &device,
+            &[Some(&globals_layout), Some(&atlas_layout)],
+            &shader,
+            ("glyph_vertex", "glyph_fragment"),
+            format,
+        );
+        WgpuRenderer {
+            device,
+            queue,
+            quad_pipeline,
+            glyph_pipeline,
+            globals,
+            globals_group,
+            atlas,
+            atlas_group,
+            shelves: Shelves::new(ATLAS_SIZE),
+            atlas_full: false,
+            glyphs: HashMap::new(),
+            library: shared_library(),
+            registry: FontRegistry::new(),
+            fonts: HashMap::new(),
+            instances: Vec::new(),
+            batches: Vec::new(),
+            clip: None,
+            viewport: (1.0, 1.0),
+            scale_factor: 1.0,
+            debug_bounds: false,
+            draw_calls: 0,
+        }
+    }
+
+    fn pipeline(
+        device: &wgpu::Device,
+        bind_group_layouts: &[Option<&wgpu::BindGroupLayout>],
+        shader: &wgpu::ShaderModule,
+        (vertex, fragment): (&str, &str),
+        format: wgpu::TextureFormat,
+    ) -> wgpu::RenderPipeline {
+        let layout = device.create_pipeline_layout(&wgpu::PipelineLayoutDescriptor {
+            label: Some(vertex),
+            bind_group_layouts,
+            immediate_size: 0,
+        });
+        device.create_render_pipeline(&wgpu::RenderPipelineDescriptor {
+            label: Some(vertex),
+            layout: Some(&layout),
+            vertex: wgpu::VertexState {
+                module: shader,
+                entry_point: Some(vertex),
+                compilation_options: Default::default(),
+                buffers: &[Some(wgpu::VertexBufferLayout {
+                    array_stride: (INSTANCE_FLOATS * 4) as wgpu::BufferAddress,
+                    step_mode: wgpu::VertexStepMode::Instance,
+                    attributes: &INSTANCE_ATTRIBUTES,
+                })],
+            },
+            primitive: wgpu::PrimitiveState::default(),
+            depth_stencil: None,
+            multisample: wgpu::MultisampleState::default(),
+            fragment: Some(wgpu::FragmentState {
+                module: shader,
+                entry_point: Some(fragment),
+                compilation_options: Default::default(),
+                targets: &[Some(wgpu::ColorTargetState {
+                    format,
+                    blend: Some(wgpu::BlendState::ALPHA_BLENDING),
+                    write_mask: wgpu::ColorWrites::ALL,
+                })],
+            }),
+            multiview_mask: None,
+            cache: None,
+        })
+    }
+
+    pub fn scale_factor(&self) -> f64 {
+        self.scale_factor
+    }
+
+    pub fn add_font_bytes(&mut self, name: &str, bytes: &[u8]) {
+        self.registry.add_bytes(name, bytes);
+        self.fonts.retain(|desc, _| desc.name != name);
+        self.glyphs.retain(|(desc, _), _| desc.name != name);
+        measure::set_registry(&self.registry);
+    }
+
+    pub fn set_font_registry(&mut self, registry: FontRegistry) {
+        self.registry = registry;
+        self.fonts.clear();
+        self.glyphs.clear();
+        measure::set_registry(&self.registry);
+    }
+
+    // encodes everything recorded since the last frame into one render pass over view,
+    // which is cleared first if a color is given
+    pub fn draw_frame(&mut self, view: &wgpu::TextureView, clear: Option<Color>) {
+        let (width, height) = self.viewport;
+        self.queue.write_buffer(
+            &self.globals,
+            0,
+            &f32_bytes(&[width as f32, height as f32, 0.0, 0.0]),
+        );
+        let instances = if self.instances.is_empty() {
+            None
+        } else {
+            Some(
+                self.device
+                    .create_buffer_init(&wgpu::util::BufferInitDescriptor {
+                        label: Some("winkel instances"),
+                        contents: &f32_bytes(&self.instances),
+                        usage: wgpu::BufferUsages::VERTEX,
+                    }),
+            )
+        };
+        let load = match clear {
+            Some([r, g, b, a]) => wgpu::LoadOp::Clear(wgpu::Color {
+                r: r as f64,
+                g: g as f64,
+                b: b as f64,
+                a: a as f64,
+            }),
+            None => wgpu::LoadOp::Load,
+        };
+        let mut encoder = self
+            .device
+            .create_command_encoder(&wgpu::CommandEncoderDescriptor {
+                label: Some("winkel frame"),
+            });
+        {
+            let mut pass = encoder.begin_render_pass(&wgpu::RenderPassDescriptor {
+                label: Some("winkel frame"),
+                color_attachments: &[Some(wgpu::RenderPassColorAttachment {
+                    view,
+                    depth_slice: None,
+                    resolve_target: None,
+                    ops: wgpu::Operations {
+                        load,
+                        store: wgpu::StoreOp::Store,
+                    },
+                })],
+                depth_stencil_attachment: None,
+                timestamp_writes: None,
+                occlusion_query_set: None,
+                multiview_mask: None,
+            });
+            if let Some(instances) = &instances {
+                pass.set_vertex_buffer(0, instances.slice(..));
+            }
+            pass.set_bind_group(0, &self.globals_group, &[]);
+            for batch in &self.batches {
+                let [x, y, width, height] = match scissor_rect(batch.clip, self.viewport) {
+                    Some(rect) => rect,
+                    None => continue,
+                };
+                pass.set_scissor_rect(x, y, width, height);
+                match batch.kind {
+                    BatchKind::Quads => pass.set_pipeline(&self.quad_pipeline),
+                    BatchKind::Glyphs => {
+                        pass.set_pipeline(&self.glyph_pipeline);
+                        pass.set_bind_group(1, &self.atlas_group, &[]);
+                    }
+                }
+                pass.draw(0..6, batch.start..batch.start + batch.count);
+                self.draw_calls += 1;
+            }
+        }
+        self.queue.submit(Some(encoder.finish()));
+        self.instances.clear();
+        self.batches.clear();
+        // glyphs that didn't fit are rasterized again into an empty atlas next frame
+        if self.atlas_full {
+            self.glyphs.clear();
+            self.shelves = Shelves::new(ATLAS_SIZE);
+            self.atlas_full = false;
+        }
+    }
+
+    fn record(&mut self, kind: BatchKind, instance: [f32; INSTANCE_FLOATS]) {
+        let index = (self.instances.len() / INSTANCE_FLOATS) as u32;
+        self.instances.extend_from_slice(&instance);
+        match self.batches.last_mut() {
+            Some(batch) if batch.kind == kind && batch.clip == self.clip => batch.count += 1,
+            _ => self.batches.push(Batch {
+                kind,
+                clip: self.clip,
+                start: index,
+                count: 1,
+            }),
+        }
+    }
+
+    // bounds are in logical pixels
+    fn record_quad(&mut self, bounds: (f64, f64, f64, f64), color: Color, radius: BorderRadius) {
+        let scale = self.scale_factor;
+        let (x, y, width, height) = bounds;
+        self.record(
+            BatchKind::Quads,
+            [
+                (x * scale) as f32,
+                (y * scale) as f32,
+                (width * scale) as f32,
+                (height * scale) as f32,
+                color[0],
+                color[1],
+                color[2],
+                color[3],
+                (radius.top_left * scale) as f32,
+                (radius.top_right * scale) as f32,
+                (radius.bottom_left * scale) as f32,
+                (radius.bottom_right * scale) as f32,
+            ],
+        );
+    }
+
+    fn set_viewport(&mut self, window_width: f64, window_height: f64) {
+        self.viewport = (
+            (window_width * self.scale_factor).max(1.0),
+            (window_height * self.scale_factor).max(1.0),
+        );
+    }
+
+    fn load_font(&mut self, name: &str, size: u32, config: FontConfig) -> FontDescription {
+        let description = FontDescription {
+            name: String::from(name),
+            size,
+            config,
+        };
+        if !self.fonts.contains_key(&description) {
+            let font = self.registry.load(&self.library, name, size, config);
+            self.fonts.insert(description.clone(), font);
+        }
+        description
+    }
+
+    fn tab_width(&mut self, style: &TextStyle, description: &FontDescription) -> f64 {
+        match style.tab_stop {
+            TabStop::Pixels(width) => width * self.scale_factor,
+            TabStop::Spaces(count) => {
+                let font = self.fonts.get_mut(description).unwrap();
+                count as f64 * font.glyph_advance(GlyphKey::Char(' '))
+            }
+        }
+    }
+
+    // rasterizes and uploads a glyph the first time it is drawn, None if there is nothing to draw
+    fn atlas_glyph(&mut self, description: &FontDescription, key: GlyphKey) -> Option<AtlasGlyph> {
+        let cache_key = (description.clone(), key);
+        if let Some(glyph) = self.glyphs.get(&cache_key) {
+            return *glyph;
+        }
+        let coverage = self.fonts.get_mut(description).unwrap().coverage(key);
+        let coverage = match coverage {
+            Some(coverage) if coverage.width > 0 && coverage.height > 0 => coverage,
+            _ => {
+                self.glyphs.insert(cache_key, None);
+                return None;
+            }
+        };
+        let (width, height) = (coverage.width as u32, coverage.height as u32);
+        let (x, y) = match self.shelves.place(width, height) {
+            Some(position) => position,
+            None => {
+                self.atlas_full = true;
+                return None;
+            }
+        };
+        self.queue.write_texture(
+            wgpu::TexelCopyTextureInfo {
+                texture: &self.atlas,
+                mip_level: 0,
+                origin: wgpu::Origin3d { x, y, z: 0 },
+                aspect: wgpu::TextureAspect::All,
+            },
+            &coverage.data,
+            wgpu::TexelCopyBufferLayout {
+                offset: 0,
+                bytes_per_row: Some(width),
+                rows_per_image: Some(height),
+            },
+            wgpu::Extent3d {
+                width,
+                height,
+                depth_or_array_layers: 1,
+            },
+        );
+        let size = ATLAS_SIZE as f32;
+        let glyph = AtlasGlyph {
+            left: coverage.left as f64,
+            top: coverage.top as f64,
+            width: width as f64,
+            height: height as f64,
+            uv_rect: [
+                x as f32 / size,
+                y as f32 / size,
+                width as f32 / size,
+                height as f32 / size,
+            ],
+        };
+        self.glyphs.insert(cache_key, Some(glyph));
+        Some(glyph)
+    }
+}
+
+impl Renderer for WgpuRenderer {
+    fn render_quad(
+        &mut self,
+        x: f64,
+        y: f64,
+        _z: usize,
+        width: f64,
+        height: f64,
+        style: &Style,
+        window_width: f64,
+        window_height: f64,
+    ) {
+        self.set_viewport(window_width, window_height);
+        if let Some(color) = style.color {
+            self.record_quad((x, y, width, height), color, style.border_radius);
+        }
+    }
+
+    fn render_quads(&mut self, quads: &[Quad], window_width: f64, window_height: f64) {
+        self.set_viewport(window_width, window_height);
+        for quad in quads {
+            self.record_quad(
+                (quad.x, quad.y, quad.width, quad.height),
+                quad.color,
+                quad.border_radius,
+            );
+        }
+    }
+
+    fn render_text<'b>(
+        &mut self,
+        x: f64,
+        y: f64,
+        _z: usize,
+        width: f64,
+        _height: f64,
+        text: &'b str,
+        style: &TextStyle<'b>,
+        window_width: f64,
+        window_height: f64,
+    ) {
+        self.set_viewport(window_width, window_height);
+        let config = FontConfig::from(style.hinting);
+        let size = scaled_font_size(style.size, self.scale_factor);
+        let names: Vec<String> = self.registry.chain(style.font).map(String::from).collect();
+        let descriptions: Vec<FontDescription> = names
+            .iter()
+            .map(|name| self.load_font(name, size, config))
+            .collect();
+        // glyphs are placed in framebuffer pixels
+        let scale = self.scale_factor;
+        let (x, y, width) = (x * scale, y * scale, width * scale);
+        let fontsize = size as f64;
+        let origin = match style.direction {
+            TextDirection::Ltr => x,
+            TextDirection::Rtl => x + width,
+        };
+        let tab_width = self.tab_width(style, &descriptions[0]);
+        // horizontal extent of each line, keyed by its y offset
+        let mut lines: Vec<(f64, f64, f64)> = Vec::new();
+        for glyph in layout_glyphs(&mut self.fonts, text, style, &descriptions, tab_width) {
+            let description = &descriptions[glyph.font];
+            if style.decoration != TextDecoration::None {
+                let start = origin + glyph.x;
+                let end = start
+                    + self
+                        .fonts
+                        .get_mut(description)
+                        .unwrap()
+                        .glyph_advance(glyph.key);
+                match lines.iter_mut().find(|line| line.0 == glyph.y) {
+                    Some(line) => {
+                        line.1 = line.1.min(start);
+                        line.2 = line.2.max(end);
+                    }
+                    None => lines.push((glyph.y, start, end)),
+                }
+            }
+            let placed = match self.atlas_glyph(description, glyph.key) {
+                Some(placed) => placed,
+                None => continue,
+            };
+            let glyph_x = config.snap(origin + glyph.x) + placed.left;
+            let glyph_y = config.snap(y + glyph.y + fontsize) - placed.top;
+            let [u, v, uv_width, uv_height] = placed.uv_rect;
+            let color = style.color;
+            self.record(
+                BatchKind::Glyphs,
+                [
+                    glyph_x as f32,
+                    glyph_y as f32,
+                    placed.width as f32,
+                    placed.height as f32,
+                    u,
+                    v,
+                    uv_width,
+                    uv_height,
+                    color[0],
+                    color[1],
+                    color[2],
+                    color[3],
+                ],
+            );
+        }
+        if lines.is_empty() {
+            return;
+        }
+        let (offset, thickness) = self.fonts[&descriptions[0]].decoration_line(style.decoration);
+        let color = style.decoration_color.unwrap_or(style.color);
+        for (line_y, start, end) in lines {
+            let (x, y, width, height) =
+                decoration_rect(y + line_y + fontsize, start, end, (offset, thickness));
+            self.record_quad(
+                (x / scale, y / scale, width / scale, height / scale),
+                color,
+                BorderRadius::default(),
+            );
+        }
+    }
+
+    fn set_clip(
+        &mut self,
+        clip: Option<(f64, f64, f64, f64)>,
+        window_width: f64,
+        window_height: f64,
+    ) {
+        self.set_viewport(window_width, window_height);
+        let scale = self.scale_factor;
+        self.clip =
+            clip.map(|(x, y, width, height)| (x * scale, y * scale, width * scale, height * scale));
+    }
+
+    fn set_scale_factor(&mut self, scale_factor: f64) {
+        self.scale_factor = scale_factor;
+    }
+
+    fn debug_bounds(&self) -> bool {
+        self.debug_bounds
+    }
+
+    fn set_debug_bounds(&mut self, enabled: bool) {
+        self.debug_bounds = enabled;
+    }
+
+    // draw calls issued by draw_frame
+    fn take_draw_calls(&mut self) -> usize {
+        std::mem::replace(&mut self.draw_calls, 0)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::color;
+    use crate::compute;
+    use crate::widgets::{Rectangle, Stack, Text, Widget};
+    use std::cell::RefCell;
+
+    const FONT: &str = "Raleway-Regular.ttf";
+
+    fn renderer<R: Renderer>() {}
+
+    fn noop_renderer() -> WgpuRenderer {
+        let (device, queue) = wgpu::Device::noop(&wgpu::DeviceDescriptor::default());
+        WgpuRenderer::new(device, queue, wgpu::TextureFormat::Rgba8Unorm)
+    }
+
+    fn target(renderer: &WgpuRenderer, width: u32, height: u32) -> wgpu::TextureView {
+        renderer
+            .device
+            .create_texture(&wgpu::TextureDescriptor {
+                label: None,
+                size: wgpu::Extent3d {
+                    width,
+                    height,
+                    depth_or_array_layers: 1,
+                },
+                mip_level_count: 1,
+                sample_count: 1,
+                dimension: wgpu::TextureDimension::D2,
+                format: wgpu::TextureFormat::Rgba8Unorm,
+                usage: wgpu::TextureUsages::RENDER_ATTACHMENT,
+                view_formats: &[],
+            })
+            .create_view(&wgpu::TextureViewDescriptor::default())
+    }
+
+    #[test]
+    fn implements_the_renderer_trait() {
+        renderer::<WgpuRenderer>();
+    }
+
+    #[test]
+    fn shelves_start_a_new_row_when_full() {
+        let mut shelves = Shelves::new(16);
+        assert_eq!(shelves.place(6, 4), Some((0, 0)));
+        assert_eq!(shelves.place(6, 8), Some((7, 0)));
+        assert_eq!(shelves.place(6, 4), Some((0, 9)));
+        assert_eq!(shelves.place(6, 8), None);
+        assert_eq!(shelves.place(17, 1), None);
+    }
+
+    #[test]
+    fn clips_become_scissor_rects_inside_the_viewport() {
+        assert_eq!(scissor_rect(None, (100.0, 50.0)), Some([0, 0, 100, 50]));
+        assert_eq!(
+            scissor_rect(Some((-10.0, 10.5, 40.0, 100.0)), (100.0, 50.0)),
+            Some([0, 10, 30, 40])
+        );
+        assert_eq!(
+            scissor_rect(Some((120.0, 0.0, 10.0, 10.0)), (100.0, 50.0)),
+            None
+        );
+    }
+
+    #[test]
+    fn pipelines_build_on_a_device() {
+        let renderer = noop_renderer();
+        assert!(renderer.batches.is_empty());
+        assert_eq!(renderer.viewport, (1.0, 1.0));
+    }
+
+    #[test]
+    fn batches_split_on_kind_and_clip() {
+        let mut renderer = noop_renderer();
+        let radius = BorderRadius::uniform(4.0);
+        renderer.record_quad((0.0, 0.0, 10.0, 10.0), [1.0; 4], radius);
+        renderer.record_quad((10.0, 0.0, 10.0, 10.0), [1.0; 4], radius);
+        renderer.record(BatchKind::Glyphs, [0.0; INSTANCE_FLOATS]);
+        renderer.set_clip(Some((0.0, 0.0, 5.0, 5.0)), 100.0, 100.0);
+        renderer.record_quad((0.0, 0.0, 10.0, 10.0), [1.0; 4], radius);
+        let batches: Vec<(BatchKind, u32, u32)> = renderer
+            .batches
+            .iter()
+            .map(|batch| (batch.kind, batch.start, batch.count))
+            .collect();
+        assert_eq!(
+            batches,
+            vec![
+                (BatchKind::Quads, 0, 2),
+                (BatchKind::Glyphs, 2, 1),
+                (BatchKind::Quads, 3, 1)
+            ]
+        );
+        assert_eq!(&renderer.instances[8..12], &[4.0; 4]);
+    }
+
+    #[test]
+    fn frames_draw_one_call_per_batch() {
+        let mut renderer = noop_renderer();
+        let tree: Rc<RefCell<dyn Widget>> = Stack::new()
+            .add(Rectangle::new(color::RED))
+            .add(Text::new("wgpu", 16, FONT).decoration(TextDecoration::Underline))
+            .build();
+        let computed = compute(&tree, 100.0, 40.0);
+        renderer.render(&computed, 100.0, 40.0);
+        let view = target(&renderer, 100, 40);
+        renderer.draw_frame(&view, Some(color::WHITE));
+        // the rectangle, the glyphs over it and their underline
+        assert_eq!(renderer.take_draw_calls(), 3);
+        assert!(renderer.instances.is_empty());
+        assert!(renderer.glyphs.values().any(Option::is_some));
+    }
+}
@@ -1,8 +1,18 @@
-use std::cell::RefCell;
-use std::collections::HashMap;
+use std::borrow::Cow;
+use std::cell::{Cell, RefCell};
+use std::collections::{HashMap, VecDeque};
 use std::rc::Rc;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, Mutex};
 
 pub mod color;
+mod macros;
+#[cfg(feature = "spec")]
+pub mod spec;
+#[cfg(feature = "testing")]
+pub mod testing;
+#[cfg(feature = "trace")]
+pub mod trace;
 pub mod widgets;
 
 use color::Color;
@@ -21,6 +31,26 @@ pub trait Renderer {
         window_height: f64,
     );
 
+    // falls back to a fully rounded quad, which is only exact for circles
+    fn render_ellipse(
+        &mut self,
+        x: f64,
+        y: f64,
+        z: usize,
+        width: f64,
+        height: f64,
+        style: &Style,
+        window_width: f64,
+        window_height: f64,
+    ) {
+        let style = Style {
+            color: style.color,
+            border_radius: BorderRadius::uniform(width.min(height) / 2.0),
+            image: style.image.clone(),
+        };
+        self.render_quad(x, y, z, width, height, &style, window_width, window_height);
+    }
+
     fn render_text<'a>(
         &mut self,
         x: f64,
@@ -34,16 +64,77 @@ pub trait Renderer {
         window_height: f64,
     );
 
+    // solid rectangles that follow each other in drawing order and share a clip,
+    // renderers that can draw them in one go should
+    fn render_quads(&mut self, quads: &[Quad], window_width: f64, window_height: f64) {
+        for quad in quads {
+            let style = Style {
+                color: Some(quad.color),
+                border_radius: quad.border_radius,
+                image: None,
+            };
+            self.render_quad(
+                quad.x,
+                quad.y,
+                quad.z,
+                quad.width,
+                quad.height,
+                &style,
+                window_width,
+                window_height,
+            );
+        }
+    }
+
     fn render(
         &mut self,
         computed: &HashMap<usize, ComputedWidget>,
         window_width: f64,
         window_height: f64,
     ) {
+        if window_width <= 0.0 || window_height <= 0.0 {
+            return;
+        }
+        // depth testing alone can't blend translucent widgets correctly,
+        // so they are still drawn back to front
         let mut widgets: Vec<&ComputedWidget> =
             computed.values().filter(|w| w.render.is_some()).collect();
         widgets.sort_by_key(|w| w.z);
+        let mut batch: Vec<Quad> = Vec::new();
+        let mut batch_clip = None;
         for widget in widgets {
+            if let Some(RenderObject::Rectangle {
+                style:
+                    Style {
+                        color: Some(color),
+                        border_radius,
+                        image: None,
+                    },
+            }) = &widget.render
+            {
+                if !batch.is_empty() && batch_clip != widget.clip {
+                    self.set_clip(batch_clip, window_width, window_height);
+                    self.render_quads(&batch, window_width, window_height);
+                    batch.clear();
+                }
+                batch_clip = widget.clip;
+                batch.push(Quad {
+                    x: widget.x,
+                    y: widget.y,
+                    z: widget.z,
+                    width: widget.width,
+                    height: widget.height,
+                    color: *color,
+                    border_radius: *border_radius,
+                });
+                continue;
+            }
+            if !batch.is_empty() {
+                self.set_clip(batch_clip, window_width, window_height);
+                self.render_quads(&batch, window_width, window_height);
+                batch.clear();
+            }
+            self.set_clip(widget.clip, window_width, window_height);
             match widget.render.as_ref().unwrap() {
                 RenderObject::Rectangle { style } => {
                     self.render_quad(
@@ -57,6 +148,18 @@ pub trait Renderer {
                         window_height,
                     );
                 }
+                RenderObject::Ellipse { style } => {
+                    self.render_ellipse(
+                        widget.x,
+                        widget.y,
+                        widget.z,
+                        widget.width,
+                        widget.height,
+                        style,
+                        window_width,
+                        window_height,
+                    );
+                }
                 RenderObject::Text { text, style } => {
                     self.render_text(
                         widget.x,
@@ -70,45 +173,409 @@ pub trait Renderer {
                         window_height,
                     );
                 }
+                RenderObject::Line {
+                    points,
+                    width,
+                    color,
+                } => {
+                    let stroke = LineStroke {
+                        points,
+                        width: *width,
+                        color: *color,
+                    };
+                    self.render_line(
+                        widget.x,
+                        widget.y,
+                        widget.z,
+                        &stroke,
+                        window_width,
+                        window_height,
+                    );
+                }
+                RenderObject::Custom(commands) => {
+                    self.render_custom(
+                        widget.x,
+                        widget.y,
+                        widget.z,
+                        commands,
+                        window_width,
+                        window_height,
+                    );
+                }
+                RenderObject::RichText { spans, hinting } => {
+                    let block = RichTextBlock {
+                        x: widget.x,
+                        y: widget.y,
+                        z: widget.z,
+                        width: widget.width,
+                        height: widget.height,
+                        spans,
+                        hinting: *hinting,
+                    };
+                    self.render_rich_text(&block, window_width, window_height);
+                }
+                RenderObject::NinePatch { path, insets } => {
+                    let patch = NinePatchQuad {
+                        x: widget.x,
+                        y: widget.y,
+                        z: widget.z,
+                        width: widget.width,
+                        height: widget.height,
+                        path,
+                        insets: *insets,
+                    };
+                    self.render_nine_patch(&patch, window_width, window_height);
+                }
+            }
+        }
+        if !batch.is_empty() {
+            self.set_clip(batch_clip, window_width, window_height);
+            self.render_quads(&batch, window_width, window_height);
+        }
+        self.set_clip(None, window_width, window_height);
+        if self.debug_bounds() {
+            let top = computed.values().map(|w| w.z).max().unwrap_or(0) + 1;
+            let style = Style {
+                color: Some(color::MAGENTA),
+                border_radius: BorderRadius::default(),
+                image: None,
+            };
+            for widget in computed.values() {
+                let (x, y, w, h) = (widget.x, widget.y, widget.width, widget.height);
+                let t = DEBUG_BOUNDS_WIDTH.min(w).min(h);
+                for (x, y, w, h) in [
+                    (x, y, w, t),
+                    (x, y + h - t, w, t),
+                    (x, y, t, h),
+                    (x + w - t, y, t, h),
+                ] {
+                    self.render_quad(x, y, top, w, h, &style, window_width, window_height);
+                }
+            }
+        }
+    }
+
+    // draws the spans one after another, each with its own style
+    fn render_rich_text(&mut self, block: &RichTextBlock, window_width: f64, window_height: f64) {
+        let (pieces, _) = span_layout(block.spans, block.hinting);
+        for piece in pieces {
+            self.render_text(
+                block.x + piece.x,
+                block.y + piece.y,
+                block.z,
+                block.width - piece.x,
+                block.height - piece.y,
+                piece.text,
+                &block.spans[piece.span].style(block.hinting),
+                window_width,
+                window_height,
+            );
+        }
+    }
+
+    // command coordinates are relative to x and y
+    fn render_custom(
+        &mut self,
+        x: f64,
+        y: f64,
+        z: usize,
+        commands: &[DrawCommand],
+        window_width: f64,
+        window_height: f64,
+    ) {
+        for command in commands {
+            match *command {
+                DrawCommand::FillRect {
+                    x: rx,
+                    y: ry,
+                    width,
+                    height,
+                    color,
+                } => {
+                    let style = Style {
+                        color: Some(color),
+                        border_radius: BorderRadius::default(),
+                        image: None,
+                    };
+                    self.render_quad(
+                        x + rx,
+                        y + ry,
+                        z,
+                        width,
+                        height,
+                        &style,
+                        window_width,
+                        window_height,
+                    );
+                }
+                DrawCommand::FillCircle {
+                    x: cx,
+                    y: cy,
+                    radius,
+                    color,
+                } => {
+                    let style = Style {
+                        color: Some(color),
+                        border_radius: BorderRadius::uniform(radius),
+                        image: None,
+                    };
+                    self.render_quad(
+                        x + cx - radius,
+                        y + cy - radius,
+                        z,
+                        radius * 2.0,
+                        radius * 2.0,
+                        &style,
+                        window_width,
+                        window_height,
+                    );
+                }
+                DrawCommand::StrokeLine {
+                    from,
+                    to,
+                    width,
+                    color,
+                } => {
+                    let stroke = LineStroke {
+                        points: &[from, to],
+                        width,
+                        color,
+                    };
+                    self.render_line(x, y, z, &stroke, window_width, window_height);
+                }
             }
         }
     }
+
+    // renderers without image support leave nine-patches out
+    fn render_nine_patch(
+        &mut self,
+        _patch: &NinePatchQuad,
+        _window_width: f64,
+        _window_height: f64,
+    ) {
+    }
+
+    // renderers without line support leave lines out. point coordinates are relative to x and y
+    fn render_line(
+        &mut self,
+        _x: f64,
+        _y: f64,
+        _z: usize,
+        _stroke: &LineStroke,
+        _window_width: f64,
+        _window_height: f64,
+    ) {
+    }
+
+    // ratio of framebuffer pixels to the logical pixels widgets are laid out in
+    fn set_scale_factor(&mut self, _scale_factor: f64) {}
+
+    fn set_clip(
+        &mut self,
+        _clip: Option<(f64, f64, f64, f64)>,
+        _window_width: f64,
+        _window_height: f64,
+    ) {
+    }
+
+    fn debug_bounds(&self) -> bool {
+        false
+    }
+
+    fn set_debug_bounds(&mut self, _enabled: bool) {}
+
+    // draw calls issued since the last time this was called
+    fn take_draw_calls(&mut self) -> usize {
+        0
+    }
+}
+
+const DEBUG_BOUNDS_WIDTH: f64 = 1.0;
+
+type Waker = Box<dyn Fn() + Send>;
+
+// lets other threads ask the run loop for a new frame, e.g. once a download finished
+#[derive(Clone, Default)]
+pub struct RedrawHandle {
+    requested: Arc<AtomicBool>,
+    waker: Arc<Mutex<Option<Waker>>>,
+}
+
+impl RedrawHandle {
+    pub fn new() -> Self {
+        RedrawHandle::default()
+    }
+
+    pub fn request_redraw(&self) {
+        self.requested.store(true, Ordering::SeqCst);
+        if let Some(wake) = self.waker.lock().unwrap().as_ref() {
+            wake();
+        }
+    }
+
+    // whether a redraw was requested since the last call
+    pub fn take_request(&self) -> bool {
+        self.requested.swap(false, Ordering::SeqCst)
+    }
+
+    // the loop installs a waker while it runs, requests before that only set the flag
+    pub fn set_waker(&self, waker: Option<Waker>) {
+        *self.waker.lock().unwrap() = waker;
+    }
+}
+
+// where text inputs copy to and paste from. the run loop keeps one in sync with the
+// window's clipboard, tests can hand in a MemoryClipboard and look at it afterwards
+pub trait Clipboard {
+    fn get(&self) -> Option<String>;
+    fn set(&self, text: &str);
+}
+
+// clones share the same contents
+#[derive(Debug, Clone, Default)]
+pub struct MemoryClipboard {
+    text: Rc<RefCell<Option<String>>>,
+    changed: Rc<Cell<bool>>,
+}
+
+impl MemoryClipboard {
+    pub fn new() -> Self {
+        MemoryClipboard::default()
+    }
+
+    // whether anything was set since the last call, even the same text again
+    pub fn take_changed(&self) -> bool {
+        self.changed.replace(false)
+    }
+}
+
+impl Clipboard for MemoryClipboard {
+    fn get(&self) -> Option<String> {
+        self.text.borrow().clone()
+    }
+
+    fn set(&self, text: &str) {
+        *self.text.borrow_mut() = Some(String::from(text));
+        self.changed.set(true);
+    }
+}
+
+// frames the fps is averaged over
+const FRAME_STATS_WINDOW: usize = 60;
+
+// timing and size of recent frames, kept up to date by the run loop
+#[derive(Debug, Clone, Default)]
+pub struct FrameStats {
+    // seconds the last frame took
+    pub frame_time: f64,
+    pub fps: f64,
+    pub widgets: usize,
+    pub draw_calls: usize,
+    durations: VecDeque<f64>,
+}
+
+impl FrameStats {
+    pub fn new() -> Self {
+        FrameStats::default()
+    }
+
+    // the fps is a moving average, so single slow frames don't make it jump around
+    pub fn record_frame(&mut self, duration: f64) {
+        self.frame_time = duration;
+        if self.durations.len() == FRAME_STATS_WINDOW {
+            self.durations.pop_front();
+        }
+        self.durations.push_back(duration);
+        let total: f64 = self.durations.iter().sum();
+        self.fps = if total > 0.0 {
+            self.durations.len() as f64 / total
+        } else {
+            0.0
+        };
+    }
 }
 
 mod gl_renderer;
-pub use gl_renderer::GlRenderer;
+#[cfg(feature = "sdf")]
+pub use gl_renderer::SdfStyle;
+#[cfg(feature = "wgpu")]
+pub use gl_renderer::WgpuRenderer;
+pub use gl_renderer::{BlendMode, FontRegistry, GlRenderer, Texture};
+
+type Subscribers = Rc<RefCell<Vec<Box<dyn Fn()>>>>;
 
 pub struct State<T> {
-    reference: Rc<RefCell<T>>,
-    bound: bool,
+    // None until a widget is bound
+    reference: Option<Rc<RefCell<T>>>,
+    subscribers: Subscribers,
+}
+
+// clones share the value and the subscribers, but only if the state was bound before cloning
+impl<T> Clone for State<T> {
+    fn clone(&self) -> Self {
+        State {
+            reference: self.reference.clone(),
+            subscribers: self.subscribers.clone(),
+        }
+    }
 }
 
 impl<T> State<T> {
     pub fn new() -> Self {
-        let inner_rc: Rc<RefCell<T>> = unsafe {
-            let ptr = std::alloc::alloc(std::alloc::Layout::new::<RefCell<T>>()) as *mut RefCell<T>;
-            let b = Box::from_raw(ptr);
-            Rc::from(b)
-        };
         State {
-            reference: inner_rc,
-            bound: false,
+            reference: None,
+            subscribers: Rc::new(RefCell::new(Vec::new())),
         }
     }
 
+    // a state that holds a plain value instead of a widget
+    pub fn with_value(value: T) -> Self {
+        let mut state = State::new();
+        state.bind(Rc::new(RefCell::new(value)));
+        state
+    }
+
     pub fn bind(&mut self, reference: Rc<RefCell<T>>) {
-        self.reference = reference;
-        self.bound = true;
+        self.reference = Some(reference);
+    }
+
+    // called after every set or update. changes made through borrow_mut aren't seen
+    pub fn subscribe<F: Fn() + 'static>(&self, subscriber: F) {
+        self.subscribers.borrow_mut().push(Box::new(subscriber));
+    }
+
+    pub fn set(&self, value: T) {
+        *self.reference().borrow_mut() = value;
+        self.notify();
+    }
+
+    pub fn update<F: FnOnce(&mut T)>(&self, update: F) {
+        update(&mut self.reference().borrow_mut());
+        self.notify();
+    }
+
+    fn notify(&self) {
+        for subscriber in self.subscribers.borrow().iter() {
+            subscriber();
+        }
+    }
+
+    fn reference(&self) -> &Rc<RefCell<T>> {
+        self.reference
+            .as_ref()
+            .expect("state was used before a widget was bound to it")
     }
 
     pub fn borrow(&self) -> std::cell::Ref<'_, T> {
-        self.reference.borrow()
+        self.reference().borrow()
     }
     pub fn borrow_mut(&self) -> std::cell::RefMut<'_, T> {
-        self.reference.borrow_mut()
+        self.reference().borrow_mut()
     }
 }
 
+#[derive(Debug)]
 pub enum Event {
     MouseDown {
         x: f64,
@@ -126,28 +593,551 @@ pub enum Event {
         x: f64,
         y: f64,
     },
+    // a wheel or touchpad scroll with the cursor at x and y. the deltas are in logical
+    // pixels, positive dy moves the content up towards its end
+    Scroll {
+        x: f64,
+        y: f64,
+        dx: f64,
+        dy: f64,
+    },
+    Tick {
+        time: f64,
+    },
+    KeyDown {
+        key: Key,
+        modifiers: Modifiers,
+    },
+    // text as it was typed, after keyboard layout, dead keys and input methods.
+    // control keys only show up as KeyDown
+    Char {
+        codepoint: char,
+    },
 }
 
+// what an EventListener's capture or bubble handler did with an event
 #[derive(Debug)]
+pub enum EventFlow {
+    Consumed,
+    // handed back so it can travel on
+    Ignored(Event),
+}
+
+// letters are reported lowercase, shift shows up in the modifiers instead
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Key {
+    Char(char),
+    Enter,
+    Escape,
+    Tab,
+    Backspace,
+    Delete,
+    Space,
+    Left,
+    Right,
+    Up,
+    Down,
+    Home,
+    End,
+    F(u8),
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Default)]
+pub struct Modifiers {
+    pub ctrl: bool,
+    pub shift: bool,
+    pub alt: bool,
+    pub logo: bool,
+}
+
+impl Modifiers {
+    pub const NONE: Modifiers = Modifiers {
+        ctrl: false,
+        shift: false,
+        alt: false,
+        logo: false,
+    };
+    pub const CTRL: Modifiers = Modifiers {
+        ctrl: true,
+        ..Modifiers::NONE
+    };
+    pub const SHIFT: Modifiers = Modifiers {
+        shift: true,
+        ..Modifiers::NONE
+    };
+    pub const ALT: Modifiers = Modifiers {
+        alt: true,
+        ..Modifiers::NONE
+    };
+    pub const LOGO: Modifiers = Modifiers {
+        logo: true,
+        ..Modifiers::NONE
+    };
+
+    pub fn with(self, other: Modifiers) -> Modifiers {
+        Modifiers {
+            ctrl: self.ctrl || other.ctrl,
+            shift: self.shift || other.shift,
+            alt: self.alt || other.alt,
+            logo: self.logo || other.logo,
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
 pub struct Style {
     color: Option<Color>,
-    border_radius: f64,
+    border_radius: BorderRadius,
+    image: Option<BackgroundImage>,
 }
 
-#[derive(Debug)]
+// a polyline, as handed to Renderer::render_line
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct LineStroke<'a> {
+    pub points: &'a [(f64, f64)],
+    pub width: f64,
+    pub color: Color,
+}
+
+// a solid rounded rectangle, as handed to Renderer::render_quads
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Quad {
+    pub x: f64,
+    pub y: f64,
+    pub z: usize,
+    pub width: f64,
+    pub height: f64,
+    pub color: Color,
+    pub border_radius: BorderRadius,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Default)]
+pub struct BorderRadius {
+    pub top_left: f64,
+    pub top_right: f64,
+    pub bottom_left: f64,
+    pub bottom_right: f64,
+}
+
+impl BorderRadius {
+    pub fn uniform(radius: f64) -> Self {
+        BorderRadius::each(radius, radius, radius, radius)
+    }
+
+    pub fn each(top_left: f64, top_right: f64, bottom_left: f64, bottom_right: f64) -> Self {
+        BorderRadius {
+            top_left,
+            top_right,
+            bottom_left,
+            bottom_right,
+        }
+    }
+
+    // each quadrant of the box is rounded by its own corner, which is clamped to half
+    // the shorter side. dx and dy are relative to the center
+    pub fn corner(&self, dx: f64, dy: f64, width: f64, height: f64) -> f64 {
+        let radius = match (dx < 0.0, dy < 0.0) {
+            (true, true) => self.top_left,
+            (false, true) => self.top_right,
+            (true, false) => self.bottom_left,
+            (false, false) => self.bottom_right,
+        };
+        radius.min(width / 2.0).min(height / 2.0)
+    }
+
+    // same test as the rect shader, x and y are relative to the top left of the box
+    pub fn contains(&self, x: f64, y: f64, width: f64, height: f64) -> bool {
+        let (dx, dy) = (x - width / 2.0, y - height / 2.0);
+        if dx.abs() > width / 2.0 || dy.abs() > height / 2.0 {
+            return false;
+        }
+        let radius = self.corner(dx, dy, width, height);
+        if radius <= 0.0 {
+            return true;
+        }
+        let qx = dx.abs() - width / 2.0 + radius;
+        let qy = dy.abs() - height / 2.0 + radius;
+        qx <= 0.0 || qy <= 0.0 || qx * qx + qy * qy <= radius * radius
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ImageFit {
+    // stretches the image over the whole box
+    Fill,
+    // shows the whole image, leaving the rest of the box to the color
+    Contain,
+    // fills the box, cropping whatever sticks out
+    Cover,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct BackgroundImage {
+    pub path: Rc<str>,
+    pub fit: ImageFit,
+}
+
+impl BackgroundImage {
+    pub fn new(path: &str, fit: ImageFit) -> Self {
+        BackgroundImage {
+            path: Rc::from(path),
+            fit,
+        }
+    }
+
+    // offset and scale mapping box coordinates in 0..1 to image coordinates
+    pub fn uv_rect(
+        &self,
+        width: f64,
+        height: f64,
+        image_width: f64,
+        image_height: f64,
+    ) -> [f32; 4] {
+        if width <= 0.0 || height <= 0.0 || image_width <= 0.0 || image_height <= 0.0 {
+            return [0.0, 0.0, 1.0, 1.0];
+        }
+        let ratio = (width / height) / (image_width / image_height);
+        let (sx, sy) = match self.fit {
+            ImageFit::Fill => (1.0, 1.0),
+            ImageFit::Cover if ratio > 1.0 => (1.0, 1.0 / ratio),
+            ImageFit::Cover => (ratio, 1.0),
+            ImageFit::Contain if ratio > 1.0 => (ratio, 1.0),
+            ImageFit::Contain => (1.0, 1.0 / ratio),
+        };
+        [
+            ((1.0 - sx) / 2.0) as f32,
+            ((1.0 - sy) / 2.0) as f32,
+            sx as f32,
+            sy as f32,
+        ]
+    }
+}
+
+// an image stretched over a box with fixed-size corners, as handed to
+// Renderer::render_nine_patch. insets are left, top, right, bottom in image pixels
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct NinePatchQuad<'a> {
+    pub x: f64,
+    pub y: f64,
+    pub z: usize,
+    pub width: f64,
+    pub height: f64,
+    pub path: &'a str,
+    pub insets: (f64, f64, f64, f64),
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct NinePatchRegion {
+    // destination relative to the widget
+    pub x: f64,
+    pub y: f64,
+    pub width: f64,
+    pub height: f64,
+    // source offset and size in 0..1 image coordinates
+    pub uv_rect: [f32; 4],
+}
+
+// corners keep their size, edges stretch along one axis and the center along both.
+// corners shrink proportionally when the box is smaller than the insets
+pub fn nine_patch_regions(
+    width: f64,
+    height: f64,
+    image_width: f64,
+    image_height: f64,
+    insets: (f64, f64, f64, f64),
+) -> Vec<NinePatchRegion> {
+    let (left, top, right, bottom) = insets;
+    let shrink = |size: f64, start: f64, end: f64| {
+        if start + end > size && start + end > 0.0 {
+            size / (start + end)
+        } else {
+            1.0
+        }
+    };
+    let sx = shrink(width, left, right);
+    let sy = shrink(height, top, bottom);
+    let columns = [
+        (0.0, left * sx, 0.0, left),
+        (
+            left * sx,
+            width - (left + right) * sx,
+            left,
+            image_width - left - right,
+        ),
+        (width - right * sx, right * sx, image_width - right, right),
+    ];
+    let rows = [
+        (0.0, top * sy, 0.0, top),
+        (
+            top * sy,
+            height - (top + bottom) * sy,
+            top,
+            image_height - top - bottom,
+        ),
+        (
+            height - bottom * sy,
+            bottom * sy,
+            image_height - bottom,
+            bottom,
+        ),
+    ];
+    let mut regions = Vec::with_capacity(9);
+    for (y, h, v, dv) in rows.iter() {
+        for (x, w, u, du) in columns.iter() {
+            regions.push(NinePatchRegion {
+                x: *x,
+                y: *y,
+                width: *w,
+                height: *h,
+                uv_rect: [
+                    (u / image_width) as f32,
+                    (v / image_height) as f32,
+                    (du / image_width) as f32,
+                    (dv / image_height) as f32,
+                ],
+            });
+        }
+    }
+    regions
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Hinting {
+    None,
+    Light,
+    Normal,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum TabStop {
+    Pixels(f64),
+    Spaces(u32),
+}
+
+// Rtl only reverses the visual glyph order, it does not shape or apply bidi
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum TextDirection {
+    Ltr,
+    Rtl,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum TextDecoration {
+    None,
+    Underline,
+    Strikethrough,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
 pub struct TextStyle<'a> {
     font: &'a str,
     color: Color,
     size: u32,
+    hinting: Hinting,
+    tab_stop: TabStop,
+    direction: TextDirection,
+    decoration: TextDecoration,
+    // falls back to the text color
+    decoration_color: Option<Color>,
 }
 
-#[derive(Debug)]
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct TextSpan<'a> {
+    pub text: &'a str,
+    pub color: Color,
+    pub font: &'a str,
+    pub size: u32,
+}
+
+impl<'a> TextSpan<'a> {
+    pub fn new(text: &'a str, size: u32, font: &'a str) -> Self {
+        TextSpan {
+            text,
+            color: [0.0, 0.0, 0.0, 1.0],
+            font,
+            size,
+        }
+    }
+
+    pub fn color(mut self, color: Color) -> Self {
+        self.color = color;
+        self
+    }
+
+    fn style(&self, hinting: Hinting) -> TextStyle<'a> {
+        TextStyle {
+            font: self.font,
+            color: self.color,
+            size: self.size,
+            hinting,
+            tab_stop: TabStop::Spaces(4),
+            direction: TextDirection::Ltr,
+            decoration: TextDecoration::None,
+            decoration_color: None,
+        }
+    }
+}
+
+// spans laid out in a box, as handed to Renderer::render_rich_text
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct RichTextBlock<'a> {
+    pub x: f64,
+    pub y: f64,
+    pub z: usize,
+    pub width: f64,
+    pub height: f64,
+    pub spans: &'a [TextSpan<'a>],
+    pub hinting: Hinting,
+}
+
+// a run of one span that stays on a single line, positioned relative to the text's origin
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct SpanPiece<'a> {
+    pub span: usize,
+    pub text: &'a str,
+    pub x: f64,
+    pub y: f64,
+}
+
+// spans continue each other's pen position, lines break only at '\n'.
+// pieces on a line share a baseline, returns the pieces and the total size
+pub fn span_layout<'a>(
+    spans: &[TextSpan<'a>],
+    hinting: Hinting,
+) -> (Vec<SpanPiece<'a>>, (f64, f64)) {
+    // pieces, largest font size and line height of every line
+    let mut lines: Vec<(Vec<SpanPiece<'a>>, u32, f64)> = vec![(Vec::new(), 0, 0.0)];
+    let mut pen = 0.0;
+    let mut width: f64 = 0.0;
+    for (index, span) in spans.iter().enumerate() {
+        let style = span.style(hinting);
+        let line_height = gl_renderer::line_height(&style);
+        for (n, part) in span.text.split('\n').enumerate() {
+            if n > 0 {
+                lines.push((Vec::new(), 0, 0.0));
+                pen = 0.0;
+            }
+            let line = lines.last_mut().unwrap();
+            line.1 = line.1.max(span.size);
+            line.2 = line.2.max(line_height);
+            if !part.is_empty() {
+                line.0.push(SpanPiece {
+                    span: index,
+                    text: part,
+                    x: pen,
+                    y: 0.0,
+                });
+                pen += gl_renderer::measure_text(part, &style).0;
+                width = width.max(pen);
+            }
+        }
+    }
+    let mut pieces = Vec::new();
+    let mut y = 0.0;
+    for (line, size, line_height) in lines {
+        for piece in line {
+            let offset = (size - spans[piece.span].size) as f64;
+            pieces.push(SpanPiece {
+                y: y + offset,
+                ..piece
+            });
+        }
+        y += line_height;
+    }
+    (pieces, (width, y))
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum DrawCommand {
+    FillRect {
+        x: f64,
+        y: f64,
+        width: f64,
+        height: f64,
+        color: Color,
+    },
+    StrokeLine {
+        from: (f64, f64),
+        to: (f64, f64),
+        width: f64,
+        color: Color,
+    },
+    FillCircle {
+        x: f64,
+        y: f64,
+        radius: f64,
+        color: Color,
+    },
+}
+
+pub trait Painter {
+    fn size(&self) -> (f64, f64);
+    fn fill_rect(&mut self, x: f64, y: f64, width: f64, height: f64, color: Color);
+    fn stroke_line(&mut self, from: (f64, f64), to: (f64, f64), width: f64, color: Color);
+    fn fill_circle(&mut self, x: f64, y: f64, radius: f64, color: Color);
+}
+
+#[derive(Debug, Clone, PartialEq)]
 pub enum RenderObject<'a> {
-    Rectangle { style: Style },
-    Text { text: &'a str, style: TextStyle<'a> },
+    Rectangle {
+        style: Style,
+    },
+    Ellipse {
+        style: Style,
+    },
+    Text {
+        text: Cow<'a, str>,
+        style: TextStyle<'a>,
+    },
+    Line {
+        points: Vec<(f64, f64)>,
+        width: f64,
+        color: Color,
+    },
+    Custom(Vec<DrawCommand>),
+    RichText {
+        spans: Vec<TextSpan<'a>>,
+        hinting: Hinting,
+    },
+    // insets are left, top, right, bottom in image pixels
+    NinePatch {
+        path: Rc<str>,
+        insets: (f64, f64, f64, f64),
+    },
 }
 
-#[derive(Debug)]
+impl<'a> RenderObject<'a> {
+    // scales the alpha of every color the object draws with
+    fn fade(&mut self, factor: f32) {
+        let fade = |color: &mut Color| *color = color::with_alpha(*color, color[3] * factor);
+        match self {
+            RenderObject::Rectangle { style } | RenderObject::Ellipse { style } => {
+                if let Some(color) = &mut style.color {
+                    fade(color);
+                }
+            }
+            RenderObject::Text { style, .. } => fade(&mut style.color),
+            RenderObject::RichText { spans, .. } => {
+                for span in spans {
+                    fade(&mut span.color);
+                }
+            }
+            RenderObject::Line { color, .. } => fade(color),
+            RenderObject::NinePatch { .. } => {}
+            RenderObject::Custom(commands) => {
+                for command in commands {
+                    match command {
+                        DrawCommand::FillRect { color, .. }
+                        | DrawCommand::StrokeLine { color, .. }
+                        | DrawCommand::FillCircle { color, .. } => fade(color),
+                    }
+                }
+            }
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
 pub struct ComputedWidget<'a> {
     x: f64,
     y: f64,
@@ -155,13 +1145,395 @@ pub struct ComputedWidget<'a> {
     width: f64,
     height: f64,
     render: Option<RenderObject<'a>>,
+    clip: Option<(f64, f64, f64, f64)>,
+    text_bounds: Option<(f64, f64)>,
+    // overlays ignore the clips of their ancestors
+    overlay: bool,
 }
 
 impl<'a> ComputedWidget<'a> {
-    fn in_hitbox(&self, x: f64, y: f64, border_radius: f64) -> bool {
-        x >= self.x && y >= self.y && x < self.x + self.width && y < self.y + self.height
-        // TODO
+    // size of the rendered text, as opposed to the slot it was laid out in
+    pub fn text_bounds(&self) -> Option<(f64, f64)> {
+        self.text_bounds
+    }
+
+    // copies the borrowed strings, so the result can outlive the tree
+    pub fn into_owned(self) -> ComputedWidgetOwned {
+        let render = self.render.map(|render| match render {
+            RenderObject::Rectangle { style } => OwnedRender::Rectangle(OwnedStyle::from(style)),
+            RenderObject::Ellipse { style } => OwnedRender::Ellipse(OwnedStyle::from(style)),
+            RenderObject::Text { text, style } => OwnedRender::Text {
+                text: text.into_owned(),
+                font: String::from(style.font),
+                style: TextStyle { font: "", ..style },
+            },
+            RenderObject::Line {
+                points,
+                width,
+                color,
+            } => OwnedRender::Line {
+                points,
+                width,
+                color,
+            },
+            RenderObject::Custom(commands) => OwnedRender::Custom(commands),
+            RenderObject::RichText { spans, hinting } => OwnedRender::RichText {
+                spans: spans
+                    .iter()
+                    .map(|span| {
+                        (
+                            String::from(span.text),
+                            String::from(span.font),
+                            span.color,
+                            span.size,
+                        )
+                    })
+                    .collect(),
+                hinting,
+            },
+            RenderObject::NinePatch { path, insets } => OwnedRender::NinePatch {
+                path: String::from(&*path),
+                insets,
+            },
+        });
+        ComputedWidgetOwned {
+            x: self.x,
+            y: self.y,
+            z: self.z,
+            width: self.width,
+            height: self.height,
+            render,
+            clip: self.clip,
+            text_bounds: self.text_bounds,
+            overlay: self.overlay,
+        }
+    }
+
+    fn clip_to(&mut self, x: f64, y: f64, width: f64, height: f64) {
+        if self.overlay {
+            return;
+        }
+        self.clip = Some(match self.clip {
+            Some((cx, cy, cw, ch)) => {
+                let left = x.max(cx);
+                let top = y.max(cy);
+                let right = (x + width).min(cx + cw);
+                let bottom = (y + height).min(cy + ch);
+                (left, top, (right - left).max(0.0), (bottom - top).max(0.0))
+            }
+            None => (x, y, width, height),
+        });
+    }
+
+    fn in_hitbox(&self, x: f64, y: f64, border_radius: BorderRadius) -> bool {
+        if !(x >= self.x && y >= self.y && x < self.x + self.width && y < self.y + self.height) {
+            return false;
+        }
+        if let Some((cx, cy, cw, ch)) = self.clip {
+            if !(x >= cx && y >= cy && x < cx + cw && y < cy + ch) {
+                return false;
+            }
+        }
+        border_radius.contains(x - self.x, y - self.y, self.width, self.height)
+    }
+
+    fn border_radius(&self) -> BorderRadius {
+        match &self.render {
+            Some(RenderObject::Rectangle { style }) => style.border_radius,
+            Some(RenderObject::Ellipse { .. }) => {
+                BorderRadius::uniform(self.width.min(self.height) / 2.0)
+            }
+            _ => BorderRadius::default(),
+        }
+    }
+}
+
+// a laid out widget that doesn't borrow from the tree, e.g. to keep a layout around
+// after the tree is gone or to hand it to another thread
+#[derive(Debug, Clone)]
+pub struct ComputedWidgetOwned {
+    x: f64,
+    y: f64,
+    z: usize,
+    width: f64,
+    height: f64,
+    render: Option<OwnedRender>,
+    clip: Option<(f64, f64, f64, f64)>,
+    text_bounds: Option<(f64, f64)>,
+    overlay: bool,
+}
+
+#[derive(Debug, Clone)]
+struct OwnedStyle {
+    color: Option<Color>,
+    border_radius: BorderRadius,
+    image: Option<(String, ImageFit)>,
+}
+
+impl From<Style> for OwnedStyle {
+    fn from(style: Style) -> Self {
+        OwnedStyle {
+            color: style.color,
+            border_radius: style.border_radius,
+            image: style
+                .image
+                .map(|image| (String::from(&*image.path), image.fit)),
+        }
+    }
+}
+
+impl OwnedStyle {
+    fn to_style(&self) -> Style {
+        Style {
+            color: self.color,
+            border_radius: self.border_radius,
+            image: self
+                .image
+                .as_ref()
+                .map(|(path, fit)| BackgroundImage::new(path, *fit)),
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+enum OwnedRender {
+    Rectangle(OwnedStyle),
+    Ellipse(OwnedStyle),
+    // the style's own font is left empty, it is put back from font when borrowed
+    Text {
+        text: String,
+        font: String,
+        style: TextStyle<'static>,
+    },
+    Line {
+        points: Vec<(f64, f64)>,
+        width: f64,
+        color: Color,
+    },
+    Custom(Vec<DrawCommand>),
+    // text, font, color and size of each span
+    RichText {
+        spans: Vec<(String, String, Color, u32)>,
+        hinting: Hinting,
+    },
+    NinePatch {
+        path: String,
+        insets: (f64, f64, f64, f64),
+    },
+}
+
+impl ComputedWidgetOwned {
+    // x, y, width, height
+    pub fn bounds(&self) -> (f64, f64, f64, f64) {
+        (self.x, self.y, self.width, self.height)
+    }
+
+    pub fn text(&self) -> Option<&str> {
+        match &self.render {
+            Some(OwnedRender::Text { text, .. }) => Some(text),
+            _ => None,
+        }
+    }
+
+    pub fn text_bounds(&self) -> Option<(f64, f64)> {
+        self.text_bounds
+    }
+
+    // borrows the strings back, e.g. to hand the layout to a renderer
+    pub fn to_computed(&self) -> ComputedWidget<'_> {
+        let render = self.render.as_ref().map(|render| match render {
+            OwnedRender::Rectangle(style) => RenderObject::Rectangle {
+                style: style.to_style(),
+            },
+            OwnedRender::Ellipse(style) => RenderObject::Ellipse {
+                style: style.to_style(),
+            },
+            OwnedRender::Text { text, font, style } => RenderObject::Text {
+                text: Cow::Borrowed(text),
+                style: TextStyle { font, ..*style },
+            },
+            OwnedRender::Line {
+                points,
+                width,
+                color,
+            } => RenderObject::Line {
+                points: points.clone(),
+                width: *width,
+                color: *color,
+            },
+            OwnedRender::Custom(commands) => RenderObject::Custom(commands.clone()),
+            OwnedRender::RichText { spans, hinting } => RenderObject::RichText {
+                spans: spans
+                    .iter()
+                    .map(|(text, font, color, size)| TextSpan {
+                        text,
+                        font,
+                        color: *color,
+                        size: *size,
+                    })
+                    .collect(),
+                hinting: *hinting,
+            },
+            OwnedRender::NinePatch { path, insets } => RenderObject::NinePatch {
+                path: Rc::from(path.as_str()),
+                insets: *insets,
+            },
+        });
+        ComputedWidget {
+            x: self.x,
+            y: self.y,
+            z: self.z,
+            width: self.width,
+            height: self.height,
+            render,
+            clip: self.clip,
+            text_bounds: self.text_bounds,
+            overlay: self.overlay,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Role {
+    Button,
+    Text,
+    Checkbox,
+    ComboBox,
+    TextField,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct SemanticNode {
+    pub role: Role,
+    pub label: String,
+    pub enabled: bool,
+    pub checked: Option<bool>,
+    // x, y, width, height, filled in from the layout by `semantics`
+    pub bounds: (f64, f64, f64, f64),
+}
+
+impl SemanticNode {
+    pub fn new<S: Into<String>>(role: Role, label: S) -> Self {
+        SemanticNode {
+            role,
+            label: label.into(),
+            enabled: true,
+            checked: None,
+            bounds: (0.0, 0.0, 0.0, 0.0),
+        }
+    }
+}
+
+// the announced widgets of a laid out tree, depth first. widgets that weren't laid out
+// are left out, and so are the descendants of buttons, which make up the button's label
+pub fn semantics<'a>(
+    tree: &Rc<RefCell<dyn Widget<'a> + 'a>>,
+    map: &HashMap<usize, ComputedWidget>,
+) -> Vec<SemanticNode> {
+    let mut nodes = Vec::new();
+    collect_semantics(tree, map, &mut nodes);
+    nodes
+}
+
+fn collect_semantics<'a>(
+    widget: &Rc<RefCell<dyn Widget<'a> + 'a>>,
+    map: &HashMap<usize, ComputedWidget>,
+    nodes: &mut Vec<SemanticNode>,
+) {
+    let widget = widget.borrow();
+    if let (Some(mut node), Some(computed)) = (widget.semantics(), map.get(&widget.get_id())) {
+        node.bounds = (computed.x, computed.y, computed.width, computed.height);
+        let merged = node.role == Role::Button;
+        nodes.push(node);
+        if merged {
+            return;
+        }
+    }
+    for child in widget.children() {
+        collect_semantics(&child, map, nodes);
+    }
+}
+
+// the text labels below a widget joined by spaces, used as the label of buttons
+pub(crate) fn subtree_label<'a>(widget: &Rc<RefCell<dyn Widget<'a> + 'a>>) -> String {
+    let mut labels = Vec::new();
+    let mut stack = vec![widget.clone()];
+    while let Some(widget) = stack.pop() {
+        let widget = widget.borrow();
+        if let Some(node) = widget.semantics() {
+            if node.role == Role::Text {
+                labels.push(node.label);
+            }
+        }
+        stack.extend(widget.children().into_iter().rev());
     }
+    labels.join(" ")
+}
+
+pub fn hit_test(map: &HashMap<usize, ComputedWidget>, x: f64, y: f64) -> Option<usize> {
+    let mut hits: Vec<(&usize, &ComputedWidget)> = map
+        .iter()
+        .filter(|(_, w)| w.in_hitbox(x, y, w.border_radius()))
+        .collect();
+    hits.sort_by_key(|(id, w)| (std::cmp::Reverse(w.z), **id));
+    hits.first().map(|(id, _)| **id)
+}
+
+// the areas that look different between two layouts: the old and the new bounds of
+// every widget that moved or changed, and the bounds of widgets that came or went.
+// empty when drawing next would give the same frame as prev
+pub fn dirty_regions(
+    prev: &HashMap<usize, ComputedWidget>,
+    next: &HashMap<usize, ComputedWidget>,
+) -> Vec<(f64, f64, f64, f64)> {
+    let bounds = |w: &ComputedWidget| (w.x, w.y, w.width, w.height);
+    let mut regions = Vec::new();
+    for (id, old) in prev {
+        match next.get(id) {
+            Some(new) if new == old => {}
+            Some(new) => {
+                regions.push(bounds(old));
+                if bounds(new) != bounds(old) {
+                    regions.push(bounds(new));
+                }
+            }
+            None => regions.push(bounds(old)),
+        }
+    }
+    for (id, new) in next {
+        if !prev.contains_key(id) {
+            regions.push(bounds(new));
+        }
+    }
+    regions
+}
+
+// one line per widget, sorted by z and then id, for looking at a layout while debugging
+pub fn pretty_layout(map: &HashMap<usize, ComputedWidget>) -> String {
+    let mut widgets: Vec<(&usize, &ComputedWidget)> = map.iter().collect();
+    widgets.sort_by_key(|(id, w)| (w.z, **id));
+    let mut out = String::new();
+    for (id, w) in widgets {
+        let kind = match &w.render {
+            None => String::from("-"),
+            Some(RenderObject::Rectangle { .. }) => String::from("Rectangle"),
+            Some(RenderObject::Ellipse { .. }) => String::from("Ellipse"),
+            Some(RenderObject::Text { text, .. }) => format!("Text {:?}", text),
+            Some(RenderObject::Line { .. }) => String::from("Line"),
+            Some(RenderObject::Custom(_)) => String::from("Custom"),
+            Some(RenderObject::RichText { .. }) => String::from("RichText"),
+            Some(RenderObject::NinePatch { .. }) => String::from("NinePatch"),
+        };
+        out.push_str(&format!(
+            "{} z={} ({}, {}) {}x{} {}",
+            id, w.z, w.x, w.y, w.width, w.height, kind
+        ));
+        if let Some((x, y, width, height)) = w.clip {
+            out.push_str(&format!(" clip=({}, {}) {}x{}", x, y, width, height));
+        }
+        out.push('\n');
+    }
+    out
 }
 
 pub fn compute<'a>(
@@ -169,8 +1541,342 @@ pub fn compute<'a>(
     width: f64,
     height: f64,
 ) -> HashMap<usize, ComputedWidget<'a>> {
+    compute_static(&*tree.borrow(), width, height)
+}
+
+// lays out a tree that isn't shared, e.g. one from build_static
+pub fn compute_static<'a>(
+    tree: &dyn Widget<'a>,
+    width: f64,
+    height: f64,
+) -> HashMap<usize, ComputedWidget<'a>> {
+    // negative or NaN sizes, e.g. from a minimized window, lay out as empty
+    let width = width.max(0.0);
+    let height = height.max(0.0);
     let mut elem_map = HashMap::new();
-    tree.borrow()
-        .compute(0.0, 0.0, 0, width, height, &mut elem_map);
+    tree.compute(0.0, 0.0, 0, width, height, &mut elem_map);
     elem_map
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::atomic::AtomicUsize;
+    use widgets::{Column, Padding, Rectangle, Row, Stack};
+
+    #[derive(Default)]
+    struct CountingRenderer {
+        quads: usize,
+        draw_calls: usize,
+        debug_bounds: bool,
+    }
+
+    impl Renderer for CountingRenderer {
+        fn render_quad(
+            &mut self,
+            _x: f64,
+            _y: f64,
+            _z: usize,
+            _width: f64,
+            _height: f64,
+            _style: &Style,
+            _window_width: f64,
+            _window_height: f64,
+        ) {
+            self.quads += 1;
+            self.draw_calls += 1;
+        }
+
+        // like an instanced draw, a whole batch is one call
+        fn render_quads(&mut self, _quads: &[Quad], _window_width: f64, _window_height: f64) {
+            self.draw_calls += 1;
+        }
+
+        fn render_text(
+            &mut self,
+            _x: f64,
+            _y: f64,
+            _z: usize,
+            _width: f64,
+            _height: f64,
+            _text: &str,
+            _style: &TextStyle,
+            _window_width: f64,
+            _window_height: f64,
+        ) {
+        }
+
+        fn debug_bounds(&self) -> bool {
+            self.debug_bounds
+        }
+
+        fn set_debug_bounds(&mut self, enabled: bool) {
+            self.debug_bounds = enabled;
+        }
+    }
+
+    #[test]
+    fn debug_bounds_outline_every_widget() {
+        let tree: Rc<RefCell<dyn Widget>> = Column::new()
+            .add(Rectangle::new(color::RED))
+            .add(Rectangle::new(color::GREEN))
+            .add(Rectangle::new(color::BLUE))
+            .build();
+        let computed = compute(&tree, 100.0, 300.0);
+        let mut renderer = CountingRenderer::default();
+        renderer.render(&computed, 100.0, 300.0);
+        let plain = renderer.quads;
+        renderer.set_debug_bounds(true);
+        renderer.quads = 0;
+        renderer.render(&computed, 100.0, 300.0);
+        // one outline of four edges per widget
+        assert_eq!(renderer.quads - plain, 4 * computed.len());
+    }
+
+    #[test]
+    fn solid_rectangles_batch_into_one_draw() {
+        let mut grid = Column::new();
+        for _ in 0..10 {
+            let mut row = Row::new();
+            for _ in 0..20 {
+                row = row.add(Rectangle::new(color::RED));
+            }
+            grid = grid.add(row);
+        }
+        let tree: Rc<RefCell<dyn Widget>> = grid.build();
+        let computed = compute(&tree, 200.0, 100.0);
+        let mut renderer = CountingRenderer::default();
+        renderer.render(&computed, 200.0, 100.0);
+        assert_eq!((renderer.draw_calls, renderer.quads), (1, 0));
+    }
+
+    #[test]
+    fn clipboards_notice_every_set() {
+        let clipboard = MemoryClipboard::new();
+        assert!(!clipboard.take_changed());
+        clipboard.set("text");
+        let shared = clipboard.clone();
+        shared.set("text");
+        assert!(clipboard.take_changed());
+        assert!(!clipboard.take_changed());
+    }
+
+    #[test]
+    fn fps_averages_the_last_frames() {
+        let mut stats = FrameStats::new();
+        for _ in 0..10 {
+            stats.record_frame(0.01);
+        }
+        assert!((stats.fps - 100.0).abs() < 1e-6);
+        // one slow frame only drags the average down a little
+        stats.record_frame(0.1);
+        assert_eq!(stats.frame_time, 0.1);
+        assert!((stats.fps - 11.0 / 0.2).abs() < 1e-6);
+        // once the window is full the slow frame falls out
+        for _ in 0..FRAME_STATS_WINDOW {
+            stats.record_frame(0.02);
+        }
+        assert!((stats.fps - 50.0).abs() < 1e-6);
+    }
+
+    #[test]
+    fn redraw_requests_wake_the_loop_once() {
+        let handle = RedrawHandle::new();
+        // before the loop runs a request only sets the flag
+        handle.request_redraw();
+        let wakes = Arc::new(AtomicUsize::new(0));
+        let counted = wakes.clone();
+        handle.set_waker(Some(Box::new(move || {
+            counted.fetch_add(1, Ordering::SeqCst);
+        })));
+        assert!(handle.take_request());
+        assert!(!handle.take_request());
+        let remote = handle.clone();
+        std::thread::spawn(move || remote.request_redraw())
+            .join()
+            .unwrap();
+        assert_eq!(wakes.load(Ordering::SeqCst), 1);
+        assert!(handle.take_request());
+        assert!(!handle.take_request());
+    }
+
+    #[test]
+    fn static_columns_lay_out_like_shared_ones() {
+        let tree = Column::new()
+            .add_static(Rectangle::new(color::RED).build_static())
+            .add_static(Rectangle::new(color::BLUE).build_static())
+            .build_static();
+        let computed = compute_static(&*tree, 100.0, 200.0);
+        let mut rects: Vec<_> = computed
+            .values()
+            .filter(|w| matches!(w.render, Some(RenderObject::Rectangle { .. })))
+            .map(|w| (w.x, w.y, w.width, w.height))
+            .collect();
+        rects.sort_by(|a, b| a.1.partial_cmp(&b.1).unwrap());
+        assert_eq!(
+            rects,
+            vec![(0.0, 0.0, 100.0, 100.0), (0.0, 100.0, 100.0, 100.0)]
+        );
+    }
+
+    #[test]
+    fn owned_layouts_outlive_their_tree() {
+        let owned = {
+            let label = String::from("Hello");
+            let text = widgets::Text::new(&label, 16, "Raleway-Regular.ttf").build();
+            let tree: Rc<RefCell<dyn Widget>> = text.clone();
+            let id = text.borrow().get_id();
+            let mut computed = compute(&tree, 200.0, 50.0);
+            computed.remove(&id).unwrap().into_owned()
+        };
+        assert_eq!(owned.text(), Some("Hello"));
+        assert_eq!(owned.bounds().0, 0.0);
+        // and can be borrowed back for a renderer
+        match owned.to_computed().render {
+            Some(RenderObject::Text { text, style }) => {
+                assert_eq!((&*text, style.font), ("Hello", "Raleway-Regular.ttf"));
+            }
+            _ => panic!("expected text"),
+        }
+    }
+
+    #[test]
+    fn pretty_layouts_list_widgets_in_order() {
+        let (left, right) = (
+            Rectangle::new(color::RED).build(),
+            Rectangle::new(color::BLUE).build(),
+        );
+        let tree: Rc<RefCell<dyn Widget>> = Row::new().add(left.clone()).add(right.clone()).build();
+        let computed = compute(&tree, 100.0, 50.0);
+        let layout = pretty_layout(&computed);
+        let lines: Vec<&str> = layout.lines().collect();
+        assert_eq!(lines.len(), 2);
+        let (first, second) = (left.borrow().get_id(), right.borrow().get_id());
+        assert!(first < second);
+        assert!(lines[0].starts_with(&format!("{} z=", first)));
+        assert!(lines[0].ends_with("(0, 0) 50x50 Rectangle"));
+        assert!(lines[1].starts_with(&format!("{} z=", second)));
+        assert!(lines[1].ends_with("(50, 0) 50x50 Rectangle"));
+    }
+
+    #[test]
+    fn unchanged_layouts_have_nothing_to_redraw() {
+        let moving = Rectangle::new(color::RED).build();
+        let tree: Rc<RefCell<dyn Widget>> = Row::new()
+            .add(moving.clone())
+            .add(Rectangle::new(color::BLUE))
+            .build();
+        let first = compute(&tree, 200.0, 100.0);
+        let second = compute(&tree, 200.0, 100.0);
+        assert!(dirty_regions(&first, &second).is_empty());
+        // a recolored widget needs its own bounds redrawn
+        moving.borrow_mut().color = color::GREEN;
+        let recolored = compute(&tree, 200.0, 100.0);
+        assert_eq!(
+            dirty_regions(&second, &recolored),
+            vec![(0.0, 0.0, 100.0, 100.0)]
+        );
+    }
+
+    #[test]
+    fn hit_test_picks_the_highest_z() {
+        let back = Rectangle::new(color::RED).build();
+        let front = Rectangle::new(color::BLUE).build();
+        let tree: Rc<RefCell<dyn Widget>> =
+            Stack::new().add(back.clone()).add(front.clone()).build();
+        let computed = compute(&tree, 100.0, 100.0);
+        assert!(computed[&front.borrow().get_id()].z > computed[&back.borrow().get_id()].z);
+        assert_eq!(
+            hit_test(&computed, 50.0, 50.0),
+            Some(front.borrow().get_id())
+        );
+        assert_eq!(hit_test(&computed, 150.0, 50.0), None);
+    }
+
+    #[test]
+    fn empty_windows_lay_out_as_empty() {
+        let child = Rectangle::new(color::RED).build();
+        let tree: Rc<RefCell<dyn Widget>> = Padding::new(
+            Row::new()
+                .add(child.clone())
+                .add(Column::new().add(Rectangle::new(color::BLUE))),
+        )
+        .all(10.0)
+        .build();
+        for (width, height) in [(0.0, 300.0), (-20.0, 300.0), (f64::NAN, 0.0)] {
+            let computed = compute(&tree, width, height);
+            for w in computed.values() {
+                assert!([w.x, w.y, w.width, w.height].iter().all(|v| v.is_finite()));
+                assert!(w.width >= 0.0 && w.height >= 0.0);
+            }
+            assert_eq!(computed[&child.borrow().get_id()].width, 0.0);
+        }
+    }
+
+    #[test]
+    fn nine_patch_regions_keep_the_corners() {
+        // a 30px image with 10px insets over a 100x60 box
+        let regions = nine_patch_regions(100.0, 60.0, 30.0, 30.0, (10.0, 10.0, 10.0, 10.0));
+        assert_eq!(regions.len(), 9);
+        let boxes: Vec<_> = regions
+            .iter()
+            .map(|r| (r.x, r.y, r.width, r.height))
+            .collect();
+        assert_eq!(boxes[0], (0.0, 0.0, 10.0, 10.0));
+        assert_eq!(boxes[2], (90.0, 0.0, 10.0, 10.0));
+        assert_eq!(boxes[4], (10.0, 10.0, 80.0, 40.0));
+        assert_eq!(boxes[8], (90.0, 50.0, 10.0, 10.0));
+        let third = 1.0 / 3.0;
+        assert_eq!(regions[0].uv_rect, [0.0, 0.0, third, third]);
+        assert_eq!(regions[4].uv_rect, [third, third, third, third]);
+        // a box smaller than the insets shrinks the corners and leaves no middle
+        let regions = nine_patch_regions(10.0, 10.0, 30.0, 30.0, (10.0, 10.0, 10.0, 10.0));
+        assert_eq!(
+            (regions[0].width, regions[4].width, regions[8].x),
+            (5.0, 0.0, 5.0)
+        );
+    }
+
+    #[test]
+    fn spans_continue_each_other() {
+        const FONT: &str = "Raleway-Regular.ttf";
+        let spans = [
+            TextSpan::new("bold ", 16, FONT),
+            TextSpan::new("word", 16, FONT).color(color::RED),
+        ];
+        let (pieces, (width, _)) = span_layout(&spans, Hinting::Normal);
+        let widths: Vec<f64> = spans
+            .iter()
+            .map(|span| gl_renderer::measure_text(span.text, &span.style(Hinting::Normal)).0)
+            .collect();
+        assert_eq!(width, widths[0] + widths[1]);
+        assert_eq!(pieces.len(), 2);
+        assert_eq!((pieces[0].x, pieces[1].x), (0.0, widths[0]));
+        assert_eq!(pieces[0].y, pieces[1].y);
+    }
+
+    #[test]
+    fn corners_round_independently() {
+        let rect = Rectangle::new(color::RED)
+            .border_each(20.0, 0.0, 0.0, 5.0)
+            .build();
+        let radius = rect.borrow().border_radius;
+        assert_eq!(
+            (
+                radius.top_left,
+                radius.top_right,
+                radius.bottom_left,
+                radius.bottom_right
+            ),
+            (20.0, 0.0, 0.0, 5.0)
+        );
+        // the same distance tests the rect shader runs, on a 100x100 box
+        assert!(!radius.contains(2.0, 2.0, 100.0, 100.0));
+        assert!(radius.contains(98.0, 2.0, 100.0, 100.0));
+        assert!(radius.contains(2.0, 98.0, 100.0, 100.0));
+        assert!(!radius.contains(99.0, 99.0, 100.0, 100.0));
+        assert!(radius.contains(97.0, 97.0, 100.0, 100.0));
+        // radii are clamped to half the shorter side
+        assert_eq!(radius.corner(-1.0, -1.0, 100.0, 30.0), 15.0);
+    }
+}
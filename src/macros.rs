@@ -0,0 +1,102 @@
+// winkel! {
+//     padding(30.0) {
+//         column {
+//             text("Hello World", 20, "Raleway-Regular.ttf", color::BLUE),
+//             row { rect(color::RED), rect(color::GREEN, 10.0) },
+//             button(color::RED) { text("Press", 20, "Raleway-Regular.ttf") },
+//             widget(some_prebuilt_widget),
+//         }
+//     }
+// }
+#[macro_export]
+macro_rules! winkel {
+    (column { $($name:ident $(($($args:tt)*))? $({ $($body:tt)* })?),* $(,)? }) => {
+        $crate::widgets::Column::new()
+            $(.add($crate::winkel!($name $(($($args)*))? $({ $($body)* })?)))*
+            .build()
+    };
+    (row { $($name:ident $(($($args:tt)*))? $({ $($body:tt)* })?),* $(,)? }) => {
+        $crate::widgets::Row::new()
+            $(.add($crate::winkel!($name $(($($args)*))? $({ $($body)* })?)))*
+            .build()
+    };
+    (stack { $($name:ident $(($($args:tt)*))? $({ $($body:tt)* })?),* $(,)? }) => {
+        $crate::widgets::Stack::new()
+            $(.add($crate::winkel!($name $(($($args)*))? $({ $($body)* })?)))*
+            .build()
+    };
+    (padding($pad:expr) { $($child:tt)* }) => {
+        $crate::widgets::Padding::new($crate::winkel!($($child)*))
+            .all($pad)
+            .build()
+    };
+    (button($color:expr) { $($child:tt)* }) => {
+        $crate::widgets::Button::new($color)
+            .child($crate::winkel!($($child)*))
+            .build()
+    };
+    (text($text:expr, $size:expr, $font:expr $(,)?)) => {
+        $crate::widgets::Text::new($text, $size, $font).build()
+    };
+    (text($text:expr, $size:expr, $font:expr, $color:expr $(,)?)) => {
+        $crate::widgets::Text::new($text, $size, $font)
+            .color($color)
+            .build()
+    };
+    (rect($color:expr $(,)?)) => {
+        $crate::widgets::Rectangle::new($color).build()
+    };
+    (rect($color:expr, $border:expr $(,)?)) => {
+        $crate::widgets::Rectangle::new($color)
+            .border($border)
+            .build()
+    };
+    (widget($widget:expr)) => {
+        $widget
+    };
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::widgets::{Button, Column, Padding, Rectangle, Row, Widget};
+    use crate::{color, ComputedWidget};
+    use std::cell::RefCell;
+    use std::rc::Rc;
+
+    // ids differ between the two trees, so only the layouts are compared
+    fn layout<'a>(tree: &Rc<RefCell<dyn Widget<'a> + 'a>>) -> Vec<ComputedWidget<'a>> {
+        let mut computed: Vec<_> = crate::compute(tree, 300.0, 200.0).into_values().collect();
+        computed.sort_by(|a, b| format!("{:?}", a).cmp(&format!("{:?}", b)));
+        computed
+    }
+
+    #[test]
+    fn macro_tree_matches_the_builders() {
+        let from_macro: Rc<RefCell<dyn Widget>> = winkel! {
+            padding(30.0) {
+                column {
+                    rect(color::BLUE),
+                    row { rect(color::RED), rect(color::GREEN, 10.0) },
+                    button(color::RED) { rect(color::YELLOW) },
+                }
+            }
+        };
+        let by_hand: Rc<RefCell<dyn Widget>> = Padding::new(
+            Column::new()
+                .add(Rectangle::new(color::BLUE))
+                .add(
+                    Row::new()
+                        .add(Rectangle::new(color::RED))
+                        .add(Rectangle::new(color::GREEN).border(10.0)),
+                )
+                .add(
+                    Button::new(color::RED)
+                        .child(Rectangle::new(color::YELLOW))
+                        .build(),
+                ),
+        )
+        .all(30.0)
+        .build();
+        assert_eq!(layout(&from_macro), layout(&by_hand));
+    }
+}
@@ -0,0 +1,341 @@
+use std::cell::RefCell;
+use std::collections::HashMap;
+use std::rc::Rc;
+
+use serde::{Deserialize, Serialize};
+
+use crate::color::Color;
+use crate::widgets::*;
+
+fn no_limit() -> f64 {
+    f64::INFINITY
+}
+
+fn default_text_color() -> Color {
+    [0.0, 0.0, 0.0, 1.0]
+}
+
+fn default_flex() -> usize {
+    1
+}
+
+// a declarative description of a widget tree. callbacks can't be written down,
+// so buttons name an action that is looked up in an `Actions` registry when building
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub enum WidgetSpec {
+    Rectangle {
+        color: Color,
+        #[serde(default)]
+        border_radius: f64,
+        #[serde(default)]
+        key: Option<String>,
+    },
+    Text {
+        text: String,
+        size: u32,
+        font: String,
+        #[serde(default = "default_text_color")]
+        color: Color,
+        #[serde(default)]
+        key: Option<String>,
+    },
+    Row {
+        children: Vec<WidgetSpec>,
+        // one entry per child, missing entries default to 1
+        #[serde(default)]
+        flex: Vec<usize>,
+    },
+    Column {
+        children: Vec<WidgetSpec>,
+        #[serde(default)]
+        flex: Vec<usize>,
+    },
+    Stack {
+        children: Vec<WidgetSpec>,
+    },
+    // left, top, right, bottom
+    Padding {
+        padding: (f64, f64, f64, f64),
+        child: Box<WidgetSpec>,
+    },
+    ConstrainedBox {
+        child: Box<WidgetSpec>,
+        #[serde(default)]
+        min_width: f64,
+        #[serde(default = "no_limit")]
+        max_width: f64,
+        #[serde(default)]
+        min_height: f64,
+        #[serde(default = "no_limit")]
+        max_height: f64,
+    },
+    Clip {
+        child: Box<WidgetSpec>,
+    },
+    Button {
+        color: Color,
+        #[serde(default)]
+        hover: Option<Color>,
+        #[serde(default)]
+        active: Option<Color>,
+        #[serde(default)]
+        border_radius: f64,
+        #[serde(default)]
+        child: Option<Box<WidgetSpec>>,
+        #[serde(default)]
+        action: Option<String>,
+    },
+}
+
+impl WidgetSpec {
+    pub fn from_ron(source: &str) -> Result<WidgetSpec, ron::error::SpannedError> {
+        ron::from_str(source)
+    }
+
+    pub fn to_ron(&self) -> Result<String, ron::Error> {
+        ron::ser::to_string_pretty(self, ron::ser::PrettyConfig::default())
+    }
+}
+
+// named callbacks that spec buttons can refer to
+#[derive(Default)]
+pub struct Actions<'a> {
+    actions: HashMap<String, Rc<dyn Fn(u8) + 'a>>,
+}
+
+impl<'a> Actions<'a> {
+    pub fn new() -> Self {
+        Actions::default()
+    }
+
+    pub fn register<F: Fn(u8) + 'a>(mut self, name: &str, action: F) -> Self {
+        self.actions.insert(String::from(name), Rc::new(action));
+        self
+    }
+
+    fn get(&self, name: &str) -> Option<Rc<dyn Fn(u8) + 'a>> {
+        let action = self.actions.get(name).cloned();
+        if action.is_none() {
+            eprintln!("winkel: unknown action '{}'", name);
+        }
+        action
+    }
+}
+
+// builds the widgets a spec describes, strings are borrowed from the spec
+pub fn from_spec<'a>(
+    spec: &'a WidgetSpec,
+    actions: &Actions<'a>,
+) -> Rc<RefCell<dyn Widget<'a> + 'a>> {
+    match spec {
+        WidgetSpec::Rectangle {
+            color,
+            border_radius,
+            key,
+        } => {
+            let mut builder = Rectangle::new(*color).border(*border_radius);
+            if let Some(key) = key {
+                builder = builder.key(key.as_str());
+            }
+            builder.build()
+        }
+        WidgetSpec::Text {
+            text,
+            size,
+            font,
+            color,
+            key,
+        } => {
+            let mut builder = Text::new(text, *size, font).color(*color);
+            if let Some(key) = key {
+                builder = builder.key(key.as_str());
+            }
+            builder.build()
+        }
+        WidgetSpec::Row { children, flex } => {
+            let mut row = Row::new();
+            for (i, child) in children.iter().enumerate() {
+                let flex = flex.get(i).copied().unwrap_or_else(default_flex);
+                row = row.add_flex(from_spec(child, actions), flex);
+            }
+            row.build()
+        }
+        WidgetSpec::Column { children, flex } => {
+            let mut column = Column::new();
+            for (i, child) in children.iter().enumerate() {
+                let flex = flex.get(i).copied().unwrap_or_else(default_flex);
+                column = column.add_flex(from_spec(child, actions), flex);
+            }
+            column.build()
+        }
+        WidgetSpec::Stack { children } => children
+            .iter()
+            .fold(Stack::new(), |stack, child| {
+                stack.add(from_spec(child, actions))
+            })
+            .build(),
+        WidgetSpec::Padding { padding, child } => {
+            let (left, top, right, bottom) = *padding;
+            Padding::new(from_spec(child, actions))
+                .each(left, top, right, bottom)
+                .build()
+        }
+        WidgetSpec::ConstrainedBox {
+            child,
+            min_width,
+            max_width,
+            min_height,
+            max_height,
+        } => ConstrainedBox::new(from_spec(child, actions))
+            .min_width(*min_width)
+            .max_width(*max_width)
+            .min_height(*min_height)
+            .max_height(*max_height)
+            .build(),
+        WidgetSpec::Clip { child } => Clip::new(from_spec(child, actions)).build(),
+        WidgetSpec::Button {
+            color,
+            hover,
+            active,
+            border_radius,
+            child,
+            action,
+        } => {
+            let mut button = Button::new(*color)
+                .hover(hover.unwrap_or(*color))
+                .active(active.unwrap_or(*color))
+                .border(*border_radius);
+            if let Some(child) = child {
+                button = button.child(from_spec(child, actions));
+            }
+            if let Some(action) = action.as_ref().and_then(|name| actions.get(name)) {
+                button = button.on_pressed(move |mouse_button| action(mouse_button));
+            }
+            button.build()
+        }
+    }
+}
+
+// rebuilds a tree from a spec file whenever it changes on disk. keyed widgets
+// resolve to the same ids, so anything remembered by id carries over to the new tree
+#[cfg(feature = "hot-reload")]
+pub struct SpecReloader<'a> {
+    path: std::path::PathBuf,
+    modified: Option<std::time::SystemTime>,
+    actions: Actions<'a>,
+}
+
+#[cfg(feature = "hot-reload")]
+impl<'a> SpecReloader<'a> {
+    pub fn new<P: Into<std::path::PathBuf>>(path: P, actions: Actions<'a>) -> Self {
+        SpecReloader {
+            path: path.into(),
+            modified: None,
+            actions,
+        }
+    }
+
+    // widgets borrow their strings from the spec and a reload can't tell when the old
+    // tree is gone, so every spec is leaked. that's a few kilobytes per save
+    pub fn rebuild(&self, source: &str) -> Result<Rc<RefCell<dyn Widget<'a> + 'a>>, String> {
+        let spec = WidgetSpec::from_ron(source).map_err(|e| e.to_string())?;
+        let spec: &'a WidgetSpec = Box::leak(Box::new(spec));
+        Ok(from_spec(spec, &self.actions))
+    }
+
+    pub fn load(&mut self) -> Result<Rc<RefCell<dyn Widget<'a> + 'a>>, String> {
+        let modified = std::fs::metadata(&self.path).and_then(|m| m.modified());
+        self.modified = modified.ok();
+        let source = std::fs::read_to_string(&self.path).map_err(|e| e.to_string())?;
+        self.rebuild(&source)
+    }
+
+    // a new tree if the file changed since the last load. a spec that doesn't parse
+    // is reported and the current tree is kept
+    pub fn poll(&mut self) -> Option<Rc<RefCell<dyn Widget<'a> + 'a>>> {
+        let modified = std::fs::metadata(&self.path)
+            .and_then(|m| m.modified())
+            .ok();
+        if modified.is_none() || modified == self.modified {
+            return None;
+        }
+        match self.load() {
+            Ok(tree) => Some(tree),
+            Err(e) => {
+                eprintln!("winkel: failed to reload {}: {}", self.path.display(), e);
+                None
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{color, ComputedWidget};
+
+    const SPEC: &str = r#"Padding(
+        padding: (10.0, 10.0, 10.0, 10.0),
+        child: Column(
+            children: [
+                Rectangle(color: (0.0, 0.0, 1.0, 1.0)),
+                Row(
+                    children: [
+                        Rectangle(color: (1.0, 0.0, 0.0, 1.0), border_radius: 4.0),
+                        ConstrainedBox(
+                            child: Rectangle(color: (0.0, 1.0, 0.0, 1.0)),
+                            min_width: 50.0,
+                        ),
+                    ],
+                    flex: [1, 0],
+                ),
+            ],
+        ),
+    )"#;
+
+    // ids differ between the two trees, so only the layouts are compared
+    fn layout<'a>(tree: &Rc<RefCell<dyn Widget<'a> + 'a>>) -> Vec<ComputedWidget<'a>> {
+        let mut computed: Vec<_> = crate::compute(tree, 300.0, 200.0).into_values().collect();
+        computed.sort_by(|a, b| format!("{:?}", a).cmp(&format!("{:?}", b)));
+        computed
+    }
+
+    #[test]
+    fn spec_tree_matches_the_builders() {
+        let spec = WidgetSpec::from_ron(SPEC).unwrap();
+        let from_spec = from_spec(&spec, &Actions::new());
+        let by_hand: Rc<RefCell<dyn Widget>> = Padding::new(
+            Column::new().add(Rectangle::new(color::BLUE)).add(
+                Row::new()
+                    .add(Rectangle::new(color::RED).border(4.0))
+                    .add_flex(
+                        ConstrainedBox::new(Rectangle::new(color::GREEN)).min_width(50.0),
+                        0,
+                    ),
+            ),
+        )
+        .all(10.0)
+        .build();
+        assert_eq!(layout(&from_spec), layout(&by_hand));
+        // and it survives a round trip
+        assert_eq!(WidgetSpec::from_ron(&spec.to_ron().unwrap()).unwrap(), spec);
+    }
+
+    #[cfg(feature = "hot-reload")]
+    #[test]
+    fn reloading_swaps_in_the_new_layout() {
+        let reloader = SpecReloader::new("unused.ron", Actions::new());
+        let key = |tree: &Rc<RefCell<dyn Widget>>| tree.borrow().children()[0].borrow().get_id();
+        let v1 = reloader
+            .rebuild(r#"Padding(padding: (0.0, 0.0, 0.0, 0.0), child: Rectangle(color: (1.0, 0.0, 0.0, 1.0), key: Some("body")))"#)
+            .unwrap();
+        let v2 = reloader
+            .rebuild(r#"Padding(padding: (20.0, 0.0, 0.0, 0.0), child: Rectangle(color: (1.0, 0.0, 0.0, 1.0), key: Some("body")))"#)
+            .unwrap();
+        // the keyed rectangle keeps its id, so its state carries over
+        assert_eq!(key(&v1), key(&v2));
+        let body = crate::compute(&v2, 100.0, 100.0)[&key(&v2)].clone();
+        assert_eq!((body.x, body.width), (20.0, 80.0));
+        assert!(reloader.rebuild("Rectangle(").is_err());
+    }
+}
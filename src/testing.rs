@@ -0,0 +1,106 @@
+use std::cell::RefCell;
+use std::collections::HashMap;
+use std::rc::Rc;
+
+use crate::widgets::{IntoWidget, Widget};
+use crate::{ComputedWidget, Event, RenderObject, SemanticNode};
+
+pub struct LayoutResult<'a> {
+    tree: Rc<RefCell<dyn Widget<'a> + 'a>>,
+    map: HashMap<usize, ComputedWidget<'a>>,
+}
+
+// lays out a tree the same way a frame would, for inspecting it without a window
+pub fn layout<'a, W: IntoWidget<'a>>(widget: W, width: f64, height: f64) -> LayoutResult<'a> {
+    let tree = widget.into_widget();
+    let map = crate::compute(&tree, width, height);
+    LayoutResult { tree, map }
+}
+
+impl<'a> LayoutResult<'a> {
+    // x, y, width, height of a widget, None if it wasn't laid out
+    pub fn bounds(&self, id: usize) -> Option<(f64, f64, f64, f64)> {
+        self.map.get(&id).map(|w| (w.x, w.y, w.width, w.height))
+    }
+
+    pub fn z(&self, id: usize) -> Option<usize> {
+        self.map.get(&id).map(|w| w.z)
+    }
+
+    pub fn clip(&self, id: usize) -> Option<(f64, f64, f64, f64)> {
+        self.map.get(&id).and_then(|w| w.clip)
+    }
+
+    pub fn render_object(&self, id: usize) -> Option<&RenderObject<'a>> {
+        self.map.get(&id).and_then(|w| w.render.as_ref())
+    }
+
+    pub fn contains(&self, id: usize) -> bool {
+        self.map.contains_key(&id)
+    }
+
+    pub fn hit_test(&self, x: f64, y: f64) -> Option<usize> {
+        crate::hit_test(&self.map, x, y)
+    }
+
+    // returns whether any widget reported a state change
+    pub fn dispatch_event(&self, event: Event) -> bool {
+        self.tree.borrow().dispatch(event, false, &self.map).1
+    }
+
+    // like dispatch_event, but records which widgets saw the event
+    #[cfg(feature = "trace")]
+    pub fn dispatch_traced(&self, event: Event) -> crate::trace::DispatchTrace {
+        crate::trace::dispatch(&self.tree, event, &self.map).1
+    }
+
+    // lays the same tree out again, e.g. after a dispatched event changed state
+    pub fn relayout(&mut self, width: f64, height: f64) {
+        self.map = crate::compute(&self.tree, width, height);
+    }
+
+    pub fn semantics(&self) -> Vec<SemanticNode> {
+        crate::semantics(&self.tree, &self.map)
+    }
+
+    pub fn map(&self) -> &HashMap<usize, ComputedWidget<'a>> {
+        &self.map
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::color;
+    use crate::widgets::{MouseGesture, Padding, Rectangle};
+
+    #[test]
+    fn reports_a_padded_rectangle_inset() {
+        let rect = Rectangle::new(color::RED).build();
+        let id = rect.borrow().get_id();
+        let result = layout(
+            Padding::new(rect).each(10.0, 20.0, 30.0, 40.0),
+            200.0,
+            100.0,
+        );
+        assert_eq!(result.bounds(id), Some((10.0, 20.0, 160.0, 40.0)));
+        assert!(result.render_object(id).is_some());
+        assert_eq!(result.hit_test(100.0, 40.0), Some(id));
+        assert_eq!(result.hit_test(5.0, 5.0), None);
+    }
+
+    #[test]
+    fn dispatches_into_the_laid_out_tree() {
+        let gesture = MouseGesture::new(Rectangle::new(color::RED))
+            .on_click(|_| true)
+            .build();
+        let result = layout(gesture, 100.0, 100.0);
+        let click = |x| Event::MouseDown {
+            x,
+            y: 50.0,
+            button: 0,
+        };
+        assert!(result.dispatch_event(click(50.0)));
+        assert!(!result.dispatch_event(click(150.0)));
+    }
+}
@@ -0,0 +1,175 @@
+use std::cell::{Cell, RefCell};
+use std::collections::HashMap;
+use std::fmt;
+use std::rc::Rc;
+
+use crate::widgets::Widget;
+use crate::{ComputedWidget, Event};
+
+thread_local! {
+    static RECORDING: RefCell<Option<Vec<TraceEntry>>> = const { RefCell::new(None) };
+    static DEPTH: Cell<usize> = const { Cell::new(0) };
+}
+
+// one widget an event was handed to, in the order dispatch reached them
+#[derive(Debug, Clone, PartialEq)]
+pub struct TraceEntry {
+    pub id: usize,
+    pub depth: usize,
+    pub event: String,
+    // whether a pointer event landed in the widget's hitbox, None for other events
+    // and for widgets that weren't laid out
+    pub hit: Option<bool>,
+    pub consumed: bool,
+    pub state_change: bool,
+}
+
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct DispatchTrace {
+    pub entries: Vec<TraceEntry>,
+}
+
+impl DispatchTrace {
+    // the ancestors of a consumer report the event as consumed too, and dispatch stops
+    // once it is, so the consumer is the last consuming entry
+    pub fn consumed_by(&self) -> Option<usize> {
+        self.entries
+            .iter()
+            .rev()
+            .find(|entry| entry.consumed)
+            .map(|entry| entry.id)
+    }
+}
+
+impl fmt::Display for DispatchTrace {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        for entry in &self.entries {
+            write!(
+                f,
+                "{}#{} {}",
+                "  ".repeat(entry.depth),
+                entry.id,
+                entry.event
+            )?;
+            match entry.hit {
+                Some(true) => write!(f, " hit")?,
+                Some(false) => write!(f, " missed")?,
+                None => {}
+            }
+            if entry.consumed {
+                write!(f, " consumed")?;
+            }
+            if entry.state_change {
+                write!(f, " state changed")?;
+            }
+            writeln!(f)?;
+        }
+        Ok(())
+    }
+}
+
+fn pointer(event: &Event) -> Option<(f64, f64)> {
+    match event {
+        Event::MouseDown { x, y, .. } | Event::MouseUp { x, y, .. } => Some((*x, *y)),
+        Event::MouseMove { x, y, .. } | Event::Scroll { x, y, .. } => Some((*x, *y)),
+        _ => None,
+    }
+}
+
+// dispatches an event to a tree and records every widget it passes through
+pub fn dispatch<'a>(
+    tree: &Rc<RefCell<dyn Widget<'a> + 'a>>,
+    event: Event,
+    map: &HashMap<usize, ComputedWidget>,
+) -> ((Option<Event>, bool), DispatchTrace) {
+    let previous = RECORDING.with(|recording| recording.replace(Some(Vec::new())));
+    let result = crate::widgets::dispatch_to(tree, event, false, map);
+    let entries = RECORDING.with(|recording| recording.replace(previous));
+    (
+        result,
+        DispatchTrace {
+            entries: entries.unwrap_or_default(),
+        },
+    )
+}
+
+pub(crate) fn traced<'a>(
+    widget: &dyn Widget<'a>,
+    event: Event,
+    prev_state_change: bool,
+    map: &HashMap<usize, ComputedWidget>,
+) -> (Option<Event>, bool) {
+    let recording = RECORDING.with(|recording| recording.borrow().is_some());
+    if !recording {
+        return widget.dispatch(event, prev_state_change, map);
+    }
+    let id = widget.get_id();
+    let hit = pointer(&event).and_then(|(x, y)| {
+        map.get(&id)
+            .map(|computed| computed.in_hitbox(x, y, computed.border_radius()))
+    });
+    let depth = DEPTH.with(|depth| depth.get());
+    let index = RECORDING.with(|recording| {
+        let mut recording = recording.borrow_mut();
+        let entries = recording.as_mut().unwrap();
+        entries.push(TraceEntry {
+            id,
+            depth,
+            event: format!("{:?}", event),
+            hit,
+            consumed: false,
+            state_change: false,
+        });
+        entries.len() - 1
+    });
+    DEPTH.with(|d| d.set(depth + 1));
+    let result = widget.dispatch(event, prev_state_change, map);
+    DEPTH.with(|d| d.set(depth));
+    RECORDING.with(|recording| {
+        if let Some(entry) = recording
+            .borrow_mut()
+            .as_mut()
+            .and_then(|entries| entries.get_mut(index))
+        {
+            entry.consumed = result.0.is_none();
+            entry.state_change = result.1 && !prev_state_change;
+        }
+    });
+    result
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::color;
+    use crate::widgets::{MouseGesture, Padding, Rectangle};
+
+    #[test]
+    fn traces_the_path_to_the_consumer() {
+        let gesture = MouseGesture::new(Rectangle::new(color::RED).build())
+            .on_click(|_| true)
+            .build();
+        let padding = Padding::new(gesture.clone()).all(10.0).build();
+        let tree: Rc<RefCell<dyn Widget>> = padding.clone();
+        let map = crate::compute(&tree, 100.0, 100.0);
+        let click = Event::MouseDown {
+            x: 50.0,
+            y: 50.0,
+            button: 0,
+        };
+        let ((rest, _), trace) = dispatch(&tree, click, &map);
+        assert!(rest.is_none());
+        let path: Vec<_> = trace
+            .entries
+            .iter()
+            .map(|entry| (entry.id, entry.depth, entry.consumed))
+            .collect();
+        let (outer, inner) = (padding.borrow().get_id(), gesture.borrow().get_id());
+        assert_eq!(path, vec![(outer, 0, true), (inner, 1, true)]);
+        assert_eq!(trace.entries[1].hit, Some(true));
+        assert_eq!(trace.consumed_by(), Some(inner));
+        assert!(trace
+            .to_string()
+            .contains(&format!("  #{} MouseDown", inner)));
+    }
+}
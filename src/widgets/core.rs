@@ -1,740 +1,7460 @@
-use super::super::{color::Color, ComputedWidget, Event, RenderObject, State, Style, TextStyle};
-use super::Widget;
-use std::cell::RefCell;
-use std::collections::HashMap;
+use super::super::{
+    color::Color,
+    gl_renderer::{grapheme_offsets, line_height, measure_text},
+    span_layout, BackgroundImage, BorderRadius, Clipboard, ComputedWidget, DrawCommand, Event,
+    EventFlow, Hinting, ImageFit, Key, Modifiers, Painter, RenderObject, Role, SemanticNode, State,
+    Style, TabStop, TextDecoration, TextDirection, TextSpan, TextStyle,
+};
+use super::{dispatch_to, dispatch_widget, Child, IntoWidget, StackPosition, Widget};
+use std::borrow::Cow;
+use std::cell::{Cell, RefCell};
+use std::collections::{HashMap, VecDeque};
+use std::ops::Deref;
 use std::rc::Rc;
-use std::sync::atomic::AtomicUsize;
+use unicode_segmentation::UnicodeSegmentation;
 
-pub static COUNTER: AtomicUsize = AtomicUsize::new(0);
+// widgets can't leave the thread that built them, so ids only have to be unique per thread.
+// this also keeps ids deterministic when tests build trees in parallel
+thread_local! {
+    static COUNTER: Cell<usize> = const { Cell::new(0) };
+    static CURRENT_ALLOCATOR: RefCell<Option<IdAllocator>> = const { RefCell::new(None) };
+    static GLOBAL_KEYS: RefCell<HashMap<WidgetKey, usize>> = RefCell::new(HashMap::new());
+    static LAYOUT_DIRECTION: Cell<TextDirection> = const { Cell::new(TextDirection::Ltr) };
+}
+
+// the same key always resolves to the same id, so rebuilt widgets keep their identity
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub enum WidgetKey {
+    Int(u64),
+    Str(String),
+}
+
+impl WidgetKey {
+    pub fn id(&self) -> usize {
+        match IdAllocator::current() {
+            Some(allocator) => {
+                let existing = allocator.keys.borrow().get(self).copied();
+                existing.unwrap_or_else(|| {
+                    let id = allocator.next();
+                    allocator.keys.borrow_mut().insert(self.clone(), id);
+                    id
+                })
+            }
+            None => GLOBAL_KEYS.with(|keys| {
+                *keys
+                    .borrow_mut()
+                    .entry(self.clone())
+                    .or_insert_with(counter_id)
+            }),
+        }
+    }
+}
+
+impl From<u64> for WidgetKey {
+    fn from(key: u64) -> Self {
+        WidgetKey::Int(key)
+    }
+}
+
+impl From<&str> for WidgetKey {
+    fn from(key: &str) -> Self {
+        WidgetKey::Str(String::from(key))
+    }
+}
+
+impl From<String> for WidgetKey {
+    fn from(key: String) -> Self {
+        WidgetKey::Str(key)
+    }
+}
+
+// gives a tree its own dense ids starting at 0. build the whole tree inside one allocator's
+// scope, ids from the counter or another allocator can be the same numbers
+#[derive(Clone, Default)]
+pub struct IdAllocator {
+    next: Rc<Cell<usize>>,
+    keys: Rc<RefCell<HashMap<WidgetKey, usize>>>,
+}
+
+// puts the previous allocator back when a scope ends, also when building panicked
+struct AllocatorScope(Option<IdAllocator>);
+
+impl Drop for AllocatorScope {
+    fn drop(&mut self) {
+        let previous = self.0.take();
+        CURRENT_ALLOCATOR.with(|current| current.replace(previous));
+    }
+}
+
+impl IdAllocator {
+    pub fn new() -> Self {
+        IdAllocator::default()
+    }
+
+    pub fn next(&self) -> usize {
+        let id = self.next.get();
+        self.next.set(id + 1);
+        id
+    }
+
+    // widgets built inside `build` take their ids from this allocator
+    pub fn scope<R, F: FnOnce() -> R>(&self, build: F) -> R {
+        let previous = CURRENT_ALLOCATOR.with(|current| current.replace(Some(self.clone())));
+        let _scope = AllocatorScope(previous);
+        build()
+    }
+
+    pub fn current() -> Option<IdAllocator> {
+        CURRENT_ALLOCATOR.with(|current| current.borrow().clone())
+    }
+}
+
+fn counter_id() -> usize {
+    COUNTER.with(|counter| counter.replace(counter.get() + 1))
+}
+
+fn next_id() -> usize {
+    match IdAllocator::current() {
+        Some(allocator) => allocator.next(),
+        None => counter_id(),
+    }
+}
+
+// makes ids deterministic again, builders can also take an explicit id
+#[cfg(feature = "testing")]
+pub fn reset_counter() {
+    COUNTER.with(|counter| counter.set(0));
+    GLOBAL_KEYS.with(|keys| keys.borrow_mut().clear());
+}
 
 pub struct Text<'a> {
-    text: &'a str,
+    text: Cow<'a, str>,
     id: usize,
     size: u32,
     font: &'a str,
     color: Color,
+    hinting: Hinting,
+    tab_stop: TabStop,
+    direction: TextDirection,
+    decoration: TextDecoration,
+    decoration_color: Option<Color>,
 }
 
 pub struct TextBuilder<'a> {
-    text: &'a str,
+    text: Cow<'a, str>,
     size: u32,
     font: &'a str,
     color: Color,
+    hinting: Hinting,
+    tab_stop: TabStop,
+    direction: TextDirection,
+    decoration: TextDecoration,
+    decoration_color: Option<Color>,
+    id: Option<usize>,
 }
 
 impl<'a> Text<'a> {
-    pub fn new(text: &'a str, size: u32, font: &'a str) -> TextBuilder<'a> {
+    // takes a borrowed str for fixed labels or a String for text made at build time
+    pub fn new<T: Into<Cow<'a, str>>>(text: T, size: u32, font: &'a str) -> TextBuilder<'a> {
         TextBuilder {
-            text,
+            text: text.into(),
             font,
             color: [0.0, 0.0, 0.0, 1.0],
             size: size,
+            hinting: Hinting::Normal,
+            tab_stop: TabStop::Spaces(4),
+            direction: TextDirection::Ltr,
+            decoration: TextDecoration::None,
+            decoration_color: None,
+            id: None,
+        }
+    }
+
+    fn style(&self) -> TextStyle<'a> {
+        TextStyle {
+            color: self.color,
+            size: self.size,
+            font: self.font,
+            hinting: self.hinting,
+            tab_stop: self.tab_stop,
+            direction: self.direction,
+            decoration: self.decoration,
+            decoration_color: self.decoration_color,
         }
     }
 }
 
 impl<'a> TextBuilder<'a> {
+    pub fn id(mut self, id: usize) -> Self {
+        self.id = Some(id);
+        self
+    }
+
+    pub fn key<K: Into<WidgetKey>>(self, key: K) -> Self {
+        let id = key.into().id();
+        self.id(id)
+    }
+
     pub fn color(mut self, color: Color) -> Self {
         self.color = color;
         self
     }
 
+    pub fn hinting(mut self, hinting: Hinting) -> Self {
+        self.hinting = hinting;
+        self
+    }
+
+    pub fn tab_stop(mut self, tab_stop: TabStop) -> Self {
+        self.tab_stop = tab_stop;
+        self
+    }
+
+    pub fn direction(mut self, direction: TextDirection) -> Self {
+        self.direction = direction;
+        self
+    }
+
+    pub fn decoration(mut self, decoration: TextDecoration) -> Self {
+        self.decoration = decoration;
+        self
+    }
+
+    pub fn decoration_color(mut self, color: Color) -> Self {
+        self.decoration_color = Some(color);
+        self
+    }
+
     pub fn build(self) -> Rc<RefCell<Text<'a>>> {
-        Rc::new(RefCell::new(Text {
-            id: COUNTER.fetch_add(1, std::sync::atomic::Ordering::SeqCst),
+        Rc::new(RefCell::new(self.widget()))
+    }
+
+    // for trees that never change, skips the shared ownership build hands out
+    pub fn build_static(self) -> Box<dyn Widget<'a> + 'a> {
+        Box::new(self.widget())
+    }
+
+    fn widget(self) -> Text<'a> {
+        Text {
+            id: self.id.unwrap_or_else(next_id),
             text: self.text,
             color: self.color,
             font: self.font,
             size: self.size,
-        }))
+            hinting: self.hinting,
+            tab_stop: self.tab_stop,
+            direction: self.direction,
+            decoration: self.decoration,
+            decoration_color: self.decoration_color,
+        }
     }
 
     pub fn build_stateful(self, state: &mut State<Text<'a>>) -> Rc<RefCell<Text<'a>>> {
-        let result = Rc::new(RefCell::new(Text {
-            id: COUNTER.fetch_add(1, std::sync::atomic::Ordering::SeqCst),
-            text: self.text,
-            color: self.color,
-            size: self.size,
-            font: self.font,
-        }));
+        let result = Rc::new(RefCell::new(self.widget()));
         state.bind(result.clone());
         result
     }
 }
 
-pub struct Rectangle {
-    pub color: Color,
-    pub border_radius: f64,
-    pub id: usize,
+pub struct SelectableText<'a> {
+    text: &'a str,
+    id: usize,
+    highlight_id: usize,
+    size: u32,
+    font: &'a str,
+    color: Color,
+    selection_color: Color,
+    hinting: Hinting,
+    anchor: Cell<Option<usize>>,
+    cursor: Cell<usize>,
+    dragging: Cell<bool>,
+    clipboard: Option<Box<dyn Clipboard + 'a>>,
 }
 
-pub struct RectangleBuilder {
-    pub color: Color,
-    pub border_radius: f64,
+pub struct SelectableTextBuilder<'a> {
+    text: &'a str,
+    size: u32,
+    font: &'a str,
+    color: Color,
+    selection_color: Color,
+    hinting: Hinting,
+    id: Option<usize>,
+    clipboard: Option<Box<dyn Clipboard + 'a>>,
 }
 
-impl Rectangle {
-    pub fn new(color: Color) -> RectangleBuilder {
-        RectangleBuilder {
-            color,
-            border_radius: 0.0,
+impl<'a> SelectableText<'a> {
+    pub fn new(text: &'a str, size: u32, font: &'a str) -> SelectableTextBuilder<'a> {
+        SelectableTextBuilder {
+            text,
+            size,
+            font,
+            color: [0.0, 0.0, 0.0, 1.0],
+            selection_color: [0.2, 0.4, 1.0, 0.4],
+            hinting: Hinting::Normal,
+            id: None,
+            clipboard: None,
         }
     }
-}
 
-impl RectangleBuilder {
-    pub fn border(mut self, border_radius: f64) -> Self {
-        self.border_radius = border_radius;
-        self
+    // byte range of the selected text, None while nothing is selected
+    pub fn selection(&self) -> Option<(usize, usize)> {
+        let anchor = self.anchor.get()?;
+        let cursor = self.cursor.get();
+        if anchor == cursor {
+            None
+        } else {
+            Some((anchor.min(cursor), anchor.max(cursor)))
+        }
     }
 
-    pub fn build(self) -> Rc<RefCell<Rectangle>> {
-        Rc::new(RefCell::new(Rectangle {
-            color: self.color,
-            id: COUNTER.fetch_add(1, std::sync::atomic::Ordering::SeqCst),
-            border_radius: self.border_radius,
-        }))
+    pub fn selected_text(&self) -> &'a str {
+        match self.selection() {
+            Some((start, end)) => &self.text[start..end],
+            None => "",
+        }
     }
 
-    pub fn build_stateful(self, state: &mut State<Rectangle>) -> Rc<RefCell<Rectangle>> {
-        let result = Rc::new(RefCell::new(Rectangle {
-            color: self.color,
-            id: COUNTER.fetch_add(1, std::sync::atomic::Ordering::SeqCst),
-            border_radius: self.border_radius,
-        }));
-        state.bind(result.clone());
-        result
+    pub fn select(&self, start: usize, end: usize) {
+        self.anchor.set(Some(grapheme_floor(self.text, start)));
+        self.cursor.set(grapheme_floor(self.text, end));
     }
-}
 
-pub struct Empty {
-    pub id: usize,
-}
+    pub fn clear_selection(&self) {
+        self.anchor.set(None);
+        self.dragging.set(false);
+    }
 
-impl Empty {
-    pub fn new() -> Rc<Empty> {
-        Rc::new(Empty {
-            id: COUNTER.fetch_add(1, std::sync::atomic::Ordering::SeqCst),
-        })
+    pub fn copy(&self) {
+        if let (Some(clipboard), Some(_)) = (&self.clipboard, self.selection()) {
+            clipboard.set(self.selected_text());
+        }
     }
-}
 
-pub struct MouseGesture<'a> {
-    pub background: Rc<RefCell<dyn Widget<'a> + 'a>>,
-    pub click_callback: Option<Box<dyn Fn(u8) -> bool + 'a>>,
-    pub release_callback: Option<Box<dyn Fn(u8) -> bool + 'a>>,
-    pub enter_callback: Option<Box<dyn Fn() -> bool + 'a>>,
-    pub leave_callback: Option<Box<dyn Fn() -> bool + 'a>>,
-    border_radius: f64,
-    pub id: usize,
-}
+    fn style(&self) -> TextStyle<'a> {
+        TextStyle {
+            color: self.color,
+            size: self.size,
+            font: self.font,
+            hinting: self.hinting,
+            tab_stop: TabStop::Spaces(4),
+            direction: TextDirection::Ltr,
+            decoration: TextDecoration::None,
+            decoration_color: None,
+        }
+    }
 
-pub struct MouseGestureBuilder<'a> {
-    background: Rc<RefCell<dyn Widget<'a> + 'a>>,
-    click_callback: Option<Box<dyn Fn(u8) -> bool + 'a>>,
-    release_callback: Option<Box<dyn Fn(u8) -> bool + 'a>>,
-    enter_callback: Option<Box<dyn Fn() -> bool + 'a>>,
-    leave_callback: Option<Box<dyn Fn() -> bool + 'a>>,
-    border_radius: f64,
-}
+    // snaps a position relative to the text to the closest grapheme boundary
+    pub fn index_at(&self, x: f64, y: f64) -> usize {
+        let style = self.style();
+        let line_height = line_height(&style);
+        let lines: Vec<&str> = self.text.split('\n').collect();
+        let line = if line_height > 0.0 {
+            ((y / line_height).floor().max(0.0) as usize).min(lines.len() - 1)
+        } else {
+            0
+        };
+        let start = lines[..line].iter().map(|l| l.len() + 1).sum::<usize>();
+        let offsets = grapheme_offsets(lines[line], &style);
+        let (index, _) = offsets
+            .iter()
+            .min_by(|(_, a), (_, b)| (a - x).abs().partial_cmp(&(b - x).abs()).unwrap())
+            .unwrap();
+        start + index
+    }
 
-impl<'a> MouseGesture<'a> {
-    pub fn new(background: Rc<RefCell<dyn Widget<'a> + 'a>>) -> MouseGestureBuilder<'a> {
-        MouseGestureBuilder {
-            background,
-            border_radius: 0.0,
-            click_callback: None,
-            release_callback: None,
-            enter_callback: None,
-            leave_callback: None,
+    fn highlight(&self) -> Vec<DrawCommand> {
+        let (start, end) = match self.selection() {
+            Some(selection) => selection,
+            None => return Vec::new(),
+        };
+        let style = self.style();
+        let line_height = line_height(&style);
+        let mut commands = Vec::new();
+        let mut line_start = 0;
+        for (i, line) in self.text.split('\n').enumerate() {
+            let line_end = line_start + line.len();
+            if start <= line_end && end > line_start {
+                let offsets = grapheme_offsets(line, &style);
+                let x_of = |index: usize| {
+                    let local = index.clamp(line_start, line_end) - line_start;
+                    offsets
+                        .iter()
+                        .find(|(boundary, _)| *boundary >= local)
+                        .map(|(_, x)| *x)
+                        .unwrap_or(0.0)
+                };
+                let (from, to) = (x_of(start), x_of(end));
+                commands.push(DrawCommand::FillRect {
+                    x: from,
+                    y: i as f64 * line_height,
+                    width: to - from,
+                    height: line_height,
+                    color: self.selection_color,
+                });
+            }
+            line_start = line_end + 1;
         }
+        commands
     }
 }
 
-impl<'a> MouseGestureBuilder<'a> {
-    pub fn on_click<F: Fn(u8) -> bool + 'a>(mut self, on_click: F) -> Self {
-        self.click_callback = Some(Box::new(on_click));
+impl<'a> SelectableTextBuilder<'a> {
+    pub fn id(mut self, id: usize) -> Self {
+        self.id = Some(id);
         self
     }
 
-    pub fn on_release<F: Fn(u8) -> bool + 'a>(mut self, on_release: F) -> Self {
-        self.release_callback = Some(Box::new(on_release));
+    pub fn key<K: Into<WidgetKey>>(self, key: K) -> Self {
+        let id = key.into().id();
+        self.id(id)
+    }
+
+    pub fn color(mut self, color: Color) -> Self {
+        self.color = color;
         self
     }
-    pub fn on_enter<F: Fn() -> bool + 'a>(mut self, on_enter: F) -> Self {
-        self.enter_callback = Some(Box::new(on_enter));
+
+    pub fn selection_color(mut self, color: Color) -> Self {
+        self.selection_color = color;
         self
     }
 
-    pub fn on_leave<F: Fn() -> bool + 'a>(mut self, on_leave: F) -> Self {
-        self.leave_callback = Some(Box::new(on_leave));
+    pub fn hinting(mut self, hinting: Hinting) -> Self {
+        self.hinting = hinting;
         self
     }
 
-    pub fn border(mut self, border_radius: f64) -> Self {
-        self.border_radius = border_radius;
+    // without a clipboard, ctrl+c does nothing
+    pub fn clipboard<C: Clipboard + 'a>(mut self, clipboard: C) -> Self {
+        self.clipboard = Some(Box::new(clipboard));
         self
     }
 
-    pub fn build(self) -> Rc<RefCell<MouseGesture<'a>>> {
-        Rc::new(RefCell::new(MouseGesture {
-            background: self.background,
-            id: COUNTER.fetch_add(1, std::sync::atomic::Ordering::SeqCst),
-            border_radius: self.border_radius,
-            click_callback: self.click_callback,
-            release_callback: self.release_callback,
-            enter_callback: self.enter_callback,
-            leave_callback: self.leave_callback,
+    pub fn build(self) -> Rc<RefCell<SelectableText<'a>>> {
+        Rc::new(RefCell::new(SelectableText {
+            id: self.id.unwrap_or_else(next_id),
+            highlight_id: next_id(),
+            text: self.text,
+            size: self.size,
+            font: self.font,
+            color: self.color,
+            selection_color: self.selection_color,
+            hinting: self.hinting,
+            anchor: Cell::new(None),
+            cursor: Cell::new(0),
+            dragging: Cell::new(false),
+            clipboard: self.clipboard,
         }))
     }
 }
 
-pub struct Padding<'a> {
-    pub padding: (f64, f64, f64, f64),
-    pub child: Rc<RefCell<dyn Widget<'a> + 'a>>,
-    pub id: usize,
+// the grapheme boundary at or before index, so offsets never split a character
+fn grapheme_floor(text: &str, index: usize) -> usize {
+    text.grapheme_indices(true)
+        .map(|(start, _)| start)
+        .chain(std::iter::once(text.len()))
+        .take_while(|start| *start <= index)
+        .last()
+        .unwrap_or(0)
 }
 
-pub struct PaddingBuilder<'a> {
-    pub padding: (f64, f64, f64, f64),
-    pub child: Rc<RefCell<dyn Widget<'a> + 'a>>,
+type ChangeCallback<'a> = Box<dyn Fn(&str) + 'a>;
+type CharFilter<'a> = Box<dyn Fn(char) -> bool + 'a>;
+
+// which characters a TextInput takes, on top of its own filter
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum InputType {
+    #[default]
+    Text,
+    Numeric,
+    // digits, a decimal point and a sign
+    Decimal,
 }
 
-impl<'a> Padding<'a> {
-    pub fn new(child: Rc<RefCell<dyn Widget<'a> + 'a>>) -> PaddingBuilder<'a> {
-        PaddingBuilder {
-            child,
-            padding: (0.0, 0.0, 0.0, 0.0),
+impl InputType {
+    pub fn allows(self, ch: char) -> bool {
+        match self {
+            InputType::Text => true,
+            InputType::Numeric => ch.is_ascii_digit(),
+            InputType::Decimal => ch.is_ascii_digit() || ch == '.' || ch == '-',
         }
     }
 }
 
-impl<'a> PaddingBuilder<'a> {
-    pub fn all(mut self, pad: f64) -> Self {
-        self.padding = (pad, pad, pad, pad);
-        self
+// seconds the caret stays on, and then off
+const CARET_BLINK: f64 = 0.5;
+const CARET_WIDTH: f64 = 1.0;
+
+// a single line of editable text. clicking it focuses it, clicking anywhere else takes the
+// focus away again, and only a focused input takes typed characters and shortcuts
+pub struct TextInput<'a> {
+    id: usize,
+    highlight_id: usize,
+    caret_id: usize,
+    size: u32,
+    font: &'a str,
+    color: Color,
+    selection_color: Color,
+    hinting: Hinting,
+    buffer: RefCell<String>,
+    // byte offsets into the buffer, always on grapheme boundaries
+    cursor: Cell<usize>,
+    anchor: Cell<Option<usize>>,
+    focused: Cell<bool>,
+    dragging: Cell<bool>,
+    // the last tick's time, and when the caret last moved. it stays on while typing
+    time: Cell<f64>,
+    blink_start: Cell<f64>,
+    // drawn once per grapheme instead of the text, e.g. for passwords
+    mask: Option<char>,
+    // in graphemes
+    max_length: Option<usize>,
+    input_type: InputType,
+    filter: Option<CharFilter<'a>>,
+    clipboard: Option<Box<dyn Clipboard + 'a>>,
+    change_callback: Option<ChangeCallback<'a>>,
+}
+
+pub struct TextInputBuilder<'a> {
+    text: String,
+    size: u32,
+    font: &'a str,
+    color: Color,
+    selection_color: Color,
+    hinting: Hinting,
+    focused: bool,
+    mask: Option<char>,
+    max_length: Option<usize>,
+    input_type: InputType,
+    filter: Option<CharFilter<'a>>,
+    clipboard: Option<Box<dyn Clipboard + 'a>>,
+    change_callback: Option<ChangeCallback<'a>>,
+    id: Option<usize>,
+}
+
+impl<'a> TextInput<'a> {
+    pub fn new(size: u32, font: &'a str) -> TextInputBuilder<'a> {
+        TextInputBuilder {
+            text: String::new(),
+            size,
+            font,
+            color: [0.0, 0.0, 0.0, 1.0],
+            selection_color: [0.2, 0.4, 1.0, 0.4],
+            hinting: Hinting::Normal,
+            focused: false,
+            mask: None,
+            max_length: None,
+            input_type: InputType::Text,
+            filter: None,
+            clipboard: None,
+            change_callback: None,
+            id: None,
+        }
     }
 
-    pub fn symmetrical(mut self, horizontal: f64, vertical: f64) -> Self {
-        self.padding = (horizontal, vertical, horizontal, vertical);
-        self
+    pub fn value(&self) -> String {
+        self.buffer.borrow().clone()
     }
 
-    pub fn each(mut self, left: f64, top: f64, right: f64, bottom: f64) -> Self {
-        self.padding = (left, top, right, bottom);
-        self
+    // what is drawn, the value itself unless it is obscured
+    pub fn display_text(&self) -> String {
+        let buffer = self.buffer.borrow();
+        match self.mask {
+            Some(mask) => buffer.graphemes(true).map(|_| mask).collect(),
+            None => buffer.clone(),
+        }
     }
 
-    pub fn build(self) -> Rc<RefCell<Padding<'a>>> {
-        Rc::new(RefCell::new(Padding {
-            child: self.child,
-            padding: self.padding,
-            id: COUNTER.fetch_add(1, std::sync::atomic::Ordering::SeqCst),
-        }))
+    pub fn cursor(&self) -> usize {
+        self.cursor.get()
     }
-}
 
-pub struct Row<'a> {
-    pub children: Vec<Rc<RefCell<dyn Widget<'a> + 'a>>>,
-    pub flex: Vec<usize>,
-    pub id: usize,
-}
+    pub fn set_cursor(&self, index: usize) {
+        self.cursor
+            .set(grapheme_floor(&self.buffer.borrow(), index));
+        self.anchor.set(None);
+        self.blink_start.set(self.time.get());
+    }
 
-pub struct RowBuilder<'a> {
-    pub children: Vec<Rc<RefCell<dyn Widget<'a> + 'a>>>,
-    pub flex: Vec<usize>,
-}
+    pub fn focused(&self) -> bool {
+        self.focused.get()
+    }
 
-impl<'a> Row<'a> {
-    pub fn new() -> RowBuilder<'a> {
-        RowBuilder {
-            children: Vec::new(),
-            flex: Vec::new(),
+    pub fn set_focused(&self, focused: bool) {
+        self.focused.set(focused);
+    }
+
+    // byte range of the selected text, None while nothing is selected
+    pub fn selection(&self) -> Option<(usize, usize)> {
+        let anchor = self.anchor.get()?;
+        let cursor = self.cursor.get();
+        if anchor == cursor {
+            None
+        } else {
+            Some((anchor.min(cursor), anchor.max(cursor)))
         }
     }
-}
 
-impl<'a> RowBuilder<'a> {
-    pub fn add(mut self, child: Rc<RefCell<dyn Widget<'a> + 'a>>) -> Self {
-        self.children.push(child);
-        self.flex.push(1);
-        self
+    pub fn select(&self, start: usize, end: usize) {
+        let buffer = self.buffer.borrow();
+        self.anchor.set(Some(grapheme_floor(&buffer, start)));
+        self.cursor.set(grapheme_floor(&buffer, end));
     }
 
-    pub fn add_flex(mut self, child: Rc<RefCell<dyn Widget<'a> + 'a>>, flex: usize) -> Self {
-        self.children.push(child);
-        self.flex.push(flex);
-        self
+    pub fn selected_text(&self) -> String {
+        match self.selection() {
+            Some((start, end)) => String::from(&self.buffer.borrow()[start..end]),
+            None => String::new(),
+        }
     }
 
-    pub fn build(self) -> Rc<RefCell<Row<'a>>> {
-        Rc::new(RefCell::new(Row {
-            children: self.children,
-            flex: self.flex,
-            id: COUNTER.fetch_add(1, std::sync::atomic::Ordering::SeqCst),
-        }))
+    fn allows(&self, ch: char) -> bool {
+        self.input_type.allows(ch) && self.filter.as_ref().is_none_or(|filter| filter(ch))
     }
-}
 
-pub struct Column<'a> {
-    pub children: Vec<Rc<RefCell<dyn Widget<'a> + 'a>>>,
-    pub flex: Vec<usize>,
-    pub id: usize,
-}
+    // replaces the selection, or inserts at the cursor if there is none. characters the
+    // input doesn't allow are dropped, and so is whatever goes past the max length.
+    // returns whether the buffer changed
+    pub fn insert(&self, text: &str) -> bool {
+        let (start, end) = self.selection().unwrap_or((self.cursor(), self.cursor()));
+        let mut allowed: String = text.chars().filter(|ch| self.allows(*ch)).collect();
+        if let Some(max_length) = self.max_length {
+            let buffer = self.buffer.borrow();
+            let kept =
+                buffer[..start].graphemes(true).count() + buffer[end..].graphemes(true).count();
+            let room = max_length.saturating_sub(kept);
+            if let Some((cut, _)) = allowed.grapheme_indices(true).nth(room) {
+                allowed.truncate(cut);
+            }
+        }
+        // a rejected character leaves the selection alone instead of deleting it
+        if allowed.is_empty() && (start == end || !text.is_empty()) {
+            return false;
+        }
+        self.buffer.borrow_mut().replace_range(start..end, &allowed);
+        self.set_cursor(start + allowed.len());
+        self.changed();
+        true
+    }
 
-pub struct ColumnBuilder<'a> {
-    pub children: Vec<Rc<RefCell<dyn Widget<'a> + 'a>>>,
-    pub flex: Vec<usize>,
+    // returns whether anything was removed
+    fn delete(&self, forward: bool) -> bool {
+        let (start, end) = match self.selection() {
+            Some(selection) => selection,
+            None => {
+                let buffer = self.buffer.borrow();
+                let cursor = self.cursor();
+                let mut graphemes = buffer.grapheme_indices(true);
+                let neighbour = if forward {
+                    graphemes
+                        .find(|(start, _)| *start >= cursor)
+                        .map(|(start, grapheme)| (start, start + grapheme.len()))
+                } else {
+                    graphemes
+                        .rev()
+                        .find(|(start, _)| *start < cursor)
+                        .map(|(start, grapheme)| (start, start + grapheme.len()))
+                };
+                match neighbour {
+                    Some(range) => range,
+                    None => return false,
+                }
+            }
+        };
+        self.buffer.borrow_mut().replace_range(start..end, "");
+        self.set_cursor(start);
+        self.changed();
+        true
+    }
+
+    // obscured text never reaches the clipboard
+    pub fn copy(&self) {
+        if self.mask.is_some() {
+            return;
+        }
+        if let (Some(clipboard), Some(_)) = (&self.clipboard, self.selection()) {
+            clipboard.set(&self.selected_text());
+        }
+    }
+
+    // returns whether the buffer changed
+    pub fn cut(&self) -> bool {
+        if self.selection().is_none() || self.mask.is_some() {
+            return false;
+        }
+        self.copy();
+        self.delete(false)
+    }
+
+    // pasted text stays on one line
+    pub fn paste(&self) -> bool {
+        let text = match self
+            .clipboard
+            .as_ref()
+            .and_then(|clipboard| clipboard.get())
+        {
+            Some(text) => text.replace(['\r', '\n'], " "),
+            None => return false,
+        };
+        self.insert(&text)
+    }
+
+    // moves the caret a grapheme, or to either end. with extend the selection follows the
+    // caret, otherwise a selection collapses to the side the caret moves towards
+    fn move_cursor(&self, key: Key, extend: bool) {
+        let cursor = self.cursor();
+        let target = {
+            let buffer = self.buffer.borrow();
+            let mut ends = buffer
+                .grapheme_indices(true)
+                .map(|(start, grapheme)| (start, start + grapheme.len()));
+            match (key, self.selection()) {
+                (Key::Left, Some((start, _))) if !extend => start,
+                (Key::Right, Some((_, end))) if !extend => end,
+                (Key::Left, _) => ends
+                    .rev()
+                    .find(|(start, _)| *start < cursor)
+                    .map_or(0, |(start, _)| start),
+                (Key::Right, _) => ends
+                    .find(|(_, end)| *end > cursor)
+                    .map_or(buffer.len(), |(_, end)| end),
+                (Key::Home, _) => 0,
+                _ => buffer.len(),
+            }
+        };
+        let anchor = self.anchor.get().unwrap_or(cursor);
+        self.set_cursor(target);
+        if extend {
+            self.anchor.set(Some(anchor));
+        }
+    }
+
+    // caret positions of the drawn text, by byte offset into the buffer
+    fn offsets(&self) -> Vec<(usize, f64)> {
+        let offsets = grapheme_offsets(&self.display_text(), &self.style());
+        if self.mask.is_none() {
+            return offsets;
+        }
+        // the mask has one grapheme per grapheme of the buffer, so they pair up in order
+        let buffer = self.buffer.borrow();
+        let boundaries = std::iter::once(0).chain(
+            buffer
+                .grapheme_indices(true)
+                .map(|(start, grapheme)| start + grapheme.len()),
+        );
+        boundaries
+            .zip(offsets)
+            .map(|(boundary, (_, x))| (boundary, x))
+            .collect()
+    }
+
+    // x offset of the caret in front of the grapheme at index, from the font's advances
+    pub fn caret_x(&self, index: usize) -> f64 {
+        let offsets = self.offsets();
+        offsets
+            .iter()
+            .find(|(boundary, _)| *boundary >= index)
+            .or_else(|| offsets.last())
+            .map_or(0.0, |(_, x)| *x)
+    }
+
+    // snaps an x offset relative to the text to the closest grapheme boundary
+    pub fn index_at(&self, x: f64) -> usize {
+        let offsets = self.offsets();
+        offsets
+            .iter()
+            .min_by(|(_, a), (_, b)| (a - x).abs().partial_cmp(&(b - x).abs()).unwrap())
+            .map_or(0, |(index, _)| *index)
+    }
+
+    fn caret_visible(&self) -> bool {
+        let elapsed = self.time.get() - self.blink_start.get();
+        self.focused.get() && (elapsed / CARET_BLINK).floor() as i64 % 2 == 0
+    }
+
+    // the selection highlight and the caret, relative to the input's origin
+    fn decorations(&self, height: f64) -> (Vec<DrawCommand>, Vec<DrawCommand>) {
+        let mut highlight = Vec::new();
+        if let Some((start, end)) = self.selection() {
+            let (from, to) = (self.caret_x(start), self.caret_x(end));
+            highlight.push(DrawCommand::FillRect {
+                x: from,
+                y: 0.0,
+                width: to - from,
+                height,
+                color: self.selection_color,
+            });
+        }
+        let mut caret = Vec::new();
+        if self.caret_visible() {
+            caret.push(DrawCommand::FillRect {
+                x: self.caret_x(self.cursor()),
+                y: 0.0,
+                width: CARET_WIDTH,
+                height,
+                color: self.color,
+            });
+        }
+        (highlight, caret)
+    }
+
+    fn changed(&self) {
+        if let Some(callback) = &self.change_callback {
+            callback(&self.buffer.borrow());
+        }
+    }
+
+    fn style(&self) -> TextStyle<'a> {
+        TextStyle {
+            color: self.color,
+            size: self.size,
+            font: self.font,
+            hinting: self.hinting,
+            tab_stop: TabStop::Spaces(4),
+            direction: TextDirection::Ltr,
+            decoration: TextDecoration::None,
+            decoration_color: None,
+        }
+    }
+
+    // returns whether the key did anything
+    fn key(&self, key: Key, modifiers: Modifiers) -> Option<bool> {
+        Some(match (key, modifiers) {
+            (Key::Backspace, _) => self.delete(false),
+            (Key::Delete, _) => self.delete(true),
+            (Key::Left | Key::Right | Key::Home | Key::End, modifiers)
+                if !modifiers.ctrl && !modifiers.alt =>
+            {
+                self.move_cursor(key, modifiers.shift);
+                true
+            }
+            (Key::Char('a'), Modifiers::CTRL) => {
+                let len = self.buffer.borrow().len();
+                self.select(0, len);
+                true
+            }
+            (Key::Char('c'), Modifiers::CTRL) => {
+                self.copy();
+                false
+            }
+            (Key::Char('x'), Modifiers::CTRL) => self.cut(),
+            (Key::Char('v'), Modifiers::CTRL) => self.paste(),
+            _ => return None,
+        })
+    }
 }
 
-impl<'a> Column<'a> {
-    pub fn new() -> ColumnBuilder<'a> {
-        ColumnBuilder {
-            children: Vec::new(),
-            flex: Vec::new(),
+impl<'a> TextInputBuilder<'a> {
+    pub fn id(mut self, id: usize) -> Self {
+        self.id = Some(id);
+        self
+    }
+
+    pub fn key<K: Into<WidgetKey>>(self, key: K) -> Self {
+        let id = key.into().id();
+        self.id(id)
+    }
+
+    // what the input starts out with, the cursor goes to its end
+    pub fn text<S: Into<String>>(mut self, text: S) -> Self {
+        self.text = text.into();
+        self
+    }
+
+    pub fn color(mut self, color: Color) -> Self {
+        self.color = color;
+        self
+    }
+
+    pub fn selection_color(mut self, color: Color) -> Self {
+        self.selection_color = color;
+        self
+    }
+
+    pub fn hinting(mut self, hinting: Hinting) -> Self {
+        self.hinting = hinting;
+        self
+    }
+
+    pub fn focused(mut self, focused: bool) -> Self {
+        self.focused = focused;
+        self
+    }
+
+    // draws mask in place of every grapheme and turns off copy and cut, e.g. '\u{2022}'
+    // for passwords. the value and on_change still see the real text
+    pub fn obscure(mut self, mask: char) -> Self {
+        self.mask = Some(mask);
+        self
+    }
+
+    // the most graphemes the input takes, typing or pasting past it is cut off
+    pub fn max_length(mut self, max_length: usize) -> Self {
+        self.max_length = Some(max_length);
+        self
+    }
+
+    pub fn input_type(mut self, input_type: InputType) -> Self {
+        self.input_type = input_type;
+        self
+    }
+
+    // typed and pasted characters it returns false for are dropped, deleting always works
+    pub fn filter<F: Fn(char) -> bool + 'a>(mut self, filter: F) -> Self {
+        self.filter = Some(Box::new(filter));
+        self
+    }
+
+    // without a clipboard, copy, cut and paste do nothing
+    pub fn clipboard<C: Clipboard + 'a>(mut self, clipboard: C) -> Self {
+        self.clipboard = Some(Box::new(clipboard));
+        self
+    }
+
+    // called with the whole text after every edit
+    pub fn on_change<F: Fn(&str) + 'a>(mut self, on_change: F) -> Self {
+        self.change_callback = Some(Box::new(on_change));
+        self
+    }
+
+    pub fn build(self) -> Rc<RefCell<TextInput<'a>>> {
+        Rc::new(RefCell::new(TextInput {
+            id: self.id.unwrap_or_else(next_id),
+            highlight_id: next_id(),
+            caret_id: next_id(),
+            size: self.size,
+            font: self.font,
+            color: self.color,
+            selection_color: self.selection_color,
+            hinting: self.hinting,
+            cursor: Cell::new(self.text.len()),
+            buffer: RefCell::new(self.text),
+            anchor: Cell::new(None),
+            focused: Cell::new(self.focused),
+            dragging: Cell::new(false),
+            time: Cell::new(0.0),
+            blink_start: Cell::new(0.0),
+            mask: self.mask,
+            max_length: self.max_length,
+            input_type: self.input_type,
+            filter: self.filter,
+            clipboard: self.clipboard,
+            change_callback: self.change_callback,
+        }))
+    }
+}
+
+type Validator<'a> = Box<dyn Fn(&str) -> Result<(), String> + 'a>;
+
+const FIELD_ERROR_BORDER: f64 = 1.0;
+
+// a field that didn't pass its validator, by the id of its TextInput
+#[derive(Debug, Clone, PartialEq)]
+pub struct FieldError {
+    pub field: usize,
+    pub message: String,
+}
+
+struct FormField<'a> {
+    input: Rc<RefCell<TextInput<'a>>>,
+    validator: Validator<'a>,
+    // the border around an invalid field and the message below it
+    border_id: usize,
+    message_id: usize,
+}
+
+// validates the TextInputs registered with it, which can sit anywhere below it. Enter in
+// one of them submits, but only once every field passes. invalid fields are outlined and
+// get their message below them until the next validation
+pub struct Form<'a> {
+    child: Rc<RefCell<dyn Widget<'a> + 'a>>,
+    fields: Vec<FormField<'a>>,
+    errors: RefCell<Vec<FieldError>>,
+    error_color: Color,
+    submit_callback: Option<Box<dyn Fn() + 'a>>,
+    id: usize,
+}
+
+pub struct FormBuilder<'a> {
+    child: Rc<RefCell<dyn Widget<'a> + 'a>>,
+    fields: Vec<FormField<'a>>,
+    error_color: Color,
+    submit_callback: Option<Box<dyn Fn() + 'a>>,
+    id: Option<usize>,
+}
+
+impl<'a> Form<'a> {
+    pub fn new<W: IntoWidget<'a>>(child: W) -> FormBuilder<'a> {
+        FormBuilder {
+            child: child.into_widget(),
+            fields: Vec::new(),
+            error_color: [0.8, 0.1, 0.1, 1.0],
+            submit_callback: None,
+            id: None,
+        }
+    }
+
+    // runs every validator, in the order the fields were added
+    pub fn validate(&self) -> Result<(), Vec<FieldError>> {
+        let errors: Vec<FieldError> = self
+            .fields
+            .iter()
+            .filter_map(|field| {
+                let input = field.input.borrow();
+                (field.validator)(&input.value())
+                    .err()
+                    .map(|message| FieldError {
+                        field: input.get_id(),
+                        message,
+                    })
+            })
+            .collect();
+        *self.errors.borrow_mut() = errors.clone();
+        if errors.is_empty() {
+            Ok(())
+        } else {
+            Err(errors)
+        }
+    }
+
+    // the errors of the last validation
+    pub fn errors(&self) -> Vec<FieldError> {
+        self.errors.borrow().clone()
+    }
+
+    // returns whether the form was valid and on_submit ran
+    pub fn submit(&self) -> bool {
+        if self.validate().is_err() {
+            return false;
         }
+        if let Some(callback) = &self.submit_callback {
+            callback();
+        }
+        true
     }
 }
 
-impl<'a> ColumnBuilder<'a> {
-    pub fn add(mut self, child: Rc<RefCell<dyn Widget<'a> + 'a>>) -> Self {
-        self.children.push(child);
-        self.flex.push(1);
+impl<'a> FormBuilder<'a> {
+    pub fn id(mut self, id: usize) -> Self {
+        self.id = Some(id);
         self
     }
 
-    pub fn add_flex(mut self, child: Rc<RefCell<dyn Widget<'a> + 'a>>, flex: usize) -> Self {
-        self.children.push(child);
-        self.flex.push(flex);
+    pub fn key<K: Into<WidgetKey>>(self, key: K) -> Self {
+        let id = key.into().id();
+        self.id(id)
+    }
+
+    // the input also has to be part of the child, errors name it by its id
+    pub fn field<F: Fn(&str) -> Result<(), String> + 'a>(
+        mut self,
+        input: &Rc<RefCell<TextInput<'a>>>,
+        validator: F,
+    ) -> Self {
+        self.fields.push(FormField {
+            input: input.clone(),
+            validator: Box::new(validator),
+            border_id: next_id(),
+            message_id: next_id(),
+        });
         self
     }
 
-    pub fn build(self) -> Rc<RefCell<Column<'a>>> {
-        Rc::new(RefCell::new(Column {
-            children: self.children,
-            flex: self.flex,
-            id: COUNTER.fetch_add(1, std::sync::atomic::Ordering::SeqCst),
+    pub fn error_color(mut self, color: Color) -> Self {
+        self.error_color = color;
+        self
+    }
+
+    pub fn on_submit<F: Fn() + 'a>(mut self, on_submit: F) -> Self {
+        self.submit_callback = Some(Box::new(on_submit));
+        self
+    }
+
+    pub fn build(self) -> Rc<RefCell<Form<'a>>> {
+        Rc::new(RefCell::new(Form {
+            child: self.child,
+            fields: self.fields,
+            errors: RefCell::new(Vec::new()),
+            error_color: self.error_color,
+            submit_callback: self.submit_callback,
+            id: self.id.unwrap_or_else(next_id),
         }))
     }
 }
 
-pub struct Stack<'a> {
-    pub children: Vec<Rc<RefCell<dyn Widget<'a> + 'a>>>,
-    pub id: usize,
+pub struct RichText<'a> {
+    spans: Vec<TextSpan<'a>>,
+    id: usize,
+    hinting: Hinting,
 }
 
-pub struct StackBuilder<'a> {
-    pub children: Vec<Rc<RefCell<dyn Widget<'a> + 'a>>>,
+pub struct RichTextBuilder<'a> {
+    spans: Vec<TextSpan<'a>>,
+    hinting: Hinting,
+    id: Option<usize>,
 }
 
-impl<'a> Stack<'a> {
-    pub fn new() -> StackBuilder<'a> {
-        StackBuilder {
-            children: Vec::new(),
+impl<'a> RichText<'a> {
+    pub fn new() -> RichTextBuilder<'a> {
+        RichTextBuilder {
+            spans: Vec::new(),
+            hinting: Hinting::Normal,
+            id: None,
         }
     }
 }
 
-impl<'a> StackBuilder<'a> {
-    pub fn add(mut self, child: Rc<RefCell<dyn Widget<'a> + 'a>>) -> Self {
-        self.children.push(child);
+impl<'a> RichTextBuilder<'a> {
+    pub fn id(mut self, id: usize) -> Self {
+        self.id = Some(id);
         self
     }
 
-    pub fn build(self) -> Rc<RefCell<Stack<'a>>> {
-        Rc::new(RefCell::new(Stack {
-            children: self.children,
-            id: COUNTER.fetch_add(1, std::sync::atomic::Ordering::SeqCst),
+    pub fn key<K: Into<WidgetKey>>(self, key: K) -> Self {
+        let id = key.into().id();
+        self.id(id)
+    }
+
+    pub fn span(mut self, span: TextSpan<'a>) -> Self {
+        self.spans.push(span);
+        self
+    }
+
+    pub fn hinting(mut self, hinting: Hinting) -> Self {
+        self.hinting = hinting;
+        self
+    }
+
+    pub fn build(self) -> Rc<RefCell<RichText<'a>>> {
+        Rc::new(RefCell::new(RichText {
+            spans: self.spans,
+            hinting: self.hinting,
+            id: self.id.unwrap_or_else(next_id),
         }))
     }
+
+    pub fn build_stateful(self, state: &mut State<RichText<'a>>) -> Rc<RefCell<RichText<'a>>> {
+        let result = self.build();
+        state.bind(result.clone());
+        result
+    }
 }
 
-impl<'a> Widget<'a> for MouseGesture<'a> {
-    fn compute(
-        &self,
-        x: f64,
-        y: f64,
-        z: usize,
-        width: f64,
-        height: f64,
-        map: &mut HashMap<usize, ComputedWidget<'a>>,
-    ) {
-        self.background
-            .borrow()
-            .compute(x, y, z, width, height, map);
-        map.insert(
-            self.get_id(),
-            ComputedWidget {
-                x,
-                y,
-                z,
-                width,
-                height,
-                render: None,
-            },
-        );
+const DROPDOWN_PADDING: f64 = 8.0;
+
+// a box showing the selected option, clicking it opens the options as an overlay below it
+pub struct Dropdown<'a> {
+    options: Vec<&'a str>,
+    id: usize,
+    label_id: usize,
+    // background and label of every option row
+    row_ids: Vec<(usize, usize)>,
+    size: u32,
+    font: &'a str,
+    text_color: Color,
+    color: Color,
+    list_color: Color,
+    highlight_color: Color,
+    selected: Rc<RefCell<usize>>,
+    open: Cell<bool>,
+    select_callback: Option<Box<dyn Fn(usize) + 'a>>,
+}
+
+pub struct DropdownBuilder<'a> {
+    options: Vec<&'a str>,
+    size: u32,
+    font: &'a str,
+    text_color: Color,
+    color: Color,
+    list_color: Color,
+    highlight_color: Color,
+    selected: Rc<RefCell<usize>>,
+    select_callback: Option<Box<dyn Fn(usize) + 'a>>,
+    id: Option<usize>,
+}
+
+impl<'a> Dropdown<'a> {
+    pub fn new(size: u32, font: &'a str) -> DropdownBuilder<'a> {
+        DropdownBuilder {
+            options: Vec::new(),
+            size,
+            font,
+            text_color: [0.0, 0.0, 0.0, 1.0],
+            color: [0.9, 0.9, 0.9, 1.0],
+            list_color: [1.0, 1.0, 1.0, 1.0],
+            highlight_color: [0.8, 0.85, 1.0, 1.0],
+            selected: Rc::new(RefCell::new(0)),
+            select_callback: None,
+            id: None,
+        }
     }
 
-    fn dispatch(
-        &self,
-        event: Event,
-        prev_state_change: bool,
-        map: &HashMap<usize, ComputedWidget>,
-    ) -> (Option<Event>, bool) {
-        let computed: &ComputedWidget = map.get(&self.get_id()).unwrap();
-        match event {
-            Event::MouseDown { x, y, button } => {
-                if computed.in_hitbox(x, y, self.border_radius) {
-                    let state_change = if let Some(click) = &self.click_callback {
-                        click(button)
-                    } else {
-                        false
-                    };
-                    (None, prev_state_change | state_change)
-                } else {
-                    (Some(event), prev_state_change)
-                }
-            }
-            Event::MouseUp { x, y, button } => {
-                if computed.in_hitbox(x, y, self.border_radius) {
-                    let state_change = if let Some(release) = &self.release_callback {
-                        release(button)
-                    } else {
-                        false
-                    };
-                    (None, prev_state_change | state_change)
-                } else {
-                    (Some(event), prev_state_change)
-                }
-            }
-            Event::MouseMove {
-                prev_x,
-                prev_y,
-                x,
-                y,
-            } => {
-                if computed.in_hitbox(x, y, self.border_radius)
-                    && !computed.in_hitbox(prev_x, prev_y, self.border_radius)
-                {
-                    let state_change = if let Some(enter) = &self.enter_callback {
-                        enter()
-                    } else {
-                        false
-                    };
-                    (None, prev_state_change | state_change)
-                } else if !computed.in_hitbox(x, y, self.border_radius)
-                    && computed.in_hitbox(prev_x, prev_y, self.border_radius)
-                {
-                    let state_change = if let Some(leave) = &self.leave_callback {
-                        leave()
-                    } else {
-                        false
-                    };
-                    (None, prev_state_change | state_change)
-                } else {
-                    (Some(event), prev_state_change)
-                }
+    pub fn selected(&self) -> usize {
+        *self.selected.borrow()
+    }
+
+    pub fn is_open(&self) -> bool {
+        self.open.get()
+    }
+
+    pub fn set_open(&self, open: bool) {
+        self.open.set(open);
+    }
+
+    fn style(&self) -> TextStyle<'a> {
+        TextStyle {
+            color: self.text_color,
+            size: self.size,
+            font: self.font,
+            hinting: Hinting::Normal,
+            tab_stop: TabStop::Spaces(4),
+            direction: TextDirection::Ltr,
+            decoration: TextDecoration::None,
+            decoration_color: None,
+        }
+    }
+
+    // index of the option row under the point, rows are as tall as the box
+    fn row_at(&self, x: f64, y: f64, computed: &ComputedWidget) -> Option<usize> {
+        if x < computed.x || x >= computed.x + computed.width || computed.height <= 0.0 {
+            return None;
+        }
+        let row = ((y - computed.y) / computed.height).floor() - 1.0;
+        if row >= 0.0 && (row as usize) < self.options.len() {
+            Some(row as usize)
+        } else {
+            None
+        }
+    }
+}
+
+impl<'a> DropdownBuilder<'a> {
+    pub fn id(mut self, id: usize) -> Self {
+        self.id = Some(id);
+        self
+    }
+
+    pub fn key<K: Into<WidgetKey>>(self, key: K) -> Self {
+        let id = key.into().id();
+        self.id(id)
+    }
+
+    pub fn options(mut self, options: Vec<&'a str>) -> Self {
+        self.options = options;
+        self
+    }
+
+    pub fn selected(self, index: usize) -> Self {
+        *self.selected.borrow_mut() = index;
+        self
+    }
+
+    pub fn bind_selected(self, state: &mut State<usize>) -> Self {
+        state.bind(self.selected.clone());
+        self
+    }
+
+    pub fn text_color(mut self, color: Color) -> Self {
+        self.text_color = color;
+        self
+    }
+
+    pub fn color(mut self, color: Color) -> Self {
+        self.color = color;
+        self
+    }
+
+    pub fn list_color(mut self, color: Color) -> Self {
+        self.list_color = color;
+        self
+    }
+
+    pub fn highlight_color(mut self, color: Color) -> Self {
+        self.highlight_color = color;
+        self
+    }
+
+    pub fn on_select<F: Fn(usize) + 'a>(mut self, on_select: F) -> Self {
+        self.select_callback = Some(Box::new(on_select));
+        self
+    }
+
+    pub fn build(self) -> Rc<RefCell<Dropdown<'a>>> {
+        let row_ids = self
+            .options
+            .iter()
+            .map(|_| (next_id(), next_id()))
+            .collect();
+        Rc::new(RefCell::new(Dropdown {
+            id: self.id.unwrap_or_else(next_id),
+            label_id: next_id(),
+            row_ids,
+            options: self.options,
+            size: self.size,
+            font: self.font,
+            text_color: self.text_color,
+            color: self.color,
+            list_color: self.list_color,
+            highlight_color: self.highlight_color,
+            selected: self.selected,
+            open: Cell::new(false),
+            select_callback: self.select_callback,
+        }))
+    }
+
+    pub fn build_stateful(self, state: &mut State<Dropdown<'a>>) -> Rc<RefCell<Dropdown<'a>>> {
+        let result = self.build();
+        state.bind(result.clone());
+        result
+    }
+}
+
+pub struct Rectangle {
+    pub color: Color,
+    pub border_radius: BorderRadius,
+    pub background_image: Option<BackgroundImage>,
+    pub id: usize,
+}
+
+pub struct RectangleBuilder {
+    pub color: Color,
+    pub border_radius: BorderRadius,
+    pub background_image: Option<BackgroundImage>,
+    pub id: Option<usize>,
+}
+
+impl Rectangle {
+    pub fn new(color: Color) -> RectangleBuilder {
+        RectangleBuilder {
+            color,
+            border_radius: BorderRadius::default(),
+            background_image: None,
+            id: None,
+        }
+    }
+}
+
+impl RectangleBuilder {
+    pub fn id(mut self, id: usize) -> Self {
+        self.id = Some(id);
+        self
+    }
+
+    pub fn key<K: Into<WidgetKey>>(self, key: K) -> Self {
+        let id = key.into().id();
+        self.id(id)
+    }
+
+    pub fn border(mut self, border_radius: f64) -> Self {
+        self.border_radius = BorderRadius::uniform(border_radius);
+        self
+    }
+
+    pub fn border_each(
+        mut self,
+        top_left: f64,
+        top_right: f64,
+        bottom_left: f64,
+        bottom_right: f64,
+    ) -> Self {
+        self.border_radius = BorderRadius::each(top_left, top_right, bottom_left, bottom_right);
+        self
+    }
+
+    // drawn over the color, which stays visible where the image is transparent or doesn't reach
+    pub fn background_image(mut self, path: &str, fit: ImageFit) -> Self {
+        self.background_image = Some(BackgroundImage::new(path, fit));
+        self
+    }
+
+    pub fn build(self) -> Rc<RefCell<Rectangle>> {
+        Rc::new(RefCell::new(self.widget()))
+    }
+
+    // for trees that never change, skips the shared ownership build hands out
+    pub fn build_static<'a>(self) -> Box<dyn Widget<'a> + 'a> {
+        Box::new(self.widget())
+    }
+
+    fn widget(self) -> Rectangle {
+        Rectangle {
+            color: self.color,
+            id: self.id.unwrap_or_else(next_id),
+            border_radius: self.border_radius,
+            background_image: self.background_image,
+        }
+    }
+
+    pub fn build_stateful(self, state: &mut State<Rectangle>) -> Rc<RefCell<Rectangle>> {
+        let result = Rc::new(RefCell::new(self.widget()));
+        state.bind(result.clone());
+        result
+    }
+}
+
+pub struct Ellipse {
+    pub color: Color,
+    pub circle: bool,
+    pub id: usize,
+}
+
+pub struct EllipseBuilder {
+    pub color: Color,
+    pub circle: bool,
+    pub id: Option<usize>,
+}
+
+impl Ellipse {
+    pub fn new(color: Color) -> EllipseBuilder {
+        EllipseBuilder {
+            color,
+            circle: false,
+            id: None,
+        }
+    }
+
+    // the largest circle centered in the available box
+    pub fn circle(color: Color) -> EllipseBuilder {
+        EllipseBuilder {
+            color,
+            circle: true,
+            id: None,
+        }
+    }
+}
+
+impl EllipseBuilder {
+    pub fn id(mut self, id: usize) -> Self {
+        self.id = Some(id);
+        self
+    }
+
+    pub fn key<K: Into<WidgetKey>>(self, key: K) -> Self {
+        let id = key.into().id();
+        self.id(id)
+    }
+
+    pub fn build(self) -> Rc<RefCell<Ellipse>> {
+        Rc::new(RefCell::new(Ellipse {
+            color: self.color,
+            circle: self.circle,
+            id: self.id.unwrap_or_else(next_id),
+        }))
+    }
+
+    pub fn build_stateful(self, state: &mut State<Ellipse>) -> Rc<RefCell<Ellipse>> {
+        let result = self.build();
+        state.bind(result.clone());
+        result
+    }
+}
+
+pub struct Empty {
+    pub id: usize,
+}
+
+impl Empty {
+    pub fn new() -> Rc<Empty> {
+        Rc::new(Empty { id: next_id() })
+    }
+}
+
+struct CommandRecorder {
+    width: f64,
+    height: f64,
+    commands: Vec<DrawCommand>,
+}
+
+impl Painter for CommandRecorder {
+    fn size(&self) -> (f64, f64) {
+        (self.width, self.height)
+    }
+
+    fn fill_rect(&mut self, x: f64, y: f64, width: f64, height: f64, color: Color) {
+        self.commands.push(DrawCommand::FillRect {
+            x,
+            y,
+            width,
+            height,
+            color,
+        });
+    }
+
+    fn stroke_line(&mut self, from: (f64, f64), to: (f64, f64), width: f64, color: Color) {
+        self.commands.push(DrawCommand::StrokeLine {
+            from,
+            to,
+            width,
+            color,
+        });
+    }
+
+    fn fill_circle(&mut self, x: f64, y: f64, radius: f64, color: Color) {
+        self.commands.push(DrawCommand::FillCircle {
+            x,
+            y,
+            radius,
+            color,
+        });
+    }
+}
+
+type PaintFn<'a> = Box<dyn Fn(&mut dyn Painter) + 'a>;
+
+pub struct Canvas<'a> {
+    paint: PaintFn<'a>,
+    id: usize,
+}
+
+pub struct CanvasBuilder<'a> {
+    paint: PaintFn<'a>,
+    id: Option<usize>,
+}
+
+impl<'a> Canvas<'a> {
+    pub fn new<F: Fn(&mut dyn Painter) + 'a>(paint: F) -> CanvasBuilder<'a> {
+        CanvasBuilder {
+            paint: Box::new(paint),
+            id: None,
+        }
+    }
+}
+
+impl<'a> CanvasBuilder<'a> {
+    pub fn id(mut self, id: usize) -> Self {
+        self.id = Some(id);
+        self
+    }
+
+    pub fn key<K: Into<WidgetKey>>(self, key: K) -> Self {
+        let id = key.into().id();
+        self.id(id)
+    }
+
+    pub fn build(self) -> Rc<RefCell<Canvas<'a>>> {
+        Rc::new(RefCell::new(Canvas {
+            paint: self.paint,
+            id: self.id.unwrap_or_else(next_id),
+        }))
+    }
+}
+
+pub struct NinePatch {
+    pub source: Rc<str>,
+    pub insets: (f64, f64, f64, f64),
+    pub id: usize,
+}
+
+pub struct NinePatchBuilder {
+    pub source: Rc<str>,
+    pub insets: (f64, f64, f64, f64),
+    pub id: Option<usize>,
+}
+
+impl NinePatch {
+    pub fn new() -> NinePatchBuilder {
+        NinePatchBuilder {
+            source: Rc::from(""),
+            insets: (0.0, 0.0, 0.0, 0.0),
+            id: None,
+        }
+    }
+}
+
+impl NinePatchBuilder {
+    pub fn id(mut self, id: usize) -> Self {
+        self.id = Some(id);
+        self
+    }
+
+    pub fn key<K: Into<WidgetKey>>(self, key: K) -> Self {
+        let id = key.into().id();
+        self.id(id)
+    }
+
+    pub fn source(mut self, path: &str) -> Self {
+        self.source = Rc::from(path);
+        self
+    }
+
+    // in image pixels, these parts of the edges keep their size
+    pub fn insets(mut self, left: f64, top: f64, right: f64, bottom: f64) -> Self {
+        self.insets = (left, top, right, bottom);
+        self
+    }
+
+    pub fn build(self) -> Rc<RefCell<NinePatch>> {
+        Rc::new(RefCell::new(NinePatch {
+            source: self.source,
+            insets: self.insets,
+            id: self.id.unwrap_or_else(next_id),
+        }))
+    }
+}
+
+pub struct Polyline {
+    points: Vec<(f64, f64)>,
+    color: Color,
+    width: f64,
+    id: usize,
+}
+
+pub struct PolylineBuilder {
+    points: Vec<(f64, f64)>,
+    color: Color,
+    width: f64,
+    id: Option<usize>,
+}
+
+impl Polyline {
+    pub fn new(points: Vec<(f64, f64)>, color: Color) -> PolylineBuilder {
+        PolylineBuilder {
+            points,
+            color,
+            width: 1.0,
+            id: None,
+        }
+    }
+}
+
+impl PolylineBuilder {
+    pub fn id(mut self, id: usize) -> Self {
+        self.id = Some(id);
+        self
+    }
+
+    pub fn key<K: Into<WidgetKey>>(self, key: K) -> Self {
+        let id = key.into().id();
+        self.id(id)
+    }
+
+    pub fn width(mut self, width: f64) -> Self {
+        self.width = width;
+        self
+    }
+
+    pub fn build(self) -> Rc<RefCell<Polyline>> {
+        Rc::new(RefCell::new(Polyline {
+            points: self.points,
+            color: self.color,
+            width: self.width,
+            id: self.id.unwrap_or_else(next_id),
+        }))
+    }
+}
+
+const LONG_PRESS_SLOP: f64 = 8.0;
+
+struct LongPress {
+    threshold: f64,
+    origin: Option<(f64, f64)>,
+    start: Option<f64>,
+}
+
+impl LongPress {
+    fn new(threshold: f64) -> Self {
+        LongPress {
+            threshold,
+            origin: None,
+            start: None,
+        }
+    }
+
+    fn press(&mut self, x: f64, y: f64) {
+        self.origin = Some((x, y));
+        self.start = None;
+    }
+
+    fn moved(&mut self, x: f64, y: f64) {
+        if let Some((origin_x, origin_y)) = self.origin {
+            if (x - origin_x).hypot(y - origin_y) > LONG_PRESS_SLOP {
+                self.cancel();
+            }
+        }
+    }
+
+    fn cancel(&mut self) {
+        self.origin = None;
+        self.start = None;
+    }
+
+    // mouse events carry no timestamp, so the press starts at the first tick after it
+    fn tick(&mut self, time: f64) -> bool {
+        if self.origin.is_none() {
+            return false;
+        }
+        let start = *self.start.get_or_insert(time);
+        if time - start >= self.threshold {
+            self.cancel();
+            true
+        } else {
+            false
+        }
+    }
+}
+
+pub struct MouseGesture<'a> {
+    pub background: Rc<RefCell<dyn Widget<'a> + 'a>>,
+    pub click_callback: Option<Box<dyn Fn(u8) -> bool + 'a>>,
+    pub release_callback: Option<Box<dyn Fn(u8) -> bool + 'a>>,
+    pub tap_callback: Option<Box<dyn Fn(u8) -> bool + 'a>>,
+    pub long_press_callback: Option<Box<dyn Fn() -> bool + 'a>>,
+    pub enter_callback: Option<Box<dyn Fn() -> bool + 'a>>,
+    pub leave_callback: Option<Box<dyn Fn() -> bool + 'a>>,
+    border_radius: BorderRadius,
+    hovered: Cell<bool>,
+    pressed: Cell<bool>,
+    long_press: RefCell<LongPress>,
+    pub id: usize,
+}
+
+pub struct MouseGestureBuilder<'a> {
+    background: Rc<RefCell<dyn Widget<'a> + 'a>>,
+    click_callback: Option<Box<dyn Fn(u8) -> bool + 'a>>,
+    release_callback: Option<Box<dyn Fn(u8) -> bool + 'a>>,
+    tap_callback: Option<Box<dyn Fn(u8) -> bool + 'a>>,
+    long_press_callback: Option<Box<dyn Fn() -> bool + 'a>>,
+    long_press_threshold: f64,
+    enter_callback: Option<Box<dyn Fn() -> bool + 'a>>,
+    leave_callback: Option<Box<dyn Fn() -> bool + 'a>>,
+    border_radius: BorderRadius,
+    id: Option<usize>,
+}
+
+impl<'a> MouseGesture<'a> {
+    pub fn new<W: IntoWidget<'a>>(background: W) -> MouseGestureBuilder<'a> {
+        MouseGestureBuilder {
+            background: background.into_widget(),
+            border_radius: BorderRadius::default(),
+            click_callback: None,
+            release_callback: None,
+            tap_callback: None,
+            long_press_callback: None,
+            long_press_threshold: 0.5,
+            enter_callback: None,
+            leave_callback: None,
+            id: None,
+        }
+    }
+}
+
+impl<'a> MouseGestureBuilder<'a> {
+    pub fn id(mut self, id: usize) -> Self {
+        self.id = Some(id);
+        self
+    }
+
+    pub fn key<K: Into<WidgetKey>>(self, key: K) -> Self {
+        let id = key.into().id();
+        self.id(id)
+    }
+
+    pub fn on_click<F: Fn(u8) -> bool + 'a>(mut self, on_click: F) -> Self {
+        self.click_callback = Some(Box::new(on_click));
+        self
+    }
+
+    pub fn on_release<F: Fn(u8) -> bool + 'a>(mut self, on_release: F) -> Self {
+        self.release_callback = Some(Box::new(on_release));
+        self
+    }
+
+    pub fn on_tap<F: Fn(u8) -> bool + 'a>(mut self, on_tap: F) -> Self {
+        self.tap_callback = Some(Box::new(on_tap));
+        self
+    }
+
+    pub fn on_long_press<F: Fn() -> bool + 'a>(mut self, on_long_press: F) -> Self {
+        self.long_press_callback = Some(Box::new(on_long_press));
+        self
+    }
+
+    pub fn long_press_threshold(mut self, seconds: f64) -> Self {
+        self.long_press_threshold = seconds;
+        self
+    }
+    pub fn on_enter<F: Fn() -> bool + 'a>(mut self, on_enter: F) -> Self {
+        self.enter_callback = Some(Box::new(on_enter));
+        self
+    }
+
+    pub fn on_leave<F: Fn() -> bool + 'a>(mut self, on_leave: F) -> Self {
+        self.leave_callback = Some(Box::new(on_leave));
+        self
+    }
+
+    pub fn border(mut self, border_radius: f64) -> Self {
+        self.border_radius = BorderRadius::uniform(border_radius);
+        self
+    }
+
+    pub fn border_each(
+        mut self,
+        top_left: f64,
+        top_right: f64,
+        bottom_left: f64,
+        bottom_right: f64,
+    ) -> Self {
+        self.border_radius = BorderRadius::each(top_left, top_right, bottom_left, bottom_right);
+        self
+    }
+
+    pub fn build(self) -> Rc<RefCell<MouseGesture<'a>>> {
+        Rc::new(RefCell::new(MouseGesture {
+            background: self.background,
+            id: self.id.unwrap_or_else(next_id),
+            border_radius: self.border_radius,
+            click_callback: self.click_callback,
+            release_callback: self.release_callback,
+            tap_callback: self.tap_callback,
+            long_press_callback: self.long_press_callback,
+            enter_callback: self.enter_callback,
+            leave_callback: self.leave_callback,
+            hovered: Cell::new(false),
+            pressed: Cell::new(false),
+            long_press: RefCell::new(LongPress::new(self.long_press_threshold)),
+        }))
+    }
+}
+
+impl<'a> MouseGesture<'a> {
+    pub fn is_hovered(&self) -> bool {
+        self.hovered.get()
+    }
+
+    pub fn is_pressed(&self) -> bool {
+        self.pressed.get()
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum PadValue {
+    Px(f64),
+    Pct(f64),
+}
+
+impl PadValue {
+    fn resolve(self, extent: f64) -> f64 {
+        match self {
+            PadValue::Px(px) => px,
+            PadValue::Pct(pct) => extent * pct / 100.0,
+        }
+    }
+}
+
+pub struct Padding<'a> {
+    pub padding: (PadValue, PadValue, PadValue, PadValue),
+    pub child: Rc<RefCell<dyn Widget<'a> + 'a>>,
+    pub id: usize,
+}
+
+pub struct PaddingBuilder<'a> {
+    pub padding: (PadValue, PadValue, PadValue, PadValue),
+    pub child: Rc<RefCell<dyn Widget<'a> + 'a>>,
+    pub id: Option<usize>,
+}
+
+impl<'a> Padding<'a> {
+    pub fn new<W: IntoWidget<'a>>(child: W) -> PaddingBuilder<'a> {
+        PaddingBuilder {
+            child: child.into_widget(),
+            padding: (
+                PadValue::Px(0.0),
+                PadValue::Px(0.0),
+                PadValue::Px(0.0),
+                PadValue::Px(0.0),
+            ),
+            id: None,
+        }
+    }
+}
+
+impl<'a> PaddingBuilder<'a> {
+    pub fn id(mut self, id: usize) -> Self {
+        self.id = Some(id);
+        self
+    }
+
+    pub fn key<K: Into<WidgetKey>>(self, key: K) -> Self {
+        let id = key.into().id();
+        self.id(id)
+    }
+
+    pub fn all(self, pad: f64) -> Self {
+        self.each(pad, pad, pad, pad)
+    }
+
+    pub fn symmetrical(self, horizontal: f64, vertical: f64) -> Self {
+        self.each(horizontal, vertical, horizontal, vertical)
+    }
+
+    pub fn each(self, left: f64, top: f64, right: f64, bottom: f64) -> Self {
+        self.each_value(
+            PadValue::Px(left),
+            PadValue::Px(top),
+            PadValue::Px(right),
+            PadValue::Px(bottom),
+        )
+    }
+
+    // the single edge setters leave the other edges as they were, so they can be chained
+    pub fn left(mut self, pad: f64) -> Self {
+        self.padding.0 = PadValue::Px(pad);
+        self
+    }
+
+    pub fn top(mut self, pad: f64) -> Self {
+        self.padding.1 = PadValue::Px(pad);
+        self
+    }
+
+    pub fn right(mut self, pad: f64) -> Self {
+        self.padding.2 = PadValue::Px(pad);
+        self
+    }
+
+    pub fn bottom(mut self, pad: f64) -> Self {
+        self.padding.3 = PadValue::Px(pad);
+        self
+    }
+
+    pub fn horizontal(self, pad: f64) -> Self {
+        self.left(pad).right(pad)
+    }
+
+    pub fn vertical(self, pad: f64) -> Self {
+        self.top(pad).bottom(pad)
+    }
+
+    pub fn percent(self, pct: f64) -> Self {
+        self.percent_each(pct, pct, pct, pct)
+    }
+
+    pub fn percent_each(self, left: f64, top: f64, right: f64, bottom: f64) -> Self {
+        self.each_value(
+            PadValue::Pct(left),
+            PadValue::Pct(top),
+            PadValue::Pct(right),
+            PadValue::Pct(bottom),
+        )
+    }
+
+    pub fn each_value(
+        mut self,
+        left: PadValue,
+        top: PadValue,
+        right: PadValue,
+        bottom: PadValue,
+    ) -> Self {
+        self.padding = (left, top, right, bottom);
+        self
+    }
+
+    pub fn build(self) -> Rc<RefCell<Padding<'a>>> {
+        Rc::new(RefCell::new(self.widget()))
+    }
+
+    // for trees that never change, skips the shared ownership build hands out
+    pub fn build_static(self) -> Box<dyn Widget<'a> + 'a> {
+        Box::new(self.widget())
+    }
+
+    fn widget(self) -> Padding<'a> {
+        Padding {
+            child: self.child,
+            padding: self.padding,
+            id: self.id.unwrap_or_else(next_id),
+        }
+    }
+}
+
+pub struct ConstrainedBox<'a> {
+    pub child: Rc<RefCell<dyn Widget<'a> + 'a>>,
+    pub min_width: f64,
+    pub max_width: f64,
+    pub min_height: f64,
+    pub max_height: f64,
+    pub id: usize,
+}
+
+pub struct ConstrainedBoxBuilder<'a> {
+    pub child: Rc<RefCell<dyn Widget<'a> + 'a>>,
+    pub min_width: f64,
+    pub max_width: f64,
+    pub min_height: f64,
+    pub max_height: f64,
+    pub id: Option<usize>,
+}
+
+impl<'a> ConstrainedBox<'a> {
+    pub fn new<W: IntoWidget<'a>>(child: W) -> ConstrainedBoxBuilder<'a> {
+        ConstrainedBoxBuilder {
+            child: child.into_widget(),
+            min_width: 0.0,
+            max_width: f64::INFINITY,
+            min_height: 0.0,
+            max_height: f64::INFINITY,
+            id: None,
+        }
+    }
+}
+
+impl<'a> ConstrainedBoxBuilder<'a> {
+    pub fn id(mut self, id: usize) -> Self {
+        self.id = Some(id);
+        self
+    }
+
+    pub fn key<K: Into<WidgetKey>>(self, key: K) -> Self {
+        let id = key.into().id();
+        self.id(id)
+    }
+
+    pub fn min_width(mut self, min_width: f64) -> Self {
+        self.min_width = min_width;
+        self
+    }
+
+    pub fn max_width(mut self, max_width: f64) -> Self {
+        self.max_width = max_width;
+        self
+    }
+
+    pub fn min_height(mut self, min_height: f64) -> Self {
+        self.min_height = min_height;
+        self
+    }
+
+    pub fn max_height(mut self, max_height: f64) -> Self {
+        self.max_height = max_height;
+        self
+    }
+
+    pub fn build(self) -> Rc<RefCell<ConstrainedBox<'a>>> {
+        Rc::new(RefCell::new(self.widget()))
+    }
+
+    // for trees that never change, skips the shared ownership build hands out
+    pub fn build_static(self) -> Box<dyn Widget<'a> + 'a> {
+        Box::new(self.widget())
+    }
+
+    fn widget(self) -> ConstrainedBox<'a> {
+        ConstrainedBox {
+            child: self.child,
+            min_width: self.min_width,
+            max_width: self.max_width,
+            min_height: self.min_height,
+            max_height: self.max_height,
+            id: self.id.unwrap_or_else(next_id),
+        }
+    }
+}
+
+// sizes its child to a fraction of the slot, e.g. 0.5 for half the width, and aligns it
+// in what is left. factors and alignment go from 0 to 1, a missing factor keeps the full size
+pub struct FractionallySizedBox<'a> {
+    pub child: Rc<RefCell<dyn Widget<'a> + 'a>>,
+    pub width_factor: Option<f64>,
+    pub height_factor: Option<f64>,
+    // 0 is left or top, 1 is right or bottom
+    pub align: (f64, f64),
+    pub id: usize,
+}
+
+pub struct FractionallySizedBoxBuilder<'a> {
+    pub child: Rc<RefCell<dyn Widget<'a> + 'a>>,
+    pub width_factor: Option<f64>,
+    pub height_factor: Option<f64>,
+    pub align: (f64, f64),
+    pub id: Option<usize>,
+}
+
+impl<'a> FractionallySizedBox<'a> {
+    pub fn new<W: IntoWidget<'a>>(child: W) -> FractionallySizedBoxBuilder<'a> {
+        FractionallySizedBoxBuilder {
+            child: child.into_widget(),
+            width_factor: None,
+            height_factor: None,
+            align: (0.5, 0.5),
+            id: None,
+        }
+    }
+
+    fn size(&self, width: f64, height: f64) -> (f64, f64) {
+        (
+            width * self.width_factor.unwrap_or(1.0),
+            height * self.height_factor.unwrap_or(1.0),
+        )
+    }
+}
+
+impl<'a> FractionallySizedBoxBuilder<'a> {
+    pub fn id(mut self, id: usize) -> Self {
+        self.id = Some(id);
+        self
+    }
+
+    pub fn key<K: Into<WidgetKey>>(self, key: K) -> Self {
+        let id = key.into().id();
+        self.id(id)
+    }
+
+    pub fn width_factor(mut self, factor: f64) -> Self {
+        self.width_factor = Some(factor.clamp(0.0, 1.0));
+        self
+    }
+
+    pub fn height_factor(mut self, factor: f64) -> Self {
+        self.height_factor = Some(factor.clamp(0.0, 1.0));
+        self
+    }
+
+    pub fn align(mut self, x: f64, y: f64) -> Self {
+        self.align = (x.clamp(0.0, 1.0), y.clamp(0.0, 1.0));
+        self
+    }
+
+    pub fn build(self) -> Rc<RefCell<FractionallySizedBox<'a>>> {
+        Rc::new(RefCell::new(FractionallySizedBox {
+            child: self.child,
+            width_factor: self.width_factor,
+            height_factor: self.height_factor,
+            align: self.align,
+            id: self.id.unwrap_or_else(next_id),
+        }))
+    }
+}
+
+pub struct Expanded<'a> {
+    pub child: Rc<RefCell<dyn Widget<'a> + 'a>>,
+    pub flex: usize,
+    pub id: usize,
+}
+
+pub struct ExpandedBuilder<'a> {
+    pub child: Rc<RefCell<dyn Widget<'a> + 'a>>,
+    pub flex: usize,
+    pub id: Option<usize>,
+}
+
+impl<'a> Expanded<'a> {
+    pub fn new<W: IntoWidget<'a>>(child: W) -> ExpandedBuilder<'a> {
+        ExpandedBuilder {
+            child: child.into_widget(),
+            flex: 1,
+            id: None,
+        }
+    }
+}
+
+impl<'a> ExpandedBuilder<'a> {
+    pub fn id(mut self, id: usize) -> Self {
+        self.id = Some(id);
+        self
+    }
+
+    pub fn key<K: Into<WidgetKey>>(self, key: K) -> Self {
+        let id = key.into().id();
+        self.id(id)
+    }
+
+    pub fn flex(mut self, flex: usize) -> Self {
+        self.flex = flex;
+        self
+    }
+
+    pub fn build(self) -> Rc<RefCell<Expanded<'a>>> {
+        Rc::new(RefCell::new(Expanded {
+            child: self.child,
+            flex: self.flex,
+            id: self.id.unwrap_or_else(next_id),
+        }))
+    }
+}
+
+// places its child at an offset inside a Stack instead of filling it. without a size
+// the child gets the rest of the stack. anywhere else it passes its slot on unchanged
+pub struct Positioned<'a> {
+    pub child: Rc<RefCell<dyn Widget<'a> + 'a>>,
+    pub offset: (f64, f64),
+    pub size: Option<(f64, f64)>,
+    pub id: usize,
+}
+
+pub struct PositionedBuilder<'a> {
+    pub child: Rc<RefCell<dyn Widget<'a> + 'a>>,
+    pub offset: (f64, f64),
+    pub size: Option<(f64, f64)>,
+    pub id: Option<usize>,
+}
+
+impl<'a> Positioned<'a> {
+    pub fn new<W: IntoWidget<'a>>(child: W) -> PositionedBuilder<'a> {
+        PositionedBuilder {
+            child: child.into_widget(),
+            offset: (0.0, 0.0),
+            size: None,
+            id: None,
+        }
+    }
+}
+
+impl<'a> PositionedBuilder<'a> {
+    pub fn id(mut self, id: usize) -> Self {
+        self.id = Some(id);
+        self
+    }
+
+    pub fn key<K: Into<WidgetKey>>(self, key: K) -> Self {
+        let id = key.into().id();
+        self.id(id)
+    }
+
+    pub fn offset(mut self, x: f64, y: f64) -> Self {
+        self.offset = (x, y);
+        self
+    }
+
+    pub fn size(mut self, width: f64, height: f64) -> Self {
+        self.size = Some((width, height));
+        self
+    }
+
+    pub fn build(self) -> Rc<RefCell<Positioned<'a>>> {
+        Rc::new(RefCell::new(Positioned {
+            child: self.child,
+            offset: self.offset,
+            size: self.size,
+            id: self.id.unwrap_or_else(next_id),
+        }))
+    }
+}
+
+// flex 0 children get their intrinsic size, the others share what is left
+fn flex_extents<'a>(
+    children: &[Child<'a>],
+    flex: &[usize],
+    available: f64,
+    measure: impl Fn(&dyn Widget<'a>) -> f64,
+) -> Vec<f64> {
+    let flex: Vec<usize> = children
+        .iter()
+        .zip(flex)
+        .map(|(c, f)| c.borrow().flex().unwrap_or(*f))
+        .collect();
+    // fixed children get their measured size, but never more than the ones before them left
+    let mut remaining = available.max(0.0);
+    let fixed: Vec<f64> = children
+        .iter()
+        .zip(&flex)
+        .map(|(c, f)| {
+            if *f != 0 {
+                return 0.0;
+            }
+            let extent = measure(&*c.borrow()).clamp(0.0, remaining);
+            remaining -= extent;
+            extent
+        })
+        .collect();
+    let total_flex = flex.iter().sum::<usize>();
+    // no flexible children means nothing to divide by
+    let each = if total_flex > 0 {
+        remaining / total_flex as f64
+    } else {
+        0.0
+    };
+    flex.iter()
+        .zip(fixed)
+        .map(|(f, fixed)| if *f == 0 { fixed } else { each * *f as f64 })
+        .collect()
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum Spacing {
+    #[default]
+    Start,
+    SpaceBetween,
+    SpaceEvenly,
+}
+
+// returns the offset before the first child and the gap between children
+fn spacing_gaps(spacing: Spacing, count: usize, free: f64) -> (f64, f64) {
+    let free = free.max(0.0);
+    match spacing {
+        Spacing::Start => (0.0, 0.0),
+        Spacing::SpaceBetween if count > 1 => (0.0, free / (count - 1) as f64),
+        Spacing::SpaceBetween => (0.0, 0.0),
+        Spacing::SpaceEvenly => {
+            let gap = free / (count + 1) as f64;
+            (gap, gap)
+        }
+    }
+}
+
+// direction that Row and Padding lay out in, set by the closest Directionality
+fn layout_direction() -> TextDirection {
+    LAYOUT_DIRECTION.with(|direction| direction.get())
+}
+
+// mirrors the Rows and Paddings below it for right-to-left locales, ltr by default
+pub struct Directionality<'a> {
+    pub child: Rc<RefCell<dyn Widget<'a> + 'a>>,
+    pub direction: TextDirection,
+    pub id: usize,
+}
+
+pub struct DirectionalityBuilder<'a> {
+    pub child: Rc<RefCell<dyn Widget<'a> + 'a>>,
+    pub direction: TextDirection,
+    pub id: Option<usize>,
+}
+
+impl<'a> Directionality<'a> {
+    pub fn new<W: IntoWidget<'a>>(child: W, direction: TextDirection) -> DirectionalityBuilder<'a> {
+        DirectionalityBuilder {
+            child: child.into_widget(),
+            direction,
+            id: None,
+        }
+    }
+
+    fn scope<R, F: FnOnce() -> R>(&self, f: F) -> R {
+        let previous = LAYOUT_DIRECTION.with(|direction| direction.replace(self.direction));
+        let result = f();
+        LAYOUT_DIRECTION.with(|direction| direction.set(previous));
+        result
+    }
+}
+
+impl<'a> DirectionalityBuilder<'a> {
+    pub fn id(mut self, id: usize) -> Self {
+        self.id = Some(id);
+        self
+    }
+
+    pub fn key<K: Into<WidgetKey>>(self, key: K) -> Self {
+        let id = key.into().id();
+        self.id(id)
+    }
+
+    pub fn build(self) -> Rc<RefCell<Directionality<'a>>> {
+        Rc::new(RefCell::new(Directionality {
+            child: self.child,
+            direction: self.direction,
+            id: self.id.unwrap_or_else(next_id),
+        }))
+    }
+}
+
+pub struct Clip<'a> {
+    pub child: Rc<RefCell<dyn Widget<'a> + 'a>>,
+    pub id: usize,
+}
+
+pub struct ClipBuilder<'a> {
+    pub child: Rc<RefCell<dyn Widget<'a> + 'a>>,
+    pub id: Option<usize>,
+}
+
+impl<'a> Clip<'a> {
+    pub fn new<W: IntoWidget<'a>>(child: W) -> ClipBuilder<'a> {
+        ClipBuilder {
+            child: child.into_widget(),
+            id: None,
+        }
+    }
+}
+
+impl<'a> ClipBuilder<'a> {
+    pub fn id(mut self, id: usize) -> Self {
+        self.id = Some(id);
+        self
+    }
+
+    pub fn key<K: Into<WidgetKey>>(self, key: K) -> Self {
+        let id = key.into().id();
+        self.id(id)
+    }
+
+    pub fn build(self) -> Rc<RefCell<Clip<'a>>> {
+        Rc::new(RefCell::new(self.widget()))
+    }
+
+    // for trees that never change, skips the shared ownership build hands out
+    pub fn build_static(self) -> Box<dyn Widget<'a> + 'a> {
+        Box::new(self.widget())
+    }
+
+    fn widget(self) -> Clip<'a> {
+        Clip {
+            child: self.child,
+            id: self.id.unwrap_or_else(next_id),
+        }
+    }
+}
+
+// draws its child but lets pointer events through to whatever is behind it, for
+// decorations laid over other widgets in a Stack. other events still reach the child
+pub struct IgnorePointer<'a> {
+    pub child: Rc<RefCell<dyn Widget<'a> + 'a>>,
+    pub ignoring: bool,
+    pub id: usize,
+}
+
+pub struct IgnorePointerBuilder<'a> {
+    pub child: Rc<RefCell<dyn Widget<'a> + 'a>>,
+    pub ignoring: bool,
+    pub id: Option<usize>,
+}
+
+impl<'a> IgnorePointer<'a> {
+    pub fn new<W: IntoWidget<'a>>(child: W) -> IgnorePointerBuilder<'a> {
+        IgnorePointerBuilder {
+            child: child.into_widget(),
+            ignoring: true,
+            id: None,
+        }
+    }
+
+    pub fn set_ignoring(&mut self, ignoring: bool) {
+        self.ignoring = ignoring;
+    }
+}
+
+impl<'a> IgnorePointerBuilder<'a> {
+    pub fn id(mut self, id: usize) -> Self {
+        self.id = Some(id);
+        self
+    }
+
+    pub fn key<K: Into<WidgetKey>>(self, key: K) -> Self {
+        let id = key.into().id();
+        self.id(id)
+    }
+
+    pub fn ignoring(mut self, ignoring: bool) -> Self {
+        self.ignoring = ignoring;
+        self
+    }
+
+    pub fn build(self) -> Rc<RefCell<IgnorePointer<'a>>> {
+        Rc::new(RefCell::new(IgnorePointer {
+            child: self.child,
+            ignoring: self.ignoring,
+            id: self.id.unwrap_or_else(next_id),
+        }))
+    }
+
+    pub fn build_stateful(
+        self,
+        state: &mut State<IgnorePointer<'a>>,
+    ) -> Rc<RefCell<IgnorePointer<'a>>> {
+        let result = self.build();
+        state.bind(result.clone());
+        result
+    }
+}
+
+type EventHandler<'a> = Box<dyn Fn(Event) -> EventFlow + 'a>;
+
+// lets a container act on events before its child sees them (capture) or after the child
+// let them through (bubble). pointer events only reach the handlers inside the bounds,
+// and a consumed event lays the tree out again
+pub struct EventListener<'a> {
+    child: Rc<RefCell<dyn Widget<'a> + 'a>>,
+    capture: Option<EventHandler<'a>>,
+    bubble: Option<EventHandler<'a>>,
+    bounds: Cell<(f64, f64, f64, f64)>,
+    id: usize,
+}
+
+pub struct EventListenerBuilder<'a> {
+    child: Rc<RefCell<dyn Widget<'a> + 'a>>,
+    capture: Option<EventHandler<'a>>,
+    bubble: Option<EventHandler<'a>>,
+    id: Option<usize>,
+}
+
+impl<'a> EventListener<'a> {
+    pub fn new<W: IntoWidget<'a>>(child: W) -> EventListenerBuilder<'a> {
+        EventListenerBuilder {
+            child: child.into_widget(),
+            capture: None,
+            bubble: None,
+            id: None,
+        }
+    }
+}
+
+impl<'a> EventListenerBuilder<'a> {
+    pub fn id(mut self, id: usize) -> Self {
+        self.id = Some(id);
+        self
+    }
+
+    pub fn key<K: Into<WidgetKey>>(self, key: K) -> Self {
+        let id = key.into().id();
+        self.id(id)
+    }
+
+    pub fn capture<F: Fn(Event) -> EventFlow + 'a>(mut self, handler: F) -> Self {
+        self.capture = Some(Box::new(handler));
+        self
+    }
+
+    pub fn bubble<F: Fn(Event) -> EventFlow + 'a>(mut self, handler: F) -> Self {
+        self.bubble = Some(Box::new(handler));
+        self
+    }
+
+    pub fn build(self) -> Rc<RefCell<EventListener<'a>>> {
+        Rc::new(RefCell::new(EventListener {
+            child: self.child,
+            capture: self.capture,
+            bubble: self.bubble,
+            bounds: Cell::new((0.0, 0.0, 0.0, 0.0)),
+            id: self.id.unwrap_or_else(next_id),
+        }))
+    }
+}
+
+pub struct Visibility<'a> {
+    pub child: Rc<RefCell<dyn Widget<'a> + 'a>>,
+    pub visible: bool,
+    pub maintain_space: bool,
+    pub id: usize,
+}
+
+pub struct VisibilityBuilder<'a> {
+    pub child: Rc<RefCell<dyn Widget<'a> + 'a>>,
+    pub visible: bool,
+    pub maintain_space: bool,
+    pub id: Option<usize>,
+}
+
+impl<'a> Visibility<'a> {
+    pub fn new<W: IntoWidget<'a>>(child: W) -> VisibilityBuilder<'a> {
+        VisibilityBuilder {
+            child: child.into_widget(),
+            visible: true,
+            maintain_space: true,
+            id: None,
+        }
+    }
+
+    pub fn set_visible(&mut self, visible: bool) {
+        self.visible = visible;
+    }
+}
+
+impl<'a> VisibilityBuilder<'a> {
+    pub fn id(mut self, id: usize) -> Self {
+        self.id = Some(id);
+        self
+    }
+
+    pub fn key<K: Into<WidgetKey>>(self, key: K) -> Self {
+        let id = key.into().id();
+        self.id(id)
+    }
+
+    pub fn visible(mut self, visible: bool) -> Self {
+        self.visible = visible;
+        self
+    }
+
+    pub fn maintain_space(mut self, maintain_space: bool) -> Self {
+        self.maintain_space = maintain_space;
+        self
+    }
+
+    pub fn build(self) -> Rc<RefCell<Visibility<'a>>> {
+        Rc::new(RefCell::new(Visibility {
+            child: self.child,
+            visible: self.visible,
+            maintain_space: self.maintain_space,
+            id: self.id.unwrap_or_else(next_id),
+        }))
+    }
+
+    pub fn build_stateful(self, state: &mut State<Visibility<'a>>) -> Rc<RefCell<Visibility<'a>>> {
+        let result = self.build();
+        state.bind(result.clone());
+        result
+    }
+}
+
+type ReactiveBuild<'a, T> = Box<dyn Fn(&T) -> Rc<RefCell<dyn Widget<'a> + 'a>> + 'a>;
+
+// rebuilds its child from the state whenever the state is set or updated. give the widgets
+// inside keys if something remembers them by id, unkeyed widgets get new ids on every rebuild
+pub struct Reactive<'a, T> {
+    state: State<T>,
+    build: ReactiveBuild<'a, T>,
+    child: RefCell<Rc<RefCell<dyn Widget<'a> + 'a>>>,
+    dirty: Rc<Cell<bool>>,
+    // keys resolve through the allocator that was current when the first child was built
+    allocator: Option<IdAllocator>,
+    id: usize,
+}
+
+pub struct ReactiveBuilder<'a, T> {
+    state: State<T>,
+    build: ReactiveBuild<'a, T>,
+    id: Option<usize>,
+}
+
+impl<'a, T: 'a> Reactive<'a, T> {
+    pub fn new<W: IntoWidget<'a>, F: Fn(&T) -> W + 'a>(
+        state: State<T>,
+        build: F,
+    ) -> ReactiveBuilder<'a, T> {
+        ReactiveBuilder {
+            state,
+            build: Box::new(move |value| build(value).into_widget()),
+            id: None,
+        }
+    }
+
+    fn rebuild_if_dirty(&self) -> bool {
+        if !self.dirty.replace(false) {
+            return false;
+        }
+        let value = self.state.borrow();
+        let child = match &self.allocator {
+            Some(allocator) => allocator.scope(|| (self.build)(&value)),
+            None => (self.build)(&value),
+        };
+        *self.child.borrow_mut() = child;
+        true
+    }
+
+    fn child(&self) -> Rc<RefCell<dyn Widget<'a> + 'a>> {
+        self.child.borrow().clone()
+    }
+}
+
+impl<'a, T: 'a> ReactiveBuilder<'a, T> {
+    pub fn id(mut self, id: usize) -> Self {
+        self.id = Some(id);
+        self
+    }
+
+    pub fn key<K: Into<WidgetKey>>(self, key: K) -> Self {
+        let id = key.into().id();
+        self.id(id)
+    }
+
+    pub fn build(self) -> Rc<RefCell<Reactive<'a, T>>> {
+        let child = (self.build)(&self.state.borrow());
+        let dirty = Rc::new(Cell::new(false));
+        let weak = Rc::downgrade(&dirty);
+        self.state.subscribe(move || {
+            if let Some(dirty) = weak.upgrade() {
+                dirty.set(true);
+            }
+        });
+        Rc::new(RefCell::new(Reactive {
+            state: self.state,
+            build: self.build,
+            child: RefCell::new(child),
+            dirty,
+            allocator: IdAllocator::current(),
+            id: self.id.unwrap_or_else(next_id),
+        }))
+    }
+}
+
+// z added to everything inside an Overlay, so it draws above the regular tree
+pub const OVERLAY_Z: usize = 1 << 12;
+
+// lets a subtree escape the clips of its ancestors and draw on top of them,
+// for popups that should not be cut off by a Clip or ListView
+pub struct Overlay<'a> {
+    pub child: Rc<RefCell<dyn Widget<'a> + 'a>>,
+    pub offset: (f64, f64),
+    pub size: Option<(f64, f64)>,
+    pub id: usize,
+}
+
+pub struct OverlayBuilder<'a> {
+    pub child: Rc<RefCell<dyn Widget<'a> + 'a>>,
+    pub offset: (f64, f64),
+    pub size: Option<(f64, f64)>,
+    pub id: Option<usize>,
+}
+
+impl<'a> Overlay<'a> {
+    pub fn new<W: IntoWidget<'a>>(child: W) -> OverlayBuilder<'a> {
+        OverlayBuilder {
+            child: child.into_widget(),
+            offset: (0.0, 0.0),
+            size: None,
+            id: None,
+        }
+    }
+}
+
+impl<'a> OverlayBuilder<'a> {
+    pub fn id(mut self, id: usize) -> Self {
+        self.id = Some(id);
+        self
+    }
+
+    pub fn key<K: Into<WidgetKey>>(self, key: K) -> Self {
+        let id = key.into().id();
+        self.id(id)
+    }
+
+    // relative to the slot the overlay is laid out in
+    pub fn offset(mut self, x: f64, y: f64) -> Self {
+        self.offset = (x, y);
+        self
+    }
+
+    // defaults to the size of the slot
+    pub fn size(mut self, width: f64, height: f64) -> Self {
+        self.size = Some((width, height));
+        self
+    }
+
+    pub fn build(self) -> Rc<RefCell<Overlay<'a>>> {
+        Rc::new(RefCell::new(Overlay {
+            child: self.child,
+            offset: self.offset,
+            size: self.size,
+            id: self.id.unwrap_or_else(next_id),
+        }))
+    }
+}
+
+type DialogSlot<'a> = Rc<RefCell<Option<Rc<RefCell<dyn Widget<'a> + 'a>>>>>;
+
+// opens and closes the dialog of a DialogHost, can be cloned into callbacks
+#[derive(Clone, Default)]
+pub struct DialogHandle<'a> {
+    dialog: DialogSlot<'a>,
+}
+
+impl<'a> DialogHandle<'a> {
+    pub fn new() -> Self {
+        DialogHandle {
+            dialog: Rc::new(RefCell::new(None)),
+        }
+    }
+
+    // replaces the dialog that is currently open, if any
+    pub fn show_dialog<W: IntoWidget<'a>>(&self, content: W) {
+        *self.dialog.borrow_mut() = Some(content.into_widget());
+    }
+
+    pub fn dismiss(&self) {
+        *self.dialog.borrow_mut() = None;
+    }
+
+    pub fn is_open(&self) -> bool {
+        self.dialog.borrow().is_some()
+    }
+}
+
+// lays its child out normally and shows a modal dialog above it while one is open.
+// the dialog is centered at its measured size over a barrier dimming the whole host,
+// and nothing behind the barrier receives input until the dialog is dismissed
+pub struct DialogHost<'a> {
+    pub child: Rc<RefCell<dyn Widget<'a> + 'a>>,
+    pub handle: DialogHandle<'a>,
+    pub barrier_color: Color,
+    pub barrier_dismissible: bool,
+    pub id: usize,
+    barrier_id: usize,
+    content_bounds: Cell<(f64, f64, f64, f64)>,
+}
+
+pub struct DialogHostBuilder<'a> {
+    pub child: Rc<RefCell<dyn Widget<'a> + 'a>>,
+    pub handle: DialogHandle<'a>,
+    pub barrier_color: Color,
+    pub barrier_dismissible: bool,
+    pub id: Option<usize>,
+}
+
+impl<'a> DialogHost<'a> {
+    pub fn new<W: IntoWidget<'a>>(child: W, handle: &DialogHandle<'a>) -> DialogHostBuilder<'a> {
+        DialogHostBuilder {
+            child: child.into_widget(),
+            handle: handle.clone(),
+            barrier_color: [0.0, 0.0, 0.0, 0.5],
+            barrier_dismissible: false,
+            id: None,
+        }
+    }
+}
+
+impl<'a> DialogHostBuilder<'a> {
+    pub fn id(mut self, id: usize) -> Self {
+        self.id = Some(id);
+        self
+    }
+
+    pub fn key<K: Into<WidgetKey>>(self, key: K) -> Self {
+        let id = key.into().id();
+        self.id(id)
+    }
+
+    pub fn barrier_color(mut self, color: Color) -> Self {
+        self.barrier_color = color;
+        self
+    }
+
+    // clicking the barrier outside the dialog dismisses it
+    pub fn barrier_dismissible(mut self, dismissible: bool) -> Self {
+        self.barrier_dismissible = dismissible;
+        self
+    }
+
+    pub fn build(self) -> Rc<RefCell<DialogHost<'a>>> {
+        Rc::new(RefCell::new(DialogHost {
+            child: self.child,
+            handle: self.handle,
+            barrier_color: self.barrier_color,
+            barrier_dismissible: self.barrier_dismissible,
+            id: self.id.unwrap_or_else(next_id),
+            barrier_id: next_id(),
+            content_bounds: Cell::new((0.0, 0.0, 0.0, 0.0)),
+        }))
+    }
+}
+
+const TOAST_FADE: f64 = 0.3;
+const TOAST_PADDING: f64 = 12.0;
+const TOAST_MARGIN: f64 = 24.0;
+
+#[derive(Default)]
+struct ToastState<'a> {
+    pending: VecDeque<(&'a str, f64)>,
+    // message, duration and the tick time it was first shown at
+    showing: Option<(&'a str, f64, f64)>,
+    time: f64,
+}
+
+// toasts waiting to be shown one after another, can be cloned into callbacks
+#[derive(Clone, Default)]
+pub struct ToastQueue<'a> {
+    state: Rc<RefCell<ToastState<'a>>>,
+}
+
+impl<'a> ToastQueue<'a> {
+    pub fn new() -> Self {
+        ToastQueue::default()
+    }
+
+    // the toast stays fully visible for duration seconds, then fades out
+    pub fn show_toast(&self, message: &'a str, duration: f64) {
+        self.state
+            .borrow_mut()
+            .pending
+            .push_back((message, duration));
+    }
+
+    pub fn current(&self) -> Option<&'a str> {
+        self.state.borrow().showing.map(|(message, _, _)| message)
+    }
+
+    pub fn opacity(&self) -> f32 {
+        let state = self.state.borrow();
+        match state.showing {
+            Some((_, duration, start)) => {
+                let fading = state.time - start - duration;
+                (1.0 - fading / TOAST_FADE).clamp(0.0, 1.0) as f32
+            }
+            None => 0.0,
+        }
+    }
+
+    // advances to the given tick time, the next toast starts once the previous one faded out.
+    // returns whether the toasts need to be laid out again
+    pub fn tick(&self, time: f64) -> bool {
+        let mut state = self.state.borrow_mut();
+        state.time = time;
+        if let Some((_, duration, start)) = state.showing {
+            if time - start < duration + TOAST_FADE {
+                return true;
+            }
+            state.showing = None;
+        }
+        match state.pending.pop_front() {
+            Some((message, duration)) => {
+                state.showing = Some((message, duration, time));
+                true
+            }
+            None => false,
+        }
+    }
+}
+
+// shows the toasts of a queue centered at the bottom, above its child and any dialogs
+pub struct ToastHost<'a> {
+    pub child: Rc<RefCell<dyn Widget<'a> + 'a>>,
+    pub queue: ToastQueue<'a>,
+    pub size: u32,
+    pub font: &'a str,
+    pub color: Color,
+    pub text_color: Color,
+    pub id: usize,
+    background_id: usize,
+    text_id: usize,
+}
+
+pub struct ToastHostBuilder<'a> {
+    pub child: Rc<RefCell<dyn Widget<'a> + 'a>>,
+    pub queue: ToastQueue<'a>,
+    pub size: u32,
+    pub font: &'a str,
+    pub color: Color,
+    pub text_color: Color,
+    pub id: Option<usize>,
+}
+
+impl<'a> ToastHost<'a> {
+    pub fn new<W: IntoWidget<'a>>(
+        child: W,
+        queue: &ToastQueue<'a>,
+        size: u32,
+        font: &'a str,
+    ) -> ToastHostBuilder<'a> {
+        ToastHostBuilder {
+            child: child.into_widget(),
+            queue: queue.clone(),
+            size,
+            font,
+            color: [0.2, 0.2, 0.2, 0.9],
+            text_color: [1.0, 1.0, 1.0, 1.0],
+            id: None,
+        }
+    }
+
+    fn style(&self) -> TextStyle<'a> {
+        TextStyle {
+            color: self.text_color,
+            size: self.size,
+            font: self.font,
+            hinting: Hinting::Normal,
+            tab_stop: TabStop::Spaces(4),
+            direction: TextDirection::Ltr,
+            decoration: TextDecoration::None,
+            decoration_color: None,
+        }
+    }
+}
+
+impl<'a> ToastHostBuilder<'a> {
+    pub fn id(mut self, id: usize) -> Self {
+        self.id = Some(id);
+        self
+    }
+
+    pub fn key<K: Into<WidgetKey>>(self, key: K) -> Self {
+        let id = key.into().id();
+        self.id(id)
+    }
+
+    pub fn color(mut self, color: Color) -> Self {
+        self.color = color;
+        self
+    }
+
+    pub fn text_color(mut self, color: Color) -> Self {
+        self.text_color = color;
+        self
+    }
+
+    pub fn build(self) -> Rc<RefCell<ToastHost<'a>>> {
+        Rc::new(RefCell::new(ToastHost {
+            child: self.child,
+            queue: self.queue,
+            size: self.size,
+            font: self.font,
+            color: self.color,
+            text_color: self.text_color,
+            id: self.id.unwrap_or_else(next_id),
+            background_id: next_id(),
+            text_id: next_id(),
+        }))
+    }
+}
+
+type Accelerator<'a> = (Key, Modifiers, Box<dyn Fn() -> bool + 'a>);
+
+// shortcuts that fire regardless of focus, meant to wrap the root of a tree
+pub struct Accelerators<'a> {
+    pub child: Rc<RefCell<dyn Widget<'a> + 'a>>,
+    pub bindings: Vec<Accelerator<'a>>,
+    pub id: usize,
+}
+
+pub struct AcceleratorsBuilder<'a> {
+    pub child: Rc<RefCell<dyn Widget<'a> + 'a>>,
+    pub bindings: Vec<Accelerator<'a>>,
+    pub id: Option<usize>,
+}
+
+impl<'a> Accelerators<'a> {
+    pub fn new<W: IntoWidget<'a>>(child: W) -> AcceleratorsBuilder<'a> {
+        AcceleratorsBuilder {
+            child: child.into_widget(),
+            bindings: Vec::new(),
+            id: None,
+        }
+    }
+}
+
+impl<'a> AcceleratorsBuilder<'a> {
+    pub fn id(mut self, id: usize) -> Self {
+        self.id = Some(id);
+        self
+    }
+
+    pub fn key<K: Into<WidgetKey>>(self, key: K) -> Self {
+        let id = key.into().id();
+        self.id(id)
+    }
+
+    // modifiers have to match exactly, so ctrl+s doesn't also fire on ctrl+shift+s
+    pub fn bind<F: Fn() -> bool + 'a>(
+        mut self,
+        key: Key,
+        modifiers: Modifiers,
+        callback: F,
+    ) -> Self {
+        self.bindings.push((key, modifiers, Box::new(callback)));
+        self
+    }
+
+    pub fn build(self) -> Rc<RefCell<Accelerators<'a>>> {
+        Rc::new(RefCell::new(Accelerators {
+            child: self.child,
+            bindings: self.bindings,
+            id: self.id.unwrap_or_else(next_id),
+        }))
+    }
+}
+
+pub struct IndexedStack<'a> {
+    pub children: Vec<Rc<RefCell<dyn Widget<'a> + 'a>>>,
+    pub index: Rc<RefCell<usize>>,
+    pub compute_all: bool,
+    pub id: usize,
+}
+
+pub struct IndexedStackBuilder<'a> {
+    pub children: Vec<Rc<RefCell<dyn Widget<'a> + 'a>>>,
+    pub index: Rc<RefCell<usize>>,
+    pub compute_all: bool,
+    pub id: Option<usize>,
+}
+
+impl<'a> IndexedStack<'a> {
+    pub fn new() -> IndexedStackBuilder<'a> {
+        IndexedStackBuilder {
+            children: Vec::new(),
+            index: Rc::new(RefCell::new(0)),
+            compute_all: false,
+            id: None,
+        }
+    }
+
+    pub fn index(&self) -> usize {
+        *self.index.borrow()
+    }
+
+    pub fn set_index(&self, index: usize) {
+        *self.index.borrow_mut() = index;
+    }
+
+    fn active(&self) -> Option<&Rc<RefCell<dyn Widget<'a> + 'a>>> {
+        self.children.get(self.index())
+    }
+}
+
+impl<'a> IndexedStackBuilder<'a> {
+    pub fn id(mut self, id: usize) -> Self {
+        self.id = Some(id);
+        self
+    }
+
+    pub fn key<K: Into<WidgetKey>>(self, key: K) -> Self {
+        let id = key.into().id();
+        self.id(id)
+    }
+
+    // chained like the other container builders, not an arithmetic add
+    #[allow(clippy::should_implement_trait)]
+    pub fn add<W: IntoWidget<'a>>(mut self, child: W) -> Self {
+        self.children.push(child.into_widget());
+        self
+    }
+
+    pub fn index(self, index: usize) -> Self {
+        *self.index.borrow_mut() = index;
+        self
+    }
+
+    // lays out the hidden children too, so switching doesn't change the size
+    pub fn compute_all(mut self, compute_all: bool) -> Self {
+        self.compute_all = compute_all;
+        self
+    }
+
+    pub fn bind_index(self, state: &mut State<usize>) -> Self {
+        state.bind(self.index.clone());
+        self
+    }
+
+    pub fn build(self) -> Rc<RefCell<IndexedStack<'a>>> {
+        Rc::new(RefCell::new(IndexedStack {
+            children: self.children,
+            index: self.index,
+            compute_all: self.compute_all,
+            id: self.id.unwrap_or_else(next_id),
+        }))
+    }
+}
+
+// items just outside the viewport that are still computed
+const LIST_VIEW_BUFFER: usize = 1;
+
+type ItemBuilder<'a> = Box<dyn Fn(usize) -> Rc<RefCell<dyn Widget<'a> + 'a>> + 'a>;
+
+pub struct ListView<'a> {
+    item_count: usize,
+    item_height: f64,
+    builder: ItemBuilder<'a>,
+    items: RefCell<HashMap<usize, Rc<RefCell<dyn Widget<'a> + 'a>>>>,
+    allocator: Option<IdAllocator>,
+    visible: Cell<(usize, usize)>,
+    scroll_offset: Cell<f64>,
+    id: usize,
+}
+
+pub struct ListViewBuilder<'a> {
+    item_count: usize,
+    item_height: f64,
+    builder: ItemBuilder<'a>,
+    id: Option<usize>,
+}
+
+impl<'a> ListView<'a> {
+    pub fn new<F: Fn(usize) -> Rc<RefCell<dyn Widget<'a> + 'a>> + 'a>(
+        item_count: usize,
+        builder: F,
+    ) -> ListViewBuilder<'a> {
+        ListViewBuilder {
+            item_count,
+            item_height: 32.0,
+            builder: Box::new(builder),
+            id: None,
+        }
+    }
+
+    pub fn scroll_offset(&self) -> f64 {
+        self.scroll_offset.get()
+    }
+
+    pub fn set_scroll_offset(&self, offset: f64) {
+        self.scroll_offset.set(offset.max(0.0));
+    }
+
+    fn item(&self, index: usize) -> Rc<RefCell<dyn Widget<'a> + 'a>> {
+        self.items
+            .borrow_mut()
+            .entry(index)
+            .or_insert_with(|| match &self.allocator {
+                // items are built lazily, but belong to the tree the list was built in
+                Some(allocator) => allocator.scope(|| (self.builder)(index)),
+                None => (self.builder)(index),
+            })
+            .clone()
+    }
+}
+
+impl<'a> ListViewBuilder<'a> {
+    pub fn id(mut self, id: usize) -> Self {
+        self.id = Some(id);
+        self
+    }
+
+    pub fn key<K: Into<WidgetKey>>(self, key: K) -> Self {
+        let id = key.into().id();
+        self.id(id)
+    }
+
+    pub fn item_height(mut self, item_height: f64) -> Self {
+        self.item_height = item_height;
+        self
+    }
+
+    pub fn build(self) -> Rc<RefCell<ListView<'a>>> {
+        Rc::new(RefCell::new(ListView {
+            item_count: self.item_count,
+            item_height: self.item_height,
+            builder: self.builder,
+            items: RefCell::new(HashMap::new()),
+            allocator: IdAllocator::current(),
+            visible: Cell::new((0, 0)),
+            scroll_offset: Cell::new(0.0),
+            id: self.id.unwrap_or_else(next_id),
+        }))
+    }
+}
+
+const SCROLLBAR_WIDTH: f64 = 8.0;
+// thumbs don't shrink below this, so very long content stays draggable
+const MIN_THUMB_HEIGHT: f64 = 16.0;
+
+// share of a flick's velocity left after coasting for a second
+const SCROLL_FRICTION: f64 = 0.02;
+// pixels per second below which a flick comes to rest
+const MIN_FLING_VELOCITY: f64 = 50.0;
+// frames of scrolling in a row before letting go flings, so a single wheel notch doesn't
+const FLING_FRAMES: usize = 2;
+// how far a flick can carry the content past an end, and the share of that overshoot
+// left after springing back for a second
+const OVERSCROLL: f64 = 60.0;
+const OVERSCROLL_SPRING: f64 = 0.0001;
+
+// a flicked scroll coasting to a stop, stepped by tick events
+#[derive(Debug, Clone, Copy, PartialEq, Default)]
+pub struct Momentum {
+    pub offset: f64,
+    // pixels per second
+    pub velocity: f64,
+}
+
+impl Momentum {
+    // advances by dt seconds over content that scrolls up to max_offset. past either end
+    // the flick dies off quickly and the content springs back. returns whether it still moves
+    pub fn step(&mut self, dt: f64, max_offset: f64) -> bool {
+        self.offset += self.velocity * dt;
+        self.velocity *= SCROLL_FRICTION.powf(dt);
+        let bound = self.offset.clamp(0.0, max_offset.max(0.0));
+        if self.offset != bound {
+            let spring = OVERSCROLL_SPRING.powf(dt);
+            self.velocity *= spring;
+            self.offset = bound + (self.offset - bound).clamp(-OVERSCROLL, OVERSCROLL) * spring;
+        }
+        if self.velocity.abs() < MIN_FLING_VELOCITY {
+            self.velocity = 0.0;
+        }
+        if self.velocity == 0.0 && (self.offset - bound).abs() < 0.5 {
+            self.offset = bound;
+            return false;
+        }
+        true
+    }
+}
+
+// the thumb's offset from the top of the track and its height, None while the content fits
+pub fn scroll_thumb(viewport: f64, content: f64, offset: f64) -> Option<(f64, f64)> {
+    if viewport <= 0.0 || content <= viewport {
+        return None;
+    }
+    let height = (viewport * viewport / content)
+        .max(MIN_THUMB_HEIGHT)
+        .min(viewport);
+    let max_offset = content - viewport;
+    let y = offset.clamp(0.0, max_offset) / max_offset * (viewport - height);
+    Some((y, height))
+}
+
+// a vertical viewport onto a child that may be taller than it. the wheel scrolls it, and
+// a scrollbar drawn over the right edge can be dragged, or clicked above or below the
+// thumb to move a page. letting go of a fast scroll keeps it coasting for a while
+pub struct ScrollView<'a> {
+    child: Rc<RefCell<dyn Widget<'a> + 'a>>,
+    track_color: Color,
+    thumb_color: Color,
+    offset: Cell<f64>,
+    // viewport and content height of the last layout
+    extent: Cell<(f64, f64)>,
+    // how far below the thumb's top it was grabbed, while it is dragged
+    grab: Cell<Option<f64>>,
+    // the last tick's time, the distance scrolled since then and for how many ticks in a
+    // row there was scrolling, and how fast the last of those went
+    time: Cell<f64>,
+    pending: Cell<(f64, usize)>,
+    velocity: Cell<f64>,
+    // set while a flick coasts, the offset may then be past either end
+    coast: Cell<Option<Momentum>>,
+    id: usize,
+    track_id: usize,
+    thumb_id: usize,
+}
+
+pub struct ScrollViewBuilder<'a> {
+    child: Rc<RefCell<dyn Widget<'a> + 'a>>,
+    track_color: Color,
+    thumb_color: Color,
+    id: Option<usize>,
+}
+
+impl<'a> ScrollView<'a> {
+    pub fn new<W: IntoWidget<'a>>(child: W) -> ScrollViewBuilder<'a> {
+        ScrollViewBuilder {
+            child: child.into_widget(),
+            track_color: [0.0, 0.0, 0.0, 0.05],
+            thumb_color: [0.0, 0.0, 0.0, 0.4],
+            id: None,
+        }
+    }
+
+    pub fn scroll_offset(&self) -> f64 {
+        self.offset.get()
+    }
+
+    // clamped to the content of the last layout, stops a flick
+    pub fn set_scroll_offset(&self, offset: f64) {
+        self.coast.set(None);
+        self.offset.set(offset.clamp(0.0, self.max_offset()));
+    }
+
+    fn max_offset(&self) -> f64 {
+        let (viewport, content) = self.extent.get();
+        (content - viewport).max(0.0)
+    }
+
+    // returns whether the offset changed
+    fn scroll_to(&self, offset: f64) -> bool {
+        let before = self.offset.get();
+        self.set_scroll_offset(offset);
+        self.offset.get() != before
+    }
+
+    // a flick starts on the first tick without scrolling. returns whether the offset changed
+    fn tick(&self, time: f64) -> bool {
+        let dt = time - self.time.replace(time);
+        let (distance, streak) = self.pending.replace((0.0, 0));
+        if distance != 0.0 {
+            if dt > 0.0 {
+                self.velocity.set(distance / dt);
+            }
+            self.pending.set((0.0, streak + 1));
+            return false;
+        }
+        let velocity = self.velocity.replace(0.0);
+        if streak >= FLING_FRAMES && velocity.abs() >= MIN_FLING_VELOCITY {
+            self.coast.set(Some(Momentum {
+                offset: self.offset.get(),
+                velocity,
+            }));
+        }
+        let mut coast = match self.coast.get() {
+            Some(coast) => coast,
+            None => return false,
+        };
+        let moving = coast.step(dt.max(0.0), self.max_offset());
+        self.offset.set(coast.offset);
+        self.coast.set(if moving { Some(coast) } else { None });
+        true
+    }
+}
+
+impl<'a> ScrollViewBuilder<'a> {
+    pub fn id(mut self, id: usize) -> Self {
+        self.id = Some(id);
+        self
+    }
+
+    pub fn key<K: Into<WidgetKey>>(self, key: K) -> Self {
+        let id = key.into().id();
+        self.id(id)
+    }
+
+    pub fn track_color(mut self, color: Color) -> Self {
+        self.track_color = color;
+        self
+    }
+
+    pub fn thumb_color(mut self, color: Color) -> Self {
+        self.thumb_color = color;
+        self
+    }
+
+    pub fn build(self) -> Rc<RefCell<ScrollView<'a>>> {
+        Rc::new(RefCell::new(ScrollView {
+            child: self.child,
+            track_color: self.track_color,
+            thumb_color: self.thumb_color,
+            offset: Cell::new(0.0),
+            extent: Cell::new((0.0, 0.0)),
+            grab: Cell::new(None),
+            time: Cell::new(0.0),
+            pending: Cell::new((0.0, 0)),
+            velocity: Cell::new(0.0),
+            coast: Cell::new(None),
+            id: self.id.unwrap_or_else(next_id),
+            track_id: next_id(),
+            thumb_id: next_id(),
+        }))
+    }
+}
+
+pub struct Row<'a> {
+    pub children: Vec<Child<'a>>,
+    pub flex: Vec<usize>,
+    pub spacing: Spacing,
+    pub id: usize,
+}
+
+pub struct RowBuilder<'a> {
+    pub children: Vec<Child<'a>>,
+    pub flex: Vec<usize>,
+    pub spacing: Spacing,
+    pub id: Option<usize>,
+}
+
+impl<'a> Row<'a> {
+    pub fn new() -> RowBuilder<'a> {
+        RowBuilder {
+            children: Vec::new(),
+            flex: Vec::new(),
+            spacing: Spacing::Start,
+            id: None,
+        }
+    }
+}
+
+impl<'a> RowBuilder<'a> {
+    // only has an effect when the children leave space unused
+    pub fn spacing(mut self, spacing: Spacing) -> Self {
+        self.spacing = spacing;
+        self
+    }
+
+    pub fn id(mut self, id: usize) -> Self {
+        self.id = Some(id);
+        self
+    }
+
+    pub fn key<K: Into<WidgetKey>>(self, key: K) -> Self {
+        let id = key.into().id();
+        self.id(id)
+    }
+
+    pub fn add<W: IntoWidget<'a>>(mut self, child: W) -> Self {
+        self.children.push(Child::Shared(child.into_widget()));
+        self.flex.push(1);
+        self
+    }
+
+    // a flex of 0 sizes the child to its measured size instead of sharing the free space
+    pub fn add_flex<W: IntoWidget<'a>>(mut self, child: W, flex: usize) -> Self {
+        self.children.push(Child::Shared(child.into_widget()));
+        self.flex.push(flex);
+        self
+    }
+
+    // owned by the container, for leaves made with build_static
+    pub fn add_static(mut self, child: Box<dyn Widget<'a> + 'a>) -> Self {
+        self.children.push(Child::Owned(child));
+        self.flex.push(1);
+        self
+    }
+
+    pub fn build(self) -> Rc<RefCell<Row<'a>>> {
+        Rc::new(RefCell::new(self.widget()))
+    }
+
+    // for trees that never change, skips the shared ownership build hands out
+    pub fn build_static(self) -> Box<dyn Widget<'a> + 'a> {
+        Box::new(self.widget())
+    }
+
+    fn widget(self) -> Row<'a> {
+        Row {
+            children: self.children,
+            flex: self.flex,
+            spacing: self.spacing,
+            id: self.id.unwrap_or_else(next_id),
+        }
+    }
+}
+
+pub struct Column<'a> {
+    pub children: Vec<Child<'a>>,
+    pub flex: Vec<usize>,
+    pub spacing: Spacing,
+    pub id: usize,
+}
+
+pub struct ColumnBuilder<'a> {
+    pub children: Vec<Child<'a>>,
+    pub flex: Vec<usize>,
+    pub spacing: Spacing,
+    pub id: Option<usize>,
+}
+
+impl<'a> Column<'a> {
+    pub fn new() -> ColumnBuilder<'a> {
+        ColumnBuilder {
+            children: Vec::new(),
+            flex: Vec::new(),
+            spacing: Spacing::Start,
+            id: None,
+        }
+    }
+}
+
+impl<'a> ColumnBuilder<'a> {
+    // only has an effect when the children leave space unused
+    pub fn spacing(mut self, spacing: Spacing) -> Self {
+        self.spacing = spacing;
+        self
+    }
+
+    pub fn id(mut self, id: usize) -> Self {
+        self.id = Some(id);
+        self
+    }
+
+    pub fn key<K: Into<WidgetKey>>(self, key: K) -> Self {
+        let id = key.into().id();
+        self.id(id)
+    }
+
+    pub fn add<W: IntoWidget<'a>>(mut self, child: W) -> Self {
+        self.children.push(Child::Shared(child.into_widget()));
+        self.flex.push(1);
+        self
+    }
+
+    // a flex of 0 sizes the child to its measured size instead of sharing the free space
+    pub fn add_flex<W: IntoWidget<'a>>(mut self, child: W, flex: usize) -> Self {
+        self.children.push(Child::Shared(child.into_widget()));
+        self.flex.push(flex);
+        self
+    }
+
+    // owned by the container, for leaves made with build_static
+    pub fn add_static(mut self, child: Box<dyn Widget<'a> + 'a>) -> Self {
+        self.children.push(Child::Owned(child));
+        self.flex.push(1);
+        self
+    }
+
+    pub fn build(self) -> Rc<RefCell<Column<'a>>> {
+        Rc::new(RefCell::new(self.widget()))
+    }
+
+    // for trees that never change, skips the shared ownership build hands out
+    pub fn build_static(self) -> Box<dyn Widget<'a> + 'a> {
+        Box::new(self.widget())
+    }
+
+    fn widget(self) -> Column<'a> {
+        Column {
+            children: self.children,
+            flex: self.flex,
+            spacing: self.spacing,
+            id: self.id.unwrap_or_else(next_id),
+        }
+    }
+}
+
+pub struct Stack<'a> {
+    pub children: Vec<Child<'a>>,
+    pub id: usize,
+}
+
+pub struct StackBuilder<'a> {
+    pub children: Vec<Child<'a>>,
+    pub id: Option<usize>,
+}
+
+impl<'a> Stack<'a> {
+    pub fn new() -> StackBuilder<'a> {
+        StackBuilder {
+            children: Vec::new(),
+            id: None,
+        }
+    }
+}
+
+impl<'a> StackBuilder<'a> {
+    pub fn id(mut self, id: usize) -> Self {
+        self.id = Some(id);
+        self
+    }
+
+    pub fn key<K: Into<WidgetKey>>(self, key: K) -> Self {
+        let id = key.into().id();
+        self.id(id)
+    }
+
+    pub fn add<W: IntoWidget<'a>>(mut self, child: W) -> Self {
+        self.children.push(Child::Shared(child.into_widget()));
+        self
+    }
+
+    // owned by the stack, for leaves made with build_static
+    pub fn add_static(mut self, child: Box<dyn Widget<'a> + 'a>) -> Self {
+        self.children.push(Child::Owned(child));
+        self
+    }
+
+    pub fn build(self) -> Rc<RefCell<Stack<'a>>> {
+        Rc::new(RefCell::new(self.widget()))
+    }
+
+    // for trees that never change, skips the shared ownership build hands out
+    pub fn build_static(self) -> Box<dyn Widget<'a> + 'a> {
+        Box::new(self.widget())
+    }
+
+    fn widget(self) -> Stack<'a> {
+        Stack {
+            children: self.children,
+            id: self.id.unwrap_or_else(next_id),
+        }
+    }
+}
+
+impl<'a> Widget<'a> for MouseGesture<'a> {
+    fn compute(
+        &self,
+        x: f64,
+        y: f64,
+        z: usize,
+        width: f64,
+        height: f64,
+        map: &mut HashMap<usize, ComputedWidget<'a>>,
+    ) {
+        self.background
+            .borrow()
+            .compute(x, y, z, width, height, map);
+        map.insert(
+            self.get_id(),
+            ComputedWidget {
+                x,
+                y,
+                z,
+                width,
+                height,
+                render: None,
+                clip: None,
+                text_bounds: None,
+                overlay: false,
+            },
+        );
+    }
+
+    fn measure(&self, width: f64, height: f64) -> (f64, f64) {
+        self.background.borrow().measure(width, height)
+    }
+
+    fn dispatch(
+        &self,
+        event: Event,
+        prev_state_change: bool,
+        map: &HashMap<usize, ComputedWidget>,
+    ) -> (Option<Event>, bool) {
+        let computed: &ComputedWidget = map.get(&self.get_id()).unwrap();
+        match event {
+            Event::MouseDown { x, y, button } => {
+                if computed.in_hitbox(x, y, self.border_radius) {
+                    self.pressed.set(true);
+                    self.long_press.borrow_mut().press(x, y);
+                    let state_change = if let Some(click) = &self.click_callback {
+                        click(button)
+                    } else {
+                        false
+                    };
+                    (None, prev_state_change | state_change)
+                } else {
+                    (Some(event), prev_state_change)
+                }
+            }
+            Event::MouseUp { x, y, button } => {
+                let was_pressed = self.pressed.replace(false);
+                self.long_press.borrow_mut().cancel();
+                if computed.in_hitbox(x, y, self.border_radius) {
+                    let mut state_change = if let Some(release) = &self.release_callback {
+                        release(button)
+                    } else {
+                        false
+                    };
+                    if was_pressed {
+                        if let Some(tap) = &self.tap_callback {
+                            state_change |= tap(button);
+                        }
+                    }
+                    (None, prev_state_change | state_change)
+                } else {
+                    (Some(event), prev_state_change)
+                }
+            }
+            Event::MouseMove { x, y, .. } => {
+                self.long_press.borrow_mut().moved(x, y);
+                // compare against the remembered hover state instead of the previous
+                // position, which may be stale after the cursor jumped
+                let inside = computed.in_hitbox(x, y, self.border_radius);
+                if inside && !self.hovered.get() {
+                    self.hovered.set(true);
+                    let state_change = if let Some(enter) = &self.enter_callback {
+                        enter()
+                    } else {
+                        false
+                    };
+                    (None, prev_state_change | state_change)
+                } else if !inside && self.hovered.get() {
+                    self.hovered.set(false);
+                    let state_change = if let Some(leave) = &self.leave_callback {
+                        leave()
+                    } else {
+                        false
+                    };
+                    // keep propagating so the widget being entered sees the move too
+                    (Some(event), prev_state_change | state_change)
+                } else {
+                    (Some(event), prev_state_change)
+                }
+            }
+            Event::Tick { time } => {
+                let mut state_change = false;
+                if self.long_press.borrow_mut().tick(time) {
+                    if let Some(long_press) = &self.long_press_callback {
+                        state_change = long_press();
+                    }
+                }
+                (Some(event), prev_state_change | state_change)
+            }
+            Event::Scroll { .. } | Event::KeyDown { .. } | Event::Char { .. } => {
+                (Some(event), prev_state_change)
+            }
+        }
+    }
+
+    fn children(&self) -> Vec<Rc<RefCell<dyn Widget<'a> + 'a>>> {
+        vec![self.background.clone()]
+    }
+
+    fn get_id(&self) -> usize {
+        self.id
+    }
+}
+
+impl<'a> Widget<'a> for Text<'a> {
+    fn compute(
+        &self,
+        x: f64,
+        y: f64,
+        z: usize,
+        width: f64,
+        height: f64,
+        map: &mut HashMap<usize, ComputedWidget<'a>>,
+    ) {
+        let style = self.style();
+        let text_bounds = measure_text(&self.text, &style);
+        // the box hugs the glyphs instead of filling the slot, so hit tests and backgrounds
+        // match what is drawn. it starts at the leading edge, like the glyphs themselves
+        let (text_width, text_height) = (text_bounds.0.min(width), text_bounds.1.min(height));
+        let x = match self.direction {
+            TextDirection::Ltr => x,
+            TextDirection::Rtl => x + width - text_width,
+        };
+        map.insert(
+            self.get_id(),
+            ComputedWidget {
+                x,
+                y,
+                z,
+                width: text_width,
+                height: text_height,
+                render: Some(RenderObject::Text {
+                    text: self.text.clone(),
+                    style,
+                }),
+                clip: None,
+                text_bounds: Some(text_bounds),
+                overlay: false,
+            },
+        );
+    }
+
+    fn measure(&self, _width: f64, _height: f64) -> (f64, f64) {
+        measure_text(&self.text, &self.style())
+    }
+
+    fn dispatch(
+        &self,
+        event: Event,
+        prev_state_change: bool,
+        _map: &HashMap<usize, ComputedWidget>,
+    ) -> (Option<Event>, bool) {
+        (Some(event), prev_state_change)
+    }
+
+    fn semantics(&self) -> Option<SemanticNode> {
+        Some(SemanticNode::new(Role::Text, &*self.text))
+    }
+
+    fn get_id(&self) -> usize {
+        self.id
+    }
+}
+
+impl<'a> Widget<'a> for SelectableText<'a> {
+    fn compute(
+        &self,
+        x: f64,
+        y: f64,
+        z: usize,
+        width: f64,
+        height: f64,
+        map: &mut HashMap<usize, ComputedWidget<'a>>,
+    ) {
+        let style = self.style();
+        let text_bounds = measure_text(self.text, &style);
+        // the highlight sits one layer below the glyphs
+        map.insert(
+            self.highlight_id,
+            ComputedWidget {
+                x,
+                y,
+                z,
+                width,
+                height,
+                render: Some(RenderObject::Custom(self.highlight())),
+                clip: None,
+                text_bounds: None,
+                overlay: false,
+            },
+        );
+        map.insert(
+            self.get_id(),
+            ComputedWidget {
+                x,
+                y,
+                z: z + 1,
+                width,
+                height,
+                render: Some(RenderObject::Text {
+                    text: Cow::Borrowed(self.text),
+                    style,
+                }),
+                clip: None,
+                text_bounds: Some(text_bounds),
+                overlay: false,
+            },
+        );
+    }
+
+    fn measure(&self, _width: f64, _height: f64) -> (f64, f64) {
+        measure_text(self.text, &self.style())
+    }
+
+    fn dispatch(
+        &self,
+        event: Event,
+        prev_state_change: bool,
+        map: &HashMap<usize, ComputedWidget>,
+    ) -> (Option<Event>, bool) {
+        let computed: &ComputedWidget = map.get(&self.get_id()).unwrap();
+        match event {
+            Event::MouseDown { x, y, .. } => {
+                if computed.in_hitbox(x, y, BorderRadius::default()) {
+                    let index = self.index_at(x - computed.x, y - computed.y);
+                    self.anchor.set(Some(index));
+                    self.cursor.set(index);
+                    self.dragging.set(true);
+                    (None, true)
+                } else {
+                    // clicking elsewhere drops the selection but not the click
+                    let had_selection = self.selection().is_some();
+                    self.clear_selection();
+                    (Some(event), prev_state_change | had_selection)
+                }
+            }
+            Event::MouseMove { x, y, .. } if self.dragging.get() => {
+                let index = self.index_at(x - computed.x, y - computed.y);
+                let moved = self.cursor.replace(index) != index;
+                (Some(event), prev_state_change | moved)
+            }
+            Event::MouseUp { .. } => {
+                self.dragging.set(false);
+                (Some(event), prev_state_change)
+            }
+            // the text never takes focus, so a selection is what makes it the target
+            Event::KeyDown {
+                key: Key::Char('c'),
+                modifiers: Modifiers::CTRL,
+            } if self.selection().is_some() => {
+                self.copy();
+                (None, prev_state_change)
+            }
+            _ => (Some(event), prev_state_change),
+        }
+    }
+
+    fn semantics(&self) -> Option<SemanticNode> {
+        Some(SemanticNode::new(Role::Text, self.text))
+    }
+
+    fn get_id(&self) -> usize {
+        self.id
+    }
+}
+
+impl<'a> Widget<'a> for TextInput<'a> {
+    fn compute(
+        &self,
+        x: f64,
+        y: f64,
+        z: usize,
+        width: f64,
+        height: f64,
+        map: &mut HashMap<usize, ComputedWidget<'a>>,
+    ) {
+        let style = self.style();
+        let text = self.display_text();
+        let text_bounds = measure_text(&text, &style);
+        let (highlight, caret) = self.decorations(line_height(&style).min(height));
+        let layer = |z: usize, render: RenderObject<'a>| ComputedWidget {
+            x,
+            y,
+            z,
+            width,
+            height,
+            render: Some(render),
+            clip: None,
+            text_bounds: None,
+            overlay: false,
+        };
+        // the highlight goes below the glyphs and the caret above them
+        map.insert(self.highlight_id, layer(z, RenderObject::Custom(highlight)));
+        map.insert(
+            self.get_id(),
+            ComputedWidget {
+                text_bounds: Some(text_bounds),
+                ..layer(
+                    z + 1,
+                    RenderObject::Text {
+                        text: Cow::Owned(text),
+                        style,
+                    },
+                )
+            },
+        );
+        map.insert(self.caret_id, layer(z + 2, RenderObject::Custom(caret)));
+    }
+
+    // an empty input is still one line high
+    fn measure(&self, _width: f64, _height: f64) -> (f64, f64) {
+        let style = self.style();
+        let (width, _) = measure_text(&self.display_text(), &style);
+        (width, line_height(&style))
+    }
+
+    fn dispatch(
+        &self,
+        event: Event,
+        prev_state_change: bool,
+        map: &HashMap<usize, ComputedWidget>,
+    ) -> (Option<Event>, bool) {
+        let computed: &ComputedWidget = map.get(&self.get_id()).unwrap();
+        match event {
+            Event::MouseDown { x, y, .. } => {
+                if computed.in_hitbox(x, y, BorderRadius::default()) {
+                    self.focused.set(true);
+                    self.set_cursor(self.index_at(x - computed.x));
+                    self.anchor.set(Some(self.cursor()));
+                    self.dragging.set(true);
+                    (None, true)
+                } else {
+                    let was_focused = self.focused.replace(false);
+                    self.anchor.set(None);
+                    (Some(event), prev_state_change | was_focused)
+                }
+            }
+            Event::MouseMove { x, .. } if self.dragging.get() => {
+                let index = self.index_at(x - computed.x);
+                let moved = self.cursor.replace(index) != index;
+                (Some(event), prev_state_change | moved)
+            }
+            Event::MouseUp { .. } => {
+                self.dragging.set(false);
+                (Some(event), prev_state_change)
+            }
+            Event::Tick { time } => {
+                let was_visible = self.caret_visible();
+                self.time.set(time);
+                (
+                    Some(event),
+                    prev_state_change | (was_visible != self.caret_visible()),
+                )
+            }
+            Event::KeyDown { key, modifiers } if self.focused.get() => {
+                match self.key(key, modifiers) {
+                    Some(changed) => (None, prev_state_change | changed),
+                    None => (Some(event), prev_state_change),
+                }
+            }
+            // control characters come in as key events
+            Event::Char { codepoint } if self.focused.get() && !codepoint.is_control() => {
+                let changed = self.insert(codepoint.encode_utf8(&mut [0; 4]));
+                (None, prev_state_change | changed)
+            }
+            _ => (Some(event), prev_state_change),
+        }
+    }
+
+    fn semantics(&self) -> Option<SemanticNode> {
+        Some(SemanticNode::new(Role::TextField, self.display_text()))
+    }
+
+    fn get_id(&self) -> usize {
+        self.id
+    }
+}
+
+impl<'a> Widget<'a> for Form<'a> {
+    fn compute(
+        &self,
+        x: f64,
+        y: f64,
+        z: usize,
+        width: f64,
+        height: f64,
+        map: &mut HashMap<usize, ComputedWidget<'a>>,
+    ) {
+        self.child.borrow().compute(x, y, z, width, height, map);
+        for error in self.errors.borrow().iter() {
+            let field = match self
+                .fields
+                .iter()
+                .find(|field| field.input.borrow().get_id() == error.field)
+            {
+                Some(field) => field,
+                None => continue,
+            };
+            let computed = match map.get(&error.field) {
+                Some(computed) => computed.clone(),
+                None => continue,
+            };
+            let (w, h, color) = (computed.width, computed.height, self.error_color);
+            let edge = |x: f64, y: f64, width: f64, height: f64| DrawCommand::FillRect {
+                x,
+                y,
+                width,
+                height,
+                color,
+            };
+            let border = vec![
+                edge(0.0, 0.0, w, FIELD_ERROR_BORDER),
+                edge(0.0, h - FIELD_ERROR_BORDER, w, FIELD_ERROR_BORDER),
+                edge(0.0, 0.0, FIELD_ERROR_BORDER, h),
+                edge(w - FIELD_ERROR_BORDER, 0.0, FIELD_ERROR_BORDER, h),
+            ];
+            map.insert(
+                field.border_id,
+                ComputedWidget {
+                    z: computed.z + 3,
+                    render: Some(RenderObject::Custom(border)),
+                    text_bounds: None,
+                    ..computed.clone()
+                },
+            );
+            let style = TextStyle {
+                color,
+                ..field.input.borrow().style()
+            };
+            let text_bounds = measure_text(&error.message, &style);
+            map.insert(
+                field.message_id,
+                ComputedWidget {
+                    y: computed.y + h,
+                    z: computed.z + 3,
+                    height: line_height(&style),
+                    render: Some(RenderObject::Text {
+                        text: Cow::Owned(error.message.clone()),
+                        style,
+                    }),
+                    text_bounds: Some(text_bounds),
+                    ..computed
+                },
+            );
+        }
+    }
+
+    fn measure(&self, width: f64, height: f64) -> (f64, f64) {
+        self.child.borrow().measure(width, height)
+    }
+
+    fn flex(&self) -> Option<usize> {
+        self.child.borrow().flex()
+    }
+
+    fn dispatch(
+        &self,
+        event: Event,
+        prev_state_change: bool,
+        map: &HashMap<usize, ComputedWidget>,
+    ) -> (Option<Event>, bool) {
+        let (rest, state_change) = dispatch_to(&self.child, event, prev_state_change, map);
+        // the inputs leave Enter alone, so it comes back up from the focused one
+        if let Some(Event::KeyDown {
+            key: Key::Enter,
+            modifiers: Modifiers::NONE,
+        }) = rest
+        {
+            if self
+                .fields
+                .iter()
+                .any(|field| field.input.borrow().focused())
+            {
+                self.submit();
+                return (None, true);
+            }
+        }
+        (rest, state_change)
+    }
+
+    fn children(&self) -> Vec<Rc<RefCell<dyn Widget<'a> + 'a>>> {
+        vec![self.child.clone()]
+    }
+
+    fn get_id(&self) -> usize {
+        self.id
+    }
+}
+
+impl<'a> Widget<'a> for RichText<'a> {
+    fn compute(
+        &self,
+        x: f64,
+        y: f64,
+        z: usize,
+        width: f64,
+        height: f64,
+        map: &mut HashMap<usize, ComputedWidget<'a>>,
+    ) {
+        let (_, text_bounds) = span_layout(&self.spans, self.hinting);
+        map.insert(
+            self.get_id(),
+            ComputedWidget {
+                x,
+                y,
+                z,
+                width,
+                height,
+                render: Some(RenderObject::RichText {
+                    spans: self.spans.clone(),
+                    hinting: self.hinting,
+                }),
+                clip: None,
+                text_bounds: Some(text_bounds),
+                overlay: false,
+            },
+        );
+    }
+
+    fn measure(&self, _width: f64, _height: f64) -> (f64, f64) {
+        span_layout(&self.spans, self.hinting).1
+    }
+
+    fn dispatch(
+        &self,
+        event: Event,
+        prev_state_change: bool,
+        _map: &HashMap<usize, ComputedWidget>,
+    ) -> (Option<Event>, bool) {
+        (Some(event), prev_state_change)
+    }
+
+    fn semantics(&self) -> Option<SemanticNode> {
+        let text: String = self.spans.iter().map(|span| span.text).collect();
+        Some(SemanticNode::new(Role::Text, text))
+    }
+
+    fn get_id(&self) -> usize {
+        self.id
+    }
+}
+
+impl<'a> Widget<'a> for Dropdown<'a> {
+    fn compute(
+        &self,
+        x: f64,
+        y: f64,
+        z: usize,
+        width: f64,
+        height: f64,
+        map: &mut HashMap<usize, ComputedWidget<'a>>,
+    ) {
+        let style = self.style();
+        let text_y = (height - line_height(&style)).max(0.0) / 2.0;
+        let rect = |y: f64, z: usize, color: Color, overlay: bool| ComputedWidget {
+            x,
+            y,
+            z,
+            width,
+            height,
+            render: Some(RenderObject::Rectangle {
+                style: Style {
+                    color: Some(color),
+                    border_radius: BorderRadius::default(),
+                    image: None,
+                },
+            }),
+            clip: None,
+            text_bounds: None,
+            overlay,
+        };
+        let label = |text: &'a str, y: f64, z: usize, overlay: bool| ComputedWidget {
+            x: x + DROPDOWN_PADDING,
+            y: y + text_y,
+            z,
+            width: (width - 2.0 * DROPDOWN_PADDING).max(0.0),
+            height: height - text_y,
+            render: Some(RenderObject::Text {
+                text: Cow::Borrowed(text),
+                style: self.style(),
+            }),
+            clip: None,
+            text_bounds: Some(measure_text(text, &style)),
+            overlay,
+        };
+        let selected = self.selected();
+        let text = self.options.get(selected).copied().unwrap_or("");
+        map.insert(self.id, rect(y, z, self.color, false));
+        map.insert(self.label_id, label(text, y, z + 1, false));
+        if !self.open.get() {
+            return;
+        }
+        // the open list escapes clips like an Overlay would
+        for (i, (option, (row_id, text_id))) in self.options.iter().zip(&self.row_ids).enumerate() {
+            let row_y = y + height * (i + 1) as f64;
+            let color = if i == selected {
+                self.highlight_color
+            } else {
+                self.list_color
+            };
+            map.insert(*row_id, rect(row_y, z + OVERLAY_Z, color, true));
+            map.insert(*text_id, label(option, row_y, z + OVERLAY_Z + 1, true));
+        }
+    }
+
+    fn measure(&self, _width: f64, _height: f64) -> (f64, f64) {
+        let style = self.style();
+        let width = self
+            .options
+            .iter()
+            .map(|option| measure_text(option, &style).0)
+            .fold(0.0, f64::max);
+        (
+            width + 2.0 * DROPDOWN_PADDING,
+            line_height(&style) + DROPDOWN_PADDING,
+        )
+    }
+
+    fn dispatch(
+        &self,
+        event: Event,
+        prev_state_change: bool,
+        map: &HashMap<usize, ComputedWidget>,
+    ) -> (Option<Event>, bool) {
+        let computed: &ComputedWidget = map.get(&self.get_id()).unwrap();
+        match event {
+            Event::MouseDown { x, y, .. } => {
+                if computed.in_hitbox(x, y, BorderRadius::default()) {
+                    self.open.set(!self.open.get());
+                    return (None, true);
+                }
+                if !self.open.get() {
+                    return (Some(event), prev_state_change);
+                }
+                self.open.set(false);
+                match self.row_at(x, y, computed) {
+                    Some(index) => {
+                        *self.selected.borrow_mut() = index;
+                        if let Some(select) = &self.select_callback {
+                            select(index);
+                        }
+                        (None, true)
+                    }
+                    // clicking anywhere else closes the list without taking the click
+                    None => (Some(event), true),
+                }
+            }
+            _ => (Some(event), prev_state_change),
+        }
+    }
+
+    fn semantics(&self) -> Option<SemanticNode> {
+        let label = self.options.get(self.selected()).copied().unwrap_or("");
+        Some(SemanticNode::new(Role::ComboBox, label))
+    }
+
+    fn get_id(&self) -> usize {
+        self.id
+    }
+}
+
+impl<'a> Widget<'a> for Rectangle {
+    fn compute(
+        &self,
+        x: f64,
+        y: f64,
+        z: usize,
+        width: f64,
+        height: f64,
+        map: &mut HashMap<usize, ComputedWidget>,
+    ) {
+        map.insert(
+            self.get_id(),
+            ComputedWidget {
+                x,
+                y,
+                z,
+                width,
+                height,
+                render: Some(RenderObject::Rectangle {
+                    style: Style {
+                        color: Some(self.color),
+                        border_radius: self.border_radius,
+                        image: self.background_image.clone(),
+                    },
+                }),
+                clip: None,
+                text_bounds: None,
+                overlay: false,
+            },
+        );
+    }
+
+    fn dispatch(
+        &self,
+        event: Event,
+        prev_state_change: bool,
+        _map: &HashMap<usize, ComputedWidget>,
+    ) -> (Option<Event>, bool) {
+        (Some(event), prev_state_change)
+    }
+
+    fn get_id(&self) -> usize {
+        self.id
+    }
+}
+
+impl<'a> Widget<'a> for Ellipse {
+    fn compute(
+        &self,
+        x: f64,
+        y: f64,
+        z: usize,
+        width: f64,
+        height: f64,
+        map: &mut HashMap<usize, ComputedWidget>,
+    ) {
+        let (x, y, width, height) = if self.circle {
+            let size = width.min(height);
+            (
+                x + (width - size) / 2.0,
+                y + (height - size) / 2.0,
+                size,
+                size,
+            )
+        } else {
+            (x, y, width, height)
+        };
+        map.insert(
+            self.get_id(),
+            ComputedWidget {
+                x,
+                y,
+                z,
+                width,
+                height,
+                render: Some(RenderObject::Ellipse {
+                    style: Style {
+                        color: Some(self.color),
+                        border_radius: BorderRadius::default(),
+                        image: None,
+                    },
+                }),
+                clip: None,
+                text_bounds: None,
+                overlay: false,
+            },
+        );
+    }
+
+    fn dispatch(
+        &self,
+        event: Event,
+        prev_state_change: bool,
+        _map: &HashMap<usize, ComputedWidget>,
+    ) -> (Option<Event>, bool) {
+        (Some(event), prev_state_change)
+    }
+
+    fn get_id(&self) -> usize {
+        self.id
+    }
+}
+
+impl<'a> Widget<'a> for Empty {
+    fn compute(
+        &self,
+        _x: f64,
+        _y: f64,
+        _z: usize,
+        _width: f64,
+        _height: f64,
+        _map: &mut HashMap<usize, ComputedWidget>,
+    ) {
+    }
+
+    fn dispatch(
+        &self,
+        event: Event,
+        prev_state_change: bool,
+        _map: &HashMap<usize, ComputedWidget>,
+    ) -> (Option<Event>, bool) {
+        (Some(event), prev_state_change)
+    }
+
+    fn get_id(&self) -> usize {
+        self.id
+    }
+}
+
+impl<'a> Widget<'a> for Canvas<'a> {
+    fn compute(
+        &self,
+        x: f64,
+        y: f64,
+        z: usize,
+        width: f64,
+        height: f64,
+        map: &mut HashMap<usize, ComputedWidget<'a>>,
+    ) {
+        let mut recorder = CommandRecorder {
+            width,
+            height,
+            commands: Vec::new(),
+        };
+        (self.paint)(&mut recorder);
+        map.insert(
+            self.get_id(),
+            ComputedWidget {
+                x,
+                y,
+                z,
+                width,
+                height,
+                render: Some(RenderObject::Custom(recorder.commands)),
+                clip: None,
+                text_bounds: None,
+                overlay: false,
+            },
+        );
+    }
+
+    fn dispatch(
+        &self,
+        event: Event,
+        prev_state_change: bool,
+        _map: &HashMap<usize, ComputedWidget>,
+    ) -> (Option<Event>, bool) {
+        (Some(event), prev_state_change)
+    }
+
+    fn get_id(&self) -> usize {
+        self.id
+    }
+}
+
+impl<'a> Widget<'a> for NinePatch {
+    fn compute(
+        &self,
+        x: f64,
+        y: f64,
+        z: usize,
+        width: f64,
+        height: f64,
+        map: &mut HashMap<usize, ComputedWidget<'a>>,
+    ) {
+        map.insert(
+            self.get_id(),
+            ComputedWidget {
+                x,
+                y,
+                z,
+                width,
+                height,
+                render: Some(RenderObject::NinePatch {
+                    path: self.source.clone(),
+                    insets: self.insets,
+                }),
+                clip: None,
+                text_bounds: None,
+                overlay: false,
+            },
+        );
+    }
+
+    fn dispatch(
+        &self,
+        event: Event,
+        prev_state_change: bool,
+        _map: &HashMap<usize, ComputedWidget>,
+    ) -> (Option<Event>, bool) {
+        (Some(event), prev_state_change)
+    }
+
+    fn get_id(&self) -> usize {
+        self.id
+    }
+}
+
+impl<'a> Widget<'a> for Polyline {
+    fn compute(
+        &self,
+        x: f64,
+        y: f64,
+        z: usize,
+        width: f64,
+        height: f64,
+        map: &mut HashMap<usize, ComputedWidget<'a>>,
+    ) {
+        map.insert(
+            self.get_id(),
+            ComputedWidget {
+                x,
+                y,
+                z,
+                width,
+                height,
+                render: Some(RenderObject::Line {
+                    points: self.points.clone(),
+                    width: self.width,
+                    color: self.color,
+                }),
+                clip: None,
+                text_bounds: None,
+                overlay: false,
+            },
+        );
+    }
+
+    fn measure(&self, _width: f64, _height: f64) -> (f64, f64) {
+        self.points.iter().fold((0.0, 0.0), |(w, h), (x, y)| {
+            (f64::max(w, *x), f64::max(h, *y))
+        })
+    }
+
+    fn dispatch(
+        &self,
+        event: Event,
+        prev_state_change: bool,
+        _map: &HashMap<usize, ComputedWidget>,
+    ) -> (Option<Event>, bool) {
+        (Some(event), prev_state_change)
+    }
+
+    fn get_id(&self) -> usize {
+        self.id
+    }
+}
+
+impl<'a> Widget<'a> for Padding<'a> {
+    fn compute(
+        &self,
+        x: f64,
+        y: f64,
+        z: usize,
+        width: f64,
+        height: f64,
+        map: &mut HashMap<usize, ComputedWidget<'a>>,
+    ) {
+        let mut left = self.padding.0.resolve(width);
+        let top = self.padding.1.resolve(height);
+        let mut right = self.padding.2.resolve(width);
+        let bottom = self.padding.3.resolve(height);
+        // left and right are the start and end insets, which swap sides in rtl
+        if layout_direction() == TextDirection::Rtl {
+            std::mem::swap(&mut left, &mut right);
+        }
+        let mut w = width - right - left;
+        let mut h = height - bottom - top;
+        if w < 0.0 {
+            w = 0.0;
+        }
+        if h < 0.0 {
+            h = 0.0;
+        }
+        self.child.borrow().compute(x + left, y + top, z, w, h, map)
+    }
+
+    fn measure(&self, width: f64, height: f64) -> (f64, f64) {
+        let horizontal = self.padding.0.resolve(width) + self.padding.2.resolve(width);
+        let vertical = self.padding.1.resolve(height) + self.padding.3.resolve(height);
+        let (w, h) = self
+            .child
+            .borrow()
+            .measure(width - horizontal, height - vertical);
+        (w + horizontal, h + vertical)
+    }
+
+    fn dispatch(
+        &self,
+        event: Event,
+        prev_state_change: bool,
+        map: &HashMap<usize, ComputedWidget>,
+    ) -> (Option<Event>, bool) {
+        dispatch_to(&self.child, event, prev_state_change, map)
+    }
+
+    fn children(&self) -> Vec<Rc<RefCell<dyn Widget<'a> + 'a>>> {
+        vec![self.child.clone()]
+    }
+
+    fn get_id(&self) -> usize {
+        self.id
+    }
+}
+
+impl<'a> Widget<'a> for ConstrainedBox<'a> {
+    fn compute(
+        &self,
+        x: f64,
+        y: f64,
+        z: usize,
+        width: f64,
+        height: f64,
+        map: &mut HashMap<usize, ComputedWidget<'a>>,
+    ) {
+        // min wins over max and over the available space, overflowing if needed
+        let w = width.min(self.max_width).max(self.min_width);
+        let h = height.min(self.max_height).max(self.min_height);
+        self.child
+            .borrow()
+            .compute(x + (width - w) / 2.0, y + (height - h) / 2.0, z, w, h, map)
+    }
+
+    fn measure(&self, width: f64, height: f64) -> (f64, f64) {
+        let (w, h) = self.child.borrow().measure(width, height);
+        (
+            w.min(self.max_width).max(self.min_width),
+            h.min(self.max_height).max(self.min_height),
+        )
+    }
+
+    fn dispatch(
+        &self,
+        event: Event,
+        prev_state_change: bool,
+        map: &HashMap<usize, ComputedWidget>,
+    ) -> (Option<Event>, bool) {
+        dispatch_to(&self.child, event, prev_state_change, map)
+    }
+
+    fn children(&self) -> Vec<Rc<RefCell<dyn Widget<'a> + 'a>>> {
+        vec![self.child.clone()]
+    }
+
+    fn get_id(&self) -> usize {
+        self.id
+    }
+}
+
+impl<'a> Widget<'a> for FractionallySizedBox<'a> {
+    fn compute(
+        &self,
+        x: f64,
+        y: f64,
+        z: usize,
+        width: f64,
+        height: f64,
+        map: &mut HashMap<usize, ComputedWidget<'a>>,
+    ) {
+        let (w, h) = self.size(width, height);
+        let (ax, ay) = self.align;
+        self.child
+            .borrow()
+            .compute(x + (width - w) * ax, y + (height - h) * ay, z, w, h, map)
+    }
+
+    fn measure(&self, width: f64, height: f64) -> (f64, f64) {
+        let (w, h) = self.size(width, height);
+        self.child.borrow().measure(w, h)
+    }
+
+    fn dispatch(
+        &self,
+        event: Event,
+        prev_state_change: bool,
+        map: &HashMap<usize, ComputedWidget>,
+    ) -> (Option<Event>, bool) {
+        dispatch_to(&self.child, event, prev_state_change, map)
+    }
+
+    fn children(&self) -> Vec<Rc<RefCell<dyn Widget<'a> + 'a>>> {
+        vec![self.child.clone()]
+    }
+
+    fn get_id(&self) -> usize {
+        self.id
+    }
+}
+
+impl<'a> Widget<'a> for Expanded<'a> {
+    fn compute(
+        &self,
+        x: f64,
+        y: f64,
+        z: usize,
+        width: f64,
+        height: f64,
+        map: &mut HashMap<usize, ComputedWidget<'a>>,
+    ) {
+        self.child.borrow().compute(x, y, z, width, height, map)
+    }
+
+    fn measure(&self, width: f64, height: f64) -> (f64, f64) {
+        self.child.borrow().measure(width, height)
+    }
+
+    fn flex(&self) -> Option<usize> {
+        Some(self.flex)
+    }
+
+    fn dispatch(
+        &self,
+        event: Event,
+        prev_state_change: bool,
+        map: &HashMap<usize, ComputedWidget>,
+    ) -> (Option<Event>, bool) {
+        dispatch_to(&self.child, event, prev_state_change, map)
+    }
+
+    fn children(&self) -> Vec<Rc<RefCell<dyn Widget<'a> + 'a>>> {
+        vec![self.child.clone()]
+    }
+
+    fn get_id(&self) -> usize {
+        self.id
+    }
+}
+
+impl<'a> Widget<'a> for Positioned<'a> {
+    fn compute(
+        &self,
+        x: f64,
+        y: f64,
+        z: usize,
+        width: f64,
+        height: f64,
+        map: &mut HashMap<usize, ComputedWidget<'a>>,
+    ) {
+        self.child.borrow().compute(x, y, z, width, height, map)
+    }
+
+    fn measure(&self, width: f64, height: f64) -> (f64, f64) {
+        match self.size {
+            Some(size) => size,
+            None => self.child.borrow().measure(width, height),
+        }
+    }
+
+    fn position(&self) -> Option<StackPosition> {
+        Some((self.offset, self.size))
+    }
+
+    fn dispatch(
+        &self,
+        event: Event,
+        prev_state_change: bool,
+        map: &HashMap<usize, ComputedWidget>,
+    ) -> (Option<Event>, bool) {
+        dispatch_to(&self.child, event, prev_state_change, map)
+    }
+
+    fn children(&self) -> Vec<Rc<RefCell<dyn Widget<'a> + 'a>>> {
+        vec![self.child.clone()]
+    }
+
+    fn get_id(&self) -> usize {
+        self.id
+    }
+}
+
+impl<'a> Widget<'a> for Directionality<'a> {
+    fn compute(
+        &self,
+        x: f64,
+        y: f64,
+        z: usize,
+        width: f64,
+        height: f64,
+        map: &mut HashMap<usize, ComputedWidget<'a>>,
+    ) {
+        self.scope(|| self.child.borrow().compute(x, y, z, width, height, map));
+    }
+
+    fn measure(&self, width: f64, height: f64) -> (f64, f64) {
+        self.scope(|| self.child.borrow().measure(width, height))
+    }
+
+    fn dispatch(
+        &self,
+        event: Event,
+        prev_state_change: bool,
+        map: &HashMap<usize, ComputedWidget>,
+    ) -> (Option<Event>, bool) {
+        dispatch_to(&self.child, event, prev_state_change, map)
+    }
+
+    fn children(&self) -> Vec<Rc<RefCell<dyn Widget<'a> + 'a>>> {
+        vec![self.child.clone()]
+    }
+
+    fn get_id(&self) -> usize {
+        self.id
+    }
+}
+
+impl<'a> Widget<'a> for Clip<'a> {
+    fn compute(
+        &self,
+        x: f64,
+        y: f64,
+        z: usize,
+        width: f64,
+        height: f64,
+        map: &mut HashMap<usize, ComputedWidget<'a>>,
+    ) {
+        let mut children = HashMap::new();
+        self.child
+            .borrow()
+            .compute(x, y, z, width, height, &mut children);
+        for (id, mut computed) in children {
+            computed.clip_to(x, y, width, height);
+            map.insert(id, computed);
+        }
+    }
+
+    fn measure(&self, width: f64, height: f64) -> (f64, f64) {
+        self.child.borrow().measure(width, height)
+    }
+
+    fn dispatch(
+        &self,
+        event: Event,
+        prev_state_change: bool,
+        map: &HashMap<usize, ComputedWidget>,
+    ) -> (Option<Event>, bool) {
+        dispatch_to(&self.child, event, prev_state_change, map)
+    }
+
+    fn children(&self) -> Vec<Rc<RefCell<dyn Widget<'a> + 'a>>> {
+        vec![self.child.clone()]
+    }
+
+    fn get_id(&self) -> usize {
+        self.id
+    }
+}
+
+impl<'a> Widget<'a> for IgnorePointer<'a> {
+    fn compute(
+        &self,
+        x: f64,
+        y: f64,
+        z: usize,
+        width: f64,
+        height: f64,
+        map: &mut HashMap<usize, ComputedWidget<'a>>,
+    ) {
+        self.child.borrow().compute(x, y, z, width, height, map)
+    }
+
+    fn measure(&self, width: f64, height: f64) -> (f64, f64) {
+        self.child.borrow().measure(width, height)
+    }
+
+    fn dispatch(
+        &self,
+        event: Event,
+        prev_state_change: bool,
+        map: &HashMap<usize, ComputedWidget>,
+    ) -> (Option<Event>, bool) {
+        match event {
+            Event::MouseDown { .. }
+            | Event::MouseUp { .. }
+            | Event::MouseMove { .. }
+            | Event::Scroll { .. }
+                if self.ignoring =>
+            {
+                (Some(event), prev_state_change)
+            }
+            _ => dispatch_to(&self.child, event, prev_state_change, map),
+        }
+    }
+
+    fn children(&self) -> Vec<Rc<RefCell<dyn Widget<'a> + 'a>>> {
+        vec![self.child.clone()]
+    }
+
+    fn get_id(&self) -> usize {
+        self.id
+    }
+}
+
+impl<'a> Widget<'a> for EventListener<'a> {
+    fn compute(
+        &self,
+        x: f64,
+        y: f64,
+        z: usize,
+        width: f64,
+        height: f64,
+        map: &mut HashMap<usize, ComputedWidget<'a>>,
+    ) {
+        self.bounds.set((x, y, width, height));
+        self.child.borrow().compute(x, y, z, width, height, map);
+    }
+
+    fn measure(&self, width: f64, height: f64) -> (f64, f64) {
+        self.child.borrow().measure(width, height)
+    }
+
+    fn dispatch(
+        &self,
+        event: Event,
+        prev_state_change: bool,
+        map: &HashMap<usize, ComputedWidget>,
+    ) -> (Option<Event>, bool) {
+        let inside = match event {
+            Event::MouseDown { x, y, .. }
+            | Event::MouseUp { x, y, .. }
+            | Event::MouseMove { x, y, .. }
+            | Event::Scroll { x, y, .. } => {
+                let (bx, by, bw, bh) = self.bounds.get();
+                x >= bx && y >= by && x < bx + bw && y < by + bh
+            }
+            _ => true,
+        };
+        let event = match &self.capture {
+            Some(capture) if inside => match capture(event) {
+                EventFlow::Consumed => return (None, true),
+                EventFlow::Ignored(event) => event,
+            },
+            _ => event,
+        };
+        match dispatch_to(&self.child, event, prev_state_change, map) {
+            (Some(event), state_change) if inside && self.bubble.is_some() => {
+                match self.bubble.as_ref().unwrap()(event) {
+                    EventFlow::Consumed => (None, true),
+                    EventFlow::Ignored(event) => (Some(event), state_change),
+                }
+            }
+            result => result,
+        }
+    }
+
+    fn children(&self) -> Vec<Rc<RefCell<dyn Widget<'a> + 'a>>> {
+        vec![self.child.clone()]
+    }
+
+    fn get_id(&self) -> usize {
+        self.id
+    }
+}
+
+impl<'a> Widget<'a> for Overlay<'a> {
+    fn compute(
+        &self,
+        x: f64,
+        y: f64,
+        z: usize,
+        width: f64,
+        height: f64,
+        map: &mut HashMap<usize, ComputedWidget<'a>>,
+    ) {
+        let (width, height) = self.size.unwrap_or((width, height));
+        let mut children = HashMap::new();
+        self.child.borrow().compute(
+            x + self.offset.0,
+            y + self.offset.1,
+            z + OVERLAY_Z,
+            width,
+            height,
+            &mut children,
+        );
+        for (id, mut computed) in children {
+            computed.overlay = true;
+            map.insert(id, computed);
+        }
+    }
+
+    fn dispatch(
+        &self,
+        event: Event,
+        prev_state_change: bool,
+        map: &HashMap<usize, ComputedWidget>,
+    ) -> (Option<Event>, bool) {
+        dispatch_to(&self.child, event, prev_state_change, map)
+    }
+
+    fn children(&self) -> Vec<Rc<RefCell<dyn Widget<'a> + 'a>>> {
+        vec![self.child.clone()]
+    }
+
+    fn get_id(&self) -> usize {
+        self.id
+    }
+}
+
+impl<'a> Widget<'a> for DialogHost<'a> {
+    fn compute(
+        &self,
+        x: f64,
+        y: f64,
+        z: usize,
+        width: f64,
+        height: f64,
+        map: &mut HashMap<usize, ComputedWidget<'a>>,
+    ) {
+        self.child.borrow().compute(x, y, z, width, height, map);
+        let dialog = match self.handle.dialog.borrow().clone() {
+            Some(dialog) => dialog,
+            None => return,
+        };
+        // above anything the child put into an overlay
+        let z = z + 2 * OVERLAY_Z;
+        map.insert(
+            self.barrier_id,
+            ComputedWidget {
+                x,
+                y,
+                z,
+                width,
+                height,
+                render: Some(RenderObject::Rectangle {
+                    style: Style {
+                        color: Some(self.barrier_color),
+                        border_radius: BorderRadius::default(),
+                        image: None,
+                    },
+                }),
+                clip: None,
+                text_bounds: None,
+                overlay: true,
+            },
+        );
+        let (w, h) = dialog.borrow().measure(width, height);
+        let (w, h) = (w.min(width), h.min(height));
+        let bounds = (x + (width - w) / 2.0, y + (height - h) / 2.0, w, h);
+        self.content_bounds.set(bounds);
+        let mut children = HashMap::new();
+        dialog
+            .borrow()
+            .compute(bounds.0, bounds.1, z + 1, w, h, &mut children);
+        for (id, mut computed) in children {
+            computed.overlay = true;
+            map.insert(id, computed);
+        }
+    }
+
+    fn measure(&self, width: f64, height: f64) -> (f64, f64) {
+        self.child.borrow().measure(width, height)
+    }
+
+    fn dispatch(
+        &self,
+        event: Event,
+        prev_state_change: bool,
+        map: &HashMap<usize, ComputedWidget>,
+    ) -> (Option<Event>, bool) {
+        // cloned out so the dialog can dismiss itself while handling the event
+        let dialog = match self.handle.dialog.borrow().clone() {
+            Some(dialog) => dialog,
+            None => return dispatch_to(&self.child, event, prev_state_change, map),
+        };
+        // the dialog may have been opened since the last layout
+        if !map.contains_key(&self.barrier_id) {
+            return (None, prev_state_change);
+        }
+        if let Event::Tick { .. } = event {
+            // animations behind the barrier keep running
+            return match dispatch_to(&dialog, event, prev_state_change, map) {
+                (Some(event), state_change) => dispatch_to(&self.child, event, state_change, map),
+                result => result,
+            };
+        }
+        let (x, y, w, h) = self.content_bounds.get();
+        let outside_click = match event {
+            Event::MouseDown { x: mx, y: my, .. } => {
+                !(mx >= x && my >= y && mx < x + w && my < y + h)
+            }
+            _ => false,
+        };
+        let (_, mut state_change) = dispatch_to(&dialog, event, prev_state_change, map);
+        if outside_click && self.barrier_dismissible {
+            self.handle.dismiss();
+            state_change = true;
+        }
+        // the barrier takes whatever the dialog didn't
+        (None, state_change)
+    }
+
+    fn children(&self) -> Vec<Rc<RefCell<dyn Widget<'a> + 'a>>> {
+        let mut children = vec![self.child.clone()];
+        children.extend(self.handle.dialog.borrow().clone());
+        children
+    }
+
+    fn get_id(&self) -> usize {
+        self.id
+    }
+}
+
+impl<'a> Widget<'a> for ToastHost<'a> {
+    fn compute(
+        &self,
+        x: f64,
+        y: f64,
+        z: usize,
+        width: f64,
+        height: f64,
+        map: &mut HashMap<usize, ComputedWidget<'a>>,
+    ) {
+        self.child.borrow().compute(x, y, z, width, height, map);
+        let message = match self.queue.current() {
+            Some(message) => message,
+            None => return,
+        };
+        let style = self.style();
+        let text_bounds = measure_text(message, &style);
+        let w = (text_bounds.0 + 2.0 * TOAST_PADDING).min(width);
+        let h = text_bounds.1 + 2.0 * TOAST_PADDING;
+        let (toast_x, toast_y) = (x + (width - w) / 2.0, y + height - h - TOAST_MARGIN);
+        let z = z + 3 * OVERLAY_Z;
+        let mut background = RenderObject::Rectangle {
+            style: Style {
+                color: Some(self.color),
+                border_radius: BorderRadius::uniform(h / 2.0),
+                image: None,
+            },
+        };
+        let mut text = RenderObject::Text {
+            text: Cow::Borrowed(message),
+            style,
+        };
+        let opacity = self.queue.opacity();
+        background.fade(opacity);
+        text.fade(opacity);
+        map.insert(
+            self.background_id,
+            ComputedWidget {
+                x: toast_x,
+                y: toast_y,
+                z,
+                width: w,
+                height: h,
+                render: Some(background),
+                clip: None,
+                text_bounds: None,
+                overlay: true,
+            },
+        );
+        map.insert(
+            self.text_id,
+            ComputedWidget {
+                x: toast_x + TOAST_PADDING,
+                y: toast_y + TOAST_PADDING,
+                z: z + 1,
+                width: (w - 2.0 * TOAST_PADDING).max(0.0),
+                height: text_bounds.1,
+                render: Some(text),
+                clip: None,
+                text_bounds: Some(text_bounds),
+                overlay: true,
+            },
+        );
+    }
+
+    fn measure(&self, width: f64, height: f64) -> (f64, f64) {
+        self.child.borrow().measure(width, height)
+    }
+
+    fn dispatch(
+        &self,
+        event: Event,
+        prev_state_change: bool,
+        map: &HashMap<usize, ComputedWidget>,
+    ) -> (Option<Event>, bool) {
+        let mut state_change = prev_state_change;
+        if let Event::Tick { time } = event {
+            // a toast that just ended needs one more layout to disappear
+            let was_showing = self.queue.current().is_some();
+            state_change |= self.queue.tick(time) || was_showing;
+        }
+        dispatch_to(&self.child, event, state_change, map)
+    }
+
+    fn children(&self) -> Vec<Rc<RefCell<dyn Widget<'a> + 'a>>> {
+        vec![self.child.clone()]
+    }
+
+    fn get_id(&self) -> usize {
+        self.id
+    }
+}
+
+impl<'a> Widget<'a> for Accelerators<'a> {
+    fn compute(
+        &self,
+        x: f64,
+        y: f64,
+        z: usize,
+        width: f64,
+        height: f64,
+        map: &mut HashMap<usize, ComputedWidget<'a>>,
+    ) {
+        self.child.borrow().compute(x, y, z, width, height, map);
+    }
+
+    fn measure(&self, width: f64, height: f64) -> (f64, f64) {
+        self.child.borrow().measure(width, height)
+    }
+
+    fn flex(&self) -> Option<usize> {
+        self.child.borrow().flex()
+    }
+
+    fn dispatch(
+        &self,
+        event: Event,
+        prev_state_change: bool,
+        map: &HashMap<usize, ComputedWidget>,
+    ) -> (Option<Event>, bool) {
+        if let Event::KeyDown { key, modifiers } = event {
+            let binding = self
+                .bindings
+                .iter()
+                .find(|(k, m, _)| *k == key && *m == modifiers);
+            if let Some((_, _, callback)) = binding {
+                return (None, prev_state_change | callback());
+            }
+        }
+        dispatch_to(&self.child, event, prev_state_change, map)
+    }
+
+    fn children(&self) -> Vec<Rc<RefCell<dyn Widget<'a> + 'a>>> {
+        vec![self.child.clone()]
+    }
+
+    fn get_id(&self) -> usize {
+        self.id
+    }
+}
+
+impl<'a> Widget<'a> for Visibility<'a> {
+    fn compute(
+        &self,
+        x: f64,
+        y: f64,
+        z: usize,
+        width: f64,
+        height: f64,
+        map: &mut HashMap<usize, ComputedWidget<'a>>,
+    ) {
+        if self.visible {
+            self.child.borrow().compute(x, y, z, width, height, map);
+        } else if self.maintain_space {
+            // hidden widgets keep their layout but have nothing to render
+            let mut children = HashMap::new();
+            self.child
+                .borrow()
+                .compute(x, y, z, width, height, &mut children);
+            for (id, mut computed) in children {
+                computed.render = None;
+                map.insert(id, computed);
+            }
+        }
+    }
+
+    fn measure(&self, width: f64, height: f64) -> (f64, f64) {
+        if self.visible || self.maintain_space {
+            self.child.borrow().measure(width, height)
+        } else {
+            (0.0, 0.0)
+        }
+    }
+
+    // a collapsed child takes no share of the leftover space, otherwise its own flex counts
+    fn flex(&self) -> Option<usize> {
+        if self.visible || self.maintain_space {
+            self.child.borrow().flex()
+        } else {
+            Some(0)
+        }
+    }
+
+    fn dispatch(
+        &self,
+        event: Event,
+        prev_state_change: bool,
+        map: &HashMap<usize, ComputedWidget>,
+    ) -> (Option<Event>, bool) {
+        if self.visible {
+            dispatch_to(&self.child, event, prev_state_change, map)
+        } else {
+            (Some(event), prev_state_change)
+        }
+    }
+
+    fn children(&self) -> Vec<Rc<RefCell<dyn Widget<'a> + 'a>>> {
+        if self.visible {
+            vec![self.child.clone()]
+        } else {
+            Vec::new()
+        }
+    }
+
+    fn get_id(&self) -> usize {
+        self.id
+    }
+}
+
+impl<'a, T: 'a> Widget<'a> for Reactive<'a, T> {
+    fn compute(
+        &self,
+        x: f64,
+        y: f64,
+        z: usize,
+        width: f64,
+        height: f64,
+        map: &mut HashMap<usize, ComputedWidget<'a>>,
+    ) {
+        // the state may have changed outside of dispatch
+        self.rebuild_if_dirty();
+        self.child().borrow().compute(x, y, z, width, height, map);
+    }
+
+    fn measure(&self, width: f64, height: f64) -> (f64, f64) {
+        self.child().borrow().measure(width, height)
+    }
+
+    fn dispatch(
+        &self,
+        event: Event,
+        prev_state_change: bool,
+        map: &HashMap<usize, ComputedWidget>,
+    ) -> (Option<Event>, bool) {
+        let (rest, state_change) = dispatch_to(&self.child(), event, prev_state_change, map);
+        let rebuilt = self.rebuild_if_dirty();
+        (rest, state_change || rebuilt)
+    }
+
+    fn children(&self) -> Vec<Rc<RefCell<dyn Widget<'a> + 'a>>> {
+        vec![self.child()]
+    }
+
+    fn get_id(&self) -> usize {
+        self.id
+    }
+}
+
+impl<'a> Widget<'a> for IndexedStack<'a> {
+    fn compute(
+        &self,
+        x: f64,
+        y: f64,
+        z: usize,
+        width: f64,
+        height: f64,
+        map: &mut HashMap<usize, ComputedWidget<'a>>,
+    ) {
+        let index = self.index();
+        for (i, child) in self.children.iter().enumerate() {
+            if i == index {
+                child.borrow().compute(x, y, z, width, height, map);
+            } else if self.compute_all {
+                let mut hidden = HashMap::new();
+                child.borrow().compute(x, y, z, width, height, &mut hidden);
+                for (id, mut computed) in hidden {
+                    computed.render = None;
+                    map.insert(id, computed);
+                }
+            }
+        }
+    }
+
+    fn measure(&self, width: f64, height: f64) -> (f64, f64) {
+        if self.compute_all {
+            self.children
+                .iter()
+                .map(|c| c.borrow().measure(width, height))
+                .fold((0.0, 0.0), |(w, h), (cw, ch)| {
+                    (f64::max(w, cw), f64::max(h, ch))
+                })
+        } else {
+            self.active()
+                .map(|c| c.borrow().measure(width, height))
+                .unwrap_or((0.0, 0.0))
+        }
+    }
+
+    fn dispatch(
+        &self,
+        event: Event,
+        prev_state_change: bool,
+        map: &HashMap<usize, ComputedWidget>,
+    ) -> (Option<Event>, bool) {
+        dispatch_children(
+            self.active().into_iter().map(|child| child.borrow()),
+            event,
+            prev_state_change,
+            map,
+        )
+    }
+
+    fn children(&self) -> Vec<Rc<RefCell<dyn Widget<'a> + 'a>>> {
+        self.active().into_iter().cloned().collect()
+    }
+
+    fn get_id(&self) -> usize {
+        self.id
+    }
+}
+
+impl<'a> Widget<'a> for ListView<'a> {
+    fn compute(
+        &self,
+        x: f64,
+        y: f64,
+        z: usize,
+        width: f64,
+        height: f64,
+        map: &mut HashMap<usize, ComputedWidget<'a>>,
+    ) {
+        if self.item_height <= 0.0 {
+            self.visible.set((0, 0));
+            return;
+        }
+        let offset = self.scroll_offset();
+        let first = ((offset / self.item_height).floor() as usize).saturating_sub(LIST_VIEW_BUFFER);
+        let last = (((offset + height) / self.item_height).ceil() as usize + LIST_VIEW_BUFFER)
+            .min(self.item_count);
+        let first = first.min(last);
+        self.visible.set((first, last));
+        for index in first..last {
+            let item_y = y + index as f64 * self.item_height - offset;
+            let mut computed = HashMap::new();
+            self.item(index)
+                .borrow()
+                .compute(x, item_y, z, width, self.item_height, &mut computed);
+            for (id, mut widget) in computed {
+                widget.clip_to(x, y, width, height);
+                map.insert(id, widget);
+            }
+        }
+    }
+
+    fn measure(&self, _width: f64, _height: f64) -> (f64, f64) {
+        (0.0, self.item_count as f64 * self.item_height)
+    }
+
+    fn dispatch(
+        &self,
+        event: Event,
+        prev_state_change: bool,
+        map: &HashMap<usize, ComputedWidget>,
+    ) -> (Option<Event>, bool) {
+        let items = self.children();
+        dispatch_children(
+            items.iter().map(|item| item.borrow()),
+            event,
+            prev_state_change,
+            map,
+        )
+    }
+
+    // only the items that were laid out last
+    fn children(&self) -> Vec<Rc<RefCell<dyn Widget<'a> + 'a>>> {
+        let (first, last) = self.visible.get();
+        (first..last).map(|index| self.item(index)).collect()
+    }
+
+    fn get_id(&self) -> usize {
+        self.id
+    }
+}
+
+impl<'a> Widget<'a> for ScrollView<'a> {
+    fn compute(
+        &self,
+        x: f64,
+        y: f64,
+        z: usize,
+        width: f64,
+        height: f64,
+        map: &mut HashMap<usize, ComputedWidget<'a>>,
+    ) {
+        let content = self
+            .child
+            .borrow()
+            .desired_size((width, height))
+            .1
+            .max(height);
+        self.extent.set((height, content));
+        if self.coast.get().is_none() {
+            self.set_scroll_offset(self.offset.get());
+        }
+        let offset = self.offset.get();
+        let mut children = HashMap::new();
+        self.child
+            .borrow()
+            .compute(x, y - offset, z, width, content, &mut children);
+        let top = children.values().map(|c| c.z).max().unwrap_or(z) + 1;
+        for (id, mut computed) in children {
+            computed.clip_to(x, y, width, height);
+            map.insert(id, computed);
+        }
+        map.insert(
+            self.id,
+            ComputedWidget {
+                x,
+                y,
+                z,
+                width,
+                height,
+                render: None,
+                clip: None,
+                text_bounds: None,
+                overlay: false,
+            },
+        );
+        let (thumb_y, thumb_height) = match scroll_thumb(height, content, offset) {
+            Some(thumb) => thumb,
+            None => return,
+        };
+        let bar = |y, z, height, color| ComputedWidget {
+            x: x + width - SCROLLBAR_WIDTH,
+            y,
+            z,
+            width: SCROLLBAR_WIDTH,
+            height,
+            render: Some(RenderObject::Rectangle {
+                style: Style {
+                    color: Some(color),
+                    border_radius: BorderRadius::uniform(SCROLLBAR_WIDTH / 2.0),
+                    image: None,
+                },
+            }),
+            clip: None,
+            text_bounds: None,
+            overlay: false,
+        };
+        map.insert(self.track_id, bar(y, top, height, self.track_color));
+        map.insert(
+            self.thumb_id,
+            bar(y + thumb_y, top + 1, thumb_height, self.thumb_color),
+        );
+    }
+
+    fn measure(&self, width: f64, height: f64) -> (f64, f64) {
+        self.child.borrow().measure(width, height)
+    }
+
+    fn dispatch(
+        &self,
+        event: Event,
+        prev_state_change: bool,
+        map: &HashMap<usize, ComputedWidget>,
+    ) -> (Option<Event>, bool) {
+        let computed = match map.get(&self.id) {
+            Some(computed) => computed,
+            None => return (Some(event), prev_state_change),
+        };
+        let (viewport, content) = self.extent.get();
+        let thumb = scroll_thumb(viewport, content, self.offset.get());
+        let on_bar = |x: f64, y: f64| {
+            thumb.is_some()
+                && computed.in_hitbox(x, y, BorderRadius::default())
+                && x >= computed.x + computed.width - SCROLLBAR_WIDTH
+        };
+        match event {
+            Event::Scroll { x, y, dy, .. } if computed.in_hitbox(x, y, BorderRadius::default()) => {
+                // nested views get the wheel first, while they can still move
+                let (rest, state_change) = dispatch_to(&self.child, event, prev_state_change, map);
+                match rest {
+                    Some(_) if self.scroll_to(self.offset.get() + dy) => {
+                        let (distance, streak) = self.pending.get();
+                        self.pending.set((distance + dy, streak));
+                        (None, true)
+                    }
+                    rest => (rest, state_change),
+                }
+            }
+            Event::MouseDown { x, y, .. } if on_bar(x, y) => {
+                let (thumb_y, thumb_height) = thumb.unwrap();
+                let grab = y - computed.y - thumb_y;
+                if grab >= 0.0 && grab < thumb_height {
+                    self.grab.set(Some(grab));
+                    (None, prev_state_change)
+                } else {
+                    // a click on the track moves a page towards it
+                    let page = if grab < 0.0 { -viewport } else { viewport };
+                    let moved = self.scroll_to(self.offset.get() + page);
+                    (None, prev_state_change | moved)
+                }
+            }
+            Event::MouseMove { y, .. } if self.grab.get().is_some() => {
+                let (_, thumb_height) = thumb.unwrap_or((0.0, viewport));
+                let travel = viewport - thumb_height;
+                let thumb_y = y - computed.y - self.grab.get().unwrap();
+                let moved = travel > 0.0 && self.scroll_to(thumb_y * self.max_offset() / travel);
+                dispatch_to(&self.child, event, prev_state_change | moved, map)
+            }
+            Event::MouseUp { .. } if self.grab.get().is_some() => {
+                self.grab.set(None);
+                (None, prev_state_change)
+            }
+            Event::Tick { time } => {
+                let moved = self.tick(time);
+                dispatch_to(&self.child, event, prev_state_change | moved, map)
+            }
+            // pressing anywhere in the view catches a coasting flick
+            Event::MouseDown { x, y, .. }
+                if self.coast.get().is_some()
+                    && computed.in_hitbox(x, y, BorderRadius::default()) =>
+            {
+                self.set_scroll_offset(self.offset.get());
+                dispatch_to(&self.child, event, true, map)
+            }
+            _ => dispatch_to(&self.child, event, prev_state_change, map),
+        }
+    }
+
+    fn children(&self) -> Vec<Rc<RefCell<dyn Widget<'a> + 'a>>> {
+        vec![self.child.clone()]
+    }
+
+    fn get_id(&self) -> usize {
+        self.id
+    }
+}
+
+// hands an event to the children in order until one consumes it. mouse moves go to every
+// child anyway, the cursor can leave one child and enter another in the same move
+fn dispatch_children<'a, C: Deref<Target = dyn Widget<'a> + 'a>>(
+    children: impl Iterator<Item = C>,
+    event: Event,
+    prev_state_change: bool,
+    map: &HashMap<usize, ComputedWidget>,
+) -> (Option<Event>, bool) {
+    let mut state_change = prev_state_change;
+    if let Event::MouseMove {
+        prev_x,
+        prev_y,
+        x,
+        y,
+    } = event
+    {
+        let mut consumed = false;
+        for child in children {
+            let event = Event::MouseMove {
+                prev_x,
+                prev_y,
+                x,
+                y,
+            };
+            let (rest, child_state_change) = dispatch_widget(&*child, event, state_change, map);
+            consumed |= rest.is_none();
+            state_change = child_state_change;
+        }
+        let rest = Event::MouseMove {
+            prev_x,
+            prev_y,
+            x,
+            y,
+        };
+        return (if consumed { None } else { Some(rest) }, state_change);
+    }
+    let mut e = Some(event);
+    for child in children {
+        if let Some(ev) = e {
+            let r = dispatch_widget(&*child, ev, state_change, map);
+            e = r.0;
+            state_change = r.1;
+        } else {
+            break;
+        }
+    }
+    (e, state_change)
+}
+
+impl<'a> Widget<'a> for Row<'a> {
+    fn compute(
+        &self,
+        x: f64,
+        y: f64,
+        z: usize,
+        width: f64,
+        height: f64,
+        map: &mut HashMap<usize, ComputedWidget<'a>>,
+    ) {
+        if self.children.is_empty() {
+            return;
+        }
+        let widths = flex_extents(&self.children, &self.flex, width, |c| {
+            c.measure(width, height).0
+        });
+        let free = width - widths.iter().sum::<f64>();
+        let (mut offset, gap) = spacing_gaps(self.spacing, widths.len(), free);
+        let rtl = layout_direction() == TextDirection::Rtl;
+        for (child, w) in self.children.iter().zip(widths) {
+            // in rtl the first child sits at the right edge
+            let child_x = if rtl {
+                x + width - offset - w
+            } else {
+                x + offset
+            };
+            child.borrow().compute(child_x, y, z, w, height, map);
+            offset += w + gap;
+        }
+    }
+
+    fn measure(&self, width: f64, height: f64) -> (f64, f64) {
+        self.children
+            .iter()
+            .map(|c| c.borrow().measure(width, height))
+            .fold((0.0, 0.0), |(w, h), (cw, ch)| (w + cw, f64::max(h, ch)))
+    }
+
+    fn dispatch(
+        &self,
+        event: Event,
+        prev_state_change: bool,
+        map: &HashMap<usize, ComputedWidget>,
+    ) -> (Option<Event>, bool) {
+        dispatch_children(
+            self.children.iter().map(Child::borrow),
+            event,
+            prev_state_change,
+            map,
+        )
+    }
+
+    fn children(&self) -> Vec<Rc<RefCell<dyn Widget<'a> + 'a>>> {
+        self.children.iter().filter_map(Child::shared).collect()
+    }
+
+    fn get_id(&self) -> usize {
+        self.id
+    }
+}
+
+impl<'a> Widget<'a> for Stack<'a> {
+    fn compute(
+        &self,
+        x: f64,
+        y: f64,
+        z: usize,
+        width: f64,
+        height: f64,
+        map: &mut HashMap<usize, ComputedWidget<'a>>,
+    ) {
+        for (i, c) in self.children.iter().enumerate() {
+            let c = c.borrow();
+            match c.position() {
+                Some(((ox, oy), size)) => {
+                    let (w, h) =
+                        size.unwrap_or((f64::max(width - ox, 0.0), f64::max(height - oy, 0.0)));
+                    c.compute(x + ox, y + oy, z + i, w, h, map);
+                }
+                None => c.compute(x, y, z + i, width, height, map),
+            }
+        }
+    }
+
+    fn measure(&self, width: f64, height: f64) -> (f64, f64) {
+        self.children
+            .iter()
+            .map(|c| {
+                let c = c.borrow();
+                match c.position() {
+                    Some(((ox, oy), Some((w, h)))) => (ox + w, oy + h),
+                    Some(((ox, oy), None)) => {
+                        let (w, h) =
+                            c.measure(f64::max(width - ox, 0.0), f64::max(height - oy, 0.0));
+                        (ox + w, oy + h)
+                    }
+                    None => c.measure(width, height),
+                }
+            })
+            .fold((0.0, 0.0), |(w, h), (cw, ch)| {
+                (f64::max(w, cw), f64::max(h, ch))
+            })
+    }
+
+    fn dispatch(
+        &self,
+        event: Event,
+        prev_state_change: bool,
+        map: &HashMap<usize, ComputedWidget>,
+    ) -> (Option<Event>, bool) {
+        // the front-most child, which is rendered on top, gets the event first
+        dispatch_children(
+            self.children.iter().rev().map(Child::borrow),
+            event,
+            prev_state_change,
+            map,
+        )
+    }
+
+    fn children(&self) -> Vec<Rc<RefCell<dyn Widget<'a> + 'a>>> {
+        self.children.iter().filter_map(Child::shared).collect()
+    }
+
+    fn get_id(&self) -> usize {
+        self.id
+    }
+}
+
+impl<'a> Widget<'a> for Column<'a> {
+    fn compute(
+        &self,
+        x: f64,
+        y: f64,
+        z: usize,
+        width: f64,
+        height: f64,
+        map: &mut HashMap<usize, ComputedWidget<'a>>,
+    ) {
+        if self.children.is_empty() {
+            return;
+        }
+        let heights = flex_extents(&self.children, &self.flex, height, |c| {
+            c.measure(width, height).1
+        });
+        let free = height - heights.iter().sum::<f64>();
+        let (mut offset, gap) = spacing_gaps(self.spacing, heights.len(), free);
+        for (child, h) in self.children.iter().zip(heights) {
+            child.borrow().compute(x, y + offset, z, width, h, map);
+            offset += h + gap;
+        }
+    }
+
+    fn measure(&self, width: f64, height: f64) -> (f64, f64) {
+        self.children
+            .iter()
+            .map(|c| c.borrow().measure(width, height))
+            .fold((0.0, 0.0), |(w, h), (cw, ch)| (f64::max(w, cw), h + ch))
+    }
+
+    // children are stacked, so each gets the full width and the column grows downwards
+    fn desired_size(&self, available: (f64, f64)) -> (f64, f64) {
+        self.children
+            .iter()
+            .map(|c| c.borrow().desired_size(available))
+            .fold((0.0, 0.0), |(w, h), (cw, ch)| (f64::max(w, cw), h + ch))
+    }
+
+    fn dispatch(
+        &self,
+        event: Event,
+        prev_state_change: bool,
+        map: &HashMap<usize, ComputedWidget>,
+    ) -> (Option<Event>, bool) {
+        dispatch_children(
+            self.children.iter().map(Child::borrow),
+            event,
+            prev_state_change,
+            map,
+        )
+    }
+
+    fn children(&self) -> Vec<Rc<RefCell<dyn Widget<'a> + 'a>>> {
+        self.children.iter().filter_map(Child::shared).collect()
+    }
+
+    fn get_id(&self) -> usize {
+        self.id
+    }
+}
+
+impl<'a> IntoWidget<'a> for TextBuilder<'a> {
+    fn into_widget(self) -> Rc<RefCell<dyn Widget<'a> + 'a>> {
+        self.build()
+    }
+}
+
+impl<'a> IntoWidget<'a> for SelectableTextBuilder<'a> {
+    fn into_widget(self) -> Rc<RefCell<dyn Widget<'a> + 'a>> {
+        self.build()
+    }
+}
+
+impl<'a> IntoWidget<'a> for TextInputBuilder<'a> {
+    fn into_widget(self) -> Rc<RefCell<dyn Widget<'a> + 'a>> {
+        self.build()
+    }
+}
+
+impl<'a> IntoWidget<'a> for RichTextBuilder<'a> {
+    fn into_widget(self) -> Rc<RefCell<dyn Widget<'a> + 'a>> {
+        self.build()
+    }
+}
+
+impl<'a> IntoWidget<'a> for OverlayBuilder<'a> {
+    fn into_widget(self) -> Rc<RefCell<dyn Widget<'a> + 'a>> {
+        self.build()
+    }
+}
+
+impl<'a> IntoWidget<'a> for DropdownBuilder<'a> {
+    fn into_widget(self) -> Rc<RefCell<dyn Widget<'a> + 'a>> {
+        self.build()
+    }
+}
+
+impl<'a> IntoWidget<'a> for DialogHostBuilder<'a> {
+    fn into_widget(self) -> Rc<RefCell<dyn Widget<'a> + 'a>> {
+        self.build()
+    }
+}
+
+impl<'a> IntoWidget<'a> for ToastHostBuilder<'a> {
+    fn into_widget(self) -> Rc<RefCell<dyn Widget<'a> + 'a>> {
+        self.build()
+    }
+}
+
+impl<'a> IntoWidget<'a> for DirectionalityBuilder<'a> {
+    fn into_widget(self) -> Rc<RefCell<dyn Widget<'a> + 'a>> {
+        self.build()
+    }
+}
+
+impl<'a> IntoWidget<'a> for FormBuilder<'a> {
+    fn into_widget(self) -> Rc<RefCell<dyn Widget<'a> + 'a>> {
+        self.build()
+    }
+}
+
+impl<'a> IntoWidget<'a> for AcceleratorsBuilder<'a> {
+    fn into_widget(self) -> Rc<RefCell<dyn Widget<'a> + 'a>> {
+        self.build()
+    }
+}
+
+impl<'a> IntoWidget<'a> for RectangleBuilder {
+    fn into_widget(self) -> Rc<RefCell<dyn Widget<'a> + 'a>> {
+        self.build()
+    }
+}
+
+impl<'a> IntoWidget<'a> for MouseGestureBuilder<'a> {
+    fn into_widget(self) -> Rc<RefCell<dyn Widget<'a> + 'a>> {
+        self.build()
+    }
+}
+
+impl<'a> IntoWidget<'a> for PaddingBuilder<'a> {
+    fn into_widget(self) -> Rc<RefCell<dyn Widget<'a> + 'a>> {
+        self.build()
+    }
+}
+
+impl<'a> IntoWidget<'a> for RowBuilder<'a> {
+    fn into_widget(self) -> Rc<RefCell<dyn Widget<'a> + 'a>> {
+        self.build()
+    }
+}
+
+impl<'a> IntoWidget<'a> for ColumnBuilder<'a> {
+    fn into_widget(self) -> Rc<RefCell<dyn Widget<'a> + 'a>> {
+        self.build()
+    }
+}
+
+impl<'a> IntoWidget<'a> for StackBuilder<'a> {
+    fn into_widget(self) -> Rc<RefCell<dyn Widget<'a> + 'a>> {
+        self.build()
+    }
+}
+
+impl<'a> IntoWidget<'a> for ConstrainedBoxBuilder<'a> {
+    fn into_widget(self) -> Rc<RefCell<dyn Widget<'a> + 'a>> {
+        self.build()
+    }
+}
+
+impl<'a> IntoWidget<'a> for FractionallySizedBoxBuilder<'a> {
+    fn into_widget(self) -> Rc<RefCell<dyn Widget<'a> + 'a>> {
+        self.build()
+    }
+}
+
+impl<'a> IntoWidget<'a> for ExpandedBuilder<'a> {
+    fn into_widget(self) -> Rc<RefCell<dyn Widget<'a> + 'a>> {
+        self.build()
+    }
+}
+
+impl<'a> IntoWidget<'a> for PositionedBuilder<'a> {
+    fn into_widget(self) -> Rc<RefCell<dyn Widget<'a> + 'a>> {
+        self.build()
+    }
+}
+
+impl<'a> IntoWidget<'a> for ClipBuilder<'a> {
+    fn into_widget(self) -> Rc<RefCell<dyn Widget<'a> + 'a>> {
+        self.build()
+    }
+}
+
+impl<'a> IntoWidget<'a> for IgnorePointerBuilder<'a> {
+    fn into_widget(self) -> Rc<RefCell<dyn Widget<'a> + 'a>> {
+        self.build()
+    }
+}
+
+impl<'a> IntoWidget<'a> for EventListenerBuilder<'a> {
+    fn into_widget(self) -> Rc<RefCell<dyn Widget<'a> + 'a>> {
+        self.build()
+    }
+}
+
+impl<'a> IntoWidget<'a> for CanvasBuilder<'a> {
+    fn into_widget(self) -> Rc<RefCell<dyn Widget<'a> + 'a>> {
+        self.build()
+    }
+}
+
+impl<'a> IntoWidget<'a> for NinePatchBuilder {
+    fn into_widget(self) -> Rc<RefCell<dyn Widget<'a> + 'a>> {
+        self.build()
+    }
+}
+
+impl<'a> IntoWidget<'a> for PolylineBuilder {
+    fn into_widget(self) -> Rc<RefCell<dyn Widget<'a> + 'a>> {
+        self.build()
+    }
+}
+
+impl<'a> IntoWidget<'a> for EllipseBuilder {
+    fn into_widget(self) -> Rc<RefCell<dyn Widget<'a> + 'a>> {
+        self.build()
+    }
+}
+
+impl<'a> IntoWidget<'a> for VisibilityBuilder<'a> {
+    fn into_widget(self) -> Rc<RefCell<dyn Widget<'a> + 'a>> {
+        self.build()
+    }
+}
+
+impl<'a, T: 'a> IntoWidget<'a> for ReactiveBuilder<'a, T> {
+    fn into_widget(self) -> Rc<RefCell<dyn Widget<'a> + 'a>> {
+        self.build()
+    }
+}
+
+impl<'a> IntoWidget<'a> for IndexedStackBuilder<'a> {
+    fn into_widget(self) -> Rc<RefCell<dyn Widget<'a> + 'a>> {
+        self.build()
+    }
+}
+
+impl<'a> IntoWidget<'a> for ListViewBuilder<'a> {
+    fn into_widget(self) -> Rc<RefCell<dyn Widget<'a> + 'a>> {
+        self.build()
+    }
+}
+
+impl<'a> IntoWidget<'a> for ScrollViewBuilder<'a> {
+    fn into_widget(self) -> Rc<RefCell<dyn Widget<'a> + 'a>> {
+        self.build()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::color;
+    use crate::MemoryClipboard;
+
+    const FONT: &str = "Raleway-Regular.ttf";
+
+    fn key_down(key: Key, modifiers: Modifiers) -> Event {
+        Event::KeyDown { key, modifiers }
+    }
+
+    fn mouse_move(from: (f64, f64), to: (f64, f64)) -> Event {
+        Event::MouseMove {
+            prev_x: from.0,
+            prev_y: from.1,
+            x: to.0,
+            y: to.1,
+        }
+    }
+
+    #[test]
+    fn paste_inserts_at_cursor() {
+        let clipboard = MemoryClipboard::new();
+        clipboard.set("brave new ");
+        let input = TextInput::new(16, FONT)
+            .text("hello world")
+            .clipboard(clipboard)
+            .focused(true)
+            .build();
+        input.borrow().set_cursor(6);
+        let tree: Rc<RefCell<dyn Widget>> = input.clone();
+        let map = crate::compute(&tree, 200.0, 40.0);
+        let (rest, changed) =
+            tree.borrow()
+                .dispatch(key_down(Key::Char('v'), Modifiers::CTRL), false, &map);
+        assert!(rest.is_none());
+        assert!(changed);
+        assert_eq!(input.borrow().value(), "hello brave new world");
+        assert_eq!(input.borrow().cursor(), 16);
+    }
+
+    #[test]
+    fn caret_x_follows_glyph_advances() {
+        let input = TextInput::new(16, FONT).text("hello").build();
+        let input = input.borrow();
+        // the advances of "hel" on their own, the caret sits right after them
+        let (_, prefix) = *grapheme_offsets("hel", &input.style()).last().unwrap();
+        assert!(prefix > 0.0);
+        assert_eq!(input.caret_x(0), 0.0);
+        assert_eq!(input.caret_x(3), prefix);
+        assert!(input.caret_x(3) < input.caret_x(5));
+        assert_eq!(input.index_at(input.caret_x(3) + 0.1), 3);
+    }
+
+    #[test]
+    fn shift_arrows_extend_the_selection() {
+        let input = TextInput::new(16, FONT).text("hello").focused(true).build();
+        let tree: Rc<RefCell<dyn Widget>> = input.clone();
+        let map = crate::compute(&tree, 200.0, 40.0);
+        for _ in 0..2 {
+            tree.borrow()
+                .dispatch(key_down(Key::Left, Modifiers::SHIFT), false, &map);
+        }
+        assert_eq!(input.borrow().selected_text(), "lo");
+        tree.borrow()
+            .dispatch(key_down(Key::Left, Modifiers::NONE), false, &map);
+        assert_eq!(input.borrow().selection(), None);
+        assert_eq!(input.borrow().cursor(), 3);
+    }
+
+    #[test]
+    fn typed_characters_go_to_the_focused_input() {
+        let input = TextInput::new(16, FONT).text("ab").build();
+        let tree: Rc<RefCell<dyn Widget>> = input.clone();
+        let map = crate::compute(&tree, 200.0, 40.0);
+        let typed = |codepoint| Event::Char { codepoint };
+        let (rest, _) = tree.borrow().dispatch(typed('x'), false, &map);
+        assert!(rest.is_some());
+        assert_eq!(input.borrow().value(), "ab");
+        input.borrow().set_focused(true);
+        input.borrow().set_cursor(1);
+        for codepoint in ['\u{e4}', '\u{4e2d}'] {
+            let (rest, changed) = tree.borrow().dispatch(typed(codepoint), false, &map);
+            assert!(rest.is_none() && changed);
+        }
+        assert_eq!(input.borrow().value(), "a\u{e4}\u{4e2d}b");
+        assert_eq!(input.borrow().cursor(), 6);
+        // control characters are left to the key events
+        let (rest, _) = tree.borrow().dispatch(typed('\u{8}'), false, &map);
+        assert!(rest.is_some());
+    }
+
+    #[test]
+    fn obscured_inputs_draw_one_mask_per_grapheme() {
+        let clipboard = MemoryClipboard::new();
+        let changes = Rc::new(RefCell::new(Vec::new()));
+        let record = changes.clone();
+        let input = TextInput::new(16, FONT)
+            .text("pa\u{308}ss")
+            .obscure('\u{2022}')
+            .clipboard(clipboard.clone())
+            .on_change(move |value| record.borrow_mut().push(String::from(value)))
+            .focused(true)
+            .build();
+        let tree: Rc<RefCell<dyn Widget>> = input.clone();
+        let map = crate::compute(&tree, 200.0, 40.0);
+        tree.borrow()
+            .dispatch(Event::Char { codepoint: '!' }, false, &map);
+        let map = crate::compute(&tree, 200.0, 40.0);
+        let id = input.borrow().get_id();
+        match &map[&id].render {
+            Some(RenderObject::Text { text, .. }) => {
+                assert_eq!(text, "\u{2022}".repeat(5).as_str())
+            }
+            _ => panic!("expected text"),
+        }
+        assert_eq!(input.borrow().value(), "pa\u{308}ss!");
+        assert_eq!(*changes.borrow(), vec!["pa\u{308}ss!"]);
+        // the caret sits behind the mask of the last grapheme
+        let input = input.borrow();
+        assert_eq!(
+            input.index_at(input.caret_x(input.cursor())),
+            input.cursor()
+        );
+        input.select(0, 4);
+        input.copy();
+        assert_eq!(clipboard.get(), None);
+        assert!(!input.cut());
+    }
+
+    #[test]
+    fn numeric_inputs_reject_letters() {
+        let input = TextInput::new(16, FONT)
+            .text("12")
+            .input_type(InputType::Numeric)
+            .focused(true)
+            .build();
+        let tree: Rc<RefCell<dyn Widget>> = input.clone();
+        let map = crate::compute(&tree, 200.0, 40.0);
+        for codepoint in ['a', '3', '.'] {
+            tree.borrow()
+                .dispatch(Event::Char { codepoint }, false, &map);
+        }
+        assert_eq!(input.borrow().value(), "123");
+        // a rejected character typed over a selection keeps it
+        input.borrow().select(0, 1);
+        assert!(!input.borrow().insert("x"));
+        assert_eq!(input.borrow().selected_text(), "1");
+        // pasted text keeps only what is allowed, and deleting always works
+        assert!(input.borrow().insert("4a5"));
+        assert_eq!(input.borrow().value(), "4523");
+        tree.borrow()
+            .dispatch(key_down(Key::Backspace, Modifiers::NONE), false, &map);
+        assert_eq!(input.borrow().value(), "423");
+    }
+
+    #[test]
+    fn max_length_ignores_extra_characters() {
+        let input = TextInput::new(16, FONT)
+            .max_length(3)
+            .filter(|ch| ch != ' ')
+            .focused(true)
+            .build();
+        let tree: Rc<RefCell<dyn Widget>> = input.clone();
+        let map = crate::compute(&tree, 200.0, 40.0);
+        for codepoint in ['a', ' ', 'b', 'c'] {
+            tree.borrow()
+                .dispatch(Event::Char { codepoint }, false, &map);
+        }
+        assert_eq!(input.borrow().value(), "abc");
+        let (rest, changed) = tree
+            .borrow()
+            .dispatch(Event::Char { codepoint: 'd' }, false, &map);
+        assert!(rest.is_none() && !changed);
+        assert_eq!(input.borrow().value(), "abc");
+        // replacing a selection makes room, pastes are cut off at the limit
+        input.borrow().select(1, 3);
+        assert!(input.borrow().insert("e\u{301}fg"));
+        assert_eq!(input.borrow().value(), "ae\u{301}f");
+    }
+
+    #[test]
+    fn forms_report_the_failing_field() {
+        let submits = Rc::new(Cell::new(0));
+        let record = submits.clone();
+        let name = TextInput::new(16, FONT).text("Ada").build();
+        let email = TextInput::new(16, FONT).text("ada").build();
+        let form = Form::new(Column::new().add(name.clone()).add(email.clone()))
+            .field(&name, |value| {
+                if value.is_empty() {
+                    Err(String::from("required"))
+                } else {
+                    Ok(())
+                }
+            })
+            .field(&email, |value| {
+                if value.contains('@') {
+                    Ok(())
+                } else {
+                    Err(String::from("not an email"))
+                }
+            })
+            .on_submit(move || record.set(record.get() + 1))
+            .build();
+        let email_id = email.borrow().get_id();
+        assert_eq!(
+            form.borrow().validate(),
+            Err(vec![FieldError {
+                field: email_id,
+                message: String::from("not an email"),
+            }])
+        );
+        // enter in a field only submits a valid form
+        let tree: Rc<RefCell<dyn Widget>> = form.clone();
+        let enter = || key_down(Key::Enter, Modifiers::NONE);
+        email.borrow().set_focused(true);
+        let map = crate::compute(&tree, 200.0, 80.0);
+        let (rest, _) = tree.borrow().dispatch(enter(), false, &map);
+        assert!(rest.is_none());
+        assert_eq!(submits.get(), 0);
+        // the invalid field is outlined, with its message below it
+        let map = crate::compute(&tree, 200.0, 80.0);
+        let field = &map[&email_id];
+        let texts: Vec<_> = map
+            .values()
+            .filter_map(|computed| match &computed.render {
+                Some(RenderObject::Text { text, .. }) if text == "not an email" => Some(computed.y),
+                _ => None,
+            })
+            .collect();
+        assert_eq!(texts, vec![field.y + field.height]);
+        email.borrow().insert("@example.org");
+        tree.borrow().dispatch(enter(), false, &map);
+        assert_eq!(submits.get(), 1);
+        assert!(form.borrow().errors().is_empty());
+        // without a focused field enter is left to others
+        email.borrow().set_focused(false);
+        let (rest, _) = tree.borrow().dispatch(enter(), false, &map);
+        assert!(rest.is_some());
+        assert_eq!(submits.get(), 1);
+    }
+
+    #[test]
+    fn hover_follows_the_cursor() {
+        let gesture = MouseGesture::new(Rectangle::new(color::RED)).build();
+        let tree: Rc<RefCell<dyn Widget>> = gesture.clone();
+        let map = crate::compute(&tree, 100.0, 100.0);
+        assert!(!gesture.borrow().is_hovered());
+        tree.borrow()
+            .dispatch(mouse_move((150.0, 50.0), (50.0, 50.0)), false, &map);
+        assert!(gesture.borrow().is_hovered());
+        tree.borrow()
+            .dispatch(mouse_move((50.0, 50.0), (150.0, 50.0)), false, &map);
+        assert!(!gesture.borrow().is_hovered());
+    }
+
+    #[test]
+    fn jumping_between_widgets_leaves_and_enters() {
+        let log = Rc::new(RefCell::new(Vec::new()));
+        let gesture = |name: &'static str| {
+            let (enter, leave) = (log.clone(), log.clone());
+            MouseGesture::new(Rectangle::new(color::RED))
+                .on_enter(move || {
+                    enter.borrow_mut().push(format!("{} enter", name));
+                    true
+                })
+                .on_leave(move || {
+                    leave.borrow_mut().push(format!("{} leave", name));
+                    true
+                })
+                .build()
+        };
+        let (a, b) = (gesture("a"), gesture("b"));
+        let tree: Rc<RefCell<dyn Widget>> = Row::new()
+            .add_flex(a.clone(), 1)
+            .add_flex(b.clone(), 1)
+            .build();
+        let map = crate::compute(&tree, 100.0, 50.0);
+        tree.borrow()
+            .dispatch(mouse_move((-1.0, -1.0), (25.0, 25.0)), false, &map);
+        log.borrow_mut().clear();
+        // the previous position is stale, as after the window lost focus
+        tree.borrow()
+            .dispatch(mouse_move((-1.0, -1.0), (75.0, 25.0)), false, &map);
+        assert_eq!(*log.borrow(), vec!["a leave", "b enter"]);
+        assert!(!a.borrow().is_hovered());
+        assert!(b.borrow().is_hovered());
+    }
+
+    #[test]
+    fn front_most_gesture_consumes_the_click() {
+        let clicked = Rc::new(RefCell::new(Vec::new()));
+        let gesture = |name: &'static str| {
+            let clicked = clicked.clone();
+            MouseGesture::new(Rectangle::new(color::RED)).on_click(move |_| {
+                clicked.borrow_mut().push(name);
+                true
+            })
+        };
+        let tree: Rc<RefCell<dyn Widget>> = Stack::new()
+            .add(gesture("back"))
+            .add(gesture("front"))
+            .build();
+        let map = crate::compute(&tree, 100.0, 100.0);
+        let click = Event::MouseDown {
+            x: 50.0,
+            y: 50.0,
+            button: 0,
+        };
+        let (rest, _) = tree.borrow().dispatch(click, false, &map);
+        assert!(rest.is_none());
+        assert_eq!(*clicked.borrow(), vec!["front"]);
+    }
+
+    #[test]
+    fn tap_needs_press_and_release_inside() {
+        let taps = Rc::new(Cell::new(0));
+        let counter = taps.clone();
+        let tree: Rc<RefCell<dyn Widget>> = MouseGesture::new(Rectangle::new(color::RED))
+            .on_tap(move |_| {
+                counter.set(counter.get() + 1);
+                true
+            })
+            .build();
+        let map = crate::compute(&tree, 100.0, 100.0);
+        let down = |x, y| Event::MouseDown { x, y, button: 0 };
+        let up = |x, y| Event::MouseUp { x, y, button: 0 };
+        let tree = tree.borrow();
+        tree.dispatch(down(50.0, 50.0), false, &map);
+        tree.dispatch(mouse_move((50.0, 50.0), (150.0, 50.0)), false, &map);
+        tree.dispatch(up(150.0, 50.0), false, &map);
+        assert_eq!(taps.get(), 0);
+        tree.dispatch(down(50.0, 50.0), false, &map);
+        tree.dispatch(up(60.0, 50.0), false, &map);
+        assert_eq!(taps.get(), 1);
+    }
+
+    #[test]
+    fn long_press_fires_once_after_the_threshold() {
+        let mut timer = LongPress::new(0.5);
+        assert!(!timer.tick(0.0));
+        timer.press(10.0, 10.0);
+        assert!(!timer.tick(1.0));
+        assert!(!timer.tick(1.4));
+        assert!(timer.tick(1.5));
+        assert!(!timer.tick(2.5));
+        // moving past the slop or releasing cancels
+        timer.press(10.0, 10.0);
+        timer.tick(3.0);
+        timer.moved(30.0, 10.0);
+        assert!(!timer.tick(4.0));
+        timer.press(10.0, 10.0);
+        timer.tick(5.0);
+        timer.moved(12.0, 12.0);
+        timer.cancel();
+        assert!(!timer.tick(6.0));
+    }
+
+    #[test]
+    fn percent_padding_scales_with_the_slot() {
+        let child = Rectangle::new(color::RED).build();
+        let tree: Rc<RefCell<dyn Widget>> = Padding::new(child.clone()).percent(10.0).build();
+        let map = crate::compute(&tree, 400.0, 200.0);
+        let computed = &map[&child.borrow().get_id()];
+        assert_eq!((computed.x, computed.y), (40.0, 20.0));
+        assert_eq!((computed.width, computed.height), (320.0, 160.0));
+    }
+
+    #[test]
+    fn single_edges_keep_the_others() {
+        let px = PadValue::Px;
+        let padding = Padding::new(Rectangle::new(color::RED)).left(10.0).top(5.0);
+        assert_eq!(padding.padding, (px(10.0), px(5.0), px(0.0), px(0.0)));
+        let padding = padding.all(2.0).horizontal(8.0).bottom(1.0);
+        assert_eq!(padding.padding, (px(8.0), px(2.0), px(8.0), px(1.0)));
+    }
+
+    #[test]
+    fn constrained_box_clamps_down_to_max() {
+        let child = Rectangle::new(color::RED).build();
+        let tree: Rc<RefCell<dyn Widget>> = ConstrainedBox::new(child.clone())
+            .max_width(100.0)
+            .max_height(50.0)
+            .build();
+        let map = crate::compute(&tree, 300.0, 200.0);
+        let computed = &map[&child.borrow().get_id()];
+        assert_eq!((computed.width, computed.height), (100.0, 50.0));
+        // centered in the slot
+        assert_eq!((computed.x, computed.y), (100.0, 75.0));
+    }
+
+    #[test]
+    fn constrained_box_clamps_up_to_min() {
+        let child = Rectangle::new(color::RED).build();
+        let tree: Rc<RefCell<dyn Widget>> = ConstrainedBox::new(child.clone())
+            .min_width(400.0)
+            .min_height(50.0)
+            .build();
+        let map = crate::compute(&tree, 300.0, 20.0);
+        let computed = &map[&child.borrow().get_id()];
+        // min wins over the available space, overflowing the slot
+        assert_eq!((computed.width, computed.height), (400.0, 50.0));
+        assert_eq!((computed.x, computed.y), (-50.0, -15.0));
+    }
+
+    #[test]
+    fn expanded_takes_what_the_fixed_child_leaves() {
+        let fixed = Rectangle::new(color::RED).build();
+        let rest = Rectangle::new(color::BLUE).build();
+        let tree: Rc<RefCell<dyn Widget>> = Row::new()
+            .add_flex(ConstrainedBox::new(fixed.clone()).min_width(100.0), 0)
+            .add(Expanded::new(rest.clone()).flex(1))
+            .build();
+        let map = crate::compute(&tree, 300.0, 50.0);
+        let fixed = &map[&fixed.borrow().get_id()];
+        let rest = &map[&rest.borrow().get_id()];
+        assert_eq!((fixed.x, fixed.width), (0.0, 100.0));
+        assert_eq!((rest.x, rest.width), (100.0, 200.0));
+    }
+
+    #[test]
+    fn text_is_sized_to_its_glyphs_in_a_row() {
+        let label = Text::new("hello", 16, FONT).build();
+        let rest = Rectangle::new(color::BLUE).build();
+        let tree: Rc<RefCell<dyn Widget>> = Row::new()
+            .add_flex(label.clone(), 0)
+            .add(Expanded::new(rest.clone()))
+            .build();
+        let map = crate::compute(&tree, 300.0, 50.0);
+        let (width, _) = label.borrow().measure(300.0, 50.0);
+        assert!(width > 0.0);
+        let rest = &map[&rest.borrow().get_id()];
+        assert_eq!((rest.x, rest.width), (width, 300.0 - width));
+    }
+
+    #[test]
+    fn clip_applies_to_an_overflowing_child() {
+        let child = Rectangle::new(color::RED).build();
+        let oversized = ConstrainedBox::new(child.clone())
+            .min_width(200.0)
+            .min_height(200.0);
+        let tree: Rc<RefCell<dyn Widget>> = ConstrainedBox::new(Clip::new(oversized))
+            .max_width(100.0)
+            .max_height(100.0)
+            .build();
+        let map = crate::compute(&tree, 300.0, 300.0);
+        let computed = &map[&child.borrow().get_id()];
+        assert_eq!((computed.width, computed.height), (200.0, 200.0));
+        assert_eq!(computed.clip, Some((100.0, 100.0, 100.0, 100.0)));
+    }
+
+    #[test]
+    fn seeded_cursor_enters_once() {
+        let enters = Rc::new(Cell::new(0));
+        let counter = enters.clone();
+        let tree: Rc<RefCell<dyn Widget>> = MouseGesture::new(Rectangle::new(color::RED))
+            .on_enter(move || {
+                counter.set(counter.get() + 1);
+                true
+            })
+            .build();
+        let map = crate::compute(&tree, 100.0, 100.0);
+        // the run loop seeds the hover state from the cursor position at startup
+        tree.borrow()
+            .dispatch(mouse_move((-1.0, -1.0), (50.0, 50.0)), false, &map);
+        assert_eq!(enters.get(), 1);
+        // the first real move starts from there and stays inside, so nothing new
+        let (_, changed) =
+            tree.borrow()
+                .dispatch(mouse_move((50.0, 50.0), (55.0, 50.0)), false, &map);
+        assert!(!changed);
+        assert_eq!(enters.get(), 1);
+    }
+
+    #[test]
+    fn canvas_records_its_commands() {
+        let canvas = Canvas::new(|painter| {
+            painter.fill_rect(0.0, 0.0, 10.0, 10.0, color::RED);
+            painter.fill_rect(10.0, 10.0, 5.0, 5.0, color::BLUE);
+        })
+        .build();
+        let tree: Rc<RefCell<dyn Widget>> = canvas.clone();
+        let map = crate::compute(&tree, 100.0, 100.0);
+        let id = canvas.borrow().get_id();
+        match &map[&id].render {
+            Some(RenderObject::Custom(commands)) => {
+                assert_eq!(commands.len(), 2);
+                assert!(matches!(
+                    commands[1],
+                    DrawCommand::FillRect { x, width, .. } if x == 10.0 && width == 5.0
+                ));
             }
+            other => panic!("expected custom commands, got {:?}", other),
         }
     }
 
-    fn get_id(&self) -> usize {
-        self.id
+    #[test]
+    fn circle_emits_an_ellipse_in_its_box() {
+        let circle = Ellipse::circle(color::RED).build();
+        let tree: Rc<RefCell<dyn Widget>> = circle.clone();
+        let map = crate::compute(&tree, 100.0, 60.0);
+        let id = circle.borrow().get_id();
+        let computed = &map[&id];
+        assert!(matches!(
+            computed.render,
+            Some(RenderObject::Ellipse { .. })
+        ));
+        // the largest circle centered in the box
+        assert_eq!((computed.x, computed.y), (20.0, 0.0));
+        assert_eq!((computed.width, computed.height), (60.0, 60.0));
     }
-}
 
-impl<'a> Widget<'a> for Text<'a> {
-    fn compute(
-        &self,
-        x: f64,
-        y: f64,
-        z: usize,
-        width: f64,
-        height: f64,
-        map: &mut HashMap<usize, ComputedWidget<'a>>,
-    ) {
-        map.insert(
-            self.get_id(),
-            ComputedWidget {
-                x,
-                y,
-                z,
-                width,
-                height,
-                render: Some(RenderObject::Text {
-                    text: self.text,
-                    style: TextStyle {
-                        color: self.color,
-                        size: self.size,
-                        font: self.font,
-                    },
-                }),
-            },
-        );
+    #[test]
+    fn hidden_subtree_keeps_its_box_without_rendering() {
+        let hidden = Rectangle::new(color::RED).build();
+        let below = Rectangle::new(color::BLUE).build();
+        let tree: Rc<RefCell<dyn Widget>> = Column::new()
+            .add(Visibility::new(hidden.clone()).visible(false))
+            .add(below.clone())
+            .build();
+        let map = crate::compute(&tree, 100.0, 100.0);
+        let computed = &map[&hidden.borrow().get_id()];
+        assert!(computed.render.is_none());
+        assert_eq!((computed.width, computed.height), (100.0, 50.0));
+        assert_eq!(map[&below.borrow().get_id()].y, 50.0);
     }
 
-    fn dispatch(
-        &self,
-        event: Event,
-        prev_state_change: bool,
-        _map: &HashMap<usize, ComputedWidget>,
-    ) -> (Option<Event>, bool) {
-        (Some(event), prev_state_change)
+    #[test]
+    fn visible_subtrees_keep_their_flex() {
+        let wide = Rectangle::new(color::RED).build();
+        let tree: Rc<RefCell<dyn Widget>> = Row::new()
+            .add(Visibility::new(Expanded::new(wide.clone()).flex(3)))
+            .add(Rectangle::new(color::BLUE))
+            .build();
+        let map = crate::compute(&tree, 400.0, 100.0);
+        assert_eq!(map[&wide.borrow().get_id()].width, 300.0);
     }
 
-    fn get_id(&self) -> usize {
-        self.id
+    #[test]
+    fn collapsed_subtree_gives_up_its_space() {
+        let hidden = Rectangle::new(color::RED).build();
+        let below = Rectangle::new(color::BLUE).build();
+        let tree: Rc<RefCell<dyn Widget>> = Column::new()
+            .add(
+                Visibility::new(hidden.clone())
+                    .visible(false)
+                    .maintain_space(false),
+            )
+            .add(below.clone())
+            .build();
+        let map = crate::compute(&tree, 100.0, 100.0);
+        assert!(!map.contains_key(&hidden.borrow().get_id()));
+        let below = &map[&below.borrow().get_id()];
+        assert_eq!((below.y, below.height), (0.0, 100.0));
     }
-}
 
-impl<'a> Widget<'a> for Rectangle {
-    fn compute(
-        &self,
-        x: f64,
-        y: f64,
-        z: usize,
-        width: f64,
-        height: f64,
-        map: &mut HashMap<usize, ComputedWidget>,
-    ) {
-        map.insert(
-            self.get_id(),
-            ComputedWidget {
-                x,
-                y,
-                z,
-                width,
-                height,
-                render: Some(RenderObject::Rectangle {
-                    style: Style {
-                        color: Some(self.color),
-                        border_radius: self.border_radius,
-                    },
-                }),
-            },
-        );
+    #[test]
+    fn indexed_stack_only_lays_out_the_active_child() {
+        let clicked = Rc::new(RefCell::new(Vec::new()));
+        let page = |index: usize| {
+            let clicked = clicked.clone();
+            MouseGesture::new(Rectangle::new(color::RED))
+                .on_click(move |_| {
+                    clicked.borrow_mut().push(index);
+                    true
+                })
+                .build()
+        };
+        let (first, second) = (page(0), page(1));
+        let stack = IndexedStack::new()
+            .add(first.clone())
+            .add(second.clone())
+            .index(1)
+            .build();
+        let tree: Rc<RefCell<dyn Widget>> = stack.clone();
+        let click = || Event::MouseDown {
+            x: 50.0,
+            y: 50.0,
+            button: 0,
+        };
+        let map = crate::compute(&tree, 100.0, 100.0);
+        assert!(!map.contains_key(&first.borrow().get_id()));
+        assert!(map.contains_key(&second.borrow().get_id()));
+        tree.borrow().dispatch(click(), false, &map);
+        stack.borrow().set_index(0);
+        let map = crate::compute(&tree, 100.0, 100.0);
+        assert!(map.contains_key(&first.borrow().get_id()));
+        assert!(!map.contains_key(&second.borrow().get_id()));
+        tree.borrow().dispatch(click(), false, &map);
+        assert_eq!(*clicked.borrow(), vec![1, 0]);
     }
 
-    fn dispatch(
-        &self,
-        event: Event,
-        prev_state_change: bool,
-        _map: &HashMap<usize, ComputedWidget>,
-    ) -> (Option<Event>, bool) {
-        (Some(event), prev_state_change)
+    #[test]
+    fn list_view_only_builds_visible_items() {
+        let built = Rc::new(RefCell::new(Vec::new()));
+        let record = built.clone();
+        let list = ListView::new(1000, move |index| {
+            record.borrow_mut().push(index);
+            let item: Rc<RefCell<dyn Widget>> = Rectangle::new(color::RED).build();
+            item
+        })
+        .item_height(50.0)
+        .build();
+        let tree: Rc<RefCell<dyn Widget>> = list.clone();
+        let map = crate::compute(&tree, 100.0, 100.0);
+        // two visible rows and one row of buffer below them
+        assert_eq!(map.len(), 3);
+        assert_eq!(*built.borrow(), vec![0, 1, 2]);
+        list.borrow().set_scroll_offset(500.0);
+        let map = crate::compute(&tree, 100.0, 100.0);
+        assert_eq!(map.len(), 4);
+        assert_eq!(built.borrow()[3..], [9, 10, 11, 12]);
     }
 
-    fn get_id(&self) -> usize {
-        self.id
+    #[test]
+    fn list_view_items_leave_and_enter_in_one_move() {
+        let log = Rc::new(RefCell::new(Vec::new()));
+        let record = log.clone();
+        let list = ListView::new(10, move |index| {
+            let (enter, leave) = (record.clone(), record.clone());
+            let item: Rc<RefCell<dyn Widget>> = MouseGesture::new(Rectangle::new(color::RED))
+                .on_enter(move || {
+                    enter.borrow_mut().push(format!("{} enter", index));
+                    true
+                })
+                .on_leave(move || {
+                    leave.borrow_mut().push(format!("{} leave", index));
+                    true
+                })
+                .build();
+            item
+        })
+        .item_height(50.0)
+        .build();
+        let tree: Rc<RefCell<dyn Widget>> = list;
+        let map = crate::compute(&tree, 100.0, 100.0);
+        tree.borrow()
+            .dispatch(mouse_move((-1.0, -1.0), (50.0, 75.0)), false, &map);
+        log.borrow_mut().clear();
+        // the item above consumes the move it enters, the one below still has to leave
+        tree.borrow()
+            .dispatch(mouse_move((50.0, 75.0), (50.0, 25.0)), false, &map);
+        assert_eq!(*log.borrow(), vec!["0 enter", "1 leave"]);
     }
-}
 
-impl<'a> Widget<'a> for Empty {
-    fn compute(
-        &self,
-        _x: f64,
-        _y: f64,
-        _z: usize,
-        _width: f64,
-        _height: f64,
-        _map: &mut HashMap<usize, ComputedWidget>,
-    ) {
+    #[cfg(feature = "testing")]
+    #[test]
+    fn reset_counter_repeats_the_ids() {
+        let ids = || {
+            reset_counter();
+            let child = Rectangle::new(color::RED).build();
+            let keyed = Rectangle::new(color::BLUE).key("keyed").build();
+            let padding = Padding::new(child.clone()).all(4.0).build();
+            let ids = [
+                child.borrow().get_id(),
+                keyed.borrow().get_id(),
+                padding.borrow().get_id(),
+            ];
+            ids
+        };
+        let first = ids();
+        assert_eq!(first, [0, 1, 2]);
+        assert_eq!(ids(), first);
     }
 
-    fn dispatch(
-        &self,
-        event: Event,
-        prev_state_change: bool,
-        _map: &HashMap<usize, ComputedWidget>,
-    ) -> (Option<Event>, bool) {
-        (Some(event), prev_state_change)
+    #[test]
+    fn separate_trees_start_ids_at_zero() {
+        let tree = || {
+            let child = Rectangle::new(color::RED).build();
+            let padding = Padding::new(child.clone()).all(4.0).build();
+            let ids = [child.borrow().get_id(), padding.borrow().get_id()];
+            ids
+        };
+        assert_eq!(IdAllocator::new().scope(tree), [0, 1]);
+        assert_eq!(IdAllocator::new().scope(tree), [0, 1]);
     }
 
-    fn get_id(&self) -> usize {
-        self.id
+    #[test]
+    fn panicking_scopes_restore_the_previous_allocator() {
+        let outer = IdAllocator::new();
+        outer.scope(|| {
+            let inner = IdAllocator::new();
+            let result = std::panic::catch_unwind(std::panic::AssertUnwindSafe(|| {
+                inner.scope(|| panic!("building failed"))
+            }));
+            assert!(result.is_err());
+            assert_eq!(Rectangle::new(color::RED).build().borrow().get_id(), 0);
+        });
+        assert!(IdAllocator::current().is_none());
     }
-}
 
-impl<'a> Widget<'a> for Padding<'a> {
-    fn compute(
-        &self,
-        x: f64,
-        y: f64,
-        z: usize,
-        width: f64,
-        height: f64,
-        map: &mut HashMap<usize, ComputedWidget<'a>>,
-    ) {
-        let mut w = width - self.padding.2 - self.padding.0;
-        let mut h = height - self.padding.3 - self.padding.1;
-        if w < 0.0 {
-            w = 0.0;
+    #[test]
+    fn keyed_rebuilds_share_a_slot() {
+        let build = || {
+            let keyed = Rectangle::new(color::RED).key("header").build();
+            let tree: Rc<RefCell<dyn Widget>> = Column::new()
+                .add(keyed.clone())
+                .add(Rectangle::new(color::BLUE))
+                .build();
+            let id = keyed.borrow().get_id();
+            (crate::compute(&tree, 100.0, 100.0), id)
+        };
+        let (first, first_id) = build();
+        let (second, second_id) = build();
+        assert_eq!(first_id, second_id);
+        assert_eq!(first[&first_id], second[&second_id]);
+        // unkeyed widgets get new ids on every build
+        assert!(first.keys().any(|id| !second.contains_key(id)));
+    }
+
+    #[test]
+    fn empty_containers_compute_nothing() {
+        let row: Rc<RefCell<dyn Widget>> = Row::new().build();
+        let column: Rc<RefCell<dyn Widget>> = Column::new().build();
+        assert!(crate::compute(&row, 100.0, 100.0).is_empty());
+        assert!(crate::compute(&column, 100.0, 100.0).is_empty());
+        // no flexible children leaves nothing to divide the free space by
+        let fixed: Rc<RefCell<dyn Widget>> =
+            Row::new().add_flex(Rectangle::new(color::RED), 0).build();
+        for w in crate::compute(&fixed, 100.0, 100.0).values() {
+            assert!([w.x, w.y, w.width, w.height].iter().all(|v| v.is_finite()));
         }
-        if h < 0.0 {
-            h = 0.0;
+    }
+
+    #[test]
+    fn flex_children_share_what_fixed_children_leave() {
+        let fixed = |width: f64| {
+            let rect = Rectangle::new(color::RED).build();
+            let sized = ConstrainedBox::new(rect.clone()).min_width(width).build();
+            (rect, sized)
+        };
+        let (first, first_box) = fixed(60.0);
+        let (second, third) = (
+            Rectangle::new(color::BLUE).build(),
+            Rectangle::new(color::GREEN).build(),
+        );
+        let tree: Rc<RefCell<dyn Widget>> = Row::new()
+            .add_flex(first_box, 0)
+            .add_flex(second.clone(), 1)
+            .add_flex(third.clone(), 1)
+            .build();
+        let map = crate::compute(&tree, 300.0, 50.0);
+        let extent = |w: &Rc<RefCell<Rectangle>>| {
+            let computed = &map[&w.borrow().get_id()];
+            (computed.x, computed.width)
+        };
+        assert_eq!(extent(&first), (0.0, 60.0));
+        assert_eq!(extent(&second), (60.0, 120.0));
+        assert_eq!(extent(&third), (180.0, 120.0));
+    }
+
+    #[test]
+    fn fixed_children_never_overflow_the_row() {
+        let rects: Vec<_> = (0..3).map(|_| Rectangle::new(color::RED).build()).collect();
+        let tree: Rc<RefCell<dyn Widget>> = Row::new()
+            .add_flex(ConstrainedBox::new(rects[0].clone()).min_width(200.0), 0)
+            .add_flex(rects[1].clone(), 1)
+            .add_flex(ConstrainedBox::new(rects[2].clone()).min_width(200.0), 0)
+            .build();
+        let map = crate::compute(&tree, 300.0, 50.0);
+        let widths: Vec<f64> = rects
+            .iter()
+            .map(|r| map[&r.borrow().get_id()].width)
+            .collect();
+        // the last fixed child is laid out in the 100px the first one left
+        assert_eq!(widths, [200.0, 0.0, 200.0]);
+        assert_eq!(map[&rects[2].borrow().get_id()].x, 150.0);
+    }
+
+    #[test]
+    fn space_between_puts_the_free_space_between_children() {
+        let rects: Vec<_> = (0..3).map(|_| Rectangle::new(color::RED).build()).collect();
+        let mut row = Row::new().spacing(Spacing::SpaceBetween);
+        for rect in &rects {
+            row = row.add_flex(ConstrainedBox::new(rect.clone()).min_width(40.0), 0);
         }
-        self.child
-            .borrow()
-            .compute(x + self.padding.0, y + self.padding.1, z, w, h, map)
+        let tree: Rc<RefCell<dyn Widget>> = row.build();
+        let map = crate::compute(&tree, 300.0, 50.0);
+        let xs: Vec<f64> = rects.iter().map(|r| map[&r.borrow().get_id()].x).collect();
+        // 180px left over, split into two gaps of 90px
+        assert_eq!(xs, [0.0, 130.0, 260.0]);
     }
 
-    fn dispatch(
-        &self,
-        event: Event,
-        prev_state_change: bool,
-        map: &HashMap<usize, ComputedWidget>,
-    ) -> (Option<Event>, bool) {
-        self.child.borrow().dispatch(event, prev_state_change, map)
+    #[test]
+    fn space_between_sizes_text_to_its_glyphs() {
+        let labels: Vec<_> = ["a", "b"]
+            .iter()
+            .map(|text| Text::new(*text, 16, FONT).build())
+            .collect();
+        let tree: Rc<RefCell<dyn Widget>> = Row::new()
+            .spacing(Spacing::SpaceBetween)
+            .add_flex(labels[0].clone(), 0)
+            .add_flex(labels[1].clone(), 0)
+            .build();
+        let map = crate::compute(&tree, 300.0, 50.0);
+        let last = &map[&labels[1].borrow().get_id()];
+        // the last label ends flush with the right edge
+        assert!((last.x + last.width - 300.0).abs() < 1e-9);
     }
 
-    fn get_id(&self) -> usize {
-        self.id
+    #[test]
+    fn text_bounds_sum_the_glyph_advances() {
+        let text = Text::new("hello", 16, FONT).build();
+        let tree: Rc<RefCell<dyn Widget>> = text.clone();
+        let map = crate::compute(&tree, 300.0, 50.0);
+        let id = text.borrow().get_id();
+        let (width, height) = map[&id].text_bounds().unwrap();
+        let style = text.borrow().style();
+        assert_eq!(height, line_height(&style));
+        // unshaped, the width is the pen position after the last grapheme
+        #[cfg(not(feature = "shaping"))]
+        assert_eq!(width, grapheme_offsets("hello", &style).last().unwrap().1);
+        #[cfg(feature = "shaping")]
+        assert_eq!(width, measure_text("hello", &style).0);
+        // the slot is wider than the glyphs, the box hugs them
+        assert_eq!(map[&id].width, width);
     }
-}
 
-impl<'a> Widget<'a> for Row<'a> {
-    fn compute(
-        &self,
-        x: f64,
-        y: f64,
-        z: usize,
-        width: f64,
-        height: f64,
-        map: &mut HashMap<usize, ComputedWidget<'a>>,
-    ) {
-        let total_len = self.flex.iter().sum::<usize>();
-        let each_child_width = width / total_len as f64;
-        let mut prev_flex = 0;
-        self.children.iter().enumerate().for_each(|(i, child)| {
-            let flex = self.flex[i];
-            let offset = prev_flex as f64 * each_child_width;
-            prev_flex += flex;
-            child.borrow().compute(
-                x + offset,
-                y,
-                z,
-                each_child_width * flex as f64,
-                height,
-                map,
-            );
-        });
+    #[test]
+    fn short_text_keeps_its_line_height_in_a_tall_slot() {
+        let text = Text::new("hi", 20, FONT).build();
+        let tree: Rc<RefCell<dyn Widget>> = Column::new()
+            .add(text.clone())
+            .add(Rectangle::new(color::RED))
+            .build();
+        let map = crate::compute(&tree, 400.0, 600.0);
+        let id = text.borrow().get_id();
+        let computed = &map[&id];
+        assert_eq!(computed.height, line_height(&text.borrow().style()));
+        assert!(computed.height < 300.0);
+        // below the glyphs is still the text's slot, but no longer its hitbox
+        assert_eq!(crate::hit_test(&map, 5.0, computed.height + 50.0), None);
     }
 
-    fn dispatch(
-        &self,
-        event: Event,
-        prev_state_change: bool,
-        map: &HashMap<usize, ComputedWidget>,
-    ) -> (Option<Event>, bool) {
-        let mut e = Some(event);
-        let mut state_change = prev_state_change;
-        for child in &self.children {
-            if let Some(ev) = e {
-                let r = child.borrow().dispatch(ev, prev_state_change, map);
-                e = r.0;
-                state_change = prev_state_change | r.1;
-            } else {
-                break;
+    #[test]
+    fn select_snaps_to_grapheme_boundaries() {
+        // "e\u{301}" is one grapheme over three bytes
+        let text = SelectableText::new("ae\u{301}b", 16, FONT).build();
+        let text = text.borrow();
+        text.select(2, 4);
+        assert_eq!(text.selection(), Some((1, 4)));
+        assert_eq!(text.selected_text(), "e\u{301}");
+        text.select(0, 100);
+        assert_eq!(text.selection(), Some((0, 5)));
+    }
+
+    #[test]
+    fn ctrl_c_copies_the_selection() {
+        let clipboard = MemoryClipboard::new();
+        let text = SelectableText::new("hello world", 16, FONT)
+            .clipboard(clipboard.clone())
+            .build();
+        let tree: Rc<RefCell<dyn Widget>> = text.clone();
+        let map = crate::compute(&tree, 200.0, 40.0);
+        let copy = || key_down(Key::Char('c'), Modifiers::CTRL);
+        // nothing selected, the shortcut is left for someone else
+        let (rest, _) = tree.borrow().dispatch(copy(), false, &map);
+        assert!(rest.is_some());
+        assert_eq!(clipboard.get(), None);
+        text.borrow().select(6, 11);
+        let (rest, _) = tree.borrow().dispatch(copy(), false, &map);
+        assert!(rest.is_none());
+        assert_eq!(clipboard.get().as_deref(), Some("world"));
+    }
+
+    #[test]
+    fn dragging_selects_between_the_nearest_boundaries() {
+        let text = SelectableText::new("hello world", 16, FONT).build();
+        let tree: Rc<RefCell<dyn Widget>> = text.clone();
+        let map = crate::compute(&tree, 200.0, 40.0);
+        let offsets = grapheme_offsets("hello world", &text.borrow().style());
+        // press just past the start of "w", let go just past the end of "o"
+        let (x0, x1) = (offsets[6].1 + 0.5, offsets[8].1 + 0.5);
+        let down = Event::MouseDown {
+            x: x0,
+            y: 5.0,
+            button: 0,
+        };
+        tree.borrow().dispatch(down, false, &map);
+        tree.borrow()
+            .dispatch(mouse_move((x0, 5.0), (x1, 5.0)), false, &map);
+        assert_eq!(text.borrow().selection(), Some((6, 8)));
+        assert_eq!(text.borrow().selected_text(), "wo");
+    }
+
+    #[test]
+    fn accelerators_fire_only_on_their_combo() {
+        let saved = Cell::new(0);
+        let tree: Rc<RefCell<dyn Widget>> = Accelerators::new(Rectangle::new([1.0, 1.0, 1.0, 1.0]))
+            .bind(Key::Char('s'), Modifiers::CTRL, || {
+                saved.set(saved.get() + 1);
+                true
+            })
+            .build();
+        let map = crate::compute(&tree, 100.0, 100.0);
+        let (rest, changed) =
+            tree.borrow()
+                .dispatch(key_down(Key::Char('s'), Modifiers::CTRL), false, &map);
+        assert!(rest.is_none());
+        assert!(changed);
+        assert_eq!(saved.get(), 1);
+        let shift = Modifiers {
+            shift: true,
+            ..Modifiers::CTRL
+        };
+        for event in [
+            key_down(Key::Char('s'), shift),
+            key_down(Key::Char('s'), Modifiers::NONE),
+            key_down(Key::Char('d'), Modifiers::CTRL),
+        ] {
+            let (rest, _) = tree.borrow().dispatch(event, false, &map);
+            assert!(rest.is_some());
+        }
+        assert_eq!(saved.get(), 1);
+    }
+
+    #[test]
+    fn builder_keeps_the_background_image() {
+        let rect = Rectangle::new(color::WHITE)
+            .border(8.0)
+            .background_image("photo.png", ImageFit::Cover)
+            .build();
+        assert_eq!(
+            rect.borrow().background_image,
+            Some(BackgroundImage::new("photo.png", ImageFit::Cover))
+        );
+        // the image is handed to the renderer with the rounded corners it is clipped to
+        let tree: Rc<RefCell<dyn Widget>> = rect.clone();
+        let map = crate::compute(&tree, 100.0, 100.0);
+        let id = rect.borrow().get_id();
+        match &map[&id].render {
+            Some(RenderObject::Rectangle { style }) => {
+                assert_eq!(style.image.as_ref().unwrap().path.as_ref(), "photo.png");
+                assert_eq!(style.border_radius, BorderRadius::uniform(8.0));
             }
+            _ => panic!("expected a rectangle"),
         }
-        (e, state_change)
     }
 
-    fn get_id(&self) -> usize {
-        self.id
+    #[test]
+    fn thumb_reflects_the_viewport_and_offset() {
+        assert_eq!(scroll_thumb(200.0, 800.0, 0.0), Some((0.0, 50.0)));
+        // halfway through the 600px it can scroll, halfway down the 150px it can travel
+        assert_eq!(scroll_thumb(200.0, 800.0, 300.0), Some((75.0, 50.0)));
+        assert_eq!(scroll_thumb(200.0, 800.0, 600.0), Some((150.0, 50.0)));
+        assert_eq!(scroll_thumb(200.0, 200.0, 0.0), None);
     }
-}
 
-impl<'a> Widget<'a> for Stack<'a> {
-    fn compute(
-        &self,
-        x: f64,
-        y: f64,
-        z: usize,
-        width: f64,
-        height: f64,
-        map: &mut HashMap<usize, ComputedWidget<'a>>,
-    ) {
-        self.children
-            .iter()
-            .enumerate()
-            .for_each(|(i, c)| c.borrow().compute(x, y, z + i, width, height, map));
+    #[test]
+    fn scrollbar_drags_and_pages() {
+        let content = Column::new()
+            .add(Rectangle::new(color::RED).build())
+            .build();
+        let view = ScrollView::new(ConstrainedBox::new(content).min_height(800.0)).build();
+        let tree: Rc<RefCell<dyn Widget>> = view.clone();
+        let map = crate::compute(&tree, 100.0, 200.0);
+        let scroll = Event::Scroll {
+            x: 50.0,
+            y: 50.0,
+            dx: 0.0,
+            dy: 120.0,
+        };
+        let (rest, changed) = tree.borrow().dispatch(scroll, false, &map);
+        assert!(rest.is_none() && changed);
+        assert_eq!(view.borrow().scroll_offset(), 120.0);
+        // the thumb sits at 30..80 now, the track below it pages down
+        let map = crate::compute(&tree, 100.0, 200.0);
+        let click = |y| Event::MouseDown {
+            x: 96.0,
+            y,
+            button: 0,
+        };
+        tree.borrow().dispatch(click(150.0), false, &map);
+        assert_eq!(view.borrow().scroll_offset(), 320.0);
+        // grabbing the thumb at 80..130 and pulling it 30px down scrolls 120px
+        let map = crate::compute(&tree, 100.0, 200.0);
+        tree.borrow().dispatch(click(100.0), false, &map);
+        tree.borrow()
+            .dispatch(mouse_move((96.0, 100.0), (96.0, 130.0)), false, &map);
+        tree.borrow().dispatch(
+            Event::MouseUp {
+                x: 96.0,
+                y: 130.0,
+                button: 0,
+            },
+            false,
+            &map,
+        );
+        assert_eq!(view.borrow().scroll_offset(), 440.0);
+        // the end can't be scrolled past
+        view.borrow().set_scroll_offset(1000.0);
+        assert_eq!(view.borrow().scroll_offset(), 600.0);
     }
 
-    fn dispatch(
-        &self,
-        event: Event,
-        prev_state_change: bool,
-        map: &HashMap<usize, ComputedWidget>,
-    ) -> (Option<Event>, bool) {
-        let mut e = Some(event);
-        let mut state_change = prev_state_change;
-        for child in &self.children {
-            if let Some(ev) = e {
-                let r = child.borrow().dispatch(ev, prev_state_change, map);
-                e = r.0;
-                state_change = prev_state_change | r.1;
-            } else {
+    #[test]
+    fn flicks_slow_down_to_a_stop() {
+        let mut momentum = Momentum {
+            offset: 0.0,
+            velocity: 1000.0,
+        };
+        let (mut last, mut distance) = (momentum, f64::INFINITY);
+        let mut steps = 0;
+        while momentum.step(1.0 / 60.0, 1000.0) {
+            assert!(momentum.velocity.abs() < last.velocity.abs());
+            // every step covers less ground than the one before
+            assert!(momentum.offset - last.offset < distance);
+            distance = momentum.offset - last.offset;
+            last = momentum;
+            steps += 1;
+            assert!(steps < 600);
+        }
+        // 1000px/s with 2% left after each second coasts about 1000 / ln 50 = 256px
+        assert!((momentum.offset - 256.0).abs() < 16.0);
+        assert!(!momentum.step(1.0 / 60.0, 1000.0));
+    }
+
+    #[test]
+    fn flicks_past_the_end_spring_back() {
+        let mut momentum = Momentum {
+            offset: 950.0,
+            velocity: 2000.0,
+        };
+        let mut furthest: f64 = 0.0;
+        for _ in 0..600 {
+            if !momentum.step(1.0 / 60.0, 1000.0) {
                 break;
             }
+            furthest = furthest.max(momentum.offset);
         }
-        (e, state_change)
+        assert!(furthest > 1000.0 && furthest <= 1000.0 + OVERSCROLL);
+        assert_eq!(
+            momentum,
+            Momentum {
+                offset: 1000.0,
+                velocity: 0.0
+            }
+        );
     }
 
-    fn get_id(&self) -> usize {
-        self.id
+    #[test]
+    fn letting_go_of_a_fast_scroll_coasts() {
+        let view =
+            ScrollView::new(ConstrainedBox::new(Rectangle::new(color::RED)).min_height(5000.0))
+                .build();
+        let tree: Rc<RefCell<dyn Widget>> = view.clone();
+        let map = crate::compute(&tree, 100.0, 200.0);
+        let scroll = |dy| Event::Scroll {
+            x: 50.0,
+            y: 50.0,
+            dx: 0.0,
+            dy,
+        };
+        let tick = |frame: usize| Event::Tick {
+            time: frame as f64 / 60.0,
+        };
+        // a single notch stops where it scrolled to
+        tree.borrow().dispatch(scroll(40.0), false, &map);
+        for frame in 1..4 {
+            tree.borrow().dispatch(tick(frame), false, &map);
+        }
+        assert_eq!(view.borrow().scroll_offset(), 40.0);
+        // 30px a frame for a few frames keeps going after the last one
+        for frame in 4..8 {
+            tree.borrow().dispatch(scroll(30.0), false, &map);
+            tree.borrow().dispatch(tick(frame), false, &map);
+        }
+        assert_eq!(view.borrow().scroll_offset(), 160.0);
+        let (_, changed) = tree.borrow().dispatch(tick(8), false, &map);
+        assert!(changed);
+        let coasted = view.borrow().scroll_offset();
+        assert!(coasted > 160.0);
+        // a click catches it
+        let down = Event::MouseDown {
+            x: 50.0,
+            y: 50.0,
+            button: 0,
+        };
+        tree.borrow().dispatch(down, false, &map);
+        tree.borrow().dispatch(tick(9), false, &map);
+        assert_eq!(view.borrow().scroll_offset(), coasted);
     }
-}
 
-impl<'a> Widget<'a> for Column<'a> {
-    fn compute(
-        &self,
-        x: f64,
-        y: f64,
-        z: usize,
-        width: f64,
-        height: f64,
-        map: &mut HashMap<usize, ComputedWidget<'a>>,
-    ) {
-        let total_len = self.flex.iter().sum::<usize>();
-        let each_child_height = height / total_len as f64;
-        let mut prev_flex = 0;
-        self.children.iter().enumerate().for_each(|(i, child)| {
-            let flex = self.flex[i];
-            let offset = prev_flex as f64 * each_child_height;
-            prev_flex += flex;
-            child.borrow().compute(
-                x,
-                y + offset,
-                z,
-                width,
-                each_child_height * flex as f64,
-                map,
-            );
-        });
+    #[test]
+    fn overlays_escape_the_clip() {
+        let (clipped, popup) = (
+            Rectangle::new(color::RED).build(),
+            Rectangle::new(color::BLUE).build(),
+        );
+        let tree: Rc<RefCell<dyn Widget>> = ScrollView::new(
+            Stack::new().add(clipped.clone()).add(
+                Overlay::new(popup.clone())
+                    .offset(0.0, 150.0)
+                    .size(100.0, 100.0),
+            ),
+        )
+        .build();
+        let map = crate::compute(&tree, 100.0, 200.0);
+        let (clipped, popup) = (clipped.borrow().get_id(), popup.borrow().get_id());
+        assert_eq!(map[&clipped].clip, Some((0.0, 0.0, 100.0, 200.0)));
+        // it hangs past the bottom of the view, and still isn't cut off
+        assert_eq!(map[&popup].clip, None);
+        assert_eq!((map[&popup].y, map[&popup].height), (150.0, 100.0));
+        assert!(map[&popup].z >= OVERLAY_Z);
     }
 
-    fn dispatch(
-        &self,
-        event: Event,
-        prev_state_change: bool,
-        map: &HashMap<usize, ComputedWidget>,
-    ) -> (Option<Event>, bool) {
-        let mut e = Some(event);
-        let mut state_change = prev_state_change;
-        for child in &self.children {
-            if let Some(ev) = e {
-                let r = child.borrow().dispatch(ev, prev_state_change, map);
-                e = r.0;
-                state_change = prev_state_change | r.1;
-            } else {
-                break;
-            }
+    #[test]
+    fn dropdown_opens_and_selects() {
+        let mut selected = State::new();
+        let picked = Cell::new(None);
+        let dropdown = Dropdown::new(16, FONT)
+            .options(vec!["red", "green", "blue"])
+            .bind_selected(&mut selected)
+            .on_select(|index| picked.set(Some(index)))
+            .build();
+        let tree: Rc<RefCell<dyn Widget>> = dropdown.clone();
+        let click = |y| Event::MouseDown {
+            x: 20.0,
+            y,
+            button: 0,
+        };
+        let map = crate::compute(&tree, 120.0, 30.0);
+        let (first_row, _) = dropdown.borrow().row_ids[0];
+        assert!(!map.contains_key(&first_row));
+        tree.borrow().dispatch(click(15.0), false, &map);
+        let map = crate::compute(&tree, 120.0, 30.0);
+        assert_eq!(map[&first_row].y, 30.0);
+        // rows hang below the box, one box height each
+        let (rest, changed) = tree.borrow().dispatch(click(105.0), false, &map);
+        assert!(rest.is_none() && changed);
+        assert_eq!(*selected.borrow(), 2);
+        assert_eq!(picked.get(), Some(2));
+        assert!(!dropdown.borrow().is_open());
+    }
+
+    #[test]
+    fn open_dialogs_keep_clicks_from_the_background() {
+        let clicks = Cell::new(0);
+        let background = MouseGesture::new(Rectangle::new(color::WHITE).build())
+            .on_click(|_| {
+                clicks.set(clicks.get() + 1);
+                true
+            })
+            .build();
+        let handle = DialogHandle::new();
+        let tree: Rc<RefCell<dyn Widget>> = DialogHost::new(background, &handle).build();
+        let click = || Event::MouseDown {
+            x: 5.0,
+            y: 5.0,
+            button: 0,
+        };
+        let map = crate::compute(&tree, 200.0, 200.0);
+        tree.borrow().dispatch(click(), false, &map);
+        assert_eq!(clicks.get(), 1);
+        handle.show_dialog(
+            ConstrainedBox::new(Rectangle::new(color::BLUE))
+                .min_width(50.0)
+                .min_height(50.0),
+        );
+        let map = crate::compute(&tree, 200.0, 200.0);
+        let (rest, _) = tree.borrow().dispatch(click(), false, &map);
+        assert!(rest.is_none());
+        assert_eq!(clicks.get(), 1);
+        // the barrier isn't dismissible by default
+        assert!(handle.is_open());
+    }
+
+    #[test]
+    fn half_width_factors_center_the_child() {
+        let child = Rectangle::new(color::RED).build();
+        let tree: Rc<RefCell<dyn Widget>> = FractionallySizedBox::new(child.clone())
+            .width_factor(0.5)
+            .build();
+        let map = crate::compute(&tree, 400.0, 100.0);
+        let sized = &map[&child.borrow().get_id()];
+        assert_eq!(
+            (sized.x, sized.y, sized.width, sized.height),
+            (100.0, 0.0, 200.0, 100.0)
+        );
+    }
+
+    #[test]
+    fn positioned_children_compute_at_their_offset() {
+        let child = Rectangle::new(color::RED).build();
+        let tree: Rc<RefCell<dyn Widget>> = Stack::new()
+            .add(Rectangle::new(color::WHITE))
+            .add(
+                Positioned::new(child.clone())
+                    .offset(50.0, 30.0)
+                    .size(20.0, 10.0),
+            )
+            .build();
+        let map = crate::compute(&tree, 200.0, 100.0);
+        let placed = &map[&child.borrow().get_id()];
+        assert_eq!(
+            (placed.x, placed.y, placed.width, placed.height),
+            (50.0, 30.0, 20.0, 10.0)
+        );
+    }
+
+    #[test]
+    fn clicks_pass_through_ignored_overlays() {
+        let (below, above) = (Cell::new(0), Cell::new(0));
+        fn gesture(count: &Cell<i32>) -> MouseGestureBuilder<'_> {
+            MouseGesture::new(Rectangle::new(color::WHITE).build()).on_click(move |_| {
+                count.set(count.get() + 1);
+                true
+            })
         }
-        (e, state_change)
+        let tree: Rc<RefCell<dyn Widget>> = Stack::new()
+            .add(gesture(&below))
+            .add(IgnorePointer::new(gesture(&above)))
+            .build();
+        let map = crate::compute(&tree, 100.0, 100.0);
+        let click = Event::MouseDown {
+            x: 50.0,
+            y: 50.0,
+            button: 0,
+        };
+        let (rest, _) = tree.borrow().dispatch(click, false, &map);
+        assert!(rest.is_none());
+        assert_eq!((below.get(), above.get()), (1, 0));
     }
 
-    fn get_id(&self) -> usize {
-        self.id
+    #[test]
+    fn reactive_children_rebuild_when_their_state_changes() {
+        let count = State::with_value(1);
+        let tree: Rc<RefCell<dyn Widget>> = Reactive::new(count.clone(), |count: &i32| {
+            Text::new(format!("{} items", count), 16, FONT)
+        })
+        .build();
+        fn text<'a>(tree: &Rc<RefCell<dyn Widget<'a> + 'a>>) -> String {
+            crate::compute(tree, 200.0, 40.0)
+                .values()
+                .find_map(|widget| match &widget.render {
+                    Some(RenderObject::Text { text, .. }) => Some(text.to_string()),
+                    _ => None,
+                })
+                .unwrap()
+        }
+        assert_eq!(text(&tree), "1 items");
+        count.set(3);
+        assert_eq!(text(&tree), "3 items");
+        count.update(|count| *count += 1);
+        assert_eq!(text(&tree), "4 items");
+    }
+
+    #[test]
+    fn moves_between_row_children_leave_one_and_enter_the_other() {
+        let log = RefCell::new(Vec::new());
+        let gesture = |name: &'static str| {
+            let log = &log;
+            MouseGesture::new(Rectangle::new(color::WHITE).build())
+                .on_enter(move || {
+                    log.borrow_mut().push(format!("enter {}", name));
+                    true
+                })
+                .on_leave(move || {
+                    log.borrow_mut().push(format!("leave {}", name));
+                    true
+                })
+        };
+        let tree: Rc<RefCell<dyn Widget>> = Row::new()
+            .add(gesture("first"))
+            .add(gesture("second"))
+            .build();
+        let map = crate::compute(&tree, 200.0, 100.0);
+        tree.borrow()
+            .dispatch(mouse_move((-1.0, -1.0), (50.0, 50.0)), false, &map);
+        log.borrow_mut().clear();
+        tree.borrow()
+            .dispatch(mouse_move((50.0, 50.0), (150.0, 50.0)), false, &map);
+        assert_eq!(*log.borrow(), ["leave first", "enter second"]);
+        // entering the first child consumes the move, the second still hears it leave
+        log.borrow_mut().clear();
+        tree.borrow()
+            .dispatch(mouse_move((150.0, 50.0), (50.0, 50.0)), false, &map);
+        assert_eq!(*log.borrow(), ["enter first", "leave second"]);
+    }
+
+    #[test]
+    fn capturing_listeners_consume_clicks_before_their_child() {
+        let clicks = Cell::new(0);
+        let captured = Cell::new(0);
+        let child = MouseGesture::new(Rectangle::new(color::WHITE).build()).on_click(|_| {
+            clicks.set(clicks.get() + 1);
+            true
+        });
+        let tree: Rc<RefCell<dyn Widget>> = EventListener::new(child)
+            .capture(|event| match event {
+                Event::MouseDown { .. } => {
+                    captured.set(captured.get() + 1);
+                    EventFlow::Consumed
+                }
+                event => EventFlow::Ignored(event),
+            })
+            .build();
+        let map = crate::compute(&tree, 100.0, 100.0);
+        let click = Event::MouseDown {
+            x: 50.0,
+            y: 50.0,
+            button: 0,
+        };
+        let (rest, changed) = tree.borrow().dispatch(click, false, &map);
+        assert!(rest.is_none() && changed);
+        assert_eq!((captured.get(), clicks.get()), (1, 0));
+    }
+
+    #[test]
+    fn toasts_show_one_after_another() {
+        let queue = ToastQueue::new();
+        queue.show_toast("saved", 1.0);
+        queue.show_toast("synced", 1.0);
+        assert_eq!(queue.current(), None);
+        assert!(queue.tick(10.0));
+        assert_eq!((queue.current(), queue.opacity()), (Some("saved"), 1.0));
+        // halfway through the fade
+        queue.tick(11.0 + TOAST_FADE / 2.0);
+        assert_eq!(queue.current(), Some("saved"));
+        assert!((queue.opacity() - 0.5).abs() < 1e-6);
+        queue.tick(11.0 + TOAST_FADE);
+        assert_eq!((queue.current(), queue.opacity()), (Some("synced"), 1.0));
+        queue.tick(13.0);
+        assert_eq!(queue.current(), None);
+        assert!(!queue.tick(14.0));
+    }
+
+    #[test]
+    fn rtl_mirrors_rows_and_padding() {
+        let (first, second, padded) = (
+            Rectangle::new(color::RED).build(),
+            Rectangle::new(color::GREEN).build(),
+            Rectangle::new(color::BLUE).build(),
+        );
+        let content = Column::new()
+            .add(Row::new().add(first.clone()).add(second.clone()).build())
+            .add(
+                Padding::new(padded.clone())
+                    .each(10.0, 0.0, 0.0, 0.0)
+                    .build(),
+            )
+            .build();
+        let tree: Rc<RefCell<dyn Widget>> =
+            Directionality::new(content, TextDirection::Rtl).build();
+        let map = crate::compute(&tree, 100.0, 100.0);
+        let bounds = |widget: &Rc<RefCell<Rectangle>>| {
+            let id = widget.borrow().get_id();
+            (map[&id].x, map[&id].width)
+        };
+        assert_eq!(bounds(&first), (50.0, 50.0));
+        assert_eq!(bounds(&second), (0.0, 50.0));
+        // the start inset is on the right
+        assert_eq!(bounds(&padded), (0.0, 90.0));
+    }
+
+    #[test]
+    fn columns_desire_the_sum_of_their_children() {
+        let child = || {
+            ConstrainedBox::new(Rectangle::new(color::RED))
+                .min_width(20.0)
+                .min_height(50.0)
+        };
+        let column = Column::new().add(child()).add(child()).add(child()).build();
+        // far less room than it wants, it still asks for all of it
+        assert_eq!(column.borrow().desired_size((100.0, 60.0)), (20.0, 150.0));
+        let text = Text::new("hello", 16, FONT).build();
+        let (_, line) = text.borrow().measure(100.0, 60.0);
+        let column = Column::new().add(text).add(child()).build();
+        assert_eq!(column.borrow().desired_size((100.0, 60.0)).1, line + 50.0);
     }
 }
@@ -1,17 +1,30 @@
-use super::super::color::Color;
-use super::super::State;
+use super::super::color::{self, Color};
+use super::super::{
+    subtree_label, BorderRadius, ComputedWidget, Event, Key, Painter, Role, SemanticNode, State,
+};
 use super::core::*;
-use super::Widget;
-use std::cell::RefCell;
+use super::{dispatch_to, IntoWidget, Widget};
+use std::any::Any;
+use std::cell::{Cell, RefCell};
+use std::collections::HashMap;
 use std::rc::Rc;
 
+const DISABLED_ALPHA: f32 = 0.4;
+const RIPPLE_DURATION: f64 = 0.3;
+const RIPPLE_ALPHA: f32 = 0.35;
+const COLOR_TRANSITION: f64 = 0.15;
+
 pub struct Button<'a> {
     child: Option<Rc<RefCell<dyn Widget<'a> + 'a>>>,
     base_color: Color,
     hover_color: Color,
     active_color: Color,
     pressed_callback: Option<Box<dyn Fn(u8) + 'a>>,
-    border_radius: f64,
+    border_radius: BorderRadius,
+    enabled: Rc<RefCell<bool>>,
+    focused: Rc<RefCell<bool>>,
+    ripple: bool,
+    transition: f64,
 }
 
 impl<'a> Button<'a> {
@@ -22,12 +35,16 @@ impl<'a> Button<'a> {
             base_color,
             hover_color: base_color,
             active_color: base_color,
-            border_radius: 0.0,
+            border_radius: BorderRadius::default(),
+            enabled: Rc::new(RefCell::new(true)),
+            focused: Rc::new(RefCell::new(false)),
+            ripple: false,
+            transition: COLOR_TRANSITION,
         }
     }
 
-    pub fn child(mut self, child: Rc<RefCell<dyn Widget<'a> + 'a>>) -> Self {
-        self.child = Some(child);
+    pub fn child<W: IntoWidget<'a>>(mut self, child: W) -> Self {
+        self.child = Some(child.into_widget());
         self
     }
 
@@ -42,7 +59,18 @@ impl<'a> Button<'a> {
     }
 
     pub fn border(mut self, border: f64) -> Self {
-        self.border_radius = border;
+        self.border_radius = BorderRadius::uniform(border);
+        self
+    }
+
+    pub fn border_each(
+        mut self,
+        top_left: f64,
+        top_right: f64,
+        bottom_left: f64,
+        bottom_right: f64,
+    ) -> Self {
+        self.border_radius = BorderRadius::each(top_left, top_right, bottom_left, bottom_right);
         self
     }
 
@@ -51,40 +79,1031 @@ impl<'a> Button<'a> {
         self
     }
 
+    // a disabled button still consumes clicks so they don't reach whatever is underneath,
+    // but it doesn't react to them and draws faded
+    pub fn enabled(self, enabled: bool) -> Self {
+        *self.enabled.borrow_mut() = enabled;
+        self
+    }
+
+    pub fn bind_enabled(self, state: &mut State<bool>) -> Self {
+        state.bind(self.enabled.clone());
+        self
+    }
+
+    // a focused button is pressed with enter or space. clicking it focuses it,
+    // clicking anywhere else takes the focus away again
+    pub fn focused(self, focused: bool) -> Self {
+        *self.focused.borrow_mut() = focused;
+        self
+    }
+
+    pub fn bind_focused(self, state: &mut State<bool>) -> Self {
+        state.bind(self.focused.clone());
+        self
+    }
+
+    // a translucent circle spreading from the press point, driven by tick events
+    pub fn ripple(mut self, ripple: bool) -> Self {
+        self.ripple = ripple;
+        self
+    }
+
+    // seconds the color takes to fade between base, hover and active, 0 snaps
+    pub fn transition(mut self, duration: f64) -> Self {
+        self.transition = duration;
+        self
+    }
+
+    pub fn build(self) -> Rc<RefCell<dyn Widget<'a> + 'a>> {
+        let rect = Rectangle::new(self.base_color)
+            .border_each(
+                self.border_radius.top_left,
+                self.border_radius.top_right,
+                self.border_radius.bottom_left,
+                self.border_radius.bottom_right,
+            )
+            .build();
+        self.build_with(rect)
+    }
+
     pub fn build_state(self, rect_state: &'a mut State<Rectangle>) -> Rc<RefCell<dyn Widget + 'a>> {
-        let active_color = self.active_color;
-        let hover_color = self.hover_color.clone();
-        let base_color = self.base_color.clone();
         let rect = Rectangle::new(self.base_color)
-            .border(self.border_radius)
+            .border_each(
+                self.border_radius.top_left,
+                self.border_radius.top_right,
+                self.border_radius.bottom_left,
+                self.border_radius.bottom_right,
+            )
             .build_stateful(rect_state);
-        let c_rect_state: &'a State<Rectangle> = rect_state;
-        let pressed_callback = self.pressed_callback;
-        let mut stack_builder = Stack::new().add(rect);
+        self.build_with(rect)
+    }
+
+    fn build_with(self, rect: Rc<RefCell<Rectangle>>) -> Rc<RefCell<dyn Widget<'a> + 'a>> {
+        let active_color = self.active_color;
+        let hover_color = self.hover_color;
+        let base_color = self.base_color;
+        let transition = Rc::new(RefCell::new(ColorTransition::new(
+            base_color,
+            self.transition,
+        )));
+        let (c_click, c_release, c_enter, c_leave) = (
+            transition.clone(),
+            transition.clone(),
+            transition.clone(),
+            transition.clone(),
+        );
+        let pressed_callback: Option<Rc<dyn Fn(u8) + 'a>> = self.pressed_callback.map(Rc::from);
+        let key_callback = pressed_callback.clone();
+        let hovered = Rc::new(RefCell::new(false));
+        let (enter_hovered, leave_hovered) = (hovered.clone(), hovered.clone());
+        let mut stack_builder = Stack::new().add(rect.clone());
+        let ripple = Rc::new(RefCell::new(RippleState::default()));
+        if self.ripple {
+            let ripple = ripple.clone();
+            stack_builder = stack_builder.add(Clip::new(Canvas::new(move |painter| {
+                ripple.borrow().paint(painter)
+            })));
+        }
         if let Some(child) = self.child {
             stack_builder = stack_builder.add(child);
         }
-        MouseGesture::new(stack_builder.build())
-            .border(self.border_radius)
+        let (on_click, on_release, on_tap, on_enter) = (
+            self.enabled.clone(),
+            self.enabled.clone(),
+            self.enabled.clone(),
+            self.enabled.clone(),
+        );
+        let gesture = MouseGesture::new(stack_builder.build())
+            .border_each(
+                self.border_radius.top_left,
+                self.border_radius.top_right,
+                self.border_radius.bottom_left,
+                self.border_radius.bottom_right,
+            )
             .on_click(move |_| {
-                c_rect_state.borrow_mut().color = active_color;
+                if !*on_click.borrow() {
+                    return false;
+                }
+                c_click.borrow_mut().retarget(active_color);
                 true
             })
-            .on_release(move |button| {
-                c_rect_state.borrow_mut().color = hover_color;
+            .on_release(move |_| {
+                if !*on_release.borrow() {
+                    return false;
+                }
+                c_release.borrow_mut().retarget(hover_color);
+                true
+            })
+            .on_tap(move |button| {
+                if !*on_tap.borrow() {
+                    return false;
+                }
                 if let Some(pressed) = &pressed_callback {
                     pressed(button);
                 }
-                true
+                false
             })
             .on_enter(move || {
-                c_rect_state.borrow_mut().color = hover_color;
+                *enter_hovered.borrow_mut() = true;
+                if !*on_enter.borrow() {
+                    return false;
+                }
+                c_enter.borrow_mut().retarget(hover_color);
                 true
             })
+            // leaving always resets, so a button disabled while hovered doesn't stay highlighted
             .on_leave(move || {
-                c_rect_state.borrow_mut().color = base_color;
+                *leave_hovered.borrow_mut() = false;
+                c_leave.borrow_mut().retarget(base_color);
                 true
             })
-            .build()
+            .build();
+        let gesture = Rc::new(RefCell::new(KeyActivation {
+            child: AnimatedColor::new(gesture, rect, transition.clone()),
+            focused: self.focused,
+            enabled: self.enabled.clone(),
+            hovered,
+            transition,
+            colors: (base_color, hover_color, active_color),
+            callback: key_callback,
+        }));
+        let child: Rc<RefCell<dyn Widget<'a> + 'a>> = if self.ripple {
+            Rc::new(RefCell::new(RippleInput {
+                child: gesture,
+                state: ripple,
+                enabled: self.enabled.clone(),
+            }))
+        } else {
+            gesture
+        };
+        Rc::new(RefCell::new(Faded {
+            child,
+            enabled: self.enabled,
+        }))
+    }
+}
+
+// a color easing towards a target, driven by tick events
+#[derive(Debug, Clone)]
+pub struct ColorTransition {
+    from: Color,
+    to: Color,
+    duration: f64,
+    start: Option<f64>,
+    time: f64,
+}
+
+impl ColorTransition {
+    pub fn new(color: Color, duration: f64) -> Self {
+        ColorTransition {
+            from: color,
+            to: color,
+            duration,
+            start: None,
+            time: 0.0,
+        }
+    }
+
+    // sets the color right away, without a fade
+    pub fn jump(&mut self, color: Color) {
+        self.from = color;
+        self.to = color;
+        self.start = None;
+    }
+
+    // starts from the current color, so turning around halfway doesn't jump
+    pub fn retarget(&mut self, to: Color) {
+        if to == self.to {
+            return;
+        }
+        self.from = self.color();
+        self.to = to;
+        // like ripples, the fade starts with the next tick
+        self.start = None;
+    }
+
+    // returns whether the color changed
+    pub fn tick(&mut self, time: f64) -> bool {
+        self.time = time;
+        if self.from == self.to {
+            return false;
+        }
+        let start = *self.start.get_or_insert(time);
+        if time - start >= self.duration {
+            self.from = self.to;
+            self.start = None;
+        }
+        true
+    }
+
+    pub fn color(&self) -> Color {
+        if self.duration <= 0.0 {
+            return self.to;
+        }
+        let elapsed = self.start.map(|start| self.time - start).unwrap_or(0.0);
+        let t = (elapsed / self.duration).clamp(0.0, 1.0);
+        let eased = t * t * (3.0 - 2.0 * t);
+        color::lerp(self.from, self.to, eased as f32)
+    }
+
+    pub fn target(&self) -> Color {
+        self.to
+    }
+}
+
+// keeps a rectangle's color in step with a transition, the rectangle is usually part of child
+pub struct AnimatedColor<'a> {
+    child: Rc<RefCell<dyn Widget<'a> + 'a>>,
+    rect: Rc<RefCell<Rectangle>>,
+    transition: Rc<RefCell<ColorTransition>>,
+}
+
+impl<'a> AnimatedColor<'a> {
+    pub fn new<W: IntoWidget<'a>>(
+        child: W,
+        rect: Rc<RefCell<Rectangle>>,
+        transition: Rc<RefCell<ColorTransition>>,
+    ) -> Rc<RefCell<AnimatedColor<'a>>> {
+        Rc::new(RefCell::new(AnimatedColor {
+            child: child.into_widget(),
+            rect,
+            transition,
+        }))
+    }
+
+    fn sync(&self) {
+        self.rect.borrow_mut().color = self.transition.borrow().color();
+    }
+}
+
+impl<'a> Widget<'a> for AnimatedColor<'a> {
+    fn compute(
+        &self,
+        x: f64,
+        y: f64,
+        z: usize,
+        width: f64,
+        height: f64,
+        map: &mut HashMap<usize, ComputedWidget<'a>>,
+    ) {
+        self.child.borrow().compute(x, y, z, width, height, map);
+    }
+
+    fn measure(&self, width: f64, height: f64) -> (f64, f64) {
+        self.child.borrow().measure(width, height)
+    }
+
+    fn dispatch(
+        &self,
+        event: Event,
+        prev_state_change: bool,
+        map: &HashMap<usize, ComputedWidget>,
+    ) -> (Option<Event>, bool) {
+        let mut state_change = prev_state_change;
+        if let Event::Tick { time } = event {
+            if self.transition.borrow_mut().tick(time) {
+                self.sync();
+                state_change = true;
+            }
+        }
+        let result = dispatch_to(&self.child, event, state_change, map);
+        // a zero duration transition has no ticks to wait for
+        if self.transition.borrow().duration <= 0.0 {
+            self.sync();
+        }
+        result
+    }
+
+    fn children(&self) -> Vec<Rc<RefCell<dyn Widget<'a> + 'a>>> {
+        vec![self.child.clone()]
+    }
+
+    fn get_id(&self) -> usize {
+        self.child.borrow().get_id()
+    }
+}
+
+// presses a focused button from the keyboard. there is no key up event, so the button
+// flashes its active color and fades back to where it was
+struct KeyActivation<'a> {
+    child: Rc<RefCell<dyn Widget<'a> + 'a>>,
+    focused: Rc<RefCell<bool>>,
+    enabled: Rc<RefCell<bool>>,
+    hovered: Rc<RefCell<bool>>,
+    transition: Rc<RefCell<ColorTransition>>,
+    // base, hover and active
+    colors: (Color, Color, Color),
+    callback: Option<Rc<dyn Fn(u8) + 'a>>,
+}
+
+impl<'a> Widget<'a> for KeyActivation<'a> {
+    fn compute(
+        &self,
+        x: f64,
+        y: f64,
+        z: usize,
+        width: f64,
+        height: f64,
+        map: &mut HashMap<usize, ComputedWidget<'a>>,
+    ) {
+        self.child.borrow().compute(x, y, z, width, height, map);
+    }
+
+    fn measure(&self, width: f64, height: f64) -> (f64, f64) {
+        self.child.borrow().measure(width, height)
+    }
+
+    fn dispatch(
+        &self,
+        event: Event,
+        prev_state_change: bool,
+        map: &HashMap<usize, ComputedWidget>,
+    ) -> (Option<Event>, bool) {
+        match event {
+            Event::MouseDown { x, y, .. } => {
+                let inside = map
+                    .get(&self.get_id())
+                    .is_some_and(|computed| computed.in_hitbox(x, y, computed.border_radius()));
+                *self.focused.borrow_mut() = inside && *self.enabled.borrow();
+            }
+            Event::KeyDown {
+                key: Key::Enter | Key::Space,
+                ..
+            } if *self.focused.borrow() && *self.enabled.borrow() => {
+                let (base, hover, active) = self.colors;
+                let rest = if *self.hovered.borrow() { hover } else { base };
+                let mut transition = self.transition.borrow_mut();
+                transition.jump(active);
+                transition.retarget(rest);
+                drop(transition);
+                if let Some(callback) = &self.callback {
+                    // reported as the primary mouse button
+                    callback(0);
+                }
+                return (None, true);
+            }
+            _ => {}
+        }
+        dispatch_to(&self.child, event, prev_state_change, map)
+    }
+
+    fn children(&self) -> Vec<Rc<RefCell<dyn Widget<'a> + 'a>>> {
+        vec![self.child.clone()]
+    }
+
+    fn get_id(&self) -> usize {
+        self.child.borrow().get_id()
+    }
+}
+
+#[derive(Default)]
+struct RippleState {
+    // press point relative to the button
+    origin: Option<(f64, f64)>,
+    start: Option<f64>,
+    time: f64,
+}
+
+impl RippleState {
+    fn press(&mut self, x: f64, y: f64) {
+        self.origin = Some((x, y));
+        // the animation starts with the next tick, like long presses
+        self.start = None;
+    }
+
+    // returns whether the ripple needs to be laid out again
+    fn tick(&mut self, time: f64) -> bool {
+        self.time = time;
+        if self.origin.is_none() {
+            return false;
+        }
+        let start = *self.start.get_or_insert(time);
+        if time - start >= RIPPLE_DURATION {
+            self.origin = None;
+            self.start = None;
+        }
+        true
+    }
+
+    fn progress(&self) -> Option<f64> {
+        self.origin?;
+        let elapsed = self.start.map(|start| self.time - start).unwrap_or(0.0);
+        Some((elapsed / RIPPLE_DURATION).clamp(0.0, 1.0))
+    }
+
+    // radius relative to the distance to the farthest corner, and alpha
+    fn frame(progress: f64) -> (f64, f32) {
+        let eased = 1.0 - (1.0 - progress).powi(2);
+        (eased, RIPPLE_ALPHA * (1.0 - progress as f32))
+    }
+
+    fn paint(&self, painter: &mut dyn Painter) {
+        let (progress, (x, y)) = match (self.progress(), self.origin) {
+            (Some(progress), Some(origin)) => (progress, origin),
+            _ => return,
+        };
+        let (width, height) = painter.size();
+        let reach = [(0.0, 0.0), (width, 0.0), (0.0, height), (width, height)]
+            .iter()
+            .map(|(cx, cy)| ((cx - x).powi(2) + (cy - y).powi(2)).sqrt())
+            .fold(0.0, f64::max);
+        let (scale, alpha) = RippleState::frame(progress);
+        painter.fill_circle(x, y, reach * scale, color::with_alpha(color::WHITE, alpha));
+    }
+}
+
+// gesture callbacks don't see the cursor position, so presses are picked up on the way in
+struct RippleInput<'a> {
+    child: Rc<RefCell<dyn Widget<'a> + 'a>>,
+    state: Rc<RefCell<RippleState>>,
+    enabled: Rc<RefCell<bool>>,
+}
+
+impl<'a> Widget<'a> for RippleInput<'a> {
+    fn compute(
+        &self,
+        x: f64,
+        y: f64,
+        z: usize,
+        width: f64,
+        height: f64,
+        map: &mut HashMap<usize, ComputedWidget<'a>>,
+    ) {
+        self.child.borrow().compute(x, y, z, width, height, map);
+    }
+
+    fn measure(&self, width: f64, height: f64) -> (f64, f64) {
+        self.child.borrow().measure(width, height)
+    }
+
+    fn dispatch(
+        &self,
+        event: Event,
+        prev_state_change: bool,
+        map: &HashMap<usize, ComputedWidget>,
+    ) -> (Option<Event>, bool) {
+        let mut state_change = prev_state_change;
+        match event {
+            Event::MouseDown { x, y, .. } if *self.enabled.borrow() => {
+                if let Some(computed) = map.get(&self.get_id()) {
+                    if computed.in_hitbox(x, y, computed.border_radius()) {
+                        self.state
+                            .borrow_mut()
+                            .press(x - computed.x, y - computed.y);
+                        state_change = true;
+                    }
+                }
+            }
+            Event::Tick { time } => state_change |= self.state.borrow_mut().tick(time),
+            _ => {}
+        }
+        dispatch_to(&self.child, event, state_change, map)
+    }
+
+    fn children(&self) -> Vec<Rc<RefCell<dyn Widget<'a> + 'a>>> {
+        vec![self.child.clone()]
+    }
+
+    fn get_id(&self) -> usize {
+        self.child.borrow().get_id()
+    }
+}
+
+// draws its subtree faded while disabled
+struct Faded<'a> {
+    child: Rc<RefCell<dyn Widget<'a> + 'a>>,
+    enabled: Rc<RefCell<bool>>,
+}
+
+impl<'a> Widget<'a> for Faded<'a> {
+    fn compute(
+        &self,
+        x: f64,
+        y: f64,
+        z: usize,
+        width: f64,
+        height: f64,
+        map: &mut HashMap<usize, ComputedWidget<'a>>,
+    ) {
+        if *self.enabled.borrow() {
+            self.child.borrow().compute(x, y, z, width, height, map);
+            return;
+        }
+        let mut children = HashMap::new();
+        self.child
+            .borrow()
+            .compute(x, y, z, width, height, &mut children);
+        for (id, mut computed) in children {
+            if let Some(render) = &mut computed.render {
+                render.fade(DISABLED_ALPHA);
+            }
+            map.insert(id, computed);
+        }
+    }
+
+    fn measure(&self, width: f64, height: f64) -> (f64, f64) {
+        self.child.borrow().measure(width, height)
+    }
+
+    fn dispatch(
+        &self,
+        event: Event,
+        prev_state_change: bool,
+        map: &HashMap<usize, ComputedWidget>,
+    ) -> (Option<Event>, bool) {
+        dispatch_to(&self.child, event, prev_state_change, map)
+    }
+
+    fn children(&self) -> Vec<Rc<RefCell<dyn Widget<'a> + 'a>>> {
+        vec![self.child.clone()]
+    }
+
+    // only buttons are wrapped in this
+    fn semantics(&self) -> Option<SemanticNode> {
+        let mut node = SemanticNode::new(Role::Button, subtree_label(&self.child));
+        node.enabled = *self.enabled.borrow();
+        Some(node)
+    }
+
+    fn get_id(&self) -> usize {
+        self.child.borrow().get_id()
+    }
+}
+
+pub struct TabView<'a> {
+    tabs: Vec<(&'a str, Rc<RefCell<dyn Widget<'a> + 'a>>)>,
+    font: &'a str,
+    text_size: u32,
+    header_height: f64,
+    header_color: Color,
+    active_color: Color,
+    selected: usize,
+    changed_callback: Option<Box<dyn Fn(usize) + 'a>>,
+}
+
+impl<'a> TabView<'a> {
+    pub fn new(font: &'a str) -> TabView<'a> {
+        TabView {
+            tabs: Vec::new(),
+            font,
+            text_size: 16,
+            header_height: 32.0,
+            header_color: [0.85, 0.85, 0.85, 1.0],
+            active_color: [1.0, 1.0, 1.0, 1.0],
+            selected: 0,
+            changed_callback: None,
+        }
+    }
+
+    pub fn tab<W: IntoWidget<'a>>(mut self, label: &'a str, content: W) -> Self {
+        self.tabs.push((label, content.into_widget()));
+        self
+    }
+
+    pub fn selected(mut self, index: usize) -> Self {
+        self.selected = index;
+        self
+    }
+
+    pub fn text_size(mut self, size: u32) -> Self {
+        self.text_size = size;
+        self
+    }
+
+    pub fn header_height(mut self, height: f64) -> Self {
+        self.header_height = height;
+        self
+    }
+
+    pub fn header_color(mut self, color: Color) -> Self {
+        self.header_color = color;
+        self
+    }
+
+    pub fn active_color(mut self, color: Color) -> Self {
+        self.active_color = color;
+        self
+    }
+
+    pub fn on_tab_changed<F: Fn(usize) + 'a>(mut self, on_tab_changed: F) -> Self {
+        self.changed_callback = Some(Box::new(on_tab_changed));
+        self
+    }
+
+    pub fn build(self) -> Rc<RefCell<dyn Widget<'a> + 'a>> {
+        let mut stack = IndexedStack::new().index(self.selected);
+        let index = stack.index.clone();
+        let headers: Rc<Vec<Rc<RefCell<Rectangle>>>> = Rc::new(
+            (0..self.tabs.len())
+                .map(|i| {
+                    let color = if i == self.selected {
+                        self.active_color
+                    } else {
+                        self.header_color
+                    };
+                    Rectangle::new(color).build()
+                })
+                .collect(),
+        );
+        let changed_callback = Rc::new(self.changed_callback);
+        let mut header_row = Row::new();
+        for (i, (label, content)) in self.tabs.into_iter().enumerate() {
+            stack = stack.add(content);
+            let index = index.clone();
+            let headers = headers.clone();
+            let changed_callback = changed_callback.clone();
+            let (header_color, active_color) = (self.header_color, self.active_color);
+            let header = MouseGesture::new(Stack::new().add(headers[i].clone()).add(Text::new(
+                label,
+                self.text_size,
+                self.font,
+            )))
+            .on_tap(move |_| {
+                if *index.borrow() == i {
+                    return false;
+                }
+                *index.borrow_mut() = i;
+                for (j, rect) in headers.iter().enumerate() {
+                    rect.borrow_mut().color = if j == i { active_color } else { header_color };
+                }
+                if let Some(changed) = changed_callback.as_ref() {
+                    changed(i);
+                }
+                true
+            });
+            header_row = header_row.add(header);
+        }
+        let header_row = ConstrainedBox::new(header_row)
+            .min_height(self.header_height)
+            .max_height(self.header_height);
+        Column::new().add_flex(header_row, 0).add(stack).build()
+    }
+}
+
+// a widget that keeps its own state. build is called again whenever a state it
+// asked the context for changes, so the returned tree always reflects the current values
+pub trait Component<'a> {
+    fn build(&self, ctx: &mut BuildContext) -> Rc<RefCell<dyn Widget<'a> + 'a>>;
+}
+
+// hands out state to a component. states are matched up by the order they're asked
+// for, so a component has to ask for the same states in the same order on every build
+pub struct BuildContext {
+    slots: Vec<Rc<dyn Any>>,
+    next: usize,
+    dirty: Rc<Cell<bool>>,
+}
+
+impl BuildContext {
+    fn new() -> Self {
+        BuildContext {
+            slots: Vec::new(),
+            next: 0,
+            dirty: Rc::new(Cell::new(false)),
+        }
+    }
+
+    // initial is only called on the first build
+    pub fn state<T: 'static, F: FnOnce() -> T>(&mut self, initial: F) -> ComponentState<T> {
+        if self.next == self.slots.len() {
+            self.slots.push(Rc::new(RefCell::new(initial())));
+        }
+        let value = self.slots[self.next]
+            .clone()
+            .downcast::<RefCell<T>>()
+            .expect("component asked for its states in a different order");
+        self.next += 1;
+        ComponentState {
+            value,
+            dirty: self.dirty.clone(),
+        }
+    }
+}
+
+// a value owned by a component, changing it rebuilds the component
+pub struct ComponentState<T> {
+    value: Rc<RefCell<T>>,
+    dirty: Rc<Cell<bool>>,
+}
+
+impl<T> Clone for ComponentState<T> {
+    fn clone(&self) -> Self {
+        ComponentState {
+            value: self.value.clone(),
+            dirty: self.dirty.clone(),
+        }
+    }
+}
+
+impl<T> ComponentState<T> {
+    pub fn borrow(&self) -> std::cell::Ref<'_, T> {
+        self.value.borrow()
+    }
+
+    pub fn set(&self, value: T) {
+        *self.value.borrow_mut() = value;
+        self.dirty.set(true);
+    }
+
+    pub fn update<F: FnOnce(&mut T)>(&self, update: F) {
+        update(&mut self.value.borrow_mut());
+        self.dirty.set(true);
+    }
+}
+
+impl<T: Clone> ComponentState<T> {
+    pub fn get(&self) -> T {
+        self.value.borrow().clone()
+    }
+}
+
+// puts a component into a widget tree
+pub struct ComponentHost<'a, C: Component<'a>> {
+    component: C,
+    ctx: RefCell<BuildContext>,
+    child: RefCell<Rc<RefCell<dyn Widget<'a> + 'a>>>,
+}
+
+impl<'a, C: Component<'a>> ComponentHost<'a, C> {
+    pub fn new(component: C) -> Rc<RefCell<ComponentHost<'a, C>>> {
+        let mut ctx = BuildContext::new();
+        let child = component.build(&mut ctx);
+        Rc::new(RefCell::new(ComponentHost {
+            component,
+            ctx: RefCell::new(ctx),
+            child: RefCell::new(child),
+        }))
+    }
+
+    // returns whether the component had to be rebuilt
+    fn rebuild_if_dirty(&self) -> bool {
+        let mut ctx = self.ctx.borrow_mut();
+        if !ctx.dirty.replace(false) {
+            return false;
+        }
+        ctx.next = 0;
+        let child = self.component.build(&mut ctx);
+        // building may have touched a state, that's already part of the new tree
+        ctx.dirty.set(false);
+        *self.child.borrow_mut() = child;
+        true
+    }
+
+    fn child(&self) -> Rc<RefCell<dyn Widget<'a> + 'a>> {
+        self.child.borrow().clone()
+    }
+}
+
+impl<'a, C: Component<'a>> Widget<'a> for ComponentHost<'a, C> {
+    fn compute(
+        &self,
+        x: f64,
+        y: f64,
+        z: usize,
+        width: f64,
+        height: f64,
+        map: &mut HashMap<usize, ComputedWidget<'a>>,
+    ) {
+        // a state may have been changed from outside of dispatch
+        self.rebuild_if_dirty();
+        self.child().borrow().compute(x, y, z, width, height, map);
+    }
+
+    fn measure(&self, width: f64, height: f64) -> (f64, f64) {
+        self.child().borrow().measure(width, height)
+    }
+
+    fn dispatch(
+        &self,
+        event: Event,
+        prev_state_change: bool,
+        map: &HashMap<usize, ComputedWidget>,
+    ) -> (Option<Event>, bool) {
+        let (rest, state_change) = dispatch_to(&self.child(), event, prev_state_change, map);
+        let rebuilt = self.rebuild_if_dirty();
+        (rest, state_change || rebuilt)
+    }
+
+    fn semantics(&self) -> Option<SemanticNode> {
+        self.child().borrow().semantics()
+    }
+
+    fn children(&self) -> Vec<Rc<RefCell<dyn Widget<'a> + 'a>>> {
+        self.child().borrow().children()
+    }
+
+    fn get_id(&self) -> usize {
+        self.child().borrow().get_id()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const FONT: &str = "Raleway-Regular.ttf";
+
+    #[test]
+    fn clicking_a_header_switches_the_tab() {
+        let changed = Rc::new(Cell::new(None));
+        let record = changed.clone();
+        let (first, second) = (
+            Rectangle::new(color::RED).build(),
+            Rectangle::new(color::BLUE).build(),
+        );
+        let tree = TabView::new(FONT)
+            .tab("one", first.clone())
+            .tab("two", second.clone())
+            .on_tab_changed(move |index| record.set(Some(index)))
+            .build();
+        let map = crate::compute(&tree, 200.0, 100.0);
+        assert!(map.contains_key(&first.borrow().get_id()));
+        // the second header takes the right half of the 32px header row
+        let (x, y) = (150.0, 16.0);
+        tree.borrow()
+            .dispatch(Event::MouseDown { x, y, button: 0 }, false, &map);
+        let (_, state_change) =
+            tree.borrow()
+                .dispatch(Event::MouseUp { x, y, button: 0 }, false, &map);
+        assert!(state_change);
+        assert_eq!(changed.get(), Some(1));
+        let map = crate::compute(&tree, 200.0, 100.0);
+        assert!(!map.contains_key(&first.borrow().get_id()));
+        let content = &map[&second.borrow().get_id()];
+        assert_eq!((content.y, content.height), (32.0, 68.0));
+    }
+
+    #[test]
+    fn disabled_buttons_swallow_clicks_without_firing() {
+        let presses = Rc::new(Cell::new(0));
+        let button = |enabled| {
+            let presses = presses.clone();
+            Button::new(color::RED)
+                .on_pressed(move |_| presses.set(presses.get() + 1))
+                .enabled(enabled)
+                .build()
+        };
+        fn click<'a>(tree: &Rc<RefCell<dyn Widget<'a> + 'a>>) -> Option<Event> {
+            let map = crate::compute(tree, 100.0, 40.0);
+            let (x, y) = (50.0, 20.0);
+            tree.borrow()
+                .dispatch(Event::MouseDown { x, y, button: 0 }, false, &map);
+            let (rest, _) = tree
+                .borrow()
+                .dispatch(Event::MouseUp { x, y, button: 0 }, false, &map);
+            rest
+        }
+        let enabled = button(true);
+        click(&enabled);
+        assert_eq!(presses.get(), 1);
+        let disabled = button(false);
+        assert!(click(&disabled).is_none());
+        assert_eq!(presses.get(), 1);
+        // the background is drawn faded
+        let map = crate::compute(&disabled, 100.0, 40.0);
+        let alphas: Vec<f32> = map
+            .values()
+            .filter_map(|computed| match &computed.render {
+                Some(crate::RenderObject::Rectangle { style }) => style.color.map(|c| c[3]),
+                _ => None,
+            })
+            .collect();
+        assert_eq!(alphas, vec![DISABLED_ALPHA]);
+    }
+
+    #[test]
+    fn space_presses_the_focused_button() {
+        let presses = Rc::new(Cell::new(0));
+        let record = presses.clone();
+        let mut focused = State::new();
+        let button = Button::new(color::RED)
+            .on_pressed(move |_| record.set(record.get() + 1))
+            .bind_focused(&mut focused)
+            .build();
+        let map = crate::compute(&button, 100.0, 40.0);
+        let space = || Event::KeyDown {
+            key: Key::Space,
+            modifiers: crate::Modifiers::NONE,
+        };
+        let (rest, _) = button.borrow().dispatch(space(), false, &map);
+        assert!(rest.is_some());
+        assert_eq!(presses.get(), 0);
+        focused.set(true);
+        let (rest, changed) = button.borrow().dispatch(space(), false, &map);
+        assert!(rest.is_none() && changed);
+        assert_eq!(presses.get(), 1);
+    }
+
+    #[test]
+    fn ripples_grow_and_fade_over_their_duration() {
+        let mut ripple = RippleState::default();
+        assert!(!ripple.tick(0.0));
+        ripple.press(10.0, 10.0);
+        let mut last = (-1.0, f32::MAX);
+        for frame in 1..=5 {
+            assert!(ripple.tick(1.0 + frame as f64 * RIPPLE_DURATION / 6.0));
+            let (radius, alpha) = RippleState::frame(ripple.progress().unwrap());
+            assert!(radius > last.0);
+            assert!(alpha < last.1);
+            last = (radius, alpha);
+        }
+        // the frame after the duration ends it
+        ripple.tick(1.0 + RIPPLE_DURATION * 2.0);
+        assert_eq!(ripple.progress(), None);
+        assert_eq!(RippleState::frame(0.0), (0.0, RIPPLE_ALPHA));
+        assert_eq!(RippleState::frame(1.0), (1.0, 0.0));
+    }
+
+    #[test]
+    fn colors_pass_the_midpoint_halfway() {
+        let rect = Rectangle::new(color::RED).build();
+        let transition = Rc::new(RefCell::new(ColorTransition::new(color::RED, 0.25)));
+        let animated = AnimatedColor::new(rect.clone(), rect.clone(), transition.clone());
+        let tick = |time| {
+            let map = HashMap::new();
+            animated
+                .borrow()
+                .dispatch(Event::Tick { time }, false, &map)
+                .1
+        };
+        transition.borrow_mut().retarget(color::BLUE);
+        assert!(tick(1.0));
+        assert_eq!(rect.borrow().color, color::RED);
+        assert!(tick(1.125));
+        assert_eq!(
+            rect.borrow().color,
+            color::lerp(color::RED, color::BLUE, 0.5)
+        );
+        assert!(tick(1.25));
+        assert_eq!(rect.borrow().color, color::BLUE);
+        // settled, later ticks don't redraw
+        assert!(!tick(1.5));
+    }
+
+    #[test]
+    fn buttons_are_labeled_by_their_text() {
+        let tree: Rc<RefCell<dyn Widget>> = Column::new()
+            .add(Text::new("Settings", 16, FONT).build())
+            .add(
+                Button::new(color::BLUE)
+                    .child(Text::new("Save", 16, FONT).build())
+                    .build(),
+            )
+            .build();
+        let map = crate::compute(&tree, 200.0, 100.0);
+        let nodes = crate::semantics(&tree, &map);
+        let roles: Vec<_> = nodes
+            .iter()
+            .map(|node| (node.role, node.label.as_str()))
+            .collect();
+        // the button's text is folded into the button instead of showing up on its own
+        assert_eq!(
+            roles,
+            vec![(Role::Text, "Settings"), (Role::Button, "Save")]
+        );
+        assert_eq!(nodes[1].bounds, (0.0, 50.0, 200.0, 50.0));
+        assert!(nodes[1].enabled);
+    }
+
+    #[test]
+    fn counter_components_relabel_themselves_on_click() {
+        struct Counter;
+
+        impl<'a> Component<'a> for Counter {
+            fn build(&self, ctx: &mut BuildContext) -> Rc<RefCell<dyn Widget<'a> + 'a>> {
+                let count = ctx.state(|| 0);
+                let label = format!("clicked {} times", count.get());
+                Button::new(color::RED)
+                    .child(Text::new(label, 16, FONT))
+                    .on_pressed(move |_| count.update(|count| *count += 1))
+                    .build()
+            }
+        }
+
+        fn label(map: &HashMap<usize, ComputedWidget>) -> String {
+            map.values()
+                .find_map(|widget| match &widget.render {
+                    Some(crate::RenderObject::Text { text, .. }) => Some(text.to_string()),
+                    _ => None,
+                })
+                .unwrap()
+        }
+
+        let tree: Rc<RefCell<dyn Widget>> = ComponentHost::new(Counter);
+        let mut map = crate::compute(&tree, 200.0, 40.0);
+        assert_eq!(label(&map), "clicked 0 times");
+        for clicks in 1..=2 {
+            let (x, y) = (100.0, 20.0);
+            tree.borrow()
+                .dispatch(Event::MouseDown { x, y, button: 0 }, false, &map);
+            let (_, state_change) =
+                tree.borrow()
+                    .dispatch(Event::MouseUp { x, y, button: 0 }, false, &map);
+            assert!(state_change);
+            // the rebuilt tree has new widgets, so it is laid out again
+            map = crate::compute(&tree, 200.0, 40.0);
+            assert_eq!(label(&map), format!("clicked {} times", clicks));
+        }
     }
 }
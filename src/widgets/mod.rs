@@ -1,9 +1,15 @@
-use super::{ComputedWidget, Event};
+use super::{ComputedWidget, Event, SemanticNode};
+use std::cell::{Ref, RefCell};
 use std::collections::HashMap;
+use std::ops::Deref;
+use std::rc::Rc;
 
 mod core;
 mod extra;
 
+// offset from the stack's origin, and a fixed size if there is one
+pub type StackPosition = ((f64, f64), Option<(f64, f64)>);
+
 pub trait Widget<'a> {
     fn compute(
         &self,
@@ -21,7 +27,130 @@ pub trait Widget<'a> {
         map: &HashMap<usize, ComputedWidget>,
     ) -> (Option<Event>, bool);
     fn get_id(&self) -> usize;
+
+    // intrinsic size, used by Row and Column for flex 0 children
+    fn measure(&self, _width: f64, _height: f64) -> (f64, f64) {
+        (0.0, 0.0)
+    }
+
+    // how much room the content would take if it had as much as it wanted along
+    // the scroll axis, used by scroll containers to find their extent
+    fn desired_size(&self, available: (f64, f64)) -> (f64, f64) {
+        self.measure(available.0, available.1)
+    }
+
+    // overrides the flex the parent Row or Column was given for this child
+    fn flex(&self) -> Option<usize> {
+        None
+    }
+
+    // where a Stack places this child instead of filling it
+    fn position(&self) -> Option<StackPosition> {
+        None
+    }
+
+    // what assistive technology should announce for this widget, if anything
+    fn semantics(&self) -> Option<SemanticNode> {
+        None
+    }
+
+    // direct children, for walking the tree outside of layout and dispatch
+    fn children(&self) -> Vec<Rc<RefCell<dyn Widget<'a> + 'a>>> {
+        Vec::new()
+    }
+}
+
+// containers hand events to their children through here, so a traced dispatch sees them
+pub(crate) fn dispatch_to<'a>(
+    child: &Rc<RefCell<dyn Widget<'a> + 'a>>,
+    event: Event,
+    prev_state_change: bool,
+    map: &HashMap<usize, ComputedWidget>,
+) -> (Option<Event>, bool) {
+    dispatch_widget(&*child.borrow(), event, prev_state_change, map)
+}
+
+pub(crate) fn dispatch_widget<'a>(
+    child: &(dyn Widget<'a> + 'a),
+    event: Event,
+    prev_state_change: bool,
+    map: &HashMap<usize, ComputedWidget>,
+) -> (Option<Event>, bool) {
+    #[cfg(feature = "trace")]
+    return crate::trace::traced(child, event, prev_state_change, map);
+    #[cfg(not(feature = "trace"))]
+    child.dispatch(event, prev_state_change, map)
+}
+
+// a container's child. shared children can still be reached and changed from outside,
+// owned ones belong to a static tree and skip the Rc<RefCell<..>>
+pub enum Child<'a> {
+    Shared(Rc<RefCell<dyn Widget<'a> + 'a>>),
+    Owned(Box<dyn Widget<'a> + 'a>),
+}
+
+impl<'a> Child<'a> {
+    pub fn borrow(&self) -> ChildRef<'_, 'a> {
+        match self {
+            Child::Shared(child) => ChildRef::Shared(child.borrow()),
+            Child::Owned(child) => ChildRef::Owned(&**child),
+        }
+    }
+
+    // owned children can't be handed out, so tree walks over children() skip them
+    pub fn shared(&self) -> Option<Rc<RefCell<dyn Widget<'a> + 'a>>> {
+        match self {
+            Child::Shared(child) => Some(child.clone()),
+            Child::Owned(_) => None,
+        }
+    }
+}
+
+pub enum ChildRef<'r, 'a> {
+    Shared(Ref<'r, dyn Widget<'a> + 'a>),
+    Owned(&'r (dyn Widget<'a> + 'a)),
+}
+
+impl<'r, 'a> Deref for ChildRef<'r, 'a> {
+    type Target = dyn Widget<'a> + 'a;
+
+    fn deref(&self) -> &Self::Target {
+        match self {
+            ChildRef::Shared(child) => &**child,
+            ChildRef::Owned(child) => *child,
+        }
+    }
+}
+
+pub trait IntoWidget<'a> {
+    fn into_widget(self) -> Rc<RefCell<dyn Widget<'a> + 'a>>;
+}
+
+impl<'a> IntoWidget<'a> for Rc<RefCell<dyn Widget<'a> + 'a>> {
+    fn into_widget(self) -> Rc<RefCell<dyn Widget<'a> + 'a>> {
+        self
+    }
+}
+
+impl<'a, W: Widget<'a> + 'a> IntoWidget<'a> for Rc<RefCell<W>> {
+    fn into_widget(self) -> Rc<RefCell<dyn Widget<'a> + 'a>> {
+        self
+    }
 }
 
 pub use self::core::*;
 pub use self::extra::*;
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::color;
+
+    #[test]
+    fn concrete_widgets_are_added_without_a_cast() {
+        let rect: Rc<RefCell<Rectangle>> = Rectangle::new(color::RED).build();
+        let column = Column::new().add(rect.clone()).build();
+        let child = column.borrow().children()[0].borrow().get_id();
+        assert_eq!(child, rect.borrow().get_id());
+    }
+}